@@ -0,0 +1,49 @@
+// A sample tacky-borders plugin that makes the active border pulse.
+//
+// Build it with 'cargo build --release', then point the 'plugin' config option at the resulting
+// sample_pulse.dll. These structs must match the ones in tacky-borders' src/plugins.rs.
+
+const PLUGIN_ABI_VERSION: u32 = 1;
+
+#[repr(C)]
+pub struct PluginBorderState {
+    pub width: f32,
+    pub height: f32,
+    pub border_width: f32,
+    pub border_radius: f32,
+    pub is_active: u32,
+    pub elapsed: f32,
+    pub delta: f32,
+}
+
+#[repr(C)]
+pub struct PluginBrushParams {
+    pub opacity: f32,
+    pub transform: [f32; 6],
+}
+
+#[no_mangle]
+pub extern "C" fn tacky_plugin_abi_version(_host_version: u32) -> u32 {
+    PLUGIN_ABI_VERSION
+}
+
+/// # Safety
+///
+/// `state` and `params` must be valid pointers, which tacky-borders guarantees.
+#[no_mangle]
+pub unsafe extern "C" fn tacky_plugin_on_frame(
+    state: *const PluginBorderState,
+    params: *mut PluginBrushParams,
+) {
+    let (Some(state), Some(params)) = (state.as_ref(), params.as_mut()) else {
+        return;
+    };
+
+    if state.is_active == 0 {
+        return;
+    }
+
+    // Oscillate the opacity between 0.5 and 1.0 roughly once per second
+    let wave = (state.elapsed * std::f32::consts::TAU).sin() * 0.5 + 0.5;
+    params.opacity = 0.5 + wave * 0.5;
+}
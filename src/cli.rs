@@ -0,0 +1,480 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{self, SystemTime};
+use tacky_borders_core::border::{Border, BorderHandle, BorderOptions};
+use tacky_borders_core::border_config::{Config, WindowRule, CONFIG};
+use tacky_borders_core::picker;
+use tacky_borders_core::rule_engine;
+use tacky_borders_core::simulation;
+#[cfg(feature = "self-test")]
+use tacky_borders_core::test_utils;
+use tacky_borders_core::themes;
+use tacky_borders_core::utils::*;
+use windows::Win32::Foundation::RECT;
+use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+use windows::Win32::UI::HiDpi::DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2;
+use windows::Win32::UI::WindowsAndMessaging::IsWindow;
+
+use crate::preview_window::PreviewWindow;
+use crate::theme_import;
+use crate::updater;
+
+// How often `tacky-borders preview` checks whether the config or a theme has been saved
+const PREVIEW_POLL_INTERVAL: time::Duration = time::Duration::from_millis(500);
+
+// Forward a command like `tacky-borders set-progress focused 50` to the running instance and print
+// its reply. Returns the process exit code.
+pub fn run(args: &[String]) -> i32 {
+    // Release builds use the windows subsystem, so we have to borrow the terminal's console to
+    // print anything
+    let _ = unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+
+    match tacky_borders_core::ipc::send(args) {
+        Ok(reply) => {
+            if !reply.is_empty() {
+                println!("{reply}");
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("error: {e:#}");
+            1
+        }
+    }
+}
+
+// Play a simulation script with --headless and write the border state transitions as JSON to the
+// output file, or to stdout if there isn't one. Returns the process exit code.
+pub fn headless(script_path: &str, output_path: Option<&str>) -> i32 {
+    let _ = unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+
+    let result = simulation::load_script(Path::new(script_path))
+        .and_then(|script| simulation::run(&script))
+        .and_then(|transitions| Ok(serde_json::to_string_pretty(&transitions)?));
+
+    let json = match result {
+        Ok(json) => json,
+        Err(e) => {
+            eprintln!("error: {e:#}");
+            return 1;
+        }
+    };
+
+    match output_path {
+        Some(path) => {
+            if let Err(e) = fs::write(path, json) {
+                eprintln!("error: could not write {path}: {e}");
+                return 1;
+            }
+        }
+        None => println!("{json}"),
+    }
+
+    0
+}
+
+// Run the --self-test smoke test against a dummy window. Returns the process exit code.
+#[cfg(feature = "self-test")]
+pub fn self_test() -> i32 {
+    let _ = unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+
+    match test_utils::run_smoke_test() {
+        Ok(_) => {
+            println!("self-test passed");
+            0
+        }
+        Err(e) => {
+            eprintln!("self-test failed: {e:#}");
+            1
+        }
+    }
+}
+
+// The smoke test only ships with builds that ask for it
+#[cfg(not(feature = "self-test"))]
+pub fn self_test() -> i32 {
+    let _ = unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+    eprintln!("error: this build has no self-test; build it with `--features self-test`");
+    1
+}
+
+// Check for a newer release with `tacky-borders update`, and install it unless --check was passed.
+// Returns the process exit code.
+pub fn update(check_only: bool) -> i32 {
+    let _ = unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+
+    let release = match updater::check_for_update() {
+        Ok(Some(release)) => release,
+        Ok(None) => {
+            println!("tacky-borders v{} is up to date", env!("CARGO_PKG_VERSION"));
+            return 0;
+        }
+        Err(e) => {
+            eprintln!("error: could not check for updates: {e:#}");
+            return 1;
+        }
+    };
+
+    if check_only {
+        println!(
+            "tacky-borders v{} is available: {}",
+            release.version, release.page_url
+        );
+        return 0;
+    }
+
+    match updater::install_update(&release) {
+        Ok(_) => {
+            println!(
+                "updated to tacky-borders v{}; restart tacky-borders to use it",
+                release.version
+            );
+            0
+        }
+        Err(e) => {
+            eprintln!("error: could not update: {e:#}");
+            1
+        }
+    }
+}
+
+// `tacky-borders config path` prints which config file is used, along with any others that were
+// found but aren't used. Returns the process exit code.
+pub fn config(args: &[String]) -> i32 {
+    let _ = unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+
+    if args.first().map(String::as_str) != Some("path") {
+        eprintln!("error: expected 'config path'");
+        return 1;
+    }
+
+    let config_path = match Config::get_config_path() {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("error: {e:#}");
+            return 1;
+        }
+    };
+
+    match config_path.exists() {
+        true => println!("{}", config_path.display()),
+        false => println!("{} (will be generated)", config_path.display()),
+    }
+
+    for other_path in Config::get_config_path_candidates()
+        .into_iter()
+        .filter(|path| path.exists() && *path != config_path)
+    {
+        println!("also found, but not used: {}", other_path.display());
+    }
+
+    0
+}
+
+// `tacky-borders check [path]` loads a config (the active one by default) without starting any
+// borders, then prints any problems with it and which rule each open window would get. Returns
+// the process exit code.
+pub fn check(path: Option<&String>) -> i32 {
+    let _ = unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+
+    let config_path = match path {
+        Some(path) => PathBuf::from(expand_path(path)),
+        None => match Config::get_config_path() {
+            Ok(path) => path,
+            Err(e) => {
+                eprintln!("error: {e:#}");
+                return 1;
+            }
+        },
+    };
+
+    let config = match fs::read_to_string(&config_path)
+        .map_err(anyhow::Error::new)
+        .and_then(|contents| Config::parse(&contents))
+    {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("error: could not load {}: {e:#}", config_path.display());
+            return 1;
+        }
+    };
+    println!("loaded {}", config_path.display());
+
+    let problems = config.validate();
+    for problem in problems.iter() {
+        println!("warning: {problem}");
+    }
+
+    // Only the windows that would get a border when tacky-borders starts
+    println!();
+    for hwnd in get_top_level_windows()
+        .into_iter()
+        .filter(|&hwnd| !has_filtered_style(hwnd) && is_window_visible(hwnd) && !is_cloaked(hwnd))
+    {
+        let window = get_window_info(hwnd);
+        let matching = rule_engine::find_rules(&config.window_rules, &window);
+        let resolved = rule_engine::get_rule(&config.window_rules, &window);
+        let outcome = match matching.is_empty() {
+            true => "global".to_string(),
+            false => describe_rules(&matching, &config.window_rules, &resolved),
+        };
+
+        println!(
+            "{:#x} {} | {} | \"{}\" -> {outcome}",
+            hwnd.0 as isize, window.process, window.class, window.title
+        );
+    }
+
+    match problems.is_empty() {
+        true => 0,
+        false => 1,
+    }
+}
+
+// Like 'window_rules[3] (Title "foo") over window_rules[0]', for rules that overlay other rules
+fn describe_rules(matching: &[usize], rules: &[WindowRule], resolved: &WindowRule) -> String {
+    let mut description = matching
+        .iter()
+        .map(|&index| match (&rules[index].kind, &rules[index].name) {
+            (Some(kind), Some(name)) => format!("window_rules[{index}] ({kind:?} \"{name}\")"),
+            _ => format!("window_rules[{index}]"),
+        })
+        .collect::<Vec<_>>()
+        .join(" over ");
+
+    if resolved.enabled == Some(false) {
+        description += ", border disabled";
+    }
+    description
+}
+
+// `tacky-borders pick` waits for a click on a window, then prints what it is along with a window
+// rule for it, which also goes on the clipboard. Returns the process exit code.
+pub fn pick() -> i32 {
+    let _ = unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+
+    // Otherwise the click's position is scaled differently from the windows' on high DPI monitors
+    let _ = set_process_dpi_awareness_context(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2);
+
+    println!("click a window to pick it (Escape or right-click to cancel)");
+    let hwnd = match picker::pick_window() {
+        Ok(Some(hwnd)) => hwnd,
+        Ok(None) => {
+            eprintln!("cancelled");
+            return 1;
+        }
+        Err(e) => {
+            eprintln!("error: {e:#}");
+            return 1;
+        }
+    };
+
+    let window = get_window_info(hwnd);
+    println!("process: {}", window.process);
+    println!("class: {}", window.class);
+    println!("title: {}", window.title);
+
+    let snippet = picker::rule_snippet(&window);
+    println!();
+    print!("{snippet}");
+
+    match picker::copy_to_clipboard(&snippet) {
+        Ok(_) => println!("\n(copied to the clipboard)"),
+        Err(e) => eprintln!("\nwarning: could not copy to the clipboard: {e:#}"),
+    }
+
+    0
+}
+
+// `tacky-borders preview` opens two empty windows with borders from the config's global section
+// (one of them is always unfocused, so both colors show), and recreates the borders whenever the
+// config or a theme is saved. Returns the process exit code once both windows are closed.
+pub fn preview() -> i32 {
+    let _ = unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+    let _ = set_process_dpi_awareness_context(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2);
+
+    let windows: anyhow::Result<Vec<PreviewWindow>> = [120, 720]
+        .into_iter()
+        .map(|left| {
+            let rect = RECT {
+                left,
+                top: 160,
+                right: left + 560,
+                bottom: 560,
+            };
+            PreviewWindow::spawn("tacky-borders preview", rect)
+        })
+        .collect();
+    let windows = match windows {
+        Ok(windows) => windows,
+        Err(e) => {
+            eprintln!("error: {e:#}");
+            return 1;
+        }
+    };
+
+    if let Ok(path) = Config::get_active_config_path() {
+        println!("previewing {}", path.display());
+    }
+    println!("save the config or a theme to see your changes, and close both windows to quit");
+
+    let mut borders = attach_preview_borders(&windows);
+    let mut watched = watched_files();
+
+    while windows
+        .iter()
+        .any(|window| unsafe { IsWindow(window.hwnd()) }.as_bool())
+    {
+        thread::sleep(PREVIEW_POLL_INTERVAL);
+
+        let files = watched_files();
+        if files == watched {
+            continue;
+        }
+        watched = files;
+
+        // A broken config keeps the last good one on screen until it's fixed
+        match Config::create_config() {
+            Ok(config) => *CONFIG.lock().unwrap() = config,
+            Err(e) => {
+                eprintln!("error: {e:#}");
+                continue;
+            }
+        }
+
+        for border in borders.drain(..) {
+            border.detach();
+        }
+        // Each border unregisters itself on its own thread, and attaching fails until it has
+        for _ in 0..50 {
+            if windows
+                .iter()
+                .all(|window| get_border_from_window(window.hwnd()).is_none())
+            {
+                break;
+            }
+            thread::sleep(time::Duration::from_millis(20));
+        }
+
+        borders = attach_preview_borders(&windows);
+        println!("reloaded");
+    }
+
+    0
+}
+
+// Leaving every option unset means the borders get the global section's
+fn attach_preview_borders(windows: &[PreviewWindow]) -> Vec<BorderHandle> {
+    windows
+        .iter()
+        .filter(|window| unsafe { IsWindow(window.hwnd()) }.as_bool())
+        .filter_map(
+            |window| match Border::attach(window.hwnd(), BorderOptions::default()) {
+                Ok(border) => Some(border),
+                Err(e) => {
+                    eprintln!("error: could not attach border: {e:#}");
+                    None
+                }
+            },
+        )
+        .collect()
+}
+
+// When the config and every theme were last changed, to notice when one of them is saved
+fn watched_files() -> Vec<(PathBuf, Option<SystemTime>)> {
+    let mut paths: Vec<PathBuf> = Config::get_active_config_path().into_iter().collect();
+    if let Ok(entries) = themes::get_themes_dir().and_then(|dir| Ok(fs::read_dir(dir)?)) {
+        paths.extend(
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path()),
+        );
+    }
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+            (path, modified)
+        })
+        .collect()
+}
+
+// `tacky-borders export` prints the config that borders would actually use, for figuring out why an
+// option isn't doing anything. The running instance is asked for it, since that's the config it has
+// loaded; if nothing is running, it's read the way an instance would read it. Returns the process
+// exit code.
+pub fn export() -> i32 {
+    let _ = unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+
+    let result = match tacky_borders_core::ipc::send(&["export".to_string()]) {
+        // Opening the pipe only fails with NotFound when there's no instance listening on it
+        Err(e) if is_not_found(&e) => {
+            eprintln!("(tacky-borders is not running, so this is the config it would load)");
+            Config::load().and_then(|config| config.export_effective())
+        }
+        result => result,
+    };
+
+    match result {
+        Ok(contents) => {
+            println!("{}", contents.trim_end());
+            0
+        }
+        Err(e) => {
+            eprintln!("error: {e:#}");
+            1
+        }
+    }
+}
+
+fn is_not_found(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<std::io::Error>()
+        .is_some_and(|e| e.kind() == std::io::ErrorKind::NotFound)
+}
+
+// `tacky-borders theme import <file> [--name <name>] [--force]` turns a VS Code, JetBrains, or
+// terminal color scheme into a theme file in the themes folder. Returns the process exit code.
+pub fn import_theme(args: &[String]) -> i32 {
+    let _ = unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+
+    let Some(source) = args.first().filter(|arg| !arg.starts_with("--")) else {
+        eprintln!("error: usage: theme import <file> [--name <name>] [--force]");
+        return 1;
+    };
+    let name_arg = args
+        .iter()
+        .position(|arg| arg == "--name")
+        .and_then(|i| args.get(i + 1));
+    // Without --force, importing the same scheme twice fails instead of clobbering any edits
+    let force = args.iter().any(|arg| arg == "--force");
+
+    let (name, theme) = match theme_import::import(Path::new(&expand_path(source))) {
+        Ok((name, theme)) => (
+            name_arg.map_or(name, |arg| themes::sanitize_name(arg)),
+            theme,
+        ),
+        Err(e) => {
+            eprintln!("error: {e:#}");
+            return 1;
+        }
+    };
+    if name_arg.is_some_and(|arg| *arg != name) {
+        println!("saving it as '{name}', since theme names can only have a-z, 0-9, '-', and '_'");
+    }
+
+    match themes::save_theme(&name, &theme, force) {
+        Ok(theme_path) => {
+            println!("wrote {}", theme_path.display());
+            println!("use it with 'theme: {name}' in your config or 'tacky-borders theme {name}'");
+            0
+        }
+        Err(e) => {
+            eprintln!("error: {e:#}");
+            1
+        }
+    }
+}
@@ -7,57 +7,128 @@
 extern crate log;
 extern crate simplelog;
 
-use anyhow::Context;
 use simplelog::*;
-use std::cell::Cell;
-use std::collections::HashMap;
 use std::fs::File;
-use std::sync::{LazyLock, Mutex};
-use windows::core::w;
-use windows::Win32::Foundation::{GetLastError, BOOL, HINSTANCE, HWND, LPARAM, TRUE, WPARAM};
-use windows::Win32::System::SystemServices::IMAGE_DOS_HEADER;
-use windows::Win32::UI::Accessibility::{SetWinEventHook, HWINEVENTHOOK};
+use std::path::PathBuf;
+use tacky_borders_core::border_config;
+use tacky_borders_core::notifications::{self, NotificationKind};
+use tacky_borders_core::utils::{expand_path, imm_disable_ime, set_process_dpi_awareness_context};
+use windows::Win32::Foundation::HWND;
 use windows::Win32::UI::HiDpi::DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2;
 use windows::Win32::UI::WindowsAndMessaging::{
-    DispatchMessageW, EnumWindows, GetMessageW, LoadCursorW, RegisterClassExW, TranslateMessage,
-    EVENT_MAX, EVENT_MIN, IDC_ARROW, MSG, WINEVENT_OUTOFCONTEXT, WINEVENT_SKIPOWNPROCESS,
-    WM_NCDESTROY, WNDCLASSEXW,
+    DispatchMessageW, GetMessageW, SetTimer, TranslateMessage, MSG, WM_TIMER,
 };
 
-mod anim_timer;
-mod animations;
-mod border_config;
-mod colors;
-mod event_hook;
+mod cli;
+mod preview_window;
+mod startup;
 mod sys_tray_icon;
-mod utils;
-mod window_border;
+mod theme_import;
+mod updater;
 
-use crate::utils::*;
+// How often to check whether the tray tooltip needs to mention borders in safe mode
+const TOOLTIP_INTERVAL_MS: u32 = 5000;
 
-extern "C" {
-    static __ImageBase: IMAGE_DOS_HEADER;
-}
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let has_arg = |name: &str| args.iter().any(|arg| arg == name);
+    let arg_value = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .and_then(|i| args.get(i + 1))
+            .map(String::as_str)
+    };
 
-thread_local! {
-    static EVENT_HOOK: Cell<HWINEVENTHOOK> = Cell::new(HWINEVENTHOOK::default());
-}
+    // These change where the config and log are, so they have to come before anything uses them
+    if has_arg("--portable") {
+        border_config::Config::set_portable(true);
+    }
+    if let Some(path) = arg_value("--config") {
+        border_config::Config::set_config_path(PathBuf::from(expand_path(path)));
+    }
+    if has_arg("--strict") {
+        border_config::Config::set_strict(true);
+    }
+
+    // --config, --portable, and --strict can come before a command, e.g. 'tacky-borders --config
+    // work.yaml timer 25', to pick which instance it goes to
+    let mut command_args = args.as_slice();
+    loop {
+        match command_args.first().map(String::as_str) {
+            Some("--portable" | "--strict") => command_args = &command_args[1..],
+            Some("--config") => command_args = command_args.get(2..).unwrap_or_default(),
+            _ => break,
+        }
+    }
 
-static BORDERS: LazyLock<Mutex<HashMap<isize, isize>>> =
-    LazyLock::new(|| Mutex::new(HashMap::new()));
+    // These don't need the running instance, so they don't go through it
+    match command_args.first().map(String::as_str) {
+        Some("update") => std::process::exit(cli::update(has_arg("--check"))),
+        Some("config") => std::process::exit(cli::config(&command_args[1..])),
+        Some("check") => std::process::exit(cli::check(
+            command_args[1..].iter().find(|arg| !arg.starts_with("--")),
+        )),
+        Some("export") => std::process::exit(cli::export()),
+        Some("pick") => std::process::exit(cli::pick()),
+        Some("preview") => std::process::exit(cli::preview()),
+        Some("theme") if command_args.get(1).is_some_and(|arg| arg == "import") => {
+            std::process::exit(cli::import_theme(&command_args[2..]))
+        }
+        _ => {}
+    }
 
-static INITIAL_WINDOWS: LazyLock<Mutex<Vec<isize>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+    // Anything that isn't a --flag is a command for the instance that's already running. This has
+    // to happen before we create the logger, which would otherwise truncate that instance's log.
+    if command_args
+        .first()
+        .is_some_and(|arg| !arg.starts_with("--"))
+    {
+        std::process::exit(cli::run(command_args));
+    }
 
-// This is used to send HWNDs across threads even though HWND doesn't implement Send and Sync.
-struct SendHWND(HWND);
-unsafe impl Send for SendHWND {}
-unsafe impl Sync for SendHWND {}
+    // These don't touch the user's windows, so they shouldn't touch the log either
+    if has_arg("--headless") {
+        let Some(script) = arg_value("--headless") else {
+            eprintln!("error: --headless needs a script");
+            std::process::exit(1);
+        };
+        std::process::exit(cli::headless(script, arg_value("--output")));
+    }
+
+    if has_arg("--self-test") {
+        std::process::exit(cli::self_test());
+    }
 
-fn main() {
     if let Err(e) = create_logger() {
         println!("[ERROR] {}", e);
     };
 
+    tacky_borders_core::crash::install();
+
+    // These just (un)register the logon task and exit without starting any borders
+    if has_arg("--install-startup") {
+        match startup::install_task(has_arg("--highest"), has_arg("--portable")) {
+            Ok(_) => info!("installed startup task"),
+            Err(e) => {
+                error!("could not install startup task: {e:#}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if has_arg("--uninstall-startup") {
+        match startup::uninstall_task() {
+            Ok(_) => info!("uninstalled startup task"),
+            Err(e) => {
+                error!("could not uninstall startup task: {e:#}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    updater::remove_old_exe();
+
     // xFFFFFFFF can be used to disable IME windows for all threads in the current process.
     if !imm_disable_ime(0xFFFFFFFF).as_bool() {
         error!("could not disable ime!");
@@ -67,24 +138,48 @@ fn main() {
         error!("could not make process dpi aware: {e}");
     }
 
+    // The startup task launches us with --startup, which can be before the shell is ready
+    if has_arg("--startup") {
+        startup::wait_for_shell();
+    }
+
     // This is responsible for the actual tray icon window, so it must be kept in scope
     let tray_icon_result = sys_tray_icon::create_tray_icon();
-    if let Err(e) = tray_icon_result {
+    if let Err(ref e) = tray_icon_result {
         // TODO for some reason if I use {:#} or {:?}, it repeatedly prints the error. Could be
         // something to do with how it implements .source()?
         error!("could not create tray icon: {e}");
+        notifications::notify(
+            NotificationKind::Error,
+            "Could not create tray icon",
+            &format!("{e}"),
+        );
     }
 
-    EVENT_HOOK.replace(set_event_hook());
-    log_if_err!(register_window_class());
-    log_if_err!(enum_windows());
+    tacky_borders_core::start();
+    updater::start_background_check();
 
     unsafe {
+        // Without a window, this posts WM_TIMER to the thread's message queue
+        let tooltip_timer = SetTimer(None, 0, TOOLTIP_INTERVAL_MS, None);
+
         debug!("entering message loop!");
         let mut message = MSG::default();
         while GetMessageW(&mut message, HWND::default(), 0, 0).into() {
+            if message.message == WM_TIMER && message.wParam.0 == tooltip_timer {
+                if let Ok(ref tray_icon) = tray_icon_result {
+                    sys_tray_icon::update_tooltip(tray_icon);
+                }
+            }
+
             let _ = TranslateMessage(&message);
             DispatchMessageW(&message);
+
+            // Menu clicks are handled while dispatching, so this is when the menu can be updated
+            if let Ok(ref tray_icon) = tray_icon_result {
+                sys_tray_icon::update_menu(tray_icon);
+            }
+            updater::restart_if_installed();
         }
         error!("exited messsage loop in main.rs; this should not happen");
     }
@@ -116,83 +211,3 @@ fn create_logger() -> anyhow::Result<()> {
 
     Ok(())
 }
-
-fn register_window_class() -> windows::core::Result<()> {
-    unsafe {
-        let hinstance: HINSTANCE = std::mem::transmute(&__ImageBase);
-
-        let window_class = WNDCLASSEXW {
-            cbSize: size_of::<WNDCLASSEXW>() as u32,
-            lpfnWndProc: Some(window_border::WindowBorder::s_wnd_proc),
-            hInstance: hinstance,
-            lpszClassName: w!("border"),
-            hCursor: LoadCursorW(None, IDC_ARROW)?,
-            ..Default::default()
-        };
-
-        let result = RegisterClassExW(&window_class);
-        if result == 0 {
-            let last_error = GetLastError();
-            error!("could not register window class: {last_error:?}");
-        }
-    }
-
-    Ok(())
-}
-
-fn set_event_hook() -> HWINEVENTHOOK {
-    unsafe {
-        SetWinEventHook(
-            EVENT_MIN,
-            EVENT_MAX,
-            None,
-            Some(event_hook::handle_win_event),
-            0,
-            0,
-            WINEVENT_OUTOFCONTEXT | WINEVENT_SKIPOWNPROCESS,
-        )
-    }
-}
-
-fn enum_windows() -> windows::core::Result<()> {
-    unsafe {
-        EnumWindows(Some(enum_windows_callback), LPARAM::default())?;
-    }
-    debug!("windows have been enumerated!");
-    Ok(())
-}
-
-fn reload_borders() {
-    let mut borders = BORDERS.lock().unwrap();
-
-    // Send destroy messages to all the border windows
-    for value in borders.values() {
-        let border_window = HWND(*value as _);
-        log_if_err!(
-            post_message_w(border_window, WM_NCDESTROY, WPARAM(0), LPARAM(0))
-                .context("reload_borders")
-        );
-    }
-
-    // Clear the borders hashmap
-    borders.clear();
-    drop(borders);
-
-    // Clear the initial windows list
-    INITIAL_WINDOWS.lock().unwrap().clear();
-
-    log_if_err!(enum_windows());
-}
-
-unsafe extern "system" fn enum_windows_callback(_hwnd: HWND, _lparam: LPARAM) -> BOOL {
-    if !has_filtered_style(_hwnd) {
-        if is_window_visible(_hwnd) && !is_cloaked(_hwnd) {
-            create_border_for_window(_hwnd);
-        }
-
-        // Add currently open windows to the intial windows list so we can keep track of them
-        INITIAL_WINDOWS.lock().unwrap().push(_hwnd.0 as isize);
-    }
-
-    TRUE
-}
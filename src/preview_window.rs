@@ -0,0 +1,116 @@
+use anyhow::Context;
+use std::sync::{mpsc, Once};
+use std::thread;
+use tacky_borders_core::log_if_err;
+use tacky_borders_core::utils::{post_message_w, DUMMY_WINDOW_CLASS};
+use windows::core::{HSTRING, PCWSTR};
+use windows::Win32::Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, RECT, WPARAM};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, LoadCursorW, PostQuitMessage,
+    RegisterClassExW, TranslateMessage, IDC_ARROW, MSG, WINDOW_EX_STYLE, WM_CLOSE, WM_DESTROY,
+    WNDCLASSEXW, WS_OVERLAPPEDWINDOW, WS_VISIBLE,
+};
+
+static WINDOW_CLASS: Once = Once::new();
+
+// An empty top-level window for `tacky-borders preview` to draw borders around. It uses the dummy
+// window class so a running instance doesn't give it a border of its own, and it closes when
+// dropped.
+#[derive(Debug)]
+pub struct PreviewWindow {
+    // Stored as isize so the window can be sent to other threads
+    hwnd: isize,
+}
+
+impl PreviewWindow {
+    // 'rect' is in screen coordinates, including the invisible resize borders
+    pub fn spawn(title: &str, rect: RECT) -> anyhow::Result<Self> {
+        register_class_once();
+
+        let title = HSTRING::from(title);
+        let (sender, receiver) = mpsc::channel();
+        let _ = thread::spawn(move || unsafe {
+            let hinstance: HINSTANCE = GetModuleHandleW(None).unwrap_or_default().into();
+
+            let created = CreateWindowExW(
+                WINDOW_EX_STYLE::default(),
+                &HSTRING::from(DUMMY_WINDOW_CLASS),
+                &title,
+                WS_OVERLAPPEDWINDOW | WS_VISIBLE,
+                rect.left,
+                rect.top,
+                rect.right - rect.left,
+                rect.bottom - rect.top,
+                None,
+                None,
+                hinstance,
+                None,
+            );
+
+            let hwnd = created.map(|hwnd| hwnd.0 as isize);
+            let created_ok = hwnd.is_ok();
+            let _ = sender.send(hwnd);
+            if !created_ok {
+                return;
+            }
+
+            let mut message = MSG::default();
+            while GetMessageW(&mut message, HWND::default(), 0, 0).into() {
+                let _ = TranslateMessage(&message);
+                DispatchMessageW(&message);
+            }
+        });
+
+        let hwnd = receiver
+            .recv()
+            .context("preview window thread exited before creating its window")?
+            .context("could not create preview window")?;
+
+        Ok(Self { hwnd })
+    }
+
+    pub fn hwnd(&self) -> HWND {
+        HWND(self.hwnd as _)
+    }
+}
+
+impl Drop for PreviewWindow {
+    fn drop(&mut self) {
+        log_if_err!(post_message_w(self.hwnd(), WM_CLOSE, WPARAM(0), LPARAM(0)));
+    }
+}
+
+fn register_class_once() {
+    WINDOW_CLASS.call_once(|| unsafe {
+        let class_name = HSTRING::from(DUMMY_WINDOW_CLASS);
+        let window_class = WNDCLASSEXW {
+            cbSize: size_of::<WNDCLASSEXW>() as u32,
+            lpfnWndProc: Some(preview_wnd_proc),
+            hInstance: GetModuleHandleW(None).unwrap_or_default().into(),
+            lpszClassName: PCWSTR(class_name.as_ptr()),
+            hCursor: LoadCursorW(None, IDC_ARROW).unwrap_or_default(),
+            ..Default::default()
+        };
+
+        if RegisterClassExW(&window_class) == 0 {
+            error!("could not register preview window class");
+        }
+    });
+}
+
+unsafe extern "system" fn preview_wnd_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    match message {
+        // Each preview window has its own thread, so end it along with the window
+        WM_DESTROY => {
+            PostQuitMessage(0);
+            LRESULT(0)
+        }
+        _ => DefWindowProcW(window, message, wparam, lparam),
+    }
+}
@@ -0,0 +1,132 @@
+use anyhow::{anyhow, Context};
+use std::env;
+use std::fs;
+use std::process::Command;
+use std::thread;
+use std::time;
+use windows::core::{w, PCWSTR};
+use windows::Win32::UI::WindowsAndMessaging::FindWindowW;
+
+const TASK_NAME: &str = "tacky-borders";
+
+// How long to wait for explorer.exe during --startup before giving up and starting anyways
+const SHELL_TIMEOUT: time::Duration = time::Duration::from_secs(60);
+
+// Registers a scheduled task that launches us with --startup whenever the current user logs on. We
+// use an XML definition instead of plain schtasks flags because those can't turn off the default
+// 72 hour time limit or the "only start on AC power" condition.
+pub fn install_task(highest_privileges: bool, portable: bool) -> anyhow::Result<()> {
+    let exe_path = env::current_exe().context("could not get path to tacky-borders.exe")?;
+    let user = match (env::var("USERDOMAIN"), env::var("USERNAME")) {
+        (Ok(domain), Ok(name)) => format!("{domain}\\{name}"),
+        (_, Ok(name)) => name,
+        _ => return Err(anyhow!("could not get the current user name")),
+    };
+    let run_level = match highest_privileges {
+        true => "HighestAvailable",
+        false => "LeastPrivilege",
+    };
+    // portable.txt is picked up on its own, but --portable has to be passed along
+    let arguments = match portable {
+        true => "--startup --portable",
+        false => "--startup",
+    };
+
+    let task_xml = format!(
+        r#"<?xml version="1.0" encoding="UTF-16"?>
+<Task version="1.2" xmlns="http://schemas.microsoft.com/windows/2004/02/mit/task">
+  <Triggers>
+    <LogonTrigger>
+      <Enabled>true</Enabled>
+      <UserId>{user}</UserId>
+    </LogonTrigger>
+  </Triggers>
+  <Principals>
+    <Principal id="Author">
+      <UserId>{user}</UserId>
+      <LogonType>InteractiveToken</LogonType>
+      <RunLevel>{run_level}</RunLevel>
+    </Principal>
+  </Principals>
+  <Settings>
+    <MultipleInstancesPolicy>IgnoreNew</MultipleInstancesPolicy>
+    <DisallowStartIfOnBatteries>false</DisallowStartIfOnBatteries>
+    <StopIfGoingOnBatteries>false</StopIfGoingOnBatteries>
+    <ExecutionTimeLimit>PT0S</ExecutionTimeLimit>
+    <Priority>5</Priority>
+  </Settings>
+  <Actions Context="Author">
+    <Exec>
+      <Command>{command}</Command>
+      <Arguments>{arguments}</Arguments>
+    </Exec>
+  </Actions>
+</Task>
+"#,
+        user = escape_xml(&user),
+        command = escape_xml(&exe_path.to_string_lossy()),
+    );
+
+    // schtasks wants the XML file in UTF-16 with a BOM
+    let mut task_bytes = vec![0xFF, 0xFE];
+    task_bytes.extend(task_xml.encode_utf16().flat_map(|unit| unit.to_le_bytes()));
+
+    let xml_path = env::temp_dir().join("tacky-borders-task.xml");
+    fs::write(&xml_path, task_bytes).context("could not write scheduled task definition")?;
+
+    let result = run_schtasks(&[
+        "/Create",
+        "/TN",
+        TASK_NAME,
+        "/XML",
+        &xml_path.to_string_lossy(),
+        "/F",
+    ]);
+    let _ = fs::remove_file(&xml_path);
+
+    result
+}
+
+pub fn uninstall_task() -> anyhow::Result<()> {
+    run_schtasks(&["/Delete", "/TN", TASK_NAME, "/F"])
+}
+
+// When we're launched at logon, explorer.exe and DWM may still be starting up. Creating borders (or
+// the tray icon) before then tends to fail, so we wait for the taskbar to show up first. Windows
+// that were opened before the taskbar are still picked up by the EnumWindows call in start().
+pub fn wait_for_shell() {
+    let start = time::Instant::now();
+
+    while unsafe { FindWindowW(w!("Shell_TrayWnd"), PCWSTR::null()) }.is_err() {
+        if start.elapsed() >= SHELL_TIMEOUT {
+            warn!("timed out waiting for the taskbar; starting anyways");
+            return;
+        }
+        thread::sleep(time::Duration::from_millis(250));
+    }
+
+    debug!("found taskbar after {:?}", start.elapsed());
+}
+
+fn run_schtasks(args: &[&str]) -> anyhow::Result<()> {
+    let output = Command::new("schtasks")
+        .args(args)
+        .output()
+        .context("could not run schtasks")?;
+
+    match output.status.success() {
+        true => Ok(()),
+        false => Err(anyhow!(
+            "schtasks failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+    }
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
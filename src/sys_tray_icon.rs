@@ -1,11 +1,28 @@
 use anyhow::Context;
-use tray_icon::menu::{Menu, MenuEvent, MenuItem};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use tray_icon::menu::{CheckMenuItem, Menu, MenuEvent, MenuItem, PredefinedMenuItem, Submenu};
 use tray_icon::{Icon, TrayIcon, TrayIconBuilder};
-use windows::Win32::System::Threading::ExitProcess;
-use windows::Win32::UI::Accessibility::UnhookWinEvent;
+use windows::Win32::System::Threading::{ExitProcess, GetCurrentThreadId};
 
-use crate::border_config::Config;
-use crate::{reload_borders, EVENT_HOOK};
+use tacky_borders_core::animations::AnimationType;
+use tacky_borders_core::border_config::{Config, CONFIG};
+use tacky_borders_core::crash;
+use tacky_borders_core::notifications::{self, NotificationKind};
+use tacky_borders_core::picker;
+use tacky_borders_core::settings_window;
+use tacky_borders_core::state;
+use tacky_borders_core::themes;
+use tacky_borders_core::utils::get_window_info;
+
+use crate::updater;
+
+// Set when a menu item changed something the menu shows, so the main thread rebuilds it. Menu items
+// can't be sent to the event handler's thread, so it can't update them itself.
+static MENU_OUTDATED: AtomicBool = AtomicBool::new(false);
+
+// How many crash reports the menu knew about when it was last built
+static MENU_CRASH_REPORTS: AtomicUsize = AtomicUsize::new(0);
 
 pub fn create_tray_icon() -> anyhow::Result<TrayIcon> {
     let icon = match Icon::from_resource(1, Some((64, 64))) {
@@ -20,19 +37,9 @@ pub fn create_tray_icon() -> anyhow::Result<TrayIcon> {
         }
     };
 
-    // Include the application name and version number in the tray icon tooltip
-    let tooltip = format!("{}{}", "tacky-borders v", env!("CARGO_PKG_VERSION"));
-
-    let tray_menu = Menu::new();
-    tray_menu.append_items(&[
-        &MenuItem::with_id("0", "Show Config", true, None),
-        &MenuItem::with_id("1", "Reload", true, None),
-        &MenuItem::with_id("2", "Close", true, None),
-    ])?;
-
     let tray_icon = TrayIconBuilder::new()
-        .with_menu(Box::new(tray_menu))
-        .with_tooltip(tooltip)
+        .with_menu(Box::new(build_menu()?))
+        .with_tooltip(get_tooltip())
         .with_icon(icon)
         .build();
 
@@ -51,20 +58,262 @@ pub fn create_tray_icon() -> anyhow::Result<TrayIcon> {
         }
         // Reload
         "1" => {
-            Config::reload_config();
-            reload_borders();
+            tacky_borders_core::reload();
+            // New profiles might have been added
+            MENU_OUTDATED.store(true, Ordering::SeqCst);
+        }
+        "settings" => settings_window::show(mark_menu_outdated),
+        // Start Timer
+        "3" => {
+            tacky_borders_core::timer::start(None);
+        }
+        // Stop Timer
+        "4" => tacky_borders_core::timer::stop(),
+        "crashes" => {
+            if let Ok(dir) = crash::get_crash_dir() {
+                let _ = open::that(dir);
+            }
+            crash::mark_reports_seen();
+            MENU_OUTDATED.store(true, Ordering::SeqCst);
+        }
+        "update" => {
+            let Some(release) = updater::available_update() else {
+                return;
+            };
+            let self_update = CONFIG.lock().unwrap().global.self_update.unwrap_or(false);
+            if !self_update {
+                let _ = open::that(&release.page_url);
+                return;
+            }
+
+            // Downloading can take a while, so don't hold up the main thread's message loop
+            let main_thread = unsafe { GetCurrentThreadId() };
+            let _ = thread::spawn(move || {
+                if let Err(e) = updater::install_update_and_wake(&release, main_thread) {
+                    error!("could not update tacky-borders: {e:#}");
+                    notifications::notify(
+                        NotificationKind::Error,
+                        "Could not update tacky-borders",
+                        &format!("{e:#}"),
+                    );
+                }
+            });
+        }
+        // Pick Window
+        "pick" => {
+            // The picker waits for the click on its own thread, so the menu isn't held up
+            let _ = thread::spawn(pick_window);
         }
         // Close
-        "2" => unsafe {
-            if UnhookWinEvent(EVENT_HOOK.get()).as_bool() {
+        "2" => {
+            if tacky_borders_core::stop() {
                 debug!("exiting tacky-borders!");
-                ExitProcess(0);
+                unsafe { ExitProcess(0) };
             } else {
                 error!("could not unhook win event hook");
             }
-        },
-        _ => {}
+        }
+        id => {
+            if let Some(profile) = id.strip_prefix("profile:") {
+                let profile = (!profile.is_empty()).then(|| profile.to_string());
+                update_tray_choices(|choices| choices.profile = profile);
+            } else if let Some(theme) = id.strip_prefix("theme:") {
+                let theme = (!theme.is_empty()).then(|| theme.to_string());
+                update_tray_choices(|choices| choices.theme = theme);
+            } else if let Some(name) = id.strip_prefix("animation:") {
+                let Some(anim_type) = AnimationType::ALL
+                    .into_iter()
+                    .find(|anim_type| format!("{anim_type:?}") == name)
+                else {
+                    return;
+                };
+                update_tray_choices(|choices| {
+                    match choices.disabled_animations.contains(&anim_type) {
+                        true => choices.disabled_animations.retain(|t| *t != anim_type),
+                        false => choices.disabled_animations.push(anim_type),
+                    }
+                });
+            }
+        }
     }));
 
     tray_icon.map_err(anyhow::Error::new)
 }
+
+fn build_menu() -> anyhow::Result<Menu> {
+    let choices = state::tray_choices();
+
+    // "profile:" on its own means config.yaml
+    let default_profile = CheckMenuItem::with_id(
+        "profile:",
+        "Default (config.yaml)",
+        true,
+        choices.profile.is_none(),
+        None,
+    );
+    let profile_items: Vec<CheckMenuItem> = Config::list_profiles()
+        .into_iter()
+        .map(|profile| {
+            let checked = choices.profile.as_ref() == Some(&profile);
+            CheckMenuItem::with_id(format!("profile:{profile}"), &profile, true, checked, None)
+        })
+        .collect();
+    let profiles_menu = Submenu::with_id("profiles", "Profile", true);
+    profiles_menu.append(&default_profile)?;
+    for item in profile_items.iter() {
+        profiles_menu.append(item)?;
+    }
+
+    // "theme:" on its own means whatever the config says
+    let default_theme = CheckMenuItem::with_id(
+        "theme:",
+        "Default (from config)",
+        true,
+        choices.theme.is_none(),
+        None,
+    );
+    let theme_items: Vec<CheckMenuItem> = themes::list_themes()
+        .into_iter()
+        .map(|theme| {
+            let checked = choices.theme.as_ref() == Some(&theme);
+            CheckMenuItem::with_id(format!("theme:{theme}"), &theme, true, checked, None)
+        })
+        .collect();
+    let themes_menu = Submenu::with_id("themes", "Theme", true);
+    themes_menu.append(&default_theme)?;
+    for item in theme_items.iter() {
+        themes_menu.append(item)?;
+    }
+
+    let animations_menu = Submenu::with_id("animations", "Animations", true);
+    for anim_type in AnimationType::ALL {
+        let enabled = !choices.disabled_animations.contains(&anim_type);
+        animations_menu.append(&CheckMenuItem::with_id(
+            format!("animation:{anim_type:?}"),
+            format!("{anim_type:?}"),
+            true,
+            enabled,
+            None,
+        ))?;
+    }
+
+    let tray_menu = Menu::new();
+    MENU_CRASH_REPORTS.store(crash::report_count(), Ordering::SeqCst);
+    if crash::has_new_reports() {
+        tray_menu.append_items(&[
+            &MenuItem::with_id("crashes", "Open Crash Reports", true, None),
+            &PredefinedMenuItem::separator(),
+        ])?;
+    }
+    if let Some(release) = updater::available_update() {
+        tray_menu.append_items(&[
+            &MenuItem::with_id(
+                "update",
+                format!("Update to v{}", release.version),
+                true,
+                None,
+            ),
+            &PredefinedMenuItem::separator(),
+        ])?;
+    }
+    tray_menu.append_items(&[
+        &MenuItem::with_id("0", "Show Config", true, None),
+        &MenuItem::with_id("1", "Reload", true, None),
+        &MenuItem::with_id("settings", "Settings...", true, None),
+        &PredefinedMenuItem::separator(),
+        &profiles_menu,
+        &themes_menu,
+        &animations_menu,
+        &PredefinedMenuItem::separator(),
+        &MenuItem::with_id("3", "Start Timer", true, None),
+        &MenuItem::with_id("4", "Stop Timer", true, None),
+        &PredefinedMenuItem::separator(),
+        &MenuItem::with_id("pick", "Pick Window", true, None),
+        &MenuItem::with_id("2", "Close", true, None),
+    ])?;
+
+    Ok(tray_menu)
+}
+
+// Copy a rule for whichever window gets clicked next, and say what it was
+fn pick_window() {
+    let hwnd = match picker::pick_window() {
+        Ok(Some(hwnd)) => hwnd,
+        Ok(None) => return,
+        Err(e) => {
+            error!("could not pick a window: {e:#}");
+            return;
+        }
+    };
+
+    let window = get_window_info(hwnd);
+    let copied = match picker::copy_to_clipboard(&picker::rule_snippet(&window)) {
+        Ok(_) => "A window rule for it was copied to the clipboard.",
+        Err(e) => {
+            error!("could not copy window rule: {e:#}");
+            "The window rule could not be copied (see log)."
+        }
+    };
+
+    notifications::notify(
+        NotificationKind::Result,
+        &format!("{} | {}", window.process, window.class),
+        &format!("\"{}\"\n{copied}", window.title),
+    );
+}
+
+// Apply a change to the profile, theme, or animation toggles, and recreate the borders to pick it up
+fn update_tray_choices(f: impl FnOnce(&mut state::TrayChoices)) {
+    let save = CONFIG
+        .lock()
+        .unwrap()
+        .global
+        .remember_tray_choices
+        .unwrap_or(true);
+
+    let mut choices = state::tray_choices();
+    f(&mut choices);
+    state::set_tray_choices(choices, save);
+
+    tacky_borders_core::reload();
+    MENU_OUTDATED.store(true, Ordering::SeqCst);
+}
+
+pub fn mark_menu_outdated() {
+    MENU_OUTDATED.store(true, Ordering::SeqCst);
+}
+
+// Rebuild the menu if a menu item changed what it should show, or there's a new crash report. This has to be called from the
+// thread that created the tray icon.
+pub fn update_menu(tray_icon: &TrayIcon) {
+    let new_crash_report = crash::report_count() != MENU_CRASH_REPORTS.load(Ordering::SeqCst);
+    if !MENU_OUTDATED.swap(false, Ordering::SeqCst) && !new_crash_report {
+        return;
+    }
+
+    match build_menu() {
+        Ok(menu) => tray_icon.set_menu(Some(Box::new(menu))),
+        Err(e) => error!("could not rebuild tray menu: {e}"),
+    }
+}
+
+// Include the application name and version number in the tray icon tooltip, along with a warning if
+// any borders had to fall back to safe mode or couldn't be created at all
+fn get_tooltip() -> String {
+    let mut tooltip = format!("{}{}", "tacky-borders v", env!("CARGO_PKG_VERSION"));
+
+    if let count @ 1.. = tacky_borders_core::safe_mode_count() {
+        tooltip += &format!("\n{count} border(s) in safe mode (see log)");
+    }
+    if let count @ 1.. = tacky_borders_core::failed_border_count() {
+        tooltip += &format!("\n{count} window(s) could not get a border (see log)");
+    }
+
+    tooltip
+}
+
+pub fn update_tooltip(tray_icon: &TrayIcon) {
+    if let Err(e) = tray_icon.set_tooltip(Some(get_tooltip())) {
+        error!("could not update tray icon tooltip: {e}");
+    }
+}
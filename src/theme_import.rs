@@ -0,0 +1,331 @@
+use anyhow::{anyhow, Context};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::Path;
+
+use tacky_borders_core::colors::ColorConfig;
+use tacky_borders_core::themes::{self, Theme};
+
+// Keys to take the active color from, best first. VS Code's focusBorder is literally "the color of
+// focused things", but plenty of themes leave it out.
+const VSCODE_ACTIVE_KEYS: &[&str] = &[
+    "focusBorder",
+    "tab.activeBorderTop",
+    "tab.activeBorder",
+    "activityBar.activeBorder",
+    "button.background",
+    "textLink.foreground",
+    "editorCursor.foreground",
+];
+const VSCODE_INACTIVE_KEYS: &[&str] = &[
+    "editorGroup.border",
+    "panel.border",
+    "sideBar.border",
+    "tab.border",
+    "editorWidget.border",
+];
+const JETBRAINS_ACTIVE_KEYS: &[&str] = &[
+    "Component.focusColor",
+    "*.focusColor",
+    "*.accentColor",
+    "ActionButton.focusedBorderColor",
+];
+const JETBRAINS_INACTIVE_KEYS: &[&str] = &[
+    "Component.borderColor",
+    "*.borderColor",
+    "Borders.color",
+    "Borders.ContrastBorderColor",
+];
+const TERMINAL_ACTIVE_KEYS: &[&str] =
+    &["brightBlue", "blue", "purple", "cursorColor", "foreground"];
+const TERMINAL_INACTIVE_KEYS: &[&str] = &["brightBlack", "selectionBackground", "black"];
+
+// Read a VS Code theme, JetBrains .theme.json, or Windows Terminal color scheme, and pick out a pair
+// of colors for a tacky-borders theme. Returns the theme along with a name for it.
+pub fn import(path: &Path) -> anyhow::Result<(String, Theme)> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("could not read {}", path.display()))?;
+    let json: Value = serde_json::from_str(&strip_jsonc(&contents))
+        .with_context(|| format!("could not parse {} as JSON", path.display()))?;
+
+    // Windows Terminal's settings.json keeps its schemes in a list, so just take the first one
+    let json = match json.get("schemes").and_then(Value::as_array) {
+        Some(schemes) => schemes
+            .first()
+            .cloned()
+            .ok_or_else(|| anyhow!("'schemes' is empty"))?,
+        None => json,
+    };
+    let Some(object) = json.as_object() else {
+        return Err(anyhow!("expected a JSON object"));
+    };
+
+    let (kind, active, inactive) = if let Some(ui) = object.get("ui").and_then(Value::as_object) {
+        // JetBrains colors can be names from the top-level 'colors' section instead of hex codes
+        let mut flattened = Map::new();
+        flatten(ui, "", &mut flattened);
+        let named_colors = object.get("colors").and_then(Value::as_object);
+        let resolve = |color: &str| {
+            named_colors
+                .and_then(|colors| colors.get(color))
+                .and_then(Value::as_str)
+                .unwrap_or(color)
+                .to_string()
+        };
+        (
+            "JetBrains theme",
+            find_color(&flattened, JETBRAINS_ACTIVE_KEYS, resolve),
+            find_color(&flattened, JETBRAINS_INACTIVE_KEYS, resolve),
+        )
+    } else if let Some(colors) = object.get("colors").and_then(Value::as_object) {
+        (
+            "VS Code theme",
+            find_color(colors, VSCODE_ACTIVE_KEYS, str::to_string),
+            find_color(colors, VSCODE_INACTIVE_KEYS, str::to_string),
+        )
+    } else {
+        (
+            "terminal color scheme",
+            find_color(object, TERMINAL_ACTIVE_KEYS, str::to_string),
+            find_color(object, TERMINAL_INACTIVE_KEYS, str::to_string),
+        )
+    };
+
+    let Some(active) = active else {
+        return Err(anyhow!(
+            "could not find an accent or focus color in this {kind}"
+        ));
+    };
+    // A see-through version of the accent still looks like it belongs with the theme
+    let inactive = inactive.unwrap_or_else(|| match active.len() {
+        7 => format!("{active}40"),
+        _ => active.clone(),
+    });
+
+    let name = object
+        .get("name")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .or_else(|| Some(path.file_stem()?.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| "imported".to_string());
+
+    let (active_color, inactive_color) = (
+        ColorConfig::SolidConfig(active),
+        ColorConfig::SolidConfig(inactive),
+    );
+    for color in [&active_color, &inactive_color] {
+        color
+            .validate()
+            .with_context(|| format!("could not use the colors in this {kind}"))?;
+    }
+
+    let theme = Theme {
+        description: Some(format!(
+            "Imported from the {kind} {}",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        )),
+        active_color: Some(active_color),
+        inactive_color: Some(inactive_color),
+        ..Default::default()
+    };
+
+    Ok((themes::sanitize_name(&name), theme))
+}
+
+// Turn {"Component": {"focusColor": ...}} into {"Component.focusColor": ...}
+fn flatten(object: &Map<String, Value>, prefix: &str, flattened: &mut Map<String, Value>) {
+    for (key, value) in object.iter() {
+        let key = match prefix.is_empty() {
+            true => key.clone(),
+            false => format!("{prefix}.{key}"),
+        };
+        match value.as_object() {
+            Some(child) => flatten(child, &key, flattened),
+            None => {
+                flattened.insert(key, value.clone());
+            }
+        }
+    }
+}
+
+// The first of the keys with a color we can use. Themes are full of colors in formats we don't
+// know (or names that don't go anywhere), which are skipped.
+fn find_color(
+    colors: &Map<String, Value>,
+    keys: &[&str],
+    resolve: impl Fn(&str) -> String,
+) -> Option<String> {
+    keys.iter()
+        .filter_map(|key| colors.get(*key).and_then(Value::as_str))
+        .map(|color| resolve(color.trim()).trim().to_lowercase())
+        .find(|color| ColorConfig::SolidConfig(color.clone()).validate().is_ok())
+}
+
+// VS Code themes are usually JSON with comments and trailing commas, which serde_json won't take
+fn strip_jsonc(contents: &str) -> String {
+    let mut stripped = String::with_capacity(contents.len());
+    let mut chars = contents.chars().peekable();
+    let mut in_string = false;
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            stripped.push(c);
+            match c {
+                '\\' => stripped.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                stripped.push(c);
+            }
+            ('/', Some('/')) => while chars.next_if(|&c| c != '\n').is_some() {},
+            ('/', Some('*')) => {
+                chars.next();
+                let mut last = '\0';
+                for c in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+            }
+            _ => stripped.push(c),
+        }
+    }
+
+    // Drop commas that are only followed by whitespace and a closing bracket
+    let mut result = String::with_capacity(stripped.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in stripped.char_indices() {
+        if in_string {
+            match (escaped, c) {
+                (false, '\\') => escaped = true,
+                (false, '"') => in_string = false,
+                _ => escaped = false,
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c == ',' {
+            let next = stripped[i + 1..].trim_start().chars().next();
+            if matches!(next, Some('}' | ']')) {
+                continue;
+            }
+        }
+        result.push(c);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_jsonc_table() {
+        let cases = [
+            ("{\"a\": 1}", "{\"a\": 1}"),
+            ("{\"a\": 1, // comment\n}", "{\"a\": 1 \n}"),
+            ("{/* block */\"a\": 1}", "{\"a\": 1}"),
+            ("{\"a\": [1, 2, ],}", "{\"a\": [1, 2 ]}"),
+            // Comment markers and commas inside strings stay put
+            ("{\"url\": \"http://x\"}", "{\"url\": \"http://x\"}"),
+            ("{\"a\": \"/* no */\"}", "{\"a\": \"/* no */\"}"),
+            ("{\"a\": \",}\"}", "{\"a\": \",}\"}"),
+            (
+                "{\"a\": \"say \\\"hi\\\" // there\"}",
+                "{\"a\": \"say \\\"hi\\\" // there\"}",
+            ),
+            ("{\"a\": 1 /* unterminated", "{\"a\": 1 "),
+        ];
+
+        for (jsonc, expected) in cases {
+            assert_eq!(strip_jsonc(jsonc), expected, "{jsonc:?}");
+        }
+    }
+
+    #[test]
+    fn strip_jsonc_makes_a_vs_code_theme_parse() {
+        let jsonc = r##"{
+            // The name
+            "name": "Test",
+            "colors": {
+                "focusBorder": "#ff0000", /* the accent */
+            },
+        }"##;
+
+        let json: Value = serde_json::from_str(&strip_jsonc(jsonc)).unwrap();
+        assert_eq!(json["colors"]["focusBorder"], "#ff0000");
+    }
+
+    #[test]
+    fn flatten_table() {
+        let json: Value = serde_json::from_str(
+            r##"{
+                "Component": { "focusColor": "#111111", "arc": 4 },
+                "Tree": { "Selection": { "background": "#222222" } },
+                "*": { "accentColor": "#333333" },
+                "empty": {},
+                "top": "#444444"
+            }"##,
+        )
+        .unwrap();
+
+        let mut flattened = Map::new();
+        flatten(json.as_object().unwrap(), "", &mut flattened);
+
+        let cases = [
+            ("Component.focusColor", Some("#111111")),
+            ("Tree.Selection.background", Some("#222222")),
+            ("*.accentColor", Some("#333333")),
+            ("top", Some("#444444")),
+            ("Component", None),
+            ("empty", None),
+        ];
+        for (key, expected) in cases {
+            assert_eq!(
+                flattened.get(key).and_then(Value::as_str),
+                expected,
+                "{key}"
+            );
+        }
+        assert_eq!(flattened["Component.arc"], 4);
+        assert_eq!(flattened.len(), 5);
+    }
+
+    #[test]
+    fn find_color_skips_colors_it_cant_use() {
+        let colors: Value = serde_json::from_str(
+            r##"{
+                "focusBorder": "",
+                "tab.activeBorderTop": "var(--accent)",
+                "tab.activeBorder": " #FF0000 ",
+                "button.background": "#00ff00",
+                "named": "accentBlue",
+                "accentBlue": "#0000ff"
+            }"##,
+        )
+        .unwrap();
+        let colors = colors.as_object().unwrap();
+
+        assert_eq!(
+            find_color(colors, VSCODE_ACTIVE_KEYS, str::to_string).as_deref(),
+            Some("#ff0000")
+        );
+        assert_eq!(find_color(colors, &["named"], str::to_string), None);
+        let resolve = |name: &str| match colors.get(name).and_then(Value::as_str) {
+            Some(color) => color.to_string(),
+            None => name.to_string(),
+        };
+        assert_eq!(
+            find_color(colors, &["named"], resolve).as_deref(),
+            Some("#0000ff")
+        );
+    }
+}
@@ -0,0 +1,337 @@
+use anyhow::{anyhow, Context};
+use semver::Version;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::ffi::c_void;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{LazyLock, Mutex};
+use std::thread;
+use std::time::Duration;
+use windows::core::{HSTRING, PCWSTR};
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+use windows::Win32::Security::WinTrust::{
+    WTHelperGetProvCertFromChain, WTHelperGetProvSignerFromChain, WTHelperProvDataFromStateData,
+    WinVerifyTrust, WINTRUST_ACTION_GENERIC_VERIFY_V2, WINTRUST_DATA, WINTRUST_DATA_0,
+    WINTRUST_FILE_INFO, WTD_CHOICE_FILE, WTD_REVOKE_NONE, WTD_STATEACTION_CLOSE,
+    WTD_STATEACTION_VERIFY, WTD_UI_NONE,
+};
+use windows::Win32::System::Threading::ExitProcess;
+use windows::Win32::UI::WindowsAndMessaging::{PostThreadMessageW, WM_NULL};
+
+use tacky_borders_core::border_config::CONFIG;
+use tacky_borders_core::notifications::{self, NotificationKind};
+
+use crate::sys_tray_icon;
+
+const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/kleineluka/tacky-borders/releases/latest";
+const USER_AGENT: &str = concat!("tacky-borders/", env!("CARGO_PKG_VERSION"));
+
+// Without these, a server that stops answering would hang the update check (or the tray menu's
+// install) forever
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Far more than the exe will ever be, but it stops a bad server from filling up our memory
+const MAX_DOWNLOAD_BYTES: u64 = 64 * 1024 * 1024;
+
+static AGENT: LazyLock<ureq::Agent> = LazyLock::new(|| {
+    ureq::AgentBuilder::new()
+        .user_agent(USER_AGENT)
+        .timeout_connect(CONNECT_TIMEOUT)
+        .timeout_read(READ_TIMEOUT)
+        .build()
+});
+
+#[derive(Debug, Clone)]
+pub struct Release {
+    pub version: Version,
+    pub page_url: String,
+    exe_url: Option<String>,
+    checksum_url: Option<String>,
+}
+
+// The newer release found by the background check, if any
+static AVAILABLE_UPDATE: Mutex<Option<Release>> = Mutex::new(None);
+
+// The exe we just installed, which the main thread restarts into. Only the main thread can unhook
+// the event hook, so the thread that installs the update can't restart us itself.
+static INSTALLED_EXE: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+// Ask GitHub for the latest release, returning it if it's newer than this build
+pub fn check_for_update() -> anyhow::Result<Option<Release>> {
+    let response = AGENT
+        .get(LATEST_RELEASE_URL)
+        .set("Accept", "application/vnd.github+json")
+        .call()
+        .context("could not reach GitHub")?;
+    let release: Value = serde_json::from_reader(response.into_reader())
+        .context("could not parse the latest release")?;
+
+    let tag = release["tag_name"]
+        .as_str()
+        .ok_or_else(|| anyhow!("the latest release has no tag"))?;
+    let version = Version::parse(tag.trim_start_matches('v'))
+        .with_context(|| format!("could not parse release version '{tag}'"))?;
+    let current_version = Version::parse(env!("CARGO_PKG_VERSION"))?;
+
+    if version <= current_version {
+        return Ok(None);
+    }
+
+    let assets = release["assets"].as_array().cloned().unwrap_or_default();
+    let asset_url = |name: &str| {
+        assets
+            .iter()
+            .find(|asset| asset["name"].as_str() == Some(name))
+            .and_then(|asset| asset["browser_download_url"].as_str())
+            .map(str::to_string)
+    };
+
+    // The checksum for 'tacky-borders.exe' is expected in 'tacky-borders.exe.sha256'
+    let exe_name = assets
+        .iter()
+        .filter_map(|asset| asset["name"].as_str())
+        .find(|name| name.ends_with(".exe"));
+
+    Ok(Some(Release {
+        version,
+        page_url: release["html_url"].as_str().unwrap_or_default().to_string(),
+        exe_url: exe_name.and_then(asset_url),
+        checksum_url: exe_name.and_then(|name| asset_url(&format!("{name}.sha256"))),
+    }))
+}
+
+// Check for updates in the background if the config asks us to. The tray menu picks up the result.
+pub fn start_background_check() {
+    let enabled = CONFIG
+        .lock()
+        .unwrap()
+        .global
+        .check_for_updates
+        .unwrap_or(false);
+    if !enabled {
+        return;
+    }
+
+    let _ = thread::spawn(|| match check_for_update() {
+        Ok(Some(release)) => {
+            info!("tacky-borders v{} is available", release.version);
+            notifications::notify(
+                NotificationKind::Info,
+                "Update available",
+                &format!("tacky-borders v{} is out", release.version),
+            );
+            *AVAILABLE_UPDATE.lock().unwrap() = Some(release);
+            sys_tray_icon::mark_menu_outdated();
+        }
+        Ok(None) => debug!("tacky-borders is up to date"),
+        // Probably just offline, which isn't worth a popup
+        Err(e) => warn!("could not check for updates: {e:#}"),
+    });
+}
+
+pub fn available_update() -> Option<Release> {
+    AVAILABLE_UPDATE.lock().unwrap().clone()
+}
+
+// Download the release's exe, verify it, and swap it in for the one that's running. Returns the path
+// of the new exe.
+pub fn install_update(release: &Release) -> anyhow::Result<PathBuf> {
+    let (Some(exe_url), Some(checksum_url)) = (&release.exe_url, &release.checksum_url) else {
+        return Err(anyhow!(
+            "v{} has no exe or checksum to download; get it from {}",
+            release.version,
+            release.page_url
+        ));
+    };
+
+    let exe_bytes = download(exe_url).context("could not download the update")?;
+    let checksum_text = String::from_utf8(download(checksum_url)?)
+        .context("could not read the update's checksum")?;
+
+    // The checksum comes from the same release as the exe, so it only catches a broken download
+    // (the signature check below is what catches a tampered one). It can be in the 'sha256sum'
+    // format, so only the first word is the hash.
+    let expected_hash = checksum_text
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    let actual_hash = format!("{:x}", Sha256::digest(&exe_bytes));
+    if actual_hash != expected_hash {
+        return Err(anyhow!(
+            "the downloaded exe's hash ({actual_hash}) does not match its checksum \
+             ({expected_hash})"
+        ));
+    }
+
+    let current_exe = std::env::current_exe().context("could not find the current exe")?;
+    let new_exe = current_exe.with_extension("exe.new");
+    let old_exe = current_exe.with_extension("exe.old");
+
+    fs::write(&new_exe, &exe_bytes)
+        .with_context(|| format!("could not write {}", new_exe.display()))?;
+
+    // Anyone who can change the release can change its checksum too, so updates are only ever
+    // installed if they're signed by whoever signed the exe that's running. Any code signing cert
+    // would pass the signature check on its own. Unsigned releases (and updates to unsigned builds)
+    // have to be installed by hand.
+    let current_signer = get_signer_thumbprint(&current_exe);
+    let new_signer = get_signer_thumbprint(&new_exe);
+    if current_signer.is_none() || new_signer != current_signer {
+        let _ = fs::remove_file(&new_exe);
+        let reason = match (current_signer.is_some(), new_signer.is_some()) {
+            (false, _) => "this build is not signed",
+            (true, false) => "the downloaded exe is not signed",
+            (true, true) => "the downloaded exe is signed by someone else",
+        };
+        return Err(anyhow!(
+            "{reason}; get v{} from {} instead",
+            release.version,
+            release.page_url
+        ));
+    }
+
+    // Windows won't let us overwrite a running exe, but it will let us rename it
+    let _ = fs::remove_file(&old_exe);
+    fs::rename(&current_exe, &old_exe).context("could not move the current exe out of the way")?;
+    if let Err(e) = fs::rename(&new_exe, &current_exe) {
+        let _ = fs::rename(&old_exe, &current_exe);
+        return Err(e).context("could not move the new exe into place");
+    }
+
+    info!("updated to tacky-borders v{}", release.version);
+
+    Ok(current_exe)
+}
+
+// Install the update from the tray menu, then wake up the main thread so it restarts into it
+pub fn install_update_and_wake(release: &Release, main_thread: u32) -> anyhow::Result<()> {
+    let exe = install_update(release)?;
+    *INSTALLED_EXE.lock().unwrap() = Some(exe);
+
+    unsafe { PostThreadMessageW(main_thread, WM_NULL, WPARAM(0), LPARAM(0)) }
+        .context("could not wake up the main thread to restart")
+}
+
+// Called from the main thread's message loop
+pub fn restart_if_installed() {
+    let Some(exe) = INSTALLED_EXE.lock().unwrap().take() else {
+        return;
+    };
+
+    if !tacky_borders_core::stop() {
+        error!("could not unhook win event hook before restarting");
+    }
+
+    // --startup would make the new instance wait for the shell, which is already up
+    let args = std::env::args().skip(1).filter(|arg| arg != "--startup");
+    match Command::new(&exe).args(args).spawn() {
+        Ok(_) => unsafe { ExitProcess(0) },
+        Err(e) => error!("could not start {}: {e}", exe.display()),
+    }
+}
+
+// The exe we replaced can only be deleted once it stops running, so clean it up on the next launch
+pub fn remove_old_exe() {
+    if let Ok(current_exe) = std::env::current_exe() {
+        let _ = fs::remove_file(current_exe.with_extension("exe.old"));
+    }
+}
+
+fn download(url: &str) -> anyhow::Result<Vec<u8>> {
+    let response = AGENT
+        .get(url)
+        .call()
+        .with_context(|| format!("could not download {url}"))?;
+
+    // Read one byte past the limit so we can tell a file that's exactly at it from a bigger one
+    let mut bytes = Vec::new();
+    response
+        .into_reader()
+        .take(MAX_DOWNLOAD_BYTES + 1)
+        .read_to_end(&mut bytes)
+        .with_context(|| format!("could not download {url}"))?;
+    if bytes.len() as u64 > MAX_DOWNLOAD_BYTES {
+        return Err(anyhow!(
+            "{url} is bigger than {} MB",
+            MAX_DOWNLOAD_BYTES / 1024 / 1024
+        ));
+    }
+
+    Ok(bytes)
+}
+
+// Check the exe's Authenticode signature, returning the SHA-256 of the cert that signed it if it's
+// valid
+fn get_signer_thumbprint(path: &Path) -> Option<Vec<u8>> {
+    let path = HSTRING::from(path);
+
+    let mut file_info = WINTRUST_FILE_INFO {
+        cbStruct: size_of::<WINTRUST_FILE_INFO>() as u32,
+        pcwszFilePath: PCWSTR(path.as_ptr()),
+        ..Default::default()
+    };
+    let mut trust_data = WINTRUST_DATA {
+        cbStruct: size_of::<WINTRUST_DATA>() as u32,
+        dwUIChoice: WTD_UI_NONE,
+        fdwRevocationChecks: WTD_REVOKE_NONE,
+        dwUnionChoice: WTD_CHOICE_FILE,
+        Anonymous: WINTRUST_DATA_0 {
+            pFile: &mut file_info,
+        },
+        dwStateAction: WTD_STATEACTION_VERIFY,
+        ..Default::default()
+    };
+    let mut action = WINTRUST_ACTION_GENERIC_VERIFY_V2;
+
+    unsafe {
+        let result = WinVerifyTrust(
+            HWND::default(),
+            &mut action,
+            &mut trust_data as *mut _ as *mut c_void,
+        );
+
+        // The verify call leaves the signer's cert chain in its state data until it's closed. The
+        // first cert in the chain is the one that signed the exe.
+        let thumbprint = match result == 0 {
+            true => {
+                let provider_data = WTHelperProvDataFromStateData(trust_data.hWVTStateData);
+                let signer = match provider_data.is_null() {
+                    true => std::ptr::null_mut(),
+                    false => WTHelperGetProvSignerFromChain(provider_data, 0, false, 0),
+                };
+                let cert = match signer.is_null() {
+                    true => std::ptr::null_mut(),
+                    false => WTHelperGetProvCertFromChain(signer, 0),
+                };
+                match cert.is_null() || (*cert).pCert.is_null() {
+                    true => None,
+                    false => {
+                        let context = &*(*cert).pCert;
+                        let encoded = std::slice::from_raw_parts(
+                            context.pbCertEncoded,
+                            context.cbCertEncoded as usize,
+                        );
+                        Some(Sha256::digest(encoded).to_vec())
+                    }
+                }
+            }
+            false => None,
+        };
+
+        // This frees whatever the verify call allocated
+        trust_data.dwStateAction = WTD_STATEACTION_CLOSE;
+        WinVerifyTrust(
+            HWND::default(),
+            &mut action,
+            &mut trust_data as *mut _ as *mut c_void,
+        );
+
+        thumbprint
+    }
+}
@@ -0,0 +1,214 @@
+//! A high-visibility mode for when the focused window is hard to keep track of: very thick,
+//! high-contrast borders that can blink whenever focus moves.
+//!
+//! The mode replaces the width and colors from the config (and window rules) for every border. It
+//! can be turned on from the config, the `accessibility` hotkey, or the `accessibility`
+//! [`ipc`](crate::ipc) command.
+//!
+//! With `follow_system`, the mode takes its width from Windows' focus rectangle thickness and its
+//! colors from the high contrast theme, and is on whenever high contrast is, like a nicer looking
+//! version of Windows' own focus indicator.
+
+use serde::{Deserialize, Serialize};
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use windows::Win32::Graphics::Gdi::{GetSysColor, COLOR_GRAYTEXT, COLOR_HIGHLIGHT};
+use windows::Win32::UI::Accessibility::{HCF_HIGHCONTRASTON, HIGHCONTRASTW};
+use windows::Win32::UI::WindowsAndMessaging::{
+    SystemParametersInfoW, SPI_GETFOCUSBORDERHEIGHT, SPI_GETFOCUSBORDERWIDTH, SPI_GETHIGHCONTRAST,
+    SPI_SETFOCUSBORDERHEIGHT, SPI_SETFOCUSBORDERWIDTH, SPI_SETHIGHCONTRAST,
+    SYSTEM_PARAMETERS_INFO_ACTION, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+};
+
+use crate::colors::ColorConfig;
+
+// Whether the mode is on right now. This starts out as the config's 'enabled' option every time the
+// config is loaded, and the hotkey and ipc command flip it from there.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static FOLLOW_SYSTEM: AtomicBool = AtomicBool::new(false);
+
+// Windows' focus rectangle is 1 pixel thick by default and 5 at most, which is too thin for a border
+// on its own, so each pixel of it makes the border this much wider
+const FOCUS_BORDER_SCALE: u32 = 4;
+
+// The system settings the borders were last created with, to tell whether a WM_SETTINGCHANGE
+// actually changed anything
+static SYSTEM_STYLE: Mutex<Option<SystemStyle>> = Mutex::new(None);
+
+/// The `accessibility` section of the config.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccessibilityConfig {
+    /// Whether the mode is on when tacky-borders starts.
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_border_width")]
+    pub border_width: f32,
+    #[serde(default = "default_active_color")]
+    pub active_color: ColorConfig,
+    #[serde(default = "default_inactive_color")]
+    pub inactive_color: ColorConfig,
+    /// Blink the newly focused border whenever focus moves.
+    #[serde(default = "default_blink")]
+    pub blink: bool,
+    /// Take the width and colors from Windows' accessibility settings, and turn the mode on while
+    /// high contrast is on.
+    #[serde(default)]
+    pub follow_system: bool,
+}
+
+impl Default for AccessibilityConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            border_width: default_border_width(),
+            active_color: default_active_color(),
+            inactive_color: default_inactive_color(),
+            blink: default_blink(),
+            follow_system: false,
+        }
+    }
+}
+
+fn default_border_width() -> f32 {
+    8.0
+}
+
+// Yellow on black is about as high-contrast as it gets, against both light and dark windows
+fn default_active_color() -> ColorConfig {
+    ColorConfig::SolidConfig("#ffff00".to_string())
+}
+
+fn default_inactive_color() -> ColorConfig {
+    ColorConfig::SolidConfig("#000000".to_string())
+}
+
+fn default_blink() -> bool {
+    true
+}
+
+/// Whether the mode is on, either because it was turned on or because it follows Windows' high
+/// contrast setting.
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+        || (FOLLOW_SYSTEM.load(Ordering::SeqCst) && SystemStyle::read().high_contrast)
+}
+
+// Called whenever the config is loaded. This doesn't touch any borders, since they get recreated
+// after a reload anyway.
+pub(crate) fn reset(config: &AccessibilityConfig) {
+    ENABLED.store(config.enabled, Ordering::SeqCst);
+    FOLLOW_SYSTEM.store(config.follow_system, Ordering::SeqCst);
+    *SYSTEM_STYLE.lock().unwrap() = config.follow_system.then(SystemStyle::read);
+}
+
+// The width and colors every border gets while the mode is on
+pub(crate) fn style(config: &AccessibilityConfig) -> (f32, ColorConfig, ColorConfig) {
+    if !config.follow_system {
+        return (
+            config.border_width,
+            config.active_color.clone(),
+            config.inactive_color.clone(),
+        );
+    }
+
+    let system = SystemStyle::read();
+    let border_width = (system.focus_border * FOCUS_BORDER_SCALE) as f32;
+    match system.colors {
+        Some((active, inactive)) => (border_width, hex_color(active), hex_color(inactive)),
+        None => (
+            border_width,
+            config.active_color.clone(),
+            config.inactive_color.clone(),
+        ),
+    }
+}
+
+// Whether a WM_SETTINGCHANGE is about one of the settings that follow_system uses
+pub(crate) fn is_system_setting(action: u32) -> bool {
+    FOLLOW_SYSTEM.load(Ordering::SeqCst)
+        && [
+            SPI_SETHIGHCONTRAST,
+            SPI_SETFOCUSBORDERWIDTH,
+            SPI_SETFOCUSBORDERHEIGHT,
+        ]
+        .contains(&SYSTEM_PARAMETERS_INFO_ACTION(action))
+}
+
+// Every border gets the WM_SETTINGCHANGE, so only the first one to see a change recreates them
+pub(crate) fn system_settings_changed() {
+    let style = SystemStyle::read();
+    let changed = SYSTEM_STYLE.lock().unwrap().replace(style.clone()) != Some(style);
+    if changed {
+        info!("windows accessibility settings changed; recreating borders");
+        crate::reload_borders();
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct SystemStyle {
+    high_contrast: bool,
+    // The focus rectangle's thickness in pixels, whichever side is thicker
+    focus_border: u32,
+    // The high contrast theme's highlight and disabled text colors, as COLORREFs
+    colors: Option<(u32, u32)>,
+}
+
+impl SystemStyle {
+    fn read() -> Self {
+        unsafe {
+            let mut high_contrast = HIGHCONTRASTW {
+                cbSize: size_of::<HIGHCONTRASTW>() as u32,
+                ..Default::default()
+            };
+            let _ = SystemParametersInfoW(
+                SPI_GETHIGHCONTRAST,
+                high_contrast.cbSize,
+                Some(&mut high_contrast as *mut _ as *mut c_void),
+                SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+            );
+            let high_contrast = high_contrast.dwFlags.0 & HCF_HIGHCONTRASTON.0 != 0;
+
+            let mut focus_border = 1;
+            for action in [SPI_GETFOCUSBORDERWIDTH, SPI_GETFOCUSBORDERHEIGHT] {
+                let mut size: u32 = 1;
+                let _ = SystemParametersInfoW(
+                    action,
+                    0,
+                    Some(&mut size as *mut _ as *mut c_void),
+                    SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+                );
+                focus_border = focus_border.max(size);
+            }
+
+            Self {
+                high_contrast,
+                focus_border,
+                colors: high_contrast
+                    .then(|| (GetSysColor(COLOR_HIGHLIGHT), GetSysColor(COLOR_GRAYTEXT))),
+            }
+        }
+    }
+}
+
+// COLORREFs are 0x00bbggrr
+fn hex_color(colorref: u32) -> ColorConfig {
+    let [red, green, blue, _] = colorref.to_le_bytes();
+    ColorConfig::SolidConfig(format!("#{red:02x}{green:02x}{blue:02x}"))
+}
+
+/// Turns the mode on or off, recreating every border with the new style.
+pub fn set_enabled(enabled: bool) {
+    if ENABLED.swap(enabled, Ordering::SeqCst) != enabled {
+        info!(
+            "turned {} accessibility mode",
+            if enabled { "on" } else { "off" }
+        );
+        crate::reload_borders();
+    }
+}
+
+/// Turns the mode on if it's off, or off if it's on.
+pub fn toggle() {
+    set_enabled(!is_enabled());
+}
@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Condvar, LazyLock, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+use windows::Win32::UI::WindowsAndMessaging::IsWindow;
+
+use crate::post_message_w;
+use crate::utils::WM_APP_ANIMATE;
+
+// All the animating borders share one scheduler thread instead of each having a timer thread of its
+// own. It sleeps until the next frame is due and then sends WM_APP_ANIMATE to every border that's
+// due, all in one go. Frames land on multiples of each border's interval (counted from when the
+// scheduler started), so borders with the same fps get woken up on the same tick.
+static SCHEDULER: LazyLock<FrameScheduler> = LazyLock::new(|| {
+    let _ = thread::spawn(run_scheduler);
+    FrameScheduler::default()
+});
+
+static EPOCH: LazyLock<Instant> = LazyLock::new(Instant::now);
+
+static NEXT_TIMER_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Debug, Default)]
+struct FrameScheduler {
+    borders: Mutex<HashMap<u64, ScheduledBorder>>,
+    // Wakes the scheduler up when a border is added or unpaused
+    wake: Condvar,
+}
+
+#[derive(Debug)]
+struct ScheduledBorder {
+    // HWND isn't Send, so we keep it as an isize
+    border_window: isize,
+    interval: Duration,
+    next_frame: Instant,
+    // Paused borders stay scheduled but don't get woken up (e.g. while they're covered up)
+    paused: bool,
+    // Whether the last frame's message couldn't be posted, so the error is only logged once
+    failing: bool,
+}
+
+#[derive(Debug)]
+pub struct AnimationTimer {
+    id: u64,
+}
+
+impl AnimationTimer {
+    pub fn start(hwnd: HWND, interval_ms: u64) -> Self {
+        let id = NEXT_TIMER_ID.fetch_add(1, Ordering::SeqCst);
+
+        // Make sure the epoch is set before the first frame is scheduled from it
+        LazyLock::force(&EPOCH);
+
+        SCHEDULER.borders.lock().unwrap().insert(
+            id,
+            ScheduledBorder {
+                border_window: hwnd.0 as isize,
+                interval: Duration::from_millis(interval_ms.max(1)),
+                next_frame: Instant::now(),
+                paused: false,
+                failing: false,
+            },
+        );
+        SCHEDULER.wake.notify_one();
+
+        Self { id }
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        if let Some(border) = SCHEDULER.borders.lock().unwrap().get_mut(&self.id) {
+            border.paused = paused;
+        }
+        if !paused {
+            SCHEDULER.wake.notify_one();
+        }
+    }
+
+    pub fn stop(&mut self) {
+        SCHEDULER.borders.lock().unwrap().remove(&self.id);
+    }
+}
+
+fn run_scheduler() {
+    let mut borders = SCHEDULER.borders.lock().unwrap();
+
+    loop {
+        let now = Instant::now();
+        let mut due: Vec<(u64, isize)> = Vec::new();
+
+        for (id, border) in borders.iter_mut().filter(|(_, border)| !border.paused) {
+            if border.next_frame <= now {
+                due.push((*id, border.border_window));
+                border.next_frame = next_tick(*EPOCH, now, border.interval);
+            }
+        }
+
+        if !due.is_empty() {
+            // Border threads can be busy, so don't make everyone else wait on the lock meanwhile
+            drop(borders);
+
+            let results: Vec<(u64, windows::core::Result<()>)> = due
+                .into_iter()
+                .map(|(id, border_window)| {
+                    let result = post_message_w(
+                        HWND(border_window as _),
+                        WM_APP_ANIMATE,
+                        WPARAM(0),
+                        LPARAM(0),
+                    );
+                    (id, result)
+                })
+                .collect();
+
+            borders = SCHEDULER.borders.lock().unwrap();
+
+            // A border whose message queue is full (e.g. while its thread is stuck for a moment)
+            // stays scheduled and gets another try on its next tick. Only a border window that's
+            // gone for good is dropped.
+            for (id, result) in results {
+                let Some(border) = borders.get_mut(&id) else {
+                    continue;
+                };
+                match result {
+                    Ok(()) => border.failing = false,
+                    Err(_) if !unsafe { IsWindow(HWND(border.border_window as _)) }.as_bool() => {
+                        borders.remove(&id);
+                    }
+                    Err(e) => {
+                        if !border.failing {
+                            warn!("could not send animation timer message, retrying: {e}");
+                        }
+                        border.failing = true;
+                    }
+                }
+            }
+        }
+
+        // Worked out after posting, since borders that were added or unpaused while the lock was
+        // dropped already sent their notify_one, which nobody was waiting for
+        let next_wake = borders
+            .values()
+            .filter(|border| !border.paused)
+            .map(|border| border.next_frame)
+            .min();
+
+        borders = match next_wake {
+            Some(wake) => {
+                let timeout = wake.saturating_duration_since(Instant::now());
+                SCHEDULER.wake.wait_timeout(borders, timeout).unwrap().0
+            }
+            None => SCHEDULER.wake.wait(borders).unwrap(),
+        };
+    }
+}
+
+// The first multiple of 'interval' (since 'epoch') that comes after 'now'
+fn next_tick(epoch: Instant, now: Instant, interval: Duration) -> Instant {
+    let interval_nanos = interval.as_nanos();
+    let ticks = now.duration_since(epoch).as_nanos() / interval_nanos + 1;
+
+    epoch + Duration::from_nanos((ticks * interval_nanos) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_tick_table() {
+        let epoch = Instant::now();
+        let ms = Duration::from_millis;
+
+        // (now, interval, next tick), all in milliseconds since the epoch
+        let cases = [
+            (0, 16, 16),
+            (5, 16, 16),
+            // Right on a tick means the next one
+            (16, 16, 32),
+            (33, 16, 48),
+            (999, 1000, 1000),
+            (1, 1, 2),
+            // Borders with intervals that divide each other land on the same ticks
+            (25, 10, 30),
+            (25, 20, 40),
+            (35, 20, 40),
+        ];
+
+        for (now, interval, expected) in cases {
+            assert_eq!(
+                next_tick(epoch, epoch + ms(now), ms(interval)),
+                epoch + ms(expected),
+                "{now}ms with a {interval}ms interval"
+            );
+        }
+    }
+}
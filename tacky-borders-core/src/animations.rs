@@ -0,0 +1,894 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_yaml::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time;
+
+use windows::Foundation::Numerics::Matrix3x2;
+use windows::Win32::Foundation::POINT;
+use windows::Win32::UI::WindowsAndMessaging::GetCursorPos;
+
+use crate::colors::{Color, ColorConfig};
+use crate::lifecycle::TransitionHook;
+use crate::utils::{cubic_bezier, BezierError};
+use crate::window_border::WindowBorder;
+
+// Number of samples in the precomputed easing lookup table
+const EASING_LUT_SIZE: usize = 256;
+
+// Spirals turn SPIRAL_SPEED_FACTOR degrees per second for every unit of speed, fades take
+// FADE_SPEED_DIVISOR / speed seconds, and comets take COMET_SPEED_DIVISOR / speed seconds to go
+// around once, just cuz the raw numbers are too slow/fast otherwise lol
+pub const SPIRAL_SPEED_FACTOR: f32 = 2.0;
+pub const FADE_SPEED_DIVISOR: f32 = 20.0;
+pub const COMET_SPEED_DIVISOR: f32 = 200.0;
+
+const DEFAULT_SPEED: f32 = 100.0;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AnimationType {
+    Spiral,
+    ReverseSpiral,
+    Fade,
+    Comet,
+    // One of the config's animation_defs, by name
+    #[serde(untagged)]
+    Custom(String),
+}
+
+impl AnimationType {
+    pub const ALL: [AnimationType; 4] = [
+        AnimationType::Fade,
+        AnimationType::Spiral,
+        AnimationType::ReverseSpiral,
+        AnimationType::Comet,
+    ];
+}
+
+// The point that spiral animations rotate the brush around
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AnimationAnchor {
+    #[default]
+    Center,
+    Cursor,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EasingPreset {
+    Linear,
+    Ease,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+// Easing curves can either be a preset or custom cubic-bezier control points [x1, y1, x2, y2]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum EasingConfig {
+    Preset(EasingPreset),
+    CubicBezier([f32; 4]),
+}
+
+impl EasingConfig {
+    fn control_points(&self) -> [f32; 4] {
+        match self {
+            EasingConfig::Preset(EasingPreset::Linear) => [0.0, 0.0, 1.0, 1.0],
+            EasingConfig::Preset(EasingPreset::Ease) => [0.25, 0.1, 0.25, 1.0],
+            EasingConfig::Preset(EasingPreset::EaseIn) => [0.42, 0.0, 1.0, 1.0],
+            EasingConfig::Preset(EasingPreset::EaseOut) => [0.0, 0.0, 0.58, 1.0],
+            EasingConfig::Preset(EasingPreset::EaseInOut) => [0.42, 0.0, 0.58, 1.0],
+            EasingConfig::CubicBezier(points) => *points,
+        }
+    }
+}
+
+// An easing curve with a lookup table that is precomputed when the config is loaded, so we don't
+// have to run the cubic-bezier solver on every animation frame.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "EasingConfig", into = "EasingConfig")]
+pub struct Easing {
+    config: EasingConfig,
+    lut: Arc<[f32]>,
+}
+
+impl TryFrom<EasingConfig> for Easing {
+    type Error = BezierError;
+
+    fn try_from(config: EasingConfig) -> Result<Self, Self::Error> {
+        let [x1, y1, x2, y2] = config.control_points();
+        let easing_fn = cubic_bezier(x1, y1, x2, y2)?;
+
+        let lut = (0..EASING_LUT_SIZE)
+            .map(|i| easing_fn(i as f32 / (EASING_LUT_SIZE - 1) as f32))
+            .collect();
+
+        Ok(Self { config, lut })
+    }
+}
+
+impl From<Easing> for EasingConfig {
+    fn from(easing: Easing) -> Self {
+        easing.config
+    }
+}
+
+impl Default for Easing {
+    fn default() -> Self {
+        // The presets' control points are always valid, so this should never fail
+        match Easing::try_from(EasingConfig::Preset(EasingPreset::EaseInOut)) {
+            Ok(easing) => easing,
+            Err(e) => panic!("could not create default easing: {e}"),
+        }
+    }
+}
+
+impl Easing {
+    // Sample the lookup table, linearly interpolating between the two nearest entries
+    pub fn ease(&self, x: f32) -> f32 {
+        let max_index = (self.lut.len() - 1) as f32;
+        let position = x.clamp(0.0, 1.0) * max_index;
+
+        let lower = position.floor() as usize;
+        let upper = position.ceil() as usize;
+
+        let lower_y = self.lut[lower];
+        let upper_y = self.lut[upper];
+
+        lower_y + (upper_y - lower_y) * (position - lower as f32)
+    }
+}
+
+// Extra effects for when focus moves from one window to another. Both borders are timed from the
+// same focus event, so the old border shrinks/fades out in step with the new one growing/fading in.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Handoff {
+    // How many pixels thinner inactive borders are than active ones
+    #[serde(default)]
+    pub shrink: f32,
+    // Briefly flash the newly focused border
+    #[serde(default)]
+    pub flash: bool,
+}
+
+// The bright streaks that the Comet animation sends around the border
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Comet {
+    // How many comets go around at once, evenly spaced
+    #[serde(default = "default_comet_count")]
+    pub count: u32,
+    // How much of the border each comet covers (including its tail), from 0.0 to 1.0
+    #[serde(default = "default_comet_length")]
+    pub length: f32,
+    // Defaults to white
+    pub color: Option<ColorConfig>,
+}
+
+impl Default for Comet {
+    fn default() -> Self {
+        Self {
+            count: default_comet_count(),
+            length: default_comet_length(),
+            color: None,
+        }
+    }
+}
+
+fn default_comet_count() -> u32 {
+    1
+}
+
+fn default_comet_length() -> f32 {
+    0.15
+}
+
+// More than this and the comets just run into each other
+const MAX_COMET_COUNT: u32 = 16;
+
+impl Comet {
+    // The number of comets to draw, which is at least one and at most MAX_COMET_COUNT
+    pub fn count(&self) -> u32 {
+        self.count.clamp(1, MAX_COMET_COUNT)
+    }
+
+    pub fn color(&self) -> Color {
+        let mut color = self
+            .color
+            .clone()
+            .unwrap_or(ColorConfig::SolidConfig("#ffffff".to_string()))
+            .convert_to_color(true);
+        color.set_fade_opacity(1.0);
+        color
+    }
+}
+
+// Custom deserializer for HashMap<AnimationType, Option<f32>>
+fn animation<'de, D>(deserializer: D) -> Result<HashMap<AnimationType, f32>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    // If deserialize returns an error, it's possible that an invalid AnimationType was listed
+    let hashmap = HashMap::<AnimationType, Value>::deserialize(deserializer)?;
+
+    let mut deserialized = HashMap::new();
+    for (key, value) in hashmap {
+        let speed = match value {
+            Value::Number(n) => n.as_f64().map(|f| f as f32),
+            Value::String(s) => Some(parse_speed(&key, &s).map_err(serde::de::Error::custom)?),
+            Value::Null => None, // If the value is null, we will assign default speeds later
+            _ => None,           // Handle invalid formats
+        };
+
+        // If the speed is None (either null or missing), assign the default speed
+        deserialized.insert(key, speed.unwrap_or(DEFAULT_SPEED));
+    }
+
+    Ok(deserialized)
+}
+
+// Speeds can also be a preset ("slow", "normal", or "fast"), a rate for spirals (like "720deg/s"),
+// or how long one spin or fade takes (like "300ms" or "1.5s"). These get turned into the same
+// numbers that a bare speed would be.
+pub(crate) fn parse_speed(anim_type: &AnimationType, speed: &str) -> Result<f32, String> {
+    let speed = speed.trim().to_ascii_lowercase();
+    let number = |s: &str| {
+        s.trim()
+            .parse::<f32>()
+            .ok()
+            .filter(|n| n.is_finite() && *n > 0.0)
+            .ok_or_else(|| format!("invalid {anim_type:?} animation speed '{speed}'"))
+    };
+
+    match speed.as_str() {
+        "slow" => return Ok(DEFAULT_SPEED / 2.0),
+        "normal" => return Ok(DEFAULT_SPEED),
+        "fast" => return Ok(DEFAULT_SPEED * 2.0),
+        _ => {}
+    }
+
+    if let Some(rate) = speed.strip_suffix("deg/s") {
+        return match anim_type {
+            AnimationType::Spiral | AnimationType::ReverseSpiral => {
+                Ok(number(rate)? / SPIRAL_SPEED_FACTOR)
+            }
+            _ => Err(format!(
+                "'{speed}' is a rotation speed, which only works for spiral animations"
+            )),
+        };
+    }
+
+    let seconds = match (speed.strip_suffix("ms"), speed.strip_suffix('s')) {
+        (Some(ms), _) => number(ms)? / 1000.0,
+        (None, Some(s)) => number(s)?,
+        (None, None) => {
+            return Err(format!(
+                "invalid {anim_type:?} animation speed '{speed}', expected a number, \"slow\", \
+                 \"normal\", \"fast\", a rotation speed (like \"720deg/s\"), or a duration (like \
+                 \"300ms\")"
+            ))
+        }
+    };
+
+    Ok(match anim_type {
+        AnimationType::Spiral | AnimationType::ReverseSpiral => {
+            360.0 / seconds / SPIRAL_SPEED_FACTOR
+        }
+        AnimationType::Fade => FADE_SPEED_DIVISOR / seconds,
+        AnimationType::Comet => COMET_SPEED_DIVISOR / seconds,
+        // Their steps already say how long they take
+        AnimationType::Custom(name) => {
+            return Err(format!(
+                "'{speed}' is a duration, which doesn't work for the custom animation '{name}' \
+                 (its steps set their own durations)"
+            ))
+        }
+    })
+}
+
+// One step of a custom animation. Spirals turn once, comets go around once, and fades fade the
+// border in, each over the step's duration.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnimationStep {
+    pub animation: AnimationType,
+    // Milliseconds to wait after the previous step finishes
+    #[serde(default)]
+    pub delay: f32,
+    // Milliseconds that the step takes
+    #[serde(default = "default_step_duration")]
+    pub duration: f32,
+    #[serde(default)]
+    pub easing: Easing,
+}
+
+fn default_step_duration() -> f32 {
+    500.0
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnimationDefConfig {
+    pub steps: Vec<AnimationStep>,
+    // Start over from the first step once the last one finishes
+    #[serde(default)]
+    pub repeat: bool,
+}
+
+// A custom animation from animation_defs, with its steps laid out on one timeline when the config
+// is loaded. Steps play one after another, in the order they're listed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "AnimationDefConfig", into = "AnimationDefConfig")]
+pub struct AnimationDef {
+    config: AnimationDefConfig,
+    // Each step's start and end, in seconds from the start of the animation
+    timeline: Vec<(f32, f32)>,
+    length: f32,
+}
+
+impl TryFrom<AnimationDefConfig> for AnimationDef {
+    type Error = String;
+
+    fn try_from(config: AnimationDefConfig) -> Result<Self, Self::Error> {
+        let mut timeline = Vec::with_capacity(config.steps.len());
+        let mut end = 0.0;
+
+        for step in config.steps.iter() {
+            if let AnimationType::Custom(ref name) = step.animation {
+                return Err(format!(
+                    "'{name}' is not a built-in animation (steps can only be Spiral, \
+                     ReverseSpiral, Fade, or Comet)"
+                ));
+            }
+            if !step.delay.is_finite() || step.delay < 0.0 {
+                return Err(format!("invalid step delay '{}'", step.delay));
+            }
+            if !step.duration.is_finite() || step.duration <= 0.0 {
+                return Err(format!("invalid step duration '{}'", step.duration));
+            }
+
+            let start = end + step.delay / 1000.0;
+            end = start + step.duration / 1000.0;
+            timeline.push((start, end));
+        }
+
+        Ok(Self {
+            config,
+            timeline,
+            length: end,
+        })
+    }
+}
+
+impl From<AnimationDef> for AnimationDefConfig {
+    fn from(def: AnimationDef) -> Self {
+        def.config
+    }
+}
+
+impl AnimationDef {
+    // What the animation does to the border 'elapsed' seconds in, and where its comets are (if any
+    // are going around). Returns None once a non-repeating animation has finished, which leaves the
+    // border the way it was before.
+    fn sample(&self, elapsed: f32) -> Option<(AnimationDelta, Option<f32>)> {
+        let elapsed = match (self.config.repeat, self.length > 0.0) {
+            (true, true) => elapsed % self.length,
+            _ if elapsed < self.length => elapsed,
+            _ => return None,
+        };
+
+        let mut delta = AnimationDelta::default();
+        let mut comet = None;
+
+        for (step, &(start, end)) in self.config.steps.iter().zip(self.timeline.iter()) {
+            let progress = ((elapsed - start) / (end - start)).clamp(0.0, 1.0);
+            let eased = step.easing.ease(progress);
+
+            match step.animation {
+                AnimationType::Spiral => delta.rotation = (delta.rotation + eased * 360.0) % 360.0,
+                AnimationType::ReverseSpiral => {
+                    delta.rotation = (delta.rotation - eased * 360.0) % 360.0
+                }
+                // The border stays hidden until its fade starts
+                AnimationType::Fade => delta.opacity *= eased,
+                AnimationType::Comet if (start..end).contains(&elapsed) => comet = Some(eased),
+                _ => {}
+            }
+        }
+
+        Some((delta, comet))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+pub struct Animations {
+    #[serde(default, deserialize_with = "animation")]
+    pub active: HashMap<AnimationType, f32>,
+    #[serde(default, deserialize_with = "animation")]
+    pub inactive: HashMap<AnimationType, f32>,
+    #[serde(skip)]
+    pub current: HashMap<AnimationType, f32>,
+    #[serde(default = "default_fps")]
+    pub fps: i32,
+    #[serde(default)]
+    pub spiral_anchor: AnimationAnchor,
+    #[serde(default)]
+    pub fade_easing: Easing,
+    #[serde(default)]
+    pub handoff: Handoff,
+    #[serde(default)]
+    pub comet: Comet,
+    // The animation_defs that active/inactive use, looked up when the border is created
+    #[serde(skip)]
+    pub custom: HashMap<String, AnimationDef>,
+    // Animations for moving between lifecycle states, on top of the Fade animation's defaults
+    #[serde(default)]
+    pub transitions: Vec<TransitionHook>,
+    #[serde(skip)]
+    pub fade_progress: f32,
+    #[serde(skip)]
+    pub fade_only_one_color: bool,
+    // Whether either color has been faded in since the border was created or last hidden. Until
+    // then, the next fade only brings in the color that should be visible.
+    #[serde(skip)]
+    pub fade_shown: bool,
+    #[serde(skip)]
+    pub fade_start: Option<(time::Instant, f32)>,
+    #[serde(skip)]
+    pub spirals: HashMap<AnimationType, SpiralTimeline>,
+    #[serde(skip)]
+    pub comet_timeline: CometTimeline,
+    // When each running custom animation started. They start over whenever the focus changes.
+    #[serde(skip)]
+    pub custom_starts: HashMap<String, time::Instant>,
+    // Where the comets of a custom animation's Comet step are this frame
+    #[serde(skip)]
+    pub custom_comet: Option<f32>,
+    // Everything the running animations did to the border this frame, merged together
+    #[serde(skip)]
+    pub delta: AnimationDelta,
+}
+
+// Where a spiral animation is. Spiral and ReverseSpiral each have their own, so they can run at the
+// same time.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SpiralTimeline {
+    pub angle: f32,
+    pub start: Option<(time::Instant, f32)>,
+    pub speed: f32,
+}
+
+// Where the comets are, as a fraction of the way around the border from the middle of its top edge
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CometTimeline {
+    pub position: f32,
+    pub start: Option<(time::Instant, f32)>,
+    pub speed: f32,
+}
+
+// What an animation changes about the border. Animations don't write to the border directly;
+// each one returns a delta, and the deltas from every running animation (and the plugin) are
+// merged before drawing, so two animations that touch the same property add up instead of the
+// last one winning.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnimationDelta {
+    // Degrees to rotate the brushes by, around the spiral anchor
+    pub rotation: f32,
+    // Applied to the brushes after the rotation
+    pub transform: Matrix3x2,
+    // Multiplied with the opacity of the border's colors
+    pub opacity: f32,
+    // Pixels added to the border's width
+    pub width: f32,
+}
+
+impl Default for AnimationDelta {
+    fn default() -> Self {
+        Self {
+            rotation: 0.0,
+            transform: Matrix3x2::identity(),
+            opacity: 1.0,
+            width: 0.0,
+        }
+    }
+}
+
+impl AnimationDelta {
+    pub fn merge(self, other: AnimationDelta) -> Self {
+        Self {
+            rotation: (self.rotation + other.rotation) % 360.0,
+            transform: self.transform * other.transform,
+            opacity: self.opacity * other.opacity,
+            width: self.width + other.width,
+        }
+    }
+}
+
+fn default_fps() -> i32 {
+    60
+}
+
+impl Animations {
+    // Look up the custom animations listed in active/inactive, leaving out the ones that aren't
+    // defined
+    pub fn resolve_custom(&mut self, defs: &HashMap<String, AnimationDef>) {
+        for list in [&mut self.active, &mut self.inactive] {
+            list.retain(|anim_type, _| match anim_type {
+                AnimationType::Custom(name) => match defs.get(name) {
+                    Some(def) => {
+                        self.custom.insert(name.clone(), def.clone());
+                        true
+                    }
+                    None => {
+                        error!("could not find animation '{name}' in animation_defs");
+                        false
+                    }
+                },
+                _ => true,
+            });
+        }
+    }
+}
+
+// Note: animations derive their progress from the wall-clock time since they started rather than
+// accumulating per-tick deltas, so they run at the same visual speed regardless of the fps.
+pub fn animate_spiral(
+    border: &mut WindowBorder,
+    anim_type: &AnimationType,
+    now: time::Instant,
+    anim_speed: f32,
+) -> AnimationDelta {
+    let spiral = border
+        .animations
+        .spirals
+        .entry(anim_type.clone())
+        .or_default();
+
+    // Start a new timeline from the current angle if we haven't started yet or if the speed has
+    // changed (e.g. switching from the active to the inactive animations) so the angle stays
+    // continuous.
+    let timeline = spiral.start.filter(|_| spiral.speed == anim_speed);
+    let (start_time, start_angle) = match timeline {
+        Some(timeline) => timeline,
+        None => {
+            spiral.speed = anim_speed;
+            *spiral.start.insert((now, spiral.angle))
+        }
+    };
+
+    let elapsed = now.saturating_duration_since(start_time).as_secs_f32();
+    spiral.angle = (start_angle + elapsed * anim_speed) % 360.0;
+
+    // Periodically rebase the timeline so we don't lose f32 precision on long-running animations
+    if elapsed >= 60.0 {
+        spiral.start = Some((now, spiral.angle));
+    }
+
+    AnimationDelta {
+        rotation: spiral.angle,
+        ..Default::default()
+    }
+}
+
+// Move the comets along the border. They're drawn on top of everything else instead of changing
+// the border itself, so there's no delta to return. 'anim_speed' is in laps per second.
+pub fn animate_comet(border: &mut WindowBorder, now: time::Instant, anim_speed: f32) {
+    let comet = &mut border.animations.comet_timeline;
+
+    // Same as the spirals, a new speed starts a new timeline from wherever the comets are
+    let timeline = comet.start.filter(|_| comet.speed == anim_speed);
+    let (start_time, start_position) = match timeline {
+        Some(timeline) => timeline,
+        None => {
+            comet.speed = anim_speed;
+            *comet.start.insert((now, comet.position))
+        }
+    };
+
+    let elapsed = now.saturating_duration_since(start_time).as_secs_f32();
+    comet.position = (start_position + elapsed * anim_speed).rem_euclid(1.0);
+
+    if elapsed >= 60.0 {
+        comet.start = Some((now, comet.position));
+    }
+}
+
+// Play one of the config's custom animations. 'anim_speed' scales how fast its steps go, with the
+// default speed playing them as written. Returns None once it has finished.
+pub fn animate_custom(
+    border: &mut WindowBorder,
+    name: &str,
+    now: time::Instant,
+    anim_speed: f32,
+) -> Option<AnimationDelta> {
+    let def = border.animations.custom.get(name)?;
+    let start_time = *border
+        .animations
+        .custom_starts
+        .entry(name.to_string())
+        .or_insert(now);
+
+    let elapsed =
+        now.saturating_duration_since(start_time).as_secs_f32() * anim_speed / DEFAULT_SPEED;
+    let (delta, comet) = def.sample(elapsed)?;
+
+    if comet.is_some() {
+        border.animations.custom_comet = comet;
+    }
+    Some(delta)
+}
+
+// Build the brush transform for the current spiral angle. This is computed at render time using
+// the same window_rect that the brushes are created from, so the rotation stays anchored to the
+// gradient even if the window is resized in the middle of the animation.
+pub fn spiral_transform(border: &WindowBorder, rotation: f32) -> Matrix3x2 {
+    if rotation == 0.0 {
+        return Matrix3x2::identity();
+    }
+
+    let width = (border.window_rect.right - border.window_rect.left) as f32;
+    let height = (border.window_rect.bottom - border.window_rect.top) as f32;
+
+    // Note: these coordinates are relative to the border window, not the screen
+    let (anchor_x, anchor_y) = match border.animations.spiral_anchor {
+        AnimationAnchor::Center => (width / 2.0, height / 2.0),
+        AnimationAnchor::Cursor => {
+            let mut point = POINT::default();
+            match unsafe { GetCursorPos(&mut point) } {
+                Ok(_) => (
+                    (point.x - border.window_rect.left) as f32,
+                    (point.y - border.window_rect.top) as f32,
+                ),
+                Err(e) => {
+                    error!("could not retrieve cursor position for spiral anchor: {e}");
+                    (width / 2.0, height / 2.0)
+                }
+            }
+        }
+        AnimationAnchor::TopLeft => (0.0, 0.0),
+        AnimationAnchor::TopRight => (width, 0.0),
+        AnimationAnchor::BottomLeft => (0.0, height),
+        AnimationAnchor::BottomRight => (width, height),
+    };
+
+    Matrix3x2::rotation(rotation, anchor_x, anchor_y)
+}
+
+// Returns true once the fade is finished
+pub fn animate_fade(border: &mut WindowBorder, now: time::Instant, anim_speed: f32) -> bool {
+    // The window has been opened for the first time or has been unminimized, so only one of the
+    // colors should be visible while fading
+    if !border.animations.fade_shown {
+        border.animations.fade_shown = true;
+
+        // Set fade_progress here so we start from 0 opacity for the visible color
+        border.animations.fade_progress = match border.is_active_window {
+            true => 0.0,
+            false => 1.0,
+        };
+
+        border.animations.fade_only_one_color = true;
+        border.animations.fade_start = None;
+    }
+
+    // Start the fade from wherever the previous one left off
+    let (start_time, start_progress) = *border
+        .animations
+        .fade_start
+        .get_or_insert((now, border.animations.fade_progress));
+
+    // Determine which direction we should move fade_progress
+    let direction = match border.is_active_window {
+        true => 1.0,
+        false => -1.0,
+    };
+
+    let elapsed = now.saturating_duration_since(start_time).as_secs_f32();
+    border.animations.fade_progress = start_progress + elapsed * anim_speed * direction;
+
+    // Check if the fade animation is finished
+    if !(0.0..=1.0).contains(&border.animations.fade_progress) {
+        let final_opacity = border.animations.fade_progress.clamp(0.0, 1.0);
+
+        border.active_color.set_fade_opacity(final_opacity);
+        border.inactive_color.set_fade_opacity(1.0 - final_opacity);
+
+        border.animations.fade_progress = final_opacity;
+        border.animations.fade_only_one_color = false;
+        border.animations.fade_start = None;
+        return true;
+    }
+
+    let y_coord = border
+        .animations
+        .fade_easing
+        .ease(border.animations.fade_progress);
+
+    let (new_active_opacity, new_inactive_opacity) = match border.animations.fade_only_one_color {
+        true => match border.is_active_window {
+            true => (y_coord, 0.0),
+            false => (0.0, 1.0 - y_coord),
+        },
+        false => (y_coord, 1.0 - y_coord),
+    };
+
+    border.active_color.set_fade_opacity(new_active_opacity);
+    border.inactive_color.set_fade_opacity(new_inactive_opacity);
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_def(yaml: &str) -> Result<AnimationDef, String> {
+        let config: AnimationDefConfig = serde_yaml::from_str(yaml).unwrap();
+        AnimationDef::try_from(config)
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-3,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn parse_speed_table() {
+        let custom = AnimationType::Custom("pulse".to_string());
+        let cases = [
+            (AnimationType::Fade, "slow", Some(50.0)),
+            (AnimationType::Fade, "normal", Some(100.0)),
+            (AnimationType::Spiral, " FAST ", Some(200.0)),
+            (AnimationType::Spiral, "720deg/s", Some(360.0)),
+            (AnimationType::ReverseSpiral, "90 deg/s", Some(45.0)),
+            (AnimationType::Spiral, "1s", Some(180.0)),
+            (AnimationType::Spiral, "500ms", Some(360.0)),
+            (AnimationType::Fade, "200ms", Some(100.0)),
+            (AnimationType::Fade, "1.5s", Some(FADE_SPEED_DIVISOR / 1.5)),
+            (AnimationType::Comet, "2s", Some(100.0)),
+            (AnimationType::Fade, "720deg/s", None),
+            (AnimationType::Fade, "0s", None),
+            (AnimationType::Fade, "-100ms", None),
+            (AnimationType::Fade, "nans", None),
+            (AnimationType::Spiral, "0deg/s", None),
+            (AnimationType::Fade, "quick", None),
+            (AnimationType::Fade, "300", None),
+            (custom.clone(), "fast", Some(200.0)),
+            (custom, "1s", None),
+        ];
+
+        for (anim_type, speed, expected) in cases {
+            match (parse_speed(&anim_type, speed), expected) {
+                (Ok(actual), Some(expected)) => assert_close(actual, expected),
+                (Err(_), None) => {}
+                (actual, _) => panic!("{anim_type:?} '{speed}': got {actual:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn try_from_lays_steps_out_one_after_another() {
+        let def = parse_def(
+            "steps:
+              - { animation: Fade, duration: 200 }
+              - { animation: Spiral, delay: 100, duration: 300 }",
+        )
+        .unwrap();
+
+        assert_eq!(def.timeline.len(), 2);
+        assert_close(def.timeline[0].0, 0.0);
+        assert_close(def.timeline[0].1, 0.2);
+        assert_close(def.timeline[1].0, 0.3);
+        assert_close(def.timeline[1].1, 0.6);
+        assert_close(def.length, 0.6);
+    }
+
+    #[test]
+    fn try_from_rejects_invalid_steps() {
+        for step in [
+            "{ animation: Spirl }",
+            "{ animation: Fade, delay: -1 }",
+            "{ animation: Fade, delay: .nan }",
+            "{ animation: Fade, duration: 0 }",
+            "{ animation: Fade, duration: .inf }",
+        ] {
+            assert!(parse_def(&format!("steps: [{step}]")).is_err(), "{step}");
+        }
+    }
+
+    #[test]
+    fn try_from_accepts_no_steps() {
+        let def = parse_def("steps: []").unwrap();
+        assert_eq!(def.length, 0.0);
+        assert!(def.sample(0.0).is_none());
+    }
+
+    #[test]
+    fn sample_follows_the_steps() {
+        let def = parse_def(
+            "steps:
+              - { animation: Fade, duration: 1000, easing: Linear }
+              - { animation: Spiral, duration: 1000, easing: Linear }",
+        )
+        .unwrap();
+
+        let (delta, comet) = def.sample(0.5).unwrap();
+        assert_close(delta.opacity, 0.5);
+        assert_close(delta.rotation, 0.0);
+        assert_eq!(comet, None);
+
+        // The fade is done by now, and stays done
+        let (delta, _) = def.sample(1.25).unwrap();
+        assert_close(delta.opacity, 1.0);
+        assert_close(delta.rotation, 90.0);
+    }
+
+    #[test]
+    fn sample_ends_unless_repeating() {
+        let steps = "steps: [{ animation: Spiral, duration: 1000, easing: Linear }]";
+
+        let def = parse_def(steps).unwrap();
+        assert!(def.sample(0.999).is_some());
+        assert!(def.sample(1.0).is_none());
+        assert!(def.sample(5.0).is_none());
+
+        let def = parse_def(&format!("{steps}\nrepeat: true")).unwrap();
+        let (delta, _) = def.sample(2.25).unwrap();
+        assert_close(delta.rotation, 90.0);
+    }
+
+    #[test]
+    fn sample_only_has_comets_during_their_step() {
+        let def = parse_def(
+            "steps:
+              - { animation: Comet, duration: 1000, easing: Linear }
+              - { animation: Fade, duration: 1000 }",
+        )
+        .unwrap();
+
+        let (_, comet) = def.sample(0.25).unwrap();
+        assert_close(comet.unwrap(), 0.25);
+
+        let (_, comet) = def.sample(1.5).unwrap();
+        assert_eq!(comet, None);
+    }
+
+    #[test]
+    fn fade_brings_in_one_color_until_shown() {
+        let now = time::Instant::now();
+        let mut border = WindowBorder {
+            is_active_window: true,
+            ..Default::default()
+        };
+
+        // A new border only fades in the active color
+        animate_fade(&mut border, now, 1.0);
+        assert!(border.animations.fade_shown);
+        assert!(border.animations.fade_only_one_color);
+        assert_eq!(border.inactive_color.fade_opacity(), 0.0);
+
+        // Once it's been shown, both colors crossfade, even if they're both at 0 right now
+        let mut border = WindowBorder {
+            is_active_window: true,
+            ..Default::default()
+        };
+        border.animations.fade_shown = true;
+        animate_fade(&mut border, now, 1.0);
+        assert!(!border.animations.fade_only_one_color);
+        assert_eq!(border.inactive_color.fade_opacity(), 1.0);
+    }
+
+    #[test]
+    fn comet_count_is_capped() {
+        for (count, expected) in [(0, 1), (1, 1), (3, 3), (16, 16), (u32::MAX, 16)] {
+            let comet = Comet {
+                count,
+                ..Default::default()
+            };
+            assert_eq!(comet.count(), expected, "{count}");
+        }
+    }
+}
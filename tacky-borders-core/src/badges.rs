@@ -0,0 +1,143 @@
+use serde::{Deserialize, Serialize};
+use windows::Win32::Foundation::HWND;
+use windows::Win32::Graphics::Direct2D::Common::{D2D1_COLOR_F, D2D_POINT_2F, D2D_RECT_F};
+use windows::Win32::Graphics::Direct2D::{ID2D1RenderTarget, D2D1_ELLIPSE};
+use windows::Win32::UI::WindowsAndMessaging::IsHungAppWindow;
+
+use crate::colors::get_color_from_str;
+use crate::utils::is_topmost;
+
+// Small dots drawn on the top edge of the border that show things about the window's state
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BadgesConfig {
+    // Show a badge while the window is always-on-top
+    #[serde(default = "default_true")]
+    pub topmost: bool,
+    // Show a badge while the window isn't responding
+    #[serde(default = "default_true")]
+    pub not_responding: bool,
+    // The diameter of each badge in pixels
+    #[serde(default = "default_badge_size")]
+    pub size: f32,
+    #[serde(default = "default_topmost_color")]
+    pub topmost_color: String,
+    #[serde(default = "default_not_responding_color")]
+    pub not_responding_color: String,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_badge_size() -> f32 {
+    8.0
+}
+
+fn default_topmost_color() -> String {
+    "#89b4fa".to_string()
+}
+
+fn default_not_responding_color() -> String {
+    "#f38ba8".to_string()
+}
+
+// Note: Windows doesn't have a public API for checking whether a window is pinned to all virtual
+// desktops, so we can't show a badge for that.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WindowState {
+    pub topmost: bool,
+    pub not_responding: bool,
+}
+
+impl WindowState {
+    pub fn poll(hwnd: HWND) -> Self {
+        Self {
+            topmost: is_topmost(hwnd),
+            not_responding: unsafe { IsHungAppWindow(hwnd) }.as_bool(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Badges {
+    pub state: WindowState,
+    show_topmost: bool,
+    show_not_responding: bool,
+    size: f32,
+    topmost_color: D2D1_COLOR_F,
+    not_responding_color: D2D1_COLOR_F,
+}
+
+impl Badges {
+    pub fn new(config: &BadgesConfig, dpi: f32) -> Self {
+        Self {
+            state: WindowState::default(),
+            show_topmost: config.topmost,
+            show_not_responding: config.not_responding,
+            size: config.size.max(0.0) * dpi / 96.0,
+            topmost_color: get_color_from_str(&config.topmost_color),
+            not_responding_color: get_color_from_str(&config.not_responding_color),
+        }
+    }
+
+    // Poll the tracking window's state, returning whether any of the visible badges changed
+    pub fn update(&mut self, hwnd: HWND) -> bool {
+        let state = WindowState::poll(hwnd);
+        let changed = (self.show_topmost && state.topmost != self.state.topmost)
+            || (self.show_not_responding && state.not_responding != self.state.not_responding);
+
+        self.state = state;
+        changed
+    }
+
+    // How far down from the top of the border window the badges reach
+    pub fn bottom(&self, rect: &D2D_RECT_F) -> f32 {
+        let radius = self.size / 2.0;
+        rect.top.max(radius) + radius
+    }
+
+    // Draw the badges from right to left along the top edge of 'rect', starting just past the
+    // rounded corner so they sit on the straight part of the border
+    pub fn draw(
+        &self,
+        render_target: &ID2D1RenderTarget,
+        rect: &D2D_RECT_F,
+        border_radius: f32,
+        opacity: f32,
+    ) -> windows::core::Result<()> {
+        let colors = [
+            (
+                self.show_not_responding && self.state.not_responding,
+                &self.not_responding_color,
+            ),
+            (self.show_topmost && self.state.topmost, &self.topmost_color),
+        ];
+
+        let radius = self.size / 2.0;
+        let mut x = rect.right - border_radius.max(self.size) - radius;
+        // Keep the badges from getting cut off if the border is thinner than they are
+        let y = rect.top.max(radius);
+
+        for color in colors
+            .iter()
+            .filter(|(show, _)| *show)
+            .map(|(_, color)| *color)
+        {
+            let ellipse = D2D1_ELLIPSE {
+                point: D2D_POINT_2F { x, y },
+                radiusX: radius,
+                radiusY: radius,
+            };
+
+            unsafe {
+                let brush = render_target.CreateSolidColorBrush(color, None)?;
+                brush.SetOpacity(opacity);
+                render_target.FillEllipse(&ellipse, &brush);
+            }
+
+            x -= self.size * 1.5;
+        }
+
+        Ok(())
+    }
+}
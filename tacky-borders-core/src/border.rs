@@ -0,0 +1,199 @@
+//! Attach borders to specific windows instead of letting the config's rules pick them.
+//!
+//! This is meant for tools like window managers that track windows themselves. It works from any
+//! thread, with or without [`start`](crate::start) having been called.
+//!
+//! ```no_run
+//! use tacky_borders_core::border::{Border, BorderOptions};
+//! use tacky_borders_core::colors::ColorConfig;
+//! # let hwnd = windows::Win32::Foundation::HWND::default();
+//!
+//! let handle = Border::attach(hwnd, BorderOptions::default())?;
+//! handle.set_color(ColorConfig::SolidConfig("#ff0000".to_string()))?;
+//! handle.flash(3)?;
+//! handle.detach();
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use anyhow::{anyhow, Context};
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::thread;
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+use windows::Win32::System::Threading::GetCurrentThreadId;
+use windows::Win32::UI::Accessibility::UnhookWinEvent;
+use windows::Win32::UI::WindowsAndMessaging::{
+    DispatchMessageW, GetMessageW, IsWindow, PeekMessageW, PostThreadMessageW, TranslateMessage,
+    MSG, PM_NOREMOVE, WINEVENT_OUTOFCONTEXT, WM_QUIT,
+};
+
+use crate::animations::Animations;
+use crate::border_config::WindowRule;
+use crate::colors::ColorConfig;
+use crate::event_hook;
+use crate::log_if_err;
+use crate::utils::*;
+use crate::{SendHWND, EVENT_HOOK_INSTALLED};
+
+// The thread running the attached borders' own event hook, if it's running
+static ATTACH_HOOK_THREAD: Mutex<Option<u32>> = Mutex::new(None);
+
+/// Options for a border created with [`Border::attach`]. Anything left as `None` falls back to the
+/// `global` section of the config.
+#[derive(Debug, Clone, Default)]
+pub struct BorderOptions {
+    pub border_width: Option<f32>,
+    pub border_offset: Option<i32>,
+    pub border_radius: Option<f32>,
+    pub active_color: Option<ColorConfig>,
+    pub inactive_color: Option<ColorConfig>,
+    pub animations: Option<Animations>,
+}
+
+impl From<BorderOptions> for WindowRule {
+    fn from(options: BorderOptions) -> Self {
+        WindowRule {
+            border_width: options.border_width,
+            border_offset: options.border_offset,
+            border_radius: options.border_radius,
+            active_color: options.active_color,
+            inactive_color: options.inactive_color,
+            animations: options.animations,
+            initialize_delay: Some(0),
+            ..Default::default()
+        }
+    }
+}
+
+pub struct Border;
+
+impl Border {
+    /// Creates a border around `hwnd` and waits until its window exists.
+    ///
+    /// Fails if `hwnd` isn't a window or already has a border. The border is removed automatically
+    /// when `hwnd` is destroyed, or when [`reload`](crate::reload) recreates borders.
+    pub fn attach(hwnd: HWND, options: BorderOptions) -> anyhow::Result<BorderHandle> {
+        if !unsafe { IsWindow(hwnd) }.as_bool() {
+            return Err(anyhow!("{hwnd:?} is not a window"));
+        }
+
+        crate::register_window_class_once();
+        install_attach_hook();
+
+        let (sender, receiver) = mpsc::channel();
+        let window = SendHWND(hwnd);
+        let _ = thread::spawn(move || {
+            let window_sent = window;
+            run_border_thread(window_sent.0, options.into(), Some(sender));
+        });
+
+        let border_window = receiver
+            .recv()
+            .context("border thread exited before creating its window")??;
+
+        Ok(BorderHandle {
+            tracking_window: hwnd.0 as isize,
+            border_window,
+        })
+    }
+}
+
+/// A border created by [`Border::attach`]. HWNDs are stored as isize so the handle can be sent to
+/// other threads.
+#[derive(Debug)]
+pub struct BorderHandle {
+    tracking_window: isize,
+    border_window: isize,
+}
+
+impl BorderHandle {
+    pub fn tracking_window(&self) -> HWND {
+        HWND(self.tracking_window as _)
+    }
+
+    /// Replaces both the active and inactive color.
+    pub fn set_color(&self, color: ColorConfig) -> anyhow::Result<()> {
+        set_border_color(self.border_window(), color).context("could not send color to border")
+    }
+
+    /// Blinks the border `count` times, e.g. to point out which window something happened in.
+    pub fn flash(&self, count: u32) -> anyhow::Result<()> {
+        post_message_w(
+            self.border_window(),
+            WM_APP_FLASH,
+            WPARAM(count as usize),
+            LPARAM(0),
+        )
+        .context("could not send flash to border")
+    }
+
+    /// Destroys the border. The tracking window isn't affected.
+    pub fn detach(self) {
+        destroy_border_for_window(self.tracking_window());
+    }
+
+    /// The border's own window.
+    pub fn border_window(&self) -> HWND {
+        HWND(self.border_window as _)
+    }
+}
+
+// Borders still need location, focus, etc. events even if the app embedding us never called
+// start(), so we install our own hook on a dedicated thread the first time it's needed. start()
+// removes it again, since its own hooks would deliver every event a second time.
+fn install_attach_hook() {
+    let mut hook_thread = ATTACH_HOOK_THREAD.lock().unwrap();
+    // This is checked with the lock held so start() can't slip in between the check and the install
+    if hook_thread.is_some() || EVENT_HOOK_INSTALLED.load(Ordering::SeqCst) {
+        return;
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    let _ = thread::spawn(move || unsafe {
+        // We don't skip our own process here since the embedding app may want to border its own
+        // windows. Our border windows are filtered out by has_filtered_style() anyways.
+        let hooks = event_hook::set_event_hooks(
+            Some(event_hook::handle_attached_win_event),
+            WINEVENT_OUTOFCONTEXT,
+        );
+        if hooks.is_empty() {
+            error!("could not install event hook for attached borders");
+            let _ = sender.send(None);
+            return;
+        }
+
+        // PostThreadMessageW fails until the thread has a message queue, so make sure it has one
+        // before anyone gets our thread id
+        let mut message = MSG::default();
+        let _ = PeekMessageW(&mut message, HWND::default(), 0, 0, PM_NOREMOVE);
+        let _ = sender.send(Some(GetCurrentThreadId()));
+
+        while GetMessageW(&mut message, HWND::default(), 0, 0).into() {
+            let _ = TranslateMessage(&message);
+            DispatchMessageW(&message);
+        }
+
+        // Hooks can only be removed from the thread that installed them
+        for hook in hooks {
+            if !UnhookWinEvent(hook).as_bool() {
+                error!("could not unhook event hook for attached borders");
+            }
+        }
+    });
+
+    *hook_thread = receiver.recv().ok().flatten();
+}
+
+// Called by start() once its own hooks are in place. Attached borders get their events from those
+// from now on.
+pub(crate) fn remove_attach_hook() {
+    let Some(thread_id) = ATTACH_HOOK_THREAD.lock().unwrap().take() else {
+        return;
+    };
+
+    log_if_err!(
+        unsafe { PostThreadMessageW(thread_id, WM_QUIT, WPARAM(0), LPARAM(0)) }
+            .context("border::remove_attach_hook")
+    );
+}
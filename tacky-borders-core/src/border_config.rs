@@ -0,0 +1,1080 @@
+use crate::accessibility::{self, AccessibilityConfig};
+use crate::animations::{AnimationDef, Animations};
+use crate::badges::BadgesConfig;
+use crate::colors::{ColorConfig, PalettePreset};
+use crate::cursor_ring::CursorRingConfig;
+use crate::do_not_disturb::{self, DoNotDisturbConfig};
+use crate::edges::{BorderStyle, EdgeColorsConfig};
+use crate::effects::{
+    AdaptiveQualityConfig, ContrastGuardConfig, HoverPeekConfig, NoiseConfig, NotRespondingConfig,
+    ParticlesConfig, RippleConfig,
+};
+use crate::game_mode::GameModeConfig;
+use crate::hotkeys::Hotkeys;
+use crate::migrations;
+use crate::monitor_frames::MonitorFrameConfig;
+use crate::notifications::{self, NotificationKind, NotificationLevel};
+use crate::presentation::{self, PresentationConfig};
+use crate::renderer::RendererBackend;
+use crate::rule_engine::{self, Condition};
+use crate::seasons::{self, Season};
+use crate::state;
+use crate::strict;
+use crate::strokes::{ActiveExtraConfig, StrokeConfig};
+use crate::themes;
+use crate::timer::TimerConfig;
+use crate::utils::expand_path;
+use anyhow::{anyhow, Context};
+use dirs::home_dir;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, DirBuilder};
+use std::path::{self, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex};
+
+pub static CONFIG: LazyLock<Mutex<Config>> = LazyLock::new(|| {
+    Mutex::new(match Config::create_config() {
+        Ok(config) => config,
+        Err(e) => {
+            error!("could not read config.yaml: {e}");
+            notifications::notify(
+                NotificationKind::Error,
+                "Could not read config.yaml",
+                &format!("{e}"),
+            );
+            Config::default()
+        }
+    })
+});
+pub(crate) const DEFAULT_CONFIG: &str = include_str!("resources/config.yaml");
+
+// Set by --portable. Portable mode can also be turned on with a portable.txt next to the .exe.
+static PORTABLE: AtomicBool = AtomicBool::new(false);
+const PORTABLE_MARKER: &str = "portable.txt";
+
+// Set by --config, which takes priority over every other place a config can be
+static CONFIG_PATH_OVERRIDE: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+// Set by --strict, which turns on strict mode even if the config doesn't
+static STRICT: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Config {
+    // Which version of the config format this is. Older configs are upgraded when they're loaded.
+    pub version: Option<u32>,
+    // Save the upgraded config over the old one (which is backed up to config.yaml.bak first)
+    #[serde(default)]
+    pub rewrite_migrated: bool,
+    // Fail to load instead of ignoring unknown options
+    #[serde(default)]
+    pub strict: bool,
+    pub global: Global,
+    pub window_rules: Vec<WindowRule>,
+    #[serde(default)]
+    pub groups: HashMap<String, WindowGroup>,
+    // Named animations made out of the built-in ones, for active/inactive to list like any other
+    #[serde(default)]
+    pub animation_defs: HashMap<String, AnimationDef>,
+    #[serde(default)]
+    pub hotkeys: Hotkeys,
+    #[serde(default)]
+    pub timer: TimerConfig,
+    #[serde(default)]
+    pub accessibility: AccessibilityConfig,
+    #[serde(default)]
+    pub game_mode: GameModeConfig,
+    #[serde(default)]
+    pub do_not_disturb: DoNotDisturbConfig,
+    #[serde(default)]
+    pub presentation: PresentationConfig,
+    pub monitor_frames: Option<MonitorFrameConfig>,
+    pub cursor_ring: Option<CursorRingConfig>,
+}
+
+// Windows whose rules share a group use the group's colors, and they all light up together
+// whenever any one of them is focused
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WindowGroup {
+    pub active_color: Option<ColorConfig>,
+    pub inactive_color: Option<ColorConfig>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Global {
+    pub border_width: f32,
+    pub border_offset: i32,
+    pub border_radius: f32,
+    // These can be left out when palette_preset is set
+    #[serde(default)]
+    pub active_color: ColorConfig,
+    #[serde(default)]
+    pub inactive_color: ColorConfig,
+    // A built-in pair of colors to use instead of active_color and inactive_color
+    pub palette_preset: Option<PalettePreset>,
+    // The name of a theme in the themes folder, which replaces the look set here
+    pub theme: Option<String>,
+    // Dress borders up for holidays, like candy cane stripes in December
+    pub seasonal: Option<bool>,
+    // Seasons to use on top of the built-in ones (or instead of the ones with the same names)
+    pub seasons: Option<Vec<Season>>,
+    pub animations: Option<Animations>,
+    #[serde(alias = "init_delay")]
+    pub initialize_delay: Option<u64>, // Adjust delay when creating new windows/borders
+    #[serde(alias = "restore_delay")]
+    pub unminimize_delay: Option<u64>, // Adjust delay when restoring minimized windows
+    pub script: Option<String>, // Lua script that can define hooks like active_color(win)
+    #[serde(alias = "quality")]
+    pub render_quality: Option<RenderQuality>,
+    pub antialiasing: Option<bool>,
+    pub supersampling: Option<u32>,
+    pub blend_mode: Option<BlendMode>,
+    pub noise: Option<NoiseConfig>,
+    pub plugin: Option<String>,
+    pub rect_adjust: Option<RectAdjust>,
+    // Give tool windows (palettes, detached panels, etc.) owned by a bordered window a border too
+    pub follow_owned: Option<bool>,
+    pub group_color: Option<ColorConfig>,
+    // Shift border colors to counteract Night Light while it's on
+    pub night_light_compensation: Option<bool>,
+    pub night_light_temperature: Option<f32>, // The color temperature (in kelvin) Night Light uses
+    pub contrast_guard: Option<ContrastGuardConfig>,
+    pub badges: Option<BadgesConfig>,
+    pub not_responding: Option<NotRespondingConfig>,
+    pub progress_color: Option<ColorConfig>,
+    // Different colors for the focused border's top, right, bottom, and left edges
+    pub edge_colors: Option<EdgeColorsConfig>,
+    // Dashes drawn over the border in this color, e.g. red over white for candy cane stripes
+    pub dash_color: Option<ColorConfig>,
+    // A preset for the edge colors, like 'bevel'
+    pub style: Option<BorderStyle>,
+    // Extra strokes drawn around the main border, from the inside out
+    pub strokes: Option<Vec<StrokeConfig>>,
+    // A halo around the focused window's border
+    pub active_extra: Option<ActiveExtraConfig>,
+    pub placement: Option<BorderPlacement>,
+    // Keep the border from going past the edges of the screen or over the taskbar
+    pub clip_to_work_area: Option<bool>,
+    // Never draw over the taskbar, Start menu, or notification flyouts
+    pub protect_taskbar: Option<bool>,
+    // Stop animating borders while other windows completely cover them
+    pub pause_when_occluded: Option<bool>,
+    // What borders do when another window ends up between them and their window in the z-order
+    pub zorder_policy: Option<ZOrderPolicy>,
+    // Never draw borders over the windows stacked above their window
+    pub occlusion_clipping: Option<bool>,
+    // A ring that expands out from the border when its window gets focus
+    pub ripple: Option<RippleConfig>,
+    // Sparkles or snow that come off the focused window's border
+    pub particles: Option<ParticlesConfig>,
+    // Hide inactive borders until the cursor rests on their window
+    pub hover_peek: Option<HoverPeekConfig>,
+    // Lower the animation fps and turn off expensive effects while borders can't keep up
+    pub adaptive_quality: Option<AdaptiveQualityConfig>,
+    // The color borders flash with for the 'locate' command
+    pub locate_color: Option<ColorConfig>,
+    // Draw with Direct2D or GDI (the default picks GDI if there's no hardware acceleration), or
+    // the experimental Overlay
+    pub renderer: Option<RendererBackend>,
+    // Save the profile, theme, and animation toggles picked from the tray menu to state.yaml
+    pub remember_tray_choices: Option<bool>,
+    // Which popups to show for reloads and errors
+    pub notifications: Option<NotificationLevel>,
+    // Look for a newer release on GitHub when starting up
+    pub check_for_updates: Option<bool>,
+    // Let the tray's update item install the update instead of opening the release page
+    pub self_update: Option<bool>,
+    // How borders find out about windows opening, moving, getting focus, etc.
+    pub tracking_backend: Option<TrackingBackend>,
+    // Also listen to UI Automation for the windows that don't send every WinEvent (some UWP apps)
+    pub uia_tracking: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WindowRule {
+    #[serde(rename = "match")]
+    pub kind: Option<MatchKind>,
+    pub name: Option<String>,
+    pub strategy: Option<MatchStrategy>,
+    pub when: Option<Condition>,
+    // The rule doesn't apply to windows that match this, e.g. to leave out one kind of window from
+    // a rule that matches a whole app
+    pub not: Option<Condition>,
+    // Rules with a higher priority go first when several match the same window. Rules with the
+    // same priority go in the order they're written. Defaults to 0.
+    pub priority: Option<i32>,
+    pub merge: Option<RuleMerge>,
+    pub border_width: Option<f32>,
+    pub border_offset: Option<i32>,
+    pub border_radius: Option<f32>,
+    pub active_color: Option<ColorConfig>,
+    pub inactive_color: Option<ColorConfig>,
+    pub palette_preset: Option<PalettePreset>,
+    pub enabled: Option<bool>,
+    pub animations: Option<Animations>,
+    #[serde(alias = "init_delay")]
+    pub initialize_delay: Option<u64>,
+    #[serde(alias = "restore_delay")]
+    pub unminimize_delay: Option<u64>,
+    #[serde(alias = "quality")]
+    pub render_quality: Option<RenderQuality>,
+    pub antialiasing: Option<bool>,
+    pub supersampling: Option<u32>,
+    pub blend_mode: Option<BlendMode>,
+    pub noise: Option<NoiseConfig>,
+    pub plugin: Option<String>,
+    pub rect_adjust: Option<RectAdjust>,
+    // Give tool windows (palettes, detached panels, etc.) owned by a bordered window a border too
+    pub follow_owned: Option<bool>,
+    pub group_color: Option<ColorConfig>,
+    // The name of a group in the config's 'groups'
+    pub group: Option<String>,
+    pub night_light_compensation: Option<bool>,
+    pub contrast_guard: Option<ContrastGuardConfig>,
+    pub badges: Option<BadgesConfig>,
+    pub not_responding: Option<NotRespondingConfig>,
+    pub progress_color: Option<ColorConfig>,
+    pub edge_colors: Option<EdgeColorsConfig>,
+    pub style: Option<BorderStyle>,
+    pub dash_color: Option<ColorConfig>,
+    pub strokes: Option<Vec<StrokeConfig>>,
+    pub active_extra: Option<ActiveExtraConfig>,
+    pub placement: Option<BorderPlacement>,
+    pub clip_to_work_area: Option<bool>,
+    pub protect_taskbar: Option<bool>,
+    pub pause_when_occluded: Option<bool>,
+    pub zorder_policy: Option<ZOrderPolicy>,
+    pub occlusion_clipping: Option<bool>,
+    pub ripple: Option<RippleConfig>,
+    pub particles: Option<ParticlesConfig>,
+    pub hover_peek: Option<HoverPeekConfig>,
+    pub locate_color: Option<ColorConfig>,
+    pub renderer: Option<RendererBackend>,
+    // Let the border of an always-on-top window (like a volume OSD or picture-in-picture) be always
+    // on top too, right above that window. Otherwise, it stays below every always-on-top window.
+    pub topmost: Option<bool>,
+    // Keep polling the window's position for a bit after it moves, for windows that animate their
+    // size and position faster than we get events for (like picture-in-picture videos)
+    pub high_frequency_tracking: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum MatchKind {
+    Title,
+    Class,
+}
+
+// What a rule does with the rules that come after it when they match the same window
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum RuleMerge {
+    // Only this rule is used
+    #[default]
+    #[serde(alias = "replace")]
+    Replace,
+    // This rule's options are put on top of the next matching rule's
+    #[serde(alias = "overlay")]
+    Overlay,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum MatchStrategy {
+    Equals,
+    Contains,
+    Regex,
+}
+
+// Presets for antialiasing and supersampling. These can be overridden individually using the
+// 'antialiasing' and 'supersampling' options.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum RenderQuality {
+    #[serde(alias = "low")]
+    Low,
+    #[serde(alias = "medium")]
+    Medium,
+    #[serde(alias = "high")]
+    High,
+}
+
+// Unset options serialize as null, which would bury the ones that are set
+fn remove_nulls(value: &mut Value) {
+    match value {
+        Value::Mapping(mapping) => {
+            mapping.retain(|_, value| !value.is_null());
+            mapping.values_mut().for_each(remove_nulls);
+        }
+        Value::Sequence(sequence) => sequence.iter_mut().for_each(remove_nulls),
+        _ => {}
+    }
+}
+
+impl Global {
+    // The colors for a window rule before groups and scripts get a say. A rule's own colors win over
+    // its palette, which wins over the global palette, which wins over the global colors.
+    pub fn base_colors(&self, window_rule: &WindowRule) -> (ColorConfig, ColorConfig) {
+        let (active, inactive) = match window_rule.palette_preset.or(self.palette_preset) {
+            Some(palette) => palette.colors(),
+            None => (self.active_color.clone(), self.inactive_color.clone()),
+        };
+
+        (
+            window_rule.active_color.clone().unwrap_or(active),
+            window_rule.inactive_color.clone().unwrap_or(inactive),
+        )
+    }
+}
+
+impl RenderQuality {
+    pub fn antialiasing(&self) -> bool {
+        !matches!(self, RenderQuality::Low)
+    }
+
+    pub fn supersampling(&self) -> u32 {
+        match self {
+            RenderQuality::Low | RenderQuality::Medium => 1,
+            RenderQuality::High => 2,
+        }
+    }
+}
+
+// How the top layer of the border is composited onto the layers beneath it (e.g. the active color
+// fading in over the inactive color). Note that DWM always composites the border window itself
+// normally over the desktop, so these modes can't blend with other windows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    Add,
+    Lighten,
+}
+
+// Where the border is drawn relative to the window's edge. Inner borders overlap the window itself,
+// which is useful for maximized windows where there's no room outside of them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum BorderPlacement {
+    #[default]
+    #[serde(alias = "outer")]
+    Outer,
+    #[serde(alias = "inner")]
+    Inner,
+}
+
+// What a border does when another window ends up between it and its window in the z-order (like a
+// window that opens right above it), since the border would be drawn over that window otherwise
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ZOrderPolicy {
+    // Move the border back down to right above its window
+    #[default]
+    #[serde(alias = "lower")]
+    Lower,
+    // Leave the border where it is, but don't draw over the windows in between
+    #[serde(alias = "clip")]
+    Clip,
+    // Leave the border where it is, drawn over the windows in between
+    #[serde(alias = "ignore")]
+    Ignore,
+}
+
+// Where window events come from. The shell hook is used on its own when WinEvent hooks can't be
+// installed (some sandboxed or remote sessions block them).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum TrackingBackend {
+    #[default]
+    #[serde(alias = "winevent", alias = "win_event")]
+    WinEvent,
+    // The shell's window messages, plus polling for moves, minimizes, and hides. Slower to follow
+    // windows around, but it doesn't need any hooks.
+    #[serde(alias = "shellhook", alias = "shell_hook")]
+    ShellHook,
+}
+
+// Per-side pixel adjustments for windows whose reported frame doesn't line up with what they
+// actually draw. Positive values move that edge of the border outwards, negative values inwards.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct RectAdjust {
+    #[serde(default)]
+    pub left: i32,
+    #[serde(default)]
+    pub top: i32,
+    #[serde(default)]
+    pub right: i32,
+    #[serde(default)]
+    pub bottom: i32,
+}
+
+impl RectAdjust {
+    pub fn scale(&self, dpi: f32) -> Self {
+        let scale = |value: i32| (value as f32 * dpi / 96.0).round() as i32;
+        RectAdjust {
+            left: scale(self.left),
+            top: scale(self.top),
+            right: scale(self.right),
+            bottom: scale(self.bottom),
+        }
+    }
+}
+
+impl Config {
+    pub fn create_config() -> anyhow::Result<Self> {
+        let config_dir = Self::get_config_dir()?;
+        let config_path = Self::get_config_path()?;
+
+        // If the config.yaml does not exist, try to create it
+        if !fs::exists(&config_path).context("could not check if config path exists")? {
+            let default_contents = DEFAULT_CONFIG.as_bytes();
+            fs::write(&config_path, default_contents)
+                .context("could not create default config.yaml")?;
+
+            info!("generating default config in {}", config_dir.display());
+        }
+
+        let (config, migrated, theme_result) = Self::read_active_config()?;
+        if let Err(e) = theme_result {
+            error!("could not load theme: {e:#}");
+            notifications::notify(
+                NotificationKind::Error,
+                "Could not load theme",
+                &format!("{e:#}"),
+            );
+        }
+        notifications::set_level(config.global.notifications.unwrap_or_default());
+        accessibility::reset(&config.accessibility);
+        do_not_disturb::reset(&config.do_not_disturb);
+        presentation::reset(&config.presentation);
+
+        // Serializing the config loses its comments, so this is opt-in
+        if let Some(migrated) = migrated.filter(|_| config.rewrite_migrated) {
+            let config_path = Self::get_active_config_path()?;
+            let backup_path = config_path.with_extension("yaml.bak");
+            fs::copy(&config_path, &backup_path).context("could not back up config")?;
+
+            let migrated_contents =
+                serde_yaml::to_string(&migrated).context("could not serialize migrated config")?;
+            fs::write(&config_path, migrated_contents)
+                .context("could not write migrated config")?;
+
+            info!(
+                "saved migrated {}; the old one is in {}",
+                config_path.display(),
+                backup_path.display()
+            );
+        }
+
+        Ok(config)
+    }
+
+    // Read the config the same way create_config does, but without touching anything: no default
+    // config is written, migrations aren't saved, and the modules that keep state between loads
+    // aren't reset. This is for looking at the config from outside the running instance.
+    pub fn load() -> anyhow::Result<Self> {
+        let (config, _, theme_result) = Self::read_active_config()?;
+        if let Err(e) = theme_result {
+            warn!("could not load theme: {e:#}");
+        }
+        Ok(config)
+    }
+
+    // The active config with its theme and season applied, the upgraded config as YAML if it had
+    // to be migrated, and whether the theme could be loaded
+    fn read_active_config() -> anyhow::Result<(Self, Option<Value>, anyhow::Result<()>)> {
+        let config_path = Self::get_active_config_path()?;
+        let contents = fs::read_to_string(&config_path)
+            .with_context(|| format!("could not read {}", config_path.display()))?;
+
+        let (mut config, migrated) = Self::migrate_and_parse(&contents)?;
+        let theme_result = config.apply_theme();
+        config.apply_season();
+
+        Ok((config, migrated, theme_result))
+    }
+
+    // Parse a config, upgrading it first if it's from an older version
+    pub fn parse(contents: &str) -> anyhow::Result<Self> {
+        Ok(Self::migrate_and_parse(contents)?.0)
+    }
+
+    // Also returns the upgraded config as YAML if anything had to change
+    fn migrate_and_parse(contents: &str) -> anyhow::Result<(Self, Option<Value>)> {
+        let mut value: Value = serde_yaml::from_str(contents)?;
+
+        let changes = migrations::migrate(&mut value).context("could not migrate config")?;
+        for change in changes.iter() {
+            warn!("migrated config: {change}");
+        }
+
+        let strict =
+            Self::is_strict() || value.get("strict").and_then(Value::as_bool) == Some(true);
+        let mut config: Config = match strict {
+            true => strict::parse_config(value.clone())?,
+            false => serde_yaml::from_value(value.clone())?,
+        };
+        config.expand_paths();
+
+        let unknown_animations = strict::unknown_animations(&config);
+        match (strict, unknown_animations.is_empty()) {
+            (_, true) => {}
+            (true, false) => {
+                return Err(anyhow!(
+                    "found unknown animations (strict mode is on):\n{}",
+                    unknown_animations.join("\n")
+                ))
+            }
+            // These get left out when borders are created (see Animations::resolve_custom())
+            (false, false) => {
+                for problem in unknown_animations {
+                    warn!("{}", problem.trim_start());
+                }
+            }
+        }
+
+        let migrated = match changes.is_empty() {
+            true => None,
+            false => Some(value),
+        };
+
+        Ok((config, migrated))
+    }
+
+    // A theme picked from the tray menu or with the 'theme' command wins over the config's. An empty
+    // name there means no theme at all.
+    fn apply_theme(&mut self) -> anyhow::Result<()> {
+        let theme_name = state::tray_choices()
+            .theme
+            .or_else(|| self.global.theme.clone())
+            .filter(|name| !name.is_empty());
+        let Some(theme_name) = theme_name else {
+            return Ok(());
+        };
+
+        themes::load_theme(&theme_name)?.apply(&mut self.global);
+        debug!("applied theme '{theme_name}'");
+        Ok(())
+    }
+
+    // Seasons go on top of the theme, since they only last a few days
+    fn apply_season(&mut self) {
+        if !self.global.seasonal.unwrap_or(false) {
+            return;
+        }
+
+        let seasons = self.global.seasons.clone().unwrap_or_default();
+        if let Some(season) = seasons::current_season(&seasons) {
+            season.apply(&mut self.global);
+            debug!("applied season '{}'", season.name);
+        }
+    }
+
+    // The config as borders actually see it, as YAML: with the tray's profile, theme, and animation
+    // toggles applied, migrations done, paths expanded, and the runtime overrides from state.yaml
+    // listed at the end. Options that aren't set are left out. The running instance answers the
+    // `export` ipc command with this, for its own config.
+    pub fn export_effective(&self) -> anyhow::Result<String> {
+        let mut config = self.clone();
+        let choices = state::tray_choices();
+
+        let rule_animations = config
+            .window_rules
+            .iter_mut()
+            .map(|rule| &mut rule.animations);
+        for animations in std::iter::once(&mut config.global.animations).chain(rule_animations) {
+            if let Some(animations) = animations.as_mut() {
+                for anim_type in choices.disabled_animations.iter() {
+                    animations.active.remove(anim_type);
+                    animations.inactive.remove(anim_type);
+                }
+            }
+        }
+
+        let mut value = serde_yaml::to_value(&config).context("could not serialize config")?;
+        remove_nulls(&mut value);
+
+        if let Value::Mapping(ref mut mapping) = value {
+            let overrides = state::all_overrides();
+            if !overrides.is_empty() {
+                mapping.insert(
+                    "runtime_overrides".into(),
+                    serde_yaml::to_value(overrides).context("could not serialize overrides")?,
+                );
+            }
+        }
+
+        let theme = choices
+            .theme
+            .or(config.global.theme.clone())
+            .filter(|name| !name.is_empty());
+        let header = format!(
+            "# effective config from {}
+# profile: {}
+# theme: {}
+# accessibility mode: {}
+",
+            Self::get_config_path()?.display(),
+            choices.profile.as_deref().unwrap_or("none"),
+            theme.as_deref().unwrap_or("none"),
+            if config.accessibility.enabled {
+                "on"
+            } else {
+                "off"
+            },
+        );
+
+        let contents = serde_yaml::to_string(&value).context("could not serialize config")?;
+        Ok(header + &contents)
+    }
+
+    // Problems that don't stop the config from loading, but probably aren't what was meant
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        for (i, rule) in self.window_rules.iter().enumerate() {
+            problems.extend(
+                rule_engine::validate_rule(rule)
+                    .into_iter()
+                    .map(|problem| format!("window_rules[{i}]: {problem}")),
+            );
+
+            if let Some(ref group) = rule.group {
+                if !self.groups.contains_key(group) {
+                    problems.push(format!(
+                        "window_rules[{i}]: group '{group}' is not defined in 'groups'"
+                    ));
+                }
+            }
+        }
+
+        problems
+    }
+
+    // Path-valued options can use '~', %APPDATA%-style, and ${VAR}-style environment variables
+    fn expand_paths(&mut self) {
+        let rule_paths = self.window_rules.iter_mut().map(|rule| &mut rule.plugin);
+        for path in std::iter::once(&mut self.global.plugin).chain(rule_paths) {
+            if let Some(path) = path.as_mut() {
+                *path = expand_path(path);
+            }
+        }
+    }
+
+    // Profiles are alternate configs in the 'profiles' folder next to config.yaml
+    pub fn get_profiles_dir() -> anyhow::Result<PathBuf> {
+        Ok(Self::get_config_dir()?.join("profiles"))
+    }
+
+    // The names of all the profiles, sorted
+    pub fn list_profiles() -> Vec<String> {
+        let Some(entries) = Self::get_profiles_dir()
+            .ok()
+            .and_then(|dir| fs::read_dir(dir).ok())
+        else {
+            return Vec::new();
+        };
+
+        let mut profiles: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "yaml")
+            })
+            .filter_map(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+            .collect();
+        profiles.sort();
+
+        profiles
+    }
+
+    // Keep the config, state, and logs next to the .exe instead of in the home directory, e.g. for
+    // running tacky-borders from a USB stick. This has to be called before anything uses them.
+    pub fn set_portable(portable: bool) {
+        PORTABLE.store(portable, Ordering::SeqCst);
+    }
+
+    pub fn is_portable() -> bool {
+        PORTABLE.load(Ordering::SeqCst)
+            || get_exe_dir().is_ok_and(|exe_dir| exe_dir.join(PORTABLE_MARKER).exists())
+    }
+
+    pub fn set_strict(strict: bool) {
+        STRICT.store(strict, Ordering::SeqCst);
+    }
+
+    pub fn is_strict() -> bool {
+        STRICT.load(Ordering::SeqCst)
+    }
+
+    // Use this config file instead of looking for one. This has to be called before anything uses
+    // the config.
+    pub fn set_config_path(path: PathBuf) {
+        let path = path::absolute(&path).unwrap_or(path);
+        *CONFIG_PATH_OVERRIDE.lock().unwrap() = Some(path);
+    }
+
+    // The file passed with --config, if there was one
+    pub fn get_config_path_override() -> Option<PathBuf> {
+        CONFIG_PATH_OVERRIDE.lock().unwrap().clone()
+    }
+
+    // Everywhere a config.yaml is looked for, in order. The first one that exists is used.
+    pub fn get_config_path_candidates() -> Vec<PathBuf> {
+        let mut candidates = Vec::new();
+
+        if let Some(app_data) = env::var_os("APPDATA") {
+            candidates.push(PathBuf::from(app_data).join("tacky-borders"));
+        }
+        if let Some(xdg_config_home) = env::var_os("XDG_CONFIG_HOME") {
+            candidates.push(PathBuf::from(xdg_config_home).join("tacky-borders"));
+        }
+        if let Some(home_dir) = home_dir() {
+            candidates.push(home_dir.join(".config").join("tacky-borders"));
+        }
+        if let Ok(exe_dir) = get_exe_dir() {
+            candidates.push(exe_dir);
+        }
+
+        candidates
+            .into_iter()
+            .map(|dir| dir.join("config.yaml"))
+            .collect()
+    }
+
+    // The config file that's read, which may not exist yet. The state, log, profiles, etc. all go
+    // in the same folder as it.
+    pub fn get_config_path() -> anyhow::Result<PathBuf> {
+        if let Some(path) = Self::get_config_path_override() {
+            return Ok(path);
+        }
+
+        if Self::is_portable() {
+            return Ok(get_exe_dir()?.join("config.yaml"));
+        }
+
+        if let Some(path) = Self::get_config_path_candidates()
+            .into_iter()
+            .find(|path| path.exists())
+        {
+            return Ok(path);
+        }
+
+        // Nowhere has a config yet, so this is where the default one gets generated
+        let Some(home_dir) = home_dir() else {
+            return Err(anyhow!("could not find home directory!"));
+        };
+
+        Ok(home_dir
+            .join(".config")
+            .join("tacky-borders")
+            .join("config.yaml"))
+    }
+
+    // The file the config is actually read from. A profile picked from the tray menu replaces
+    // config.yaml entirely, and so does the presentation profile while presenting.
+    pub fn get_active_config_path() -> anyhow::Result<PathBuf> {
+        let profile = presentation::profile_override().or(state::tray_choices().profile);
+        if let Some(profile) = profile {
+            let profile_path = Self::get_profiles_dir()?.join(format!("{profile}.yaml"));
+            match profile_path.exists() {
+                true => return Ok(profile_path),
+                false => warn!("could not find profile '{profile}'; using config.yaml instead"),
+            }
+        }
+
+        Self::get_config_path()
+    }
+
+    // Write options into the global section of the config file that's in use, leaving everything
+    // else in it (comments included) as it was. Returns the file that was written.
+    pub fn save_global_options(options: &[(&str, Value)]) -> anyhow::Result<PathBuf> {
+        let config_path = Self::get_active_config_path()?;
+        let contents = fs::read_to_string(&config_path)
+            .with_context(|| format!("could not read {}", config_path.display()))?;
+
+        let contents = set_global_options(&contents, options)?;
+        fs::write(&config_path, contents)
+            .with_context(|| format!("could not write {}", config_path.display()))?;
+
+        Ok(config_path)
+    }
+
+    pub fn get_config_dir() -> anyhow::Result<PathBuf> {
+        let config_path = Self::get_config_path()?;
+        let Some(config_dir) = config_path.parent() else {
+            return Err(anyhow!(
+                "could not find the folder {} is in",
+                config_path.display()
+            ));
+        };
+        let config_dir = config_dir.to_path_buf();
+
+        // If the config directory doesn't exist, try to create it
+        if !config_dir.exists() {
+            DirBuilder::new()
+                .recursive(true)
+                .create(&config_dir)
+                .context("could not create config directory")?;
+        };
+
+        Ok(config_dir)
+    }
+
+    pub fn reload_config() {
+        let new_config = match Self::create_config() {
+            Ok(config) => {
+                notifications::notify(NotificationKind::Info, "Reloaded config", "");
+                config
+            }
+            Err(e) => {
+                error!("could not reload config: {e}");
+                notifications::notify(
+                    NotificationKind::Error,
+                    "Could not reload config",
+                    &format!("{e}"),
+                );
+                Config::default()
+            }
+        };
+        *CONFIG.lock().unwrap() = new_config;
+    }
+}
+
+// Options are edited as text instead of by serializing the config, which would lose its comments.
+// An option that's already in the section has its line (and any more indented lines under it, for
+// values written as blocks) replaced, and the rest are added right under 'global:'. Editing YAML by
+// hand can go wrong in ways that are hard to see coming (flow style, anchors, ...), so the result
+// is parsed again and nothing is saved unless the options, and only the options, changed.
+fn set_global_options(contents: &str, options: &[(&str, Value)]) -> anyhow::Result<String> {
+    let indent_of = |line: &str| line.len() - line.trim_start().len();
+    let is_top_level = |line: &str| !line.is_empty() && !line.starts_with([' ', '#']);
+    let is_global_header = |line: &str| {
+        line.strip_prefix("global:").is_some_and(|rest| {
+            let rest = rest.trim();
+            rest.is_empty() || rest.starts_with('#')
+        })
+    };
+
+    let mut lines: Vec<String> = contents.lines().map(str::to_string).collect();
+    let global = match lines.iter().position(|line| is_global_header(line)) {
+        Some(index) => index,
+        None => {
+            lines.push("global:".to_string());
+            lines.len() - 1
+        }
+    };
+
+    for (key, value) in options {
+        let value =
+            serde_yaml::to_string(value).with_context(|| format!("could not serialize {key}"))?;
+        let value = value.trim_end();
+        if value.contains('\n') {
+            return Err(anyhow!("{key} does not fit on one line"));
+        }
+
+        let end = (global + 1..lines.len())
+            .find(|&i| is_top_level(&lines[i]))
+            .unwrap_or(lines.len());
+        let indent = (global + 1..end)
+            .map(|i| lines[i].as_str())
+            .find(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+            .map_or(2, indent_of);
+        let new_line = format!("{}{key}: {value}", " ".repeat(indent));
+
+        let existing = (global + 1..end).find(|&i| {
+            indent_of(&lines[i]) == indent
+                && lines[i]
+                    .trim_start()
+                    .strip_prefix(key)
+                    .is_some_and(|rest| rest.starts_with(':'))
+        });
+        match existing {
+            Some(i) => {
+                // Blocks can have blank lines in them, so they only end at the next line that isn't
+                // indented any further. The blank lines right before that one stay, since they're
+                // what separates it from this option.
+                let next = (i + 1..end)
+                    .find(|&j| !lines[j].trim().is_empty() && indent_of(&lines[j]) <= indent)
+                    .unwrap_or(end);
+                let block_end = (i + 1..next)
+                    .rev()
+                    .find(|&j| !lines[j].trim().is_empty())
+                    .map_or(i + 1, |j| j + 1);
+                lines.splice(i..block_end, [new_line]);
+            }
+            None => lines.insert(global + 1, new_line),
+        }
+    }
+
+    let mut edited = lines.join("\n");
+    edited.push('\n');
+
+    match only_options_changed(contents, &edited, options) {
+        true => Ok(edited),
+        false => Err(anyhow!(
+            "could not save the options without changing other parts of the config; set them in \
+             the config by hand instead"
+        )),
+    }
+}
+
+// Whether 'after' is 'before' with the options set in the global section, and nothing else changed
+fn only_options_changed(before: &str, after: &str, options: &[(&str, Value)]) -> bool {
+    // An empty config parses to null, and a section with nothing in it does too
+    let parse = |contents: &str| match serde_yaml::from_str(contents) {
+        Ok(Value::Mapping(mapping)) => Some(mapping),
+        Ok(Value::Null) => Some(Mapping::new()),
+        _ => None,
+    };
+    let take_global = |document: &mut Mapping| match document.remove("global") {
+        Some(Value::Mapping(global)) => Some(global),
+        Some(Value::Null) | None => Some(Mapping::new()),
+        Some(_) => None,
+    };
+
+    let (Some(mut before), Some(mut after)) = (parse(before), parse(after)) else {
+        return false;
+    };
+    let (Some(mut global_before), Some(mut global_after)) =
+        (take_global(&mut before), take_global(&mut after))
+    else {
+        return false;
+    };
+
+    for (key, value) in options {
+        global_before.remove(*key);
+        if global_after.remove(*key).as_ref() != Some(value) {
+            return false;
+        }
+    }
+
+    before == after && global_before == global_after
+}
+
+fn get_exe_dir() -> anyhow::Result<PathBuf> {
+    let exe_path = env::current_exe().context("could not get path to tacky-borders.exe")?;
+    match exe_path.parent() {
+        Some(exe_dir) => Ok(exe_dir.to_path_buf()),
+        None => Err(anyhow!(
+            "could not find the directory tacky-borders.exe is in"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(contents: &str, options: &[(&str, Value)]) -> anyhow::Result<String> {
+        set_global_options(contents, options)
+    }
+
+    fn width(width: f64) -> (&'static str, Value) {
+        ("border_width", Value::from(width))
+    }
+
+    #[test]
+    fn set_global_options_replaces_and_adds() {
+        let contents = "\
+# My config
+global:
+  # Thick borders
+  border_width: 2 # px
+  border_radius: -1
+
+window_rules: []
+";
+
+        let edited = set(
+            contents,
+            &[width(4.0), ("active_color", Value::from("#ff0000"))],
+        )
+        .unwrap();
+        assert_eq!(
+            edited,
+            "\
+# My config
+global:
+  active_color: '#ff0000'
+  # Thick borders
+  border_width: 4.0
+  border_radius: -1
+
+window_rules: []
+"
+        );
+    }
+
+    #[test]
+    fn set_global_options_replaces_blocks_with_blank_lines() {
+        let contents = "\
+global:
+  active_color:
+    colors: ['#ffffff', '#000000']
+
+    direction: 45
+  border_width: 2
+
+  border_radius: -1
+";
+
+        let edited = set(contents, &[("active_color", Value::from("#ff0000"))]).unwrap();
+        assert_eq!(
+            edited,
+            "\
+global:
+  active_color: '#ff0000'
+  border_width: 2
+
+  border_radius: -1
+"
+        );
+
+        let edited = set(contents, &[width(6.0)]).unwrap();
+        assert!(edited.contains("    direction: 45\n  border_width: 6.0\n\n  border_radius"));
+    }
+
+    #[test]
+    fn set_global_options_finds_headers_with_comments() {
+        let contents = "global: # shared by every border\n  border_width: 2\n";
+
+        let edited = set(contents, &[width(3.0)]).unwrap();
+        assert_eq!(
+            edited,
+            "global: # shared by every border\n  border_width: 3.0\n"
+        );
+    }
+
+    #[test]
+    fn set_global_options_adds_missing_sections() {
+        let cases = [
+            ("", "global:\n  border_width: 4.0\n"),
+            (
+                "window_rules: []\n",
+                "window_rules: []\nglobal:\n  border_width: 4.0\n",
+            ),
+            ("global:\n", "global:\n  border_width: 4.0\n"),
+        ];
+
+        for (contents, expected) in cases {
+            assert_eq!(
+                set(contents, &[width(4.0)]).unwrap(),
+                expected,
+                "{contents:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn set_global_options_refuses_what_it_cant_edit() {
+        for contents in [
+            // Flow style would end up with a second global section
+            "global: { border_radius: 2 }\n",
+            // A list where the section should be
+            "global:\n  - border_width: 2\n",
+            // Already broken
+            "global:\n  border_width: [\n",
+            // A quoted key isn't found, so it'd end up in the section twice
+            "global:\n  \"border_width\": 2\n",
+        ] {
+            assert!(set(contents, &[width(4.0)]).is_err(), "{contents:?}");
+        }
+    }
+}
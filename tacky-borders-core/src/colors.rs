@@ -0,0 +1,1335 @@
+use anyhow::anyhow;
+use core::f32;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use windows::Win32::Foundation::{BOOL, FALSE, RECT};
+use windows::Win32::Graphics::Direct2D::Common::{D2D1_COLOR_F, D2D1_GRADIENT_STOP, D2D_POINT_2F};
+use windows::Win32::Graphics::Direct2D::{
+    ID2D1Brush, ID2D1RenderTarget, D2D1_BRUSH_PROPERTIES, D2D1_EXTEND_MODE_CLAMP, D2D1_GAMMA_2_2,
+    D2D1_LINEAR_GRADIENT_BRUSH_PROPERTIES,
+};
+use windows::Win32::Graphics::Dwm::DwmGetColorizationColor;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ColorConfig {
+    SolidConfig(String),
+    GradientConfig(GradientConfig),
+}
+
+impl Default for ColorConfig {
+    fn default() -> Self {
+        Self::SolidConfig("#000000".to_string())
+    }
+}
+
+// Built-in active/inactive color pairs. The color-blind palettes stay apart in both hue and
+// brightness, so they're easy to tell apart even if one of them is hard to see.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PalettePreset {
+    #[serde(alias = "deuteranopia")]
+    Deuteranopia,
+    #[serde(alias = "protanopia")]
+    Protanopia,
+    #[serde(alias = "high_contrast")]
+    HighContrast,
+    #[serde(alias = "catppuccin")]
+    Catppuccin,
+    #[serde(alias = "gruvbox")]
+    Gruvbox,
+}
+
+impl PalettePreset {
+    // Returns the (active, inactive) colors
+    pub fn colors(&self) -> (ColorConfig, ColorConfig) {
+        let (active, inactive) = match self {
+            // Orange and blue, which neither kind of red-green color blindness mixes up
+            PalettePreset::Deuteranopia => ("#e69f00", "#2c4a6e"),
+            // Reds look dark without working red cones, so this sticks to blue and yellow
+            PalettePreset::Protanopia => ("#56b4e9", "#5c5334"),
+            PalettePreset::HighContrast => ("#ffffff", "#000000"),
+            // Mocha's mauve and surface1
+            PalettePreset::Catppuccin => ("#cba6f7", "#45475a"),
+            // Dark mode's yellow and bg2
+            PalettePreset::Gruvbox => ("#fabd2f", "#504945"),
+        };
+
+        (
+            ColorConfig::SolidConfig(active.to_string()),
+            ColorConfig::SolidConfig(inactive.to_string()),
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GradientConfig {
+    pub colors: Vec<String>,
+    pub direction: GradientDirection,
+    #[serde(default)]
+    pub interpolation: ColorSpace,
+}
+
+// The color space that gradients blend their colors in. Blending in sRGB (what Direct2D does)
+// tends to give dark, muddy midpoints between saturated colors, especially complementary ones.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ColorSpace {
+    #[default]
+    #[serde(alias = "sRGB", alias = "srgb")]
+    Srgb,
+    #[serde(alias = "Linear", alias = "linear")]
+    LinearRgb,
+    #[serde(alias = "OKLAB", alias = "oklab")]
+    Oklab,
+}
+
+// How many extra stops to insert between each pair of colors when interpolating in a color space
+// that Direct2D doesn't support natively
+const INTERPOLATION_STEPS: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum GradientDirection {
+    Angle(String),
+    Coordinates(GradientCoordinates),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GradientCoordinates {
+    pub start: [f32; 2],
+    pub end: [f32; 2],
+}
+
+impl ColorConfig {
+    // Check that every color in here can be parsed, for anything that's about to save or use a
+    // color that didn't come from a config we've already loaded
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let check = |color: &str| match parse_color(color) {
+            Some(_) => Ok(()),
+            None => Err(anyhow!("invalid color '{color}'")),
+        };
+
+        match self {
+            ColorConfig::SolidConfig(color) if color == "accent" => Ok(()),
+            ColorConfig::SolidConfig(color) => check(color),
+            ColorConfig::GradientConfig(gradient) => {
+                if gradient.colors.len() < 2 {
+                    return Err(anyhow!("a gradient needs at least two colors"));
+                }
+                gradient.colors.iter().try_for_each(|color| check(color))?;
+
+                if let GradientDirection::Angle(ref angle) = gradient.direction {
+                    let degree = angle
+                        .strip_suffix("deg")
+                        .and_then(|d| d.trim().parse::<f32>().ok());
+                    if degree.is_none_or(|degree| !degree.is_finite()) {
+                        return Err(anyhow!("invalid gradient direction '{angle}'"));
+                    }
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    // Convert the ColorConfig struct to a Color struct
+    pub fn convert_to_color(&self, is_active_color: bool) -> Color {
+        match self {
+            ColorConfig::SolidConfig(solid_config) => {
+                if solid_config == "accent" {
+                    Color::Solid(Solid {
+                        color: get_accent_color(is_active_color),
+                        opacity: 0.0,
+                    })
+                } else {
+                    Color::Solid(Solid {
+                        color: get_color_from_str(solid_config.as_str()),
+                        opacity: 0.0,
+                    })
+                }
+            }
+            ColorConfig::GradientConfig(gradient_config) => {
+                // We use 'step' to calculate the position of each color in the gradient below
+                let step = 1.0 / (gradient_config.colors.len() - 1) as f32;
+
+                let gradient_stops = gradient_config
+                    .clone()
+                    .colors
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, color)| D2D1_GRADIENT_STOP {
+                        position: i as f32 * step,
+                        color: get_color_from_str(color.as_str()),
+                    })
+                    .collect();
+                let gradient_stops =
+                    interpolate_stops(gradient_stops, gradient_config.interpolation);
+
+                let direction = match gradient_config.direction {
+                    // If we have an angle, we need to convert it into Coordinates
+                    GradientDirection::Angle(ref angle) => {
+                        let Some(degree) = angle
+                            .strip_suffix("deg")
+                            .and_then(|d| d.trim().parse::<f32>().ok())
+                        else {
+                            error!("config contains an invalid gradient direction!");
+                            return Color::default();
+                        };
+
+                        // We multiply degree by -1 to account for the fact that Win32's coordinate
+                        // system has its origin at the top left instead of the bottom left
+                        let rad = -degree * PI / 180.0;
+
+                        // Calculate the slope of the line whilst accounting for edge cases like 90
+                        // and 270 degrees where we would otherwise be dividing by 0 or something
+                        // close to 0.
+                        let m = match degree.abs() % 360.0 {
+                            90.0 | 270.0 => degree.signum() * f32::MAX,
+                            _ => rad.sin() / rad.cos(),
+                        };
+
+                        // y - y_p = m(x - x_p);
+                        // y = m(x - x_p) + y_p;
+                        // y = m*x - m*x_p + y_p;
+                        // b = -m*x_p + y_p;
+
+                        // Calculate the y-intercept of the line such that it goes through the
+                        // center point (0.5, 0.5)
+                        let b = -m * 0.5 + 0.5;
+
+                        // Create the line with the given slope and y-intercept
+                        let line = Line { m, b };
+
+                        // y = mx + b
+                        // 0 = mx + b
+                        // mx = -b
+                        // x = -b/m
+
+                        // y = mx + b
+                        // 1 = mx + b
+                        // mx = 1 - b
+                        // x = (1 - b)/m
+
+                        // When we cross certain angle thresholds, like 90 degrees, we need to flip
+                        // the x values (0.0 and 1.0) that we use to the calculate the start and
+                        // end points below due to the slope changing
+                        let (x_s, x_e) = match degree.abs() % 360.0 {
+                            0.0..90.0 => (0.0, 1.0),
+                            90.0..270.0 => (1.0, 0.0),
+                            270.0..360.0 => (0.0, 1.0),
+                            _ => {
+                                debug!("reached a gradient angle that is not covered by the match statement in colors.rs");
+                                (0.0, 1.0)
+                            }
+                        };
+
+                        // Here, we are checking three cases to make sure the calculated point
+                        // lies within the first quadrant:
+                        //
+                        // Case 1: the y-coordinate at x_s is between 0 and 1
+                        // Case 2: the y-coordinate at x_s is greater than 1
+                        // Case 3: the y-coordinate at x_s is less than 0
+                        let start = match line.plug_in_x(x_s) {
+                            0.0..=1.0 => [x_s, line.plug_in_x(x_s)],
+                            1.0.. => [(1.0 - line.b) / line.m, 1.0],
+                            _ => [-line.b / line.m, 0.0],
+                        };
+
+                        let end = match line.plug_in_x(x_e) {
+                            0.0..=1.0 => [x_e, line.plug_in_x(x_e)],
+                            1.0.. => [(1.0 - line.b) / line.m, 1.0],
+                            _ => [-line.b / line.m, 0.0],
+                        };
+
+                        GradientCoordinates { start, end }
+                    }
+                    GradientDirection::Coordinates(ref coordinates) => coordinates.clone(),
+                };
+
+                Color::Gradient(Gradient {
+                    gradient_stops,
+                    direction,
+                    opacity: 0.0,
+                })
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Line {
+    m: f32,
+    b: f32,
+}
+
+impl Line {
+    fn plug_in_x(&self, x: f32) -> f32 {
+        self.m * x + self.b
+    }
+}
+
+// Colors are stored with straight (not premultiplied) alpha, which is what D2D's brushes take, so a
+// color's alpha is whatever the config says. The fade opacity is kept apart from it: that's how far
+// a fade has gotten, and it goes on the brush. alpha()/set_alpha() only touch the color's own
+// alpha, and fade_opacity()/set_fade_opacity() only touch the fade, so a fade never overwrites a
+// translucent color. Colors only get premultiplied where they're mixed, since mixing straight colors
+// lets a nearly invisible color drag the mix toward its RGB.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Color {
+    Solid(Solid),
+    Gradient(Gradient),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solid {
+    // Straight alpha
+    pub color: D2D1_COLOR_F,
+    // The fade opacity
+    pub opacity: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    pub gradient_stops: Vec<D2D1_GRADIENT_STOP>, // Array of gradient stops
+    pub direction: GradientCoordinates,
+    // The fade opacity
+    pub opacity: f32,
+}
+
+impl Color {
+    pub fn create_brush(
+        &self,
+        render_target: &ID2D1RenderTarget,
+        window_rect: &RECT,
+        brush_properties: &D2D1_BRUSH_PROPERTIES,
+    ) -> windows::core::Result<ID2D1Brush> {
+        match self {
+            Color::Solid(solid) => unsafe {
+                let brush =
+                    render_target.CreateSolidColorBrush(&solid.color, Some(brush_properties))?;
+
+                brush.SetOpacity(solid.opacity);
+
+                Ok(brush.into())
+            },
+            Color::Gradient(gradient) => unsafe {
+                let width = (window_rect.right - window_rect.left) as f32;
+                let height = (window_rect.bottom - window_rect.top) as f32;
+
+                // The direction/GradientCoordinates only range from 0.0 to 1.0, but we need to
+                // convert it into coordinates in terms of pixels
+                let gradient_properties = D2D1_LINEAR_GRADIENT_BRUSH_PROPERTIES {
+                    startPoint: D2D_POINT_2F {
+                        x: gradient.direction.start[0] * width,
+                        y: gradient.direction.start[1] * height,
+                    },
+                    endPoint: D2D_POINT_2F {
+                        x: gradient.direction.end[0] * width,
+                        y: gradient.direction.end[1] * height,
+                    },
+                };
+
+                let gradient_stop_collection = render_target.CreateGradientStopCollection(
+                    &gradient.gradient_stops,
+                    D2D1_GAMMA_2_2,
+                    D2D1_EXTEND_MODE_CLAMP,
+                )?;
+
+                let brush = render_target.CreateLinearGradientBrush(
+                    &gradient_properties,
+                    Some(brush_properties),
+                    &gradient_stop_collection,
+                )?;
+
+                brush.SetOpacity(gradient.opacity);
+
+                Ok(brush.into())
+            },
+        }
+    }
+
+    // The fade opacity multiplies the color's own alpha, instead of replacing it
+    pub fn set_fade_opacity(&mut self, opacity: f32) {
+        match self {
+            Color::Gradient(gradient) => gradient.opacity = opacity,
+            Color::Solid(solid) => solid.opacity = opacity,
+        }
+    }
+
+    pub fn fade_opacity(&self) -> f32 {
+        match self {
+            Color::Gradient(gradient) => gradient.opacity,
+            Color::Solid(solid) => solid.opacity,
+        }
+    }
+
+    // The color's own (straight) alpha, leaving the fade out of it. Gradients give the highest alpha
+    // among their stops.
+    pub fn alpha(&self) -> f32 {
+        match self {
+            Color::Solid(solid) => solid.color.a,
+            Color::Gradient(gradient) => gradient
+                .gradient_stops
+                .iter()
+                .map(|stop| stop.color.a)
+                .fold(0.0, f32::max),
+        }
+    }
+
+    // Replaces the color's own alpha without touching its RGB or the fade. Gradients get it on every
+    // stop.
+    pub fn set_alpha(&mut self, alpha: f32) {
+        let alpha = alpha.clamp(0.0, 1.0);
+        match self {
+            Color::Solid(solid) => solid.color.a = alpha,
+            Color::Gradient(gradient) => {
+                for stop in gradient.gradient_stops.iter_mut() {
+                    stop.color.a = alpha;
+                }
+            }
+        }
+    }
+
+    // The alpha the color is actually drawn with: its own alpha times the fade opacity
+    pub fn drawn_alpha(&self) -> f32 {
+        self.alpha() * self.fade_opacity()
+    }
+
+    // A single color that stands in for this one, with its opacity applied. Gradients use the
+    // average of their stops, for when we can't draw a gradient (i.e. the GDI fallback).
+    pub fn flatten(&self) -> D2D1_COLOR_F {
+        match self {
+            Color::Solid(solid) => D2D1_COLOR_F {
+                a: solid.color.a * solid.opacity,
+                ..solid.color
+            },
+            Color::Gradient(gradient) => {
+                let count = gradient.gradient_stops.len().max(1) as f32;
+                let sum = gradient
+                    .gradient_stops
+                    .iter()
+                    .fold(D2D1_COLOR_F::default(), |sum, stop| {
+                        add_colors(&sum, &premultiply(&stop.color), 1.0)
+                    });
+                let average = unpremultiply(&D2D1_COLOR_F {
+                    r: sum.r / count,
+                    g: sum.g / count,
+                    b: sum.b / count,
+                    a: sum.a / count,
+                });
+
+                D2D1_COLOR_F {
+                    a: average.a * gradient.opacity,
+                    ..average
+                }
+            }
+        }
+    }
+
+    // Two solid colors mixed into one, each weighted by the opacity it would be drawn with. This is
+    // what drawing `top` over `bottom` mid-crossfade should look like, which drawing them one over
+    // the other only gets right for opaque colors: the top one never fully covers the bottom one, so
+    // translucent colors lose alpha halfway through. Gradients can't be mixed like this, so this
+    // returns None for them.
+    pub fn mix(
+        bottom: &Color,
+        bottom_opacity: f32,
+        top: &Color,
+        top_opacity: f32,
+    ) -> Option<Color> {
+        let (Color::Solid(bottom), Color::Solid(top)) = (bottom, top) else {
+            return None;
+        };
+
+        let mixed = add_colors(
+            &scale_color(&premultiply(&bottom.color), bottom_opacity),
+            &premultiply(&top.color),
+            top_opacity,
+        );
+
+        Some(Color::Solid(Solid {
+            color: unpremultiply(&D2D1_COLOR_F {
+                a: mixed.a.min(1.0),
+                ..mixed
+            }),
+            opacity: 1.0,
+        }))
+    }
+}
+
+pub fn premultiply(color: &D2D1_COLOR_F) -> D2D1_COLOR_F {
+    D2D1_COLOR_F {
+        r: color.r * color.a,
+        g: color.g * color.a,
+        b: color.b * color.a,
+        a: color.a,
+    }
+}
+
+// A fully transparent color has no RGB left to get back, so it comes out black
+pub fn unpremultiply(color: &D2D1_COLOR_F) -> D2D1_COLOR_F {
+    if color.a <= 0.0 {
+        return D2D1_COLOR_F::default();
+    }
+
+    D2D1_COLOR_F {
+        r: (color.r / color.a).min(1.0),
+        g: (color.g / color.a).min(1.0),
+        b: (color.b / color.a).min(1.0),
+        a: color.a,
+    }
+}
+
+fn scale_color(color: &D2D1_COLOR_F, factor: f32) -> D2D1_COLOR_F {
+    D2D1_COLOR_F {
+        r: color.r * factor,
+        g: color.g * factor,
+        b: color.b * factor,
+        a: color.a * factor,
+    }
+}
+
+// a + b * factor, channel by channel
+fn add_colors(a: &D2D1_COLOR_F, b: &D2D1_COLOR_F, factor: f32) -> D2D1_COLOR_F {
+    D2D1_COLOR_F {
+        r: a.r + b.r * factor,
+        g: a.g + b.g * factor,
+        b: a.b + b.b * factor,
+        a: a.a + b.a * factor,
+    }
+}
+
+impl Color {
+    // Pre-distort the color so that it looks closer to the original once Night Light warms up the
+    // whole screen. Night Light can only take blue and green away, so the best we can do is boost
+    // them and scale everything back down if a channel ends up above 1.0 (keeping the hue intact).
+    pub fn compensate_night_light(&self, kelvin: f32) -> Color {
+        let white_point = get_white_point(kelvin);
+        self.map_colors(|color| compensate_color(color, white_point))
+    }
+
+    // Adjust the lightness of the color (if needed) so that it has at least the given contrast
+    // ratio against the background
+    pub fn with_min_contrast(&self, background_luminance: f32, min_contrast: f32) -> Color {
+        self.map_colors(|color| ensure_contrast(color, background_luminance, min_contrast))
+    }
+
+    // Make the color lighter (or darker, for a negative amount) by moving its OKLAB lightness, so
+    // the hue stays (roughly) the same
+    pub fn shade(&self, amount: f32) -> Color {
+        self.map_colors(|color| shade_color(color, amount))
+    }
+
+    fn map_colors(&self, f: impl Fn(&D2D1_COLOR_F) -> D2D1_COLOR_F) -> Color {
+        match self {
+            Color::Solid(solid) => Color::Solid(Solid {
+                color: f(&solid.color),
+                opacity: solid.opacity,
+            }),
+            Color::Gradient(gradient) => Color::Gradient(Gradient {
+                gradient_stops: gradient
+                    .gradient_stops
+                    .iter()
+                    .map(|stop| D2D1_GRADIENT_STOP {
+                        position: stop.position,
+                        color: f(&stop.color),
+                    })
+                    .collect(),
+                direction: gradient.direction.clone(),
+                opacity: gradient.opacity,
+            }),
+        }
+    }
+}
+
+// Approximate RGB multipliers for a white point at the given color temperature, using Tanner
+// Helland's curve fit of blackbody radiation (good enough for 1000K to 40000K)
+fn get_white_point(kelvin: f32) -> [f32; 3] {
+    let temp = kelvin.clamp(1000.0, 40000.0) / 100.0;
+
+    let red = match temp <= 66.0 {
+        true => 255.0,
+        false => 329.69873 * (temp - 60.0).powf(-0.13320476),
+    };
+    let green = match temp <= 66.0 {
+        true => 99.4708 * temp.ln() - 161.11957,
+        false => 288.12217 * (temp - 60.0).powf(-0.07551485),
+    };
+    let blue = match temp {
+        66.0.. => 255.0,
+        ..=19.0 => 0.0,
+        _ => 138.51773 * (temp - 10.0).ln() - 305.0448,
+    };
+
+    [red, green, blue].map(|channel: f32| channel.clamp(1.0, 255.0) / 255.0)
+}
+
+fn compensate_color(color: &D2D1_COLOR_F, white_point: [f32; 3]) -> D2D1_COLOR_F {
+    let [r, g, b] = [
+        color.r / white_point[0],
+        color.g / white_point[1],
+        color.b / white_point[2],
+    ];
+    let max_channel = r.max(g).max(b).max(1.0);
+
+    D2D1_COLOR_F {
+        r: r / max_channel,
+        g: g / max_channel,
+        b: b / max_channel,
+        a: color.a,
+    }
+}
+
+fn shade_color(color: &D2D1_COLOR_F, amount: f32) -> D2D1_COLOR_F {
+    let [lightness, a, b] = linear_srgb_to_oklab([color.r, color.g, color.b].map(srgb_to_linear));
+    let [r, g, b] = oklab_to_linear_srgb([(lightness + amount).clamp(0.0, 1.0), a, b])
+        .map(|channel| linear_to_srgb(channel.clamp(0.0, 1.0)));
+
+    D2D1_COLOR_F {
+        r,
+        g,
+        b,
+        a: color.a,
+    }
+}
+
+// The WCAG definition of relative luminance
+pub(crate) fn relative_luminance(color: &D2D1_COLOR_F) -> f32 {
+    let [r, g, b] = [color.r, color.g, color.b].map(srgb_to_linear);
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+fn contrast_ratio(luminance_1: f32, luminance_2: f32) -> f32 {
+    (luminance_1.max(luminance_2) + 0.05) / (luminance_1.min(luminance_2) + 0.05)
+}
+
+fn ensure_contrast(
+    color: &D2D1_COLOR_F,
+    background_luminance: f32,
+    min_contrast: f32,
+) -> D2D1_COLOR_F {
+    if contrast_ratio(relative_luminance(color), background_luminance) >= min_contrast {
+        return *color;
+    }
+
+    // Move towards whichever of black or white contrasts more with the background, only changing
+    // the lightness in OKLAB so the hue stays (roughly) the same
+    let [lightness, a, b] = linear_srgb_to_oklab([color.r, color.g, color.b].map(srgb_to_linear));
+    let target = match contrast_ratio(1.0, background_luminance)
+        >= contrast_ratio(0.0, background_luminance)
+    {
+        true => 1.0,
+        false => 0.0,
+    };
+
+    let with_lightness = |lightness: f32| {
+        let [r, g, b] = oklab_to_linear_srgb([lightness, a, b])
+            .map(|channel| linear_to_srgb(channel.clamp(0.0, 1.0)));
+        D2D1_COLOR_F {
+            r,
+            g,
+            b,
+            a: color.a,
+        }
+    };
+
+    // Binary search for the smallest change in lightness that's enough. If even black or white
+    // isn't enough, we just end up with black or white.
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..12 {
+        let mid = (low + high) / 2.0;
+        let candidate = with_lightness(lightness + (target - lightness) * mid);
+
+        match contrast_ratio(relative_luminance(&candidate), background_luminance) >= min_contrast {
+            true => high = mid,
+            false => low = mid,
+        }
+    }
+
+    with_lightness(lightness + (target - lightness) * high)
+}
+
+impl Default for Color {
+    fn default() -> Self {
+        Color::Solid(Solid {
+            color: D2D1_COLOR_F::default(),
+            opacity: 0.0,
+        })
+    }
+}
+
+fn get_accent_color(is_active_color: bool) -> D2D1_COLOR_F {
+    let mut pcr_colorization: u32 = 0;
+    let mut pf_opaqueblend: BOOL = FALSE;
+
+    // DwmGetColorizationColor gets the accent color and places it into 'pcr_colorization'
+    if let Err(e) = unsafe { DwmGetColorizationColor(&mut pcr_colorization, &mut pf_opaqueblend) } {
+        error!("could not retrieve windows accent color: {e}");
+    }
+
+    // Bit-shift the retrieved color to separate out the rgb components
+    let accent_red = ((pcr_colorization & 0x00FF0000) >> 16) as f32 / 255.0;
+    let accent_green = ((pcr_colorization & 0x0000FF00) >> 8) as f32 / 255.0;
+    let accent_blue = (pcr_colorization & 0x000000FF) as f32 / 255.0;
+    let accent_avg = (accent_red + accent_green + accent_blue) / 3.0;
+
+    if is_active_color {
+        D2D1_COLOR_F {
+            r: accent_red,
+            g: accent_green,
+            b: accent_blue,
+            a: 1.0,
+        }
+    } else {
+        D2D1_COLOR_F {
+            r: accent_avg / 1.5 + accent_red / 10.0,
+            g: accent_avg / 1.5 + accent_green / 10.0,
+            b: accent_avg / 1.5 + accent_blue / 10.0,
+            a: 1.0,
+        }
+    }
+}
+
+// #RGB, #RGBA, #RRGGBB, or #RRGGBBAA
+fn get_color_from_hex(hex: &str) -> Option<D2D1_COLOR_F> {
+    let digits = hex.strip_prefix('#')?;
+    if !matches!(digits.len(), 3 | 4 | 6 | 8) || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    // Expand shorthand hex formats (#RGB or #RGBA to #RRGGBB or #RRGGBBAA)
+    let expanded: String = match digits.len() {
+        3 | 4 => digits.chars().flat_map(|c| [c, c]).collect(),
+        _ => digits.to_string(),
+    };
+
+    // Convert each color component to f32 between 0.0 and 1.0
+    let component = |i: usize| {
+        u8::from_str_radix(&expanded[i..i + 2], 16)
+            .ok()
+            .map(|val| val as f32 / 255.0)
+    };
+
+    Some(D2D1_COLOR_F {
+        r: component(0)?,
+        g: component(2)?,
+        b: component(4)?,
+        a: match expanded.len() {
+            8 => component(6)?,
+            _ => 1.0,
+        },
+    })
+}
+
+// Parse any of the color formats we support in the config, or None if it's in none of them. Note
+// that these are all straight (non-premultiplied) colors; Direct2D premultiplies brush colors
+// itself when drawing to our premultiplied render target.
+pub(crate) fn parse_color(color: &str) -> Option<D2D1_COLOR_F> {
+    let color = color.trim();
+
+    if color.starts_with('#') {
+        return get_color_from_hex(color);
+    }
+
+    match color.split_once('(').map(|(name, _)| name.trim()) {
+        Some("rgb" | "rgba") => get_color_from_rgba(color),
+        Some("hsl" | "hsla") => get_color_from_hsla(color),
+        Some("oklch") => get_color_from_oklch(color),
+        _ => None,
+    }
+}
+
+// Invalid colors are drawn white, so a typo is easy to spot
+pub(crate) fn get_color_from_str(color: &str) -> D2D1_COLOR_F {
+    parse_color(color).unwrap_or_else(|| {
+        error!("invalid color format: {}", color.trim());
+        D2D1_COLOR_F {
+            r: 1.0,
+            g: 1.0,
+            b: 1.0,
+            a: 1.0,
+        }
+    })
+}
+
+// Split a CSS-style function like "rgba(255, 0, 0, 0.5)" or "oklch(70% 0.1 250 / 50%)" into its
+// components and its alpha (if any). Both the comma and the space/slash syntax are accepted.
+fn parse_color_function(color: &str) -> Option<(Vec<&str>, Option<&str>)> {
+    let (_, args) = color.split_once('(')?;
+    let args = args.strip_suffix(')')?;
+
+    let (args, alpha) = match args.split_once('/') {
+        Some((args, alpha)) => (args, Some(alpha.trim())),
+        None => (args, None),
+    };
+
+    let mut components: Vec<&str> = args
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|component| !component.is_empty())
+        .collect();
+
+    match (components.len(), alpha) {
+        (3, _) => Some((components, alpha)),
+        (4, None) => {
+            let alpha = components.pop();
+            Some((components, alpha))
+        }
+        _ => None,
+    }
+}
+
+// Parse a number, or a percentage where 100% is equal to 'percent_scale'
+fn parse_color_component(component: &str, percent_scale: f32) -> Option<f32> {
+    match component.strip_suffix('%') {
+        Some(percent) => percent
+            .parse::<f32>()
+            .ok()
+            .map(|p| p / 100.0 * percent_scale),
+        None => component.parse::<f32>().ok(),
+    }
+}
+
+fn parse_alpha(alpha: Option<&str>) -> Option<f32> {
+    match alpha {
+        Some(alpha) => parse_color_component(alpha, 1.0).map(|a| a.clamp(0.0, 1.0)),
+        None => Some(1.0),
+    }
+}
+
+fn parse_hue(hue: &str) -> Option<f32> {
+    let degrees = match hue.strip_suffix("turn") {
+        Some(turns) => turns.parse::<f32>().ok()? * 360.0,
+        None => hue.strip_suffix("deg").unwrap_or(hue).parse::<f32>().ok()?,
+    };
+
+    Some(degrees.rem_euclid(360.0))
+}
+
+// rgb(255, 0, 0), rgba(255, 0, 0, 0.5), rgb(100% 0% 0% / 50%)
+fn get_color_from_rgba(rgba: &str) -> Option<D2D1_COLOR_F> {
+    let (components, alpha) = parse_color_function(rgba)?;
+
+    let channel = |component: &str| {
+        parse_color_component(component, 255.0).map(|value| (value / 255.0).clamp(0.0, 1.0))
+    };
+
+    Some(D2D1_COLOR_F {
+        r: channel(components[0])?,
+        g: channel(components[1])?,
+        b: channel(components[2])?,
+        a: parse_alpha(alpha)?,
+    })
+}
+
+// hsl(210, 50%, 40%), hsla(210deg 50% 40% / 0.5)
+fn get_color_from_hsla(hsla: &str) -> Option<D2D1_COLOR_F> {
+    let (components, alpha) = parse_color_function(hsla)?;
+
+    let hue = parse_hue(components[0])?;
+    // CSS allows saturation and lightness without the % sign, in which case they're still 0-100
+    let saturation = (parse_color_component(components[1], 100.0)? / 100.0).clamp(0.0, 1.0);
+    let lightness = (parse_color_component(components[2], 100.0)? / 100.0).clamp(0.0, 1.0);
+
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let hue_channel = |n: f32| {
+        let k = (n + hue / 30.0) % 12.0;
+        lightness - chroma / 2.0 * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0)
+    };
+
+    Some(D2D1_COLOR_F {
+        r: hue_channel(0.0),
+        g: hue_channel(8.0),
+        b: hue_channel(4.0),
+        a: parse_alpha(alpha)?,
+    })
+}
+
+// oklch(70% 0.15 250), oklch(0.7 0.15 250 / 50%)
+fn get_color_from_oklch(oklch: &str) -> Option<D2D1_COLOR_F> {
+    let (components, alpha) = parse_color_function(oklch)?;
+
+    let lightness = parse_color_component(components[0], 1.0)?.clamp(0.0, 1.0);
+    // 100% chroma is 0.4 in the CSS spec
+    let chroma = parse_color_component(components[1], 0.4)?.max(0.0);
+    let hue = parse_hue(components[2])?.to_radians();
+
+    let [r, g, b] = oklab_to_linear_srgb([lightness, chroma * hue.cos(), chroma * hue.sin()]);
+
+    // Colors outside of the sRGB gamut are simply clipped
+    Some(D2D1_COLOR_F {
+        r: linear_to_srgb(r.clamp(0.0, 1.0)),
+        g: linear_to_srgb(g.clamp(0.0, 1.0)),
+        b: linear_to_srgb(b.clamp(0.0, 1.0)),
+        a: parse_alpha(alpha)?,
+    })
+}
+
+// Our gradient brushes always interpolate in sRGB, so we approximate other color spaces by
+// precomputing a bunch of stops between each of the configured ones.
+fn interpolate_stops(stops: Vec<D2D1_GRADIENT_STOP>, space: ColorSpace) -> Vec<D2D1_GRADIENT_STOP> {
+    if space == ColorSpace::Srgb || stops.len() < 2 {
+        return stops;
+    }
+
+    let to_space = |color: &D2D1_COLOR_F| {
+        let linear = [color.r, color.g, color.b].map(srgb_to_linear);
+        match space {
+            ColorSpace::Oklab => linear_srgb_to_oklab(linear),
+            _ => linear,
+        }
+    };
+    let from_space = |channels: [f32; 3]| {
+        let linear = match space {
+            ColorSpace::Oklab => oklab_to_linear_srgb(channels),
+            _ => channels,
+        };
+        linear.map(|channel| linear_to_srgb(channel.clamp(0.0, 1.0)))
+    };
+
+    let mut interpolated = Vec::with_capacity((stops.len() - 1) * INTERPOLATION_STEPS + 1);
+    for pair in stops.windows(2) {
+        let (start, end) = (&pair[0], &pair[1]);
+        let (start_channels, end_channels) = (to_space(&start.color), to_space(&end.color));
+
+        for step in 0..INTERPOLATION_STEPS {
+            let t = step as f32 / INTERPOLATION_STEPS as f32;
+            let lerp = |a: f32, b: f32| a + (b - a) * t;
+
+            let [r, g, b] = from_space([
+                lerp(start_channels[0], end_channels[0]),
+                lerp(start_channels[1], end_channels[1]),
+                lerp(start_channels[2], end_channels[2]),
+            ]);
+
+            interpolated.push(D2D1_GRADIENT_STOP {
+                position: lerp(start.position, end.position),
+                color: D2D1_COLOR_F {
+                    r,
+                    g,
+                    b,
+                    a: lerp(start.color.a, end.color.a),
+                },
+            });
+        }
+    }
+    // The loop above stops just short of the last color
+    interpolated.extend(stops.last().copied());
+
+    interpolated
+}
+
+// See https://bottosson.github.io/posts/oklab/
+fn linear_srgb_to_oklab([r, g, b]: [f32; 3]) -> [f32; 3] {
+    let l = (0.41222147 * r + 0.53633254 * g + 0.05144599 * b).cbrt();
+    let m = (0.2119035 * r + 0.6806995 * g + 0.10739696 * b).cbrt();
+    let s = (0.08830246 * r + 0.28171884 * g + 0.6299787 * b).cbrt();
+
+    [
+        0.21045426 * l + 0.7936178 * m - 0.004072047 * s,
+        1.9779985 * l - 2.4285922 * m + 0.4505937 * s,
+        0.025904037 * l + 0.78277177 * m - 0.80867577 * s,
+    ]
+}
+
+fn oklab_to_linear_srgb([lightness, a, b]: [f32; 3]) -> [f32; 3] {
+    let l = (lightness + 0.39633778 * a + 0.21580376 * b).powi(3);
+    let m = (lightness - 0.105561346 * a - 0.06385417 * b).powi(3);
+    let s = (lightness - 0.08948418 * a - 1.2914855 * b).powi(3);
+
+    [
+        4.0767417 * l - 3.3077116 * m + 0.23096994 * s,
+        -1.268438 * l + 2.6097574 * m - 0.34131938 * s,
+        -0.0041960863 * l - 0.7034186 * m + 1.7076147 * s,
+    ]
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    match value <= 0.04045 {
+        true => value / 12.92,
+        false => ((value + 0.055) / 1.055).powf(2.4),
+    }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    match value <= 0.0031308 {
+        true => value * 12.92,
+        false => 1.055 * value.powf(1.0 / 2.4) - 0.055,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgba(r: f32, g: f32, b: f32, a: f32) -> D2D1_COLOR_F {
+        D2D1_COLOR_F { r, g, b, a }
+    }
+
+    fn solid(color: D2D1_COLOR_F) -> Color {
+        Color::Solid(Solid {
+            color,
+            opacity: 1.0,
+        })
+    }
+
+    fn assert_color_close(actual: &D2D1_COLOR_F, expected: &D2D1_COLOR_F) {
+        assert_color_near(actual, expected, 1e-4);
+    }
+
+    fn assert_color_near(actual: &D2D1_COLOR_F, expected: &D2D1_COLOR_F, tolerance: f32) {
+        let channels = |c: &D2D1_COLOR_F| [c.r, c.g, c.b, c.a];
+        let close = channels(actual)
+            .iter()
+            .zip(channels(expected))
+            .all(|(actual, expected)| (actual - expected).abs() < tolerance);
+        assert!(close, "expected {expected:?}, got {actual:?}");
+    }
+
+    #[test]
+    fn mix_table() {
+        let red = rgba(1.0, 0.0, 0.0, 1.0);
+        let blue = rgba(0.0, 0.0, 1.0, 1.0);
+
+        let cases = [
+            // (bottom, bottom opacity, top, top opacity, mixed)
+            (red, 0.5, blue, 0.5, rgba(0.5, 0.0, 0.5, 1.0)),
+            (red, 1.0, blue, 0.0, red),
+            (red, 0.0, blue, 1.0, blue),
+            (
+                rgba(1.0, 0.0, 0.0, 0.5),
+                1.0,
+                rgba(0.0, 0.0, 1.0, 0.5),
+                1.0,
+                rgba(0.5, 0.0, 0.5, 1.0),
+            ),
+            // An invisible color doesn't drag the mix toward its RGB
+            (
+                rgba(1.0, 0.0, 0.0, 0.0),
+                1.0,
+                blue,
+                0.5,
+                rgba(0.0, 0.0, 1.0, 0.5),
+            ),
+            (red, 0.0, blue, 0.0, rgba(0.0, 0.0, 0.0, 0.0)),
+        ];
+
+        for (bottom, bottom_opacity, top, top_opacity, expected) in cases {
+            let Some(Color::Solid(mixed)) =
+                Color::mix(&solid(bottom), bottom_opacity, &solid(top), top_opacity)
+            else {
+                panic!("solid colors should mix into a solid color");
+            };
+            assert_color_close(&mixed.color, &expected);
+            assert_eq!(mixed.opacity, 1.0);
+        }
+    }
+
+    #[test]
+    fn mix_skips_gradients() {
+        let gradient = Color::Gradient(Gradient {
+            gradient_stops: Vec::new(),
+            direction: GradientCoordinates {
+                start: [0.0, 0.0],
+                end: [1.0, 0.0],
+            },
+            opacity: 1.0,
+        });
+        let red = solid(rgba(1.0, 0.0, 0.0, 1.0));
+
+        assert!(Color::mix(&gradient, 1.0, &red, 1.0).is_none());
+        assert!(Color::mix(&red, 1.0, &gradient, 1.0).is_none());
+    }
+
+    #[test]
+    fn parse_color_table() {
+        let cases = [
+            ("#ff8000", Some(rgba(1.0, 128.0 / 255.0, 0.0, 1.0))),
+            (
+                "  #FF800080 ",
+                Some(rgba(1.0, 128.0 / 255.0, 0.0, 128.0 / 255.0)),
+            ),
+            ("#f80", Some(rgba(1.0, 136.0 / 255.0, 0.0, 1.0))),
+            ("#f808", Some(rgba(1.0, 136.0 / 255.0, 0.0, 136.0 / 255.0))),
+            ("rgb(255, 0, 0)", Some(rgba(1.0, 0.0, 0.0, 1.0))),
+            (
+                "rgba(255, 128, 0, 0.5)",
+                Some(rgba(1.0, 128.0 / 255.0, 0.0, 0.5)),
+            ),
+            ("rgb(100% 0% 0% / 50%)", Some(rgba(1.0, 0.0, 0.0, 0.5))),
+            // Channels outside of the range are clamped
+            ("rgb(300 -5 0 / 2)", Some(rgba(1.0, 0.0, 0.0, 1.0))),
+            ("hsl(0 100% 50%)", Some(rgba(1.0, 0.0, 0.0, 1.0))),
+            ("hsl(120, 100%, 25%)", Some(rgba(0.0, 0.5, 0.0, 1.0))),
+            (
+                "hsla(240deg 100% 50% / 0.5)",
+                Some(rgba(0.0, 0.0, 1.0, 0.5)),
+            ),
+            ("hsl(0.5turn 100% 50%)", Some(rgba(0.0, 1.0, 1.0, 1.0))),
+            ("hsl(-120 100% 50%)", Some(rgba(0.0, 0.0, 1.0, 1.0))),
+            ("hsl(210 50 40)", Some(rgba(0.2, 0.4, 0.6, 1.0))),
+            ("hsl(0 0% 50% / 25%)", Some(rgba(0.5, 0.5, 0.5, 0.25))),
+            ("oklch(0% 0 0)", Some(rgba(0.0, 0.0, 0.0, 1.0))),
+            ("oklch(100% 0 0 / 0.5)", Some(rgba(1.0, 1.0, 1.0, 0.5))),
+            ("#ff80", None),
+            ("#ff800", None),
+            ("#gg8000", None),
+            ("#+f+f+f", None),
+            ("#ééé", None),
+            ("ff8000", None),
+            ("red", None),
+            ("rgb(", None),
+            ("rgb(255, 0, 0", None),
+            ("rgb(1, 2, 3, 4, 5)", None),
+            ("rgb(255 0 0 / 1 / 1)", None),
+            ("hsl(0 100%)", None),
+            ("hsl(red 100% 50%)", None),
+            ("oklch(50% 0.1)", None),
+            ("", None),
+        ];
+
+        for (color, expected) in cases {
+            match (parse_color(color), expected) {
+                (Some(actual), Some(expected)) => assert_color_close(&actual, &expected),
+                (None, None) => {}
+                (actual, _) => panic!("'{color}': got {actual:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn parse_oklch_table() {
+        // The sRGB primaries, rounded the way CSS color tools print them
+        let cases = [
+            ("oklch(62.8% 0.2577 29.23)", rgba(1.0, 0.0, 0.0, 1.0)),
+            ("oklch(86.64% 0.2948 142.5)", rgba(0.0, 1.0, 0.0, 1.0)),
+            ("oklch(45.2% 0.313 264.05)", rgba(0.0, 0.0, 1.0, 1.0)),
+            // 100% chroma is 0.4
+            ("oklch(0.628 64.425% 29.23deg)", rgba(1.0, 0.0, 0.0, 1.0)),
+        ];
+
+        for (color, expected) in cases {
+            assert_color_near(&parse_color(color).unwrap(), &expected, 5e-3);
+        }
+    }
+
+    #[test]
+    fn get_white_point_table() {
+        let cases = [
+            // Night Light's neutral point
+            (6600.0, [1.0, 1.0, 1.0]),
+            (3400.0, [1.0, 0.7437, 0.5301]),
+            (1900.0, [1.0, 0.5167, 1.0 / 255.0]),
+            // Anything below 1000K is treated as 1000K
+            (500.0, [1.0, 0.2664, 1.0 / 255.0]),
+            (10000.0, [0.791, 0.8552, 1.0]),
+        ];
+
+        for (kelvin, expected) in cases {
+            let white_point = get_white_point(kelvin);
+            assert!(
+                white_point
+                    .iter()
+                    .zip(expected)
+                    .all(|(actual, expected)| (actual - expected).abs() < 1e-3),
+                "{kelvin}K: expected {expected:?}, got {white_point:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn compensate_color_table() {
+        let neutral = get_white_point(6600.0);
+        let warm = get_white_point(3400.0);
+
+        // A neutral white point leaves colors alone
+        for color in [rgba(1.0, 0.5, 0.25, 1.0), rgba(0.2, 0.4, 0.6, 0.5)] {
+            assert_color_close(&compensate_color(&color, neutral), &color);
+        }
+
+        // A warm one boosts blue the most, then green, and scales down so nothing goes past 1.0
+        let compensated = compensate_color(&rgba(0.5, 0.5, 0.5, 0.5), warm);
+        assert_color_close(&compensated, &rgba(0.5, 0.5 / 0.7437, 0.5 / 0.5301, 0.5));
+        let compensated = compensate_color(&rgba(1.0, 1.0, 1.0, 1.0), warm);
+        assert_color_close(&compensated, &rgba(0.5301, 0.5301 / 0.7437, 1.0, 1.0));
+    }
+
+    #[test]
+    fn ensure_contrast_table() {
+        let colors = [
+            rgba(0.1, 0.1, 0.3, 1.0),
+            rgba(1.0, 1.0, 0.0, 0.5),
+            rgba(0.5, 0.5, 0.5, 1.0),
+            rgba(0.0, 0.0, 1.0, 1.0),
+            rgba(0.8, 0.2, 0.2, 1.0),
+        ];
+
+        for background_luminance in [0.0, 1.0] {
+            for min_contrast in [3.0, 4.5, 7.0] {
+                for color in colors {
+                    let adjusted = ensure_contrast(&color, background_luminance, min_contrast);
+                    let ratio = contrast_ratio(relative_luminance(&adjusted), background_luminance);
+                    assert!(
+                        ratio >= min_contrast,
+                        "{color:?} on {background_luminance}: wanted {min_contrast}, got {ratio}"
+                    );
+                    assert_eq!(adjusted.a, color.a);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ensure_contrast_keeps_colors_that_already_pass() {
+        let cases = [
+            (rgba(1.0, 1.0, 1.0, 1.0), 0.0),
+            (rgba(0.0, 0.0, 0.0, 1.0), 1.0),
+            (rgba(1.0, 1.0, 0.0, 0.5), 0.0),
+            (rgba(0.1, 0.1, 0.3, 1.0), 1.0),
+        ];
+
+        for (color, background_luminance) in cases {
+            let adjusted = ensure_contrast(&color, background_luminance, 4.5);
+            assert_eq!(
+                (adjusted.r, adjusted.g, adjusted.b, adjusted.a),
+                (color.r, color.g, color.b, color.a)
+            );
+        }
+    }
+
+    fn stop(position: f32, color: D2D1_COLOR_F) -> D2D1_GRADIENT_STOP {
+        D2D1_GRADIENT_STOP { position, color }
+    }
+
+    #[test]
+    fn interpolate_stops_keeps_the_configured_stops() {
+        let stops = vec![
+            stop(0.0, rgba(1.0, 0.0, 0.0, 1.0)),
+            stop(0.5, rgba(0.0, 1.0, 0.0, 1.0)),
+            stop(1.0, rgba(0.0, 0.0, 1.0, 0.5)),
+        ];
+
+        for space in [ColorSpace::LinearRgb, ColorSpace::Oklab] {
+            let interpolated = interpolate_stops(stops.clone(), space);
+            assert_eq!(interpolated.len(), 2 * INTERPOLATION_STEPS + 1, "{space:?}");
+
+            // Each configured stop starts a run of steps, and the last one ends it
+            for (index, original) in [0, INTERPOLATION_STEPS, 2 * INTERPOLATION_STEPS]
+                .into_iter()
+                .zip(&stops)
+            {
+                assert_eq!(interpolated[index].position, original.position, "{space:?}");
+                assert_color_near(&interpolated[index].color, &original.color, 1e-3);
+            }
+            assert!(interpolated
+                .windows(2)
+                .all(|pair| pair[0].position < pair[1].position));
+        }
+
+        // sRGB is what the brush does anyway, and one stop has nothing to interpolate
+        assert_eq!(interpolate_stops(stops.clone(), ColorSpace::Srgb), stops);
+        assert_eq!(
+            interpolate_stops(stops[..1].to_vec(), ColorSpace::Oklab).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn interpolate_stops_midpoint_table() {
+        let stops = vec![
+            stop(0.0, rgba(1.0, 0.0, 0.0, 1.0)),
+            stop(1.0, rgba(0.0, 1.0, 0.0, 1.0)),
+        ];
+        // The brush's own sRGB midpoint is a muddy (0.5, 0.5, 0)
+        let cases = [
+            (ColorSpace::LinearRgb, rgba(0.7354, 0.7354, 0.0, 1.0)),
+            (ColorSpace::Oklab, rgba(0.8163, 0.6604, 0.0018, 1.0)),
+        ];
+
+        for (space, expected) in cases {
+            let midpoint = interpolate_stops(stops.clone(), space)[INTERPOLATION_STEPS / 2];
+            assert_eq!(midpoint.position, 0.5);
+            assert_color_near(&midpoint.color, &expected, 1e-3);
+        }
+    }
+
+    #[test]
+    fn validate_table() {
+        let cases = [
+            ("'#ff8000'", true),
+            ("accent", true),
+            ("'#ff80'", false),
+            ("nope", false),
+            ("{ colors: ['#000', '#fff'], direction: 45deg }", true),
+            (
+                "{ colors: ['#000', '#fff'], direction: { start: [0, 0], end: [1, 1] } }",
+                true,
+            ),
+            ("{ colors: ['#000'], direction: 45deg }", false),
+            ("{ colors: ['#000', 'nope'], direction: 45deg }", false),
+            ("{ colors: ['#000', '#fff'], direction: '45' }", false),
+            ("{ colors: ['#000', '#fff'], direction: infdeg }", false),
+        ];
+
+        for (yaml, valid) in cases {
+            let color: ColorConfig = serde_yaml::from_str(yaml).unwrap();
+            assert_eq!(color.validate().is_ok(), valid, "{yaml}");
+        }
+    }
+
+    #[test]
+    fn premultiply_round_trip() {
+        for color in [
+            rgba(1.0, 0.5, 0.25, 1.0),
+            rgba(1.0, 0.5, 0.25, 0.5),
+            rgba(0.2, 0.4, 0.6, 0.01),
+            rgba(0.0, 0.0, 0.0, 1.0),
+        ] {
+            assert_color_close(&unpremultiply(&premultiply(&color)), &color);
+        }
+
+        assert_color_close(
+            &premultiply(&rgba(1.0, 0.5, 0.25, 0.5)),
+            &rgba(0.5, 0.25, 0.125, 0.5),
+        );
+        // There's nothing left to get back from a fully transparent color
+        assert_color_close(
+            &unpremultiply(&premultiply(&rgba(1.0, 0.5, 0.25, 0.0))),
+            &rgba(0.0, 0.0, 0.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn alpha_is_kept_apart_from_the_fade() {
+        let mut color = solid(rgba(1.0, 0.5, 0.25, 0.5));
+        color.set_fade_opacity(0.5);
+        assert_eq!(color.alpha(), 0.5);
+        assert_eq!(color.drawn_alpha(), 0.25);
+
+        // Fading doesn't touch the color's own alpha, and setting the alpha doesn't touch the fade
+        color.set_fade_opacity(0.0);
+        assert_eq!(color.alpha(), 0.5);
+        color.set_alpha(2.0);
+        assert_eq!(color.alpha(), 1.0);
+        assert_eq!(color.fade_opacity(), 0.0);
+
+        let mut gradient = Color::Gradient(Gradient {
+            gradient_stops: vec![
+                D2D1_GRADIENT_STOP {
+                    position: 0.0,
+                    color: rgba(1.0, 0.0, 0.0, 0.25),
+                },
+                D2D1_GRADIENT_STOP {
+                    position: 1.0,
+                    color: rgba(0.0, 0.0, 1.0, 0.75),
+                },
+            ],
+            direction: GradientCoordinates {
+                start: [0.0, 0.0],
+                end: [1.0, 0.0],
+            },
+            opacity: 1.0,
+        });
+        assert_eq!(gradient.alpha(), 0.75);
+        gradient.set_alpha(0.5);
+        assert_eq!(gradient.alpha(), 0.5);
+    }
+}
@@ -0,0 +1,301 @@
+use anyhow::Context;
+use serde_yaml::Value;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+use windows::Win32::UI::WindowsAndMessaging::WM_NCDESTROY;
+
+use crate::border_config::{WindowRule, CONFIG};
+use crate::colors::ColorConfig;
+use crate::registry::{BorderKey, BORDERS};
+use crate::state;
+use crate::{log_if_err, utils::*};
+
+// A reload only recreates the borders whose options changed, so the rest don't flicker or restart
+// their animations. Colors are the one thing a border can change on the fly (along with whatever
+// uses its colors, like the progress ring), so borders whose colors are all that changed just get
+// recolored. Everything outside of the window rules and colors (like global.border_width) can
+// change every border at once, so those still recreate all of them.
+
+// Options that don't change how borders are made. Hotkeys are reloaded on their own, game mode's
+// poller picks up its new options by itself, and the rest only matter while loading the config.
+const IGNORED_OPTIONS: [&str; 5] = [
+    "version",
+    "rewrite_migrated",
+    "strict",
+    "hotkeys",
+    "game_mode",
+];
+
+// Colors are compared separately from the rest of the options. Window rules are compared window by
+// window, and groups are nothing but colors.
+const COLOR_OPTIONS: [&str; 3] = ["active_color", "inactive_color", "palette_preset"];
+const PER_WINDOW_OPTIONS: [&str; 2] = ["window_rules", "groups"];
+
+// What the config looked like before a reload
+pub(crate) struct Snapshot {
+    // Without the options that are compared per window
+    config: Value,
+    windows: HashMap<isize, WindowSnapshot>,
+}
+
+struct WindowSnapshot {
+    // Without the colors
+    rule: Value,
+    colors: (ColorConfig, ColorConfig),
+}
+
+pub(crate) fn snapshot() -> Snapshot {
+    let config = {
+        let config = CONFIG.lock().unwrap();
+        serde_yaml::to_value(&*config).unwrap_or_default()
+    };
+
+    // Every window that has a border, even if it's hidden or on another virtual desktop right now,
+    // plus the ones that could get one after the reload. Monitor frames and the cursor ring aren't
+    // windows, and they're recreated with everything else anyways.
+    let bordered = BORDERS
+        .entries()
+        .into_iter()
+        .filter_map(|(key, _)| key.hwnd());
+    let visible = get_top_level_windows()
+        .into_iter()
+        .filter(|hwnd| is_window_visible(*hwnd) && !is_cloaked(*hwnd));
+
+    let mut windows = HashMap::new();
+    for hwnd in bordered.chain(visible) {
+        if let Entry::Vacant(entry) = windows.entry(hwnd.0 as isize) {
+            if let Some(window) = snapshot_window(hwnd) {
+                entry.insert(window);
+            }
+        }
+    }
+
+    Snapshot {
+        config: shared_options(config),
+        windows,
+    }
+}
+
+fn snapshot_window(hwnd: HWND) -> Option<WindowSnapshot> {
+    let window_rule = get_border_rule(hwnd)?;
+    let colors = get_border_colors(hwnd, &window_rule, &CONFIG.lock().unwrap());
+    let rule = serde_yaml::to_value(&window_rule).unwrap_or_default();
+
+    Some(WindowSnapshot {
+        rule: without_options(rule, &COLOR_OPTIONS, &[]),
+        colors,
+    })
+}
+
+// What a reload has to do to a window's border
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Change {
+    Nothing,
+    Recolor,
+    Recreate,
+}
+
+fn classify(old: &WindowSnapshot, new: &WindowSnapshot) -> Change {
+    match (old.rule == new.rule, old.colors == new.colors) {
+        (false, _) => Change::Recreate,
+        (true, false) => Change::Recolor,
+        (true, true) => Change::Nothing,
+    }
+}
+
+// Apply the config that was just loaded, compared to 'old' from before it was
+pub(crate) fn apply(old: Snapshot) {
+    // Paused borders get recreated from scratch once they're resumed
+    if crate::is_suspended() {
+        return;
+    }
+
+    let new = snapshot();
+    if new.config != old.config {
+        debug!("options for every border changed, recreating all of them");
+        crate::reload_borders();
+        return;
+    }
+
+    let (mut recreated, mut recolored) = (0, 0);
+    for (key, window) in new.windows {
+        let Some(old_window) = old.windows.get(&key) else {
+            continue;
+        };
+        let hwnd = HWND(key as _);
+
+        match classify(old_window, &window) {
+            Change::Nothing => {}
+            Change::Recolor => {
+                if let Some(border_window) = get_border_from_window(hwnd) {
+                    let (active, inactive) = window.colors;
+                    log_if_err!(set_border_colors(border_window, active, inactive)
+                        .context("config_diff::apply"));
+                    recolored += 1;
+                }
+            }
+            Change::Recreate => {
+                if let Some(border_window) = BORDERS.take(BorderKey::window(hwnd)) {
+                    log_if_err!(
+                        post_message_w(border_window, WM_NCDESTROY, WPARAM(0), LPARAM(0))
+                            .context("config_diff::apply")
+                    );
+                }
+                // The rule may have turned the border on or off too. Hidden windows get theirs
+                // back once they're shown.
+                crate::adopt_window(hwnd);
+                recreated += 1;
+            }
+        }
+    }
+
+    debug!("config reloaded: recreated {recreated} borders and recolored {recolored}");
+}
+
+// The rule a window's border is made from, picked the same way as in create_border_for_window()
+// and create_group_border_for_window(). None if the window doesn't get a border from its styles.
+fn get_border_rule(hwnd: HWND) -> Option<WindowRule> {
+    if !has_filtered_style(hwnd) {
+        let mut window_rule = get_window_rule(hwnd);
+        if let Some(color) = state::get_override(hwnd).and_then(|window| window.color) {
+            window_rule.active_color = Some(color.clone());
+            window_rule.inactive_color = Some(color);
+        }
+        return Some(window_rule);
+    }
+
+    if !is_tool_window(hwnd) {
+        return None;
+    }
+
+    let leader = get_group_leader(hwnd)?;
+    let mut window_rule = get_window_rule(leader);
+    let global_group_color = CONFIG.lock().unwrap().global.group_color.clone();
+    if let Some(group_color) = window_rule.group_color.clone().or(global_group_color) {
+        window_rule.active_color = Some(group_color.clone());
+        window_rule.inactive_color = Some(group_color);
+    }
+    Some(window_rule)
+}
+
+// The config without the options that are compared per window, or that don't matter to borders
+fn shared_options(config: Value) -> Value {
+    without_options(
+        without_options(config, &IGNORED_OPTIONS, &[]),
+        &PER_WINDOW_OPTIONS,
+        &COLOR_OPTIONS,
+    )
+}
+
+// Remove the top-level 'options' from the mapping, and the 'global_options' from its global section
+fn without_options(mut value: Value, options: &[&str], global_options: &[&str]) -> Value {
+    if let Some(mapping) = value.as_mapping_mut() {
+        for option in options {
+            mapping.remove(*option);
+        }
+        if let Some(global) = mapping.get_mut("global").and_then(Value::as_mapping_mut) {
+            for option in global_options {
+                global.remove(*option);
+            }
+        }
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn yaml(text: &str) -> Value {
+        serde_yaml::from_str(text).unwrap()
+    }
+
+    fn window(rule: &str, active: &str) -> WindowSnapshot {
+        WindowSnapshot {
+            rule: yaml(rule),
+            colors: (
+                ColorConfig::SolidConfig(active.to_string()),
+                ColorConfig::SolidConfig("#000000".to_string()),
+            ),
+        }
+    }
+
+    #[test]
+    fn without_options_removes_top_level_and_global_options() {
+        let value = yaml(
+            "
+            version: 2
+            hotkeys: { reload: alt+r }
+            global: { border_width: 4, active_color: '#ffffff' }
+            window_rules: []",
+        );
+
+        let stripped = without_options(value, &["version", "hotkeys"], &["active_color"]);
+        assert_eq!(
+            stripped,
+            yaml("{ global: { border_width: 4 }, window_rules: [] }")
+        );
+    }
+
+    #[test]
+    fn without_options_ignores_missing_options_and_non_mappings() {
+        let value = yaml("{ window_rules: [] }");
+        assert_eq!(
+            without_options(value.clone(), &["version"], &["active_color"]),
+            value
+        );
+
+        let value = yaml("[1, 2]");
+        assert_eq!(without_options(value.clone(), &["version"], &[]), value);
+    }
+
+    #[test]
+    fn shared_options_only_sees_options_for_every_border() {
+        let old = yaml(
+            "
+            version: 1
+            global: { border_width: 4, active_color: '#ffffff' }
+            window_rules: [{ match: Title, name: a }]
+            game_mode: { enabled: false }",
+        );
+        let recolored = yaml(
+            "
+            version: 2
+            global: { border_width: 4, active_color: '#ff0000' }
+            window_rules: [{ match: Title, name: b }]
+            game_mode: { enabled: true }",
+        );
+        let resized = yaml(
+            "
+            global: { border_width: 6, active_color: '#ffffff' }
+            window_rules: [{ match: Title, name: a }]",
+        );
+
+        assert_eq!(shared_options(old.clone()), shared_options(recolored));
+        assert_ne!(shared_options(old), shared_options(resized));
+    }
+
+    #[test]
+    fn classify_picks_the_smallest_change() {
+        let old = window("{ border_width: 4 }", "#ffffff");
+
+        assert_eq!(
+            classify(&old, &window("{ border_width: 4 }", "#ffffff")),
+            Change::Nothing
+        );
+        assert_eq!(
+            classify(&old, &window("{ border_width: 4 }", "#ff0000")),
+            Change::Recolor
+        );
+        assert_eq!(
+            classify(&old, &window("{ border_width: 6 }", "#ffffff")),
+            Change::Recreate
+        );
+        // A new rule wins over new colors
+        assert_eq!(
+            classify(&old, &window("{ border_width: 6 }", "#ff0000")),
+            Change::Recreate
+        );
+    }
+}
@@ -0,0 +1,400 @@
+//! Crash reports, so bug reports come with more than "it stopped working".
+//!
+//! [`install`] sets up a panic hook and an unhandled exception filter. Either one writes a report
+//! to the `crashes` folder next to the config: a minidump, plus a text file with the active config,
+//! the windows that have borders, and the last 100 window events.
+
+use std::backtrace::Backtrace;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::os::windows::io::AsRawHandle;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{
+    AtomicBool, AtomicIsize, AtomicPtr, AtomicU32, AtomicU64, AtomicUsize, Ordering,
+};
+use std::sync::Once;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::{FALSE, HANDLE, HWND};
+use windows::Win32::System::Diagnostics::Debug::{
+    MiniDumpNormal, MiniDumpWriteDump, SetUnhandledExceptionFilter, EXCEPTION_POINTERS,
+    MINIDUMP_EXCEPTION_INFORMATION,
+};
+use windows::Win32::System::Threading::{
+    CreateEventW, GetCurrentProcess, GetCurrentProcessId, GetCurrentThreadId, SetEvent,
+    WaitForSingleObject, INFINITE,
+};
+
+use crate::border_config::{Config, CONFIG};
+use crate::event_hook;
+use crate::notifications::{self, NotificationKind};
+use crate::registry::{BorderKey, BORDERS};
+use crate::tracking::WindowEvent;
+use crate::utils::*;
+
+const MAX_EVENTS: usize = 100;
+// Older reports are deleted so a crash loop can't fill up the disk
+const MAX_REPORTS: usize = 10;
+// Written alongside each report and deleted once the user opens the crashes folder from the tray
+const NEW_REPORT_MARKER: &str = "new";
+// How long a crashing thread waits for the watchdog to write its report
+const REPORT_TIMEOUT_MS: u32 = 30_000;
+
+const EXCEPTION_CONTINUE_SEARCH: i32 = 0;
+
+// One spot in the ring of recent events. record_event() runs for every window event, so it can't
+// take a lock. Instead, every event gets the next spot, and 'sequence' is written last so readers
+// can tell which events are whole.
+struct RecordedEvent {
+    // The event's number in the ring plus one, or 0 while the spot is empty or being written
+    sequence: AtomicUsize,
+    // Milliseconds since the Unix epoch
+    time: AtomicU64,
+    // WindowEvent::index()
+    event: AtomicU32,
+    hwnd: AtomicIsize,
+}
+
+impl RecordedEvent {
+    const fn new() -> Self {
+        Self {
+            sequence: AtomicUsize::new(0),
+            time: AtomicU64::new(0),
+            event: AtomicU32::new(0),
+            hwnd: AtomicIsize::new(0),
+        }
+    }
+}
+
+static RECENT_EVENTS: [RecordedEvent; MAX_EVENTS] = [const { RecordedEvent::new() }; MAX_EVENTS];
+static NEXT_EVENT: AtomicUsize = AtomicUsize::new(0);
+
+static INSTALL: Once = Once::new();
+
+// Set while there are reports the user hasn't looked at, including ones from a previous run
+static HAS_NEW_REPORTS: AtomicBool = AtomicBool::new(false);
+
+// How many reports have been written by this process
+static REPORT_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+// A process can only die once, so the exception filter only writes one report
+static WROTE_EXCEPTION_REPORT: AtomicBool = AtomicBool::new(false);
+
+// The crashing thread can't be trusted to write its own report (its stack may have overflowed, or
+// it may hold a lock the report needs), so it hands the exception to a watchdog thread that's
+// started by install(). These are the events they signal each other with, stored as isize since
+// HANDLE isn't Send, and what crashed.
+static REPORT_REQUESTED: AtomicIsize = AtomicIsize::new(0);
+static REPORT_WRITTEN: AtomicIsize = AtomicIsize::new(0);
+static CRASHED_THREAD: AtomicU32 = AtomicU32::new(0);
+static CRASH_POINTERS: AtomicPtr<EXCEPTION_POINTERS> = AtomicPtr::new(std::ptr::null_mut());
+
+/// Installs the panic hook and exception handler. Calling this more than once does nothing.
+pub fn install() {
+    INSTALL.call_once(|| {
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            default_hook(info);
+
+            let description = format!("{info}\n\n{}", Backtrace::force_capture());
+            match write_report(&description, unsafe { GetCurrentThreadId() }, None) {
+                Ok(report) => notifications::notify(
+                    NotificationKind::Error,
+                    "tacky-borders ran into a bug",
+                    &format!(
+                        "A crash report was saved to {}. You can open it from the tray menu.",
+                        report.display()
+                    ),
+                ),
+                Err(e) => error!("could not write crash report: {e:#}"),
+            }
+        }));
+
+        match start_watchdog() {
+            Ok(()) => unsafe {
+                SetUnhandledExceptionFilter(Some(handle_exception));
+            },
+            Err(e) => error!("could not start crash watchdog: {e:#}"),
+        }
+
+        // Reports left over from a crash the last time we ran
+        if get_crash_dir().is_ok_and(|dir| dir.join(NEW_REPORT_MARKER).exists()) {
+            HAS_NEW_REPORTS.store(true, Ordering::SeqCst);
+            notifications::notify(
+                NotificationKind::Error,
+                "tacky-borders crashed last time",
+                "A crash report was saved. You can open it from the tray menu.",
+            );
+        }
+    });
+}
+
+// Remember a window event for the next crash report, whichever tracker it came from
+pub(crate) fn record_event(event: WindowEvent) {
+    let sequence = NEXT_EVENT.fetch_add(1, Ordering::Relaxed);
+    let recorded = &RECENT_EVENTS[sequence % MAX_EVENTS];
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+
+    recorded.sequence.store(0, Ordering::Release);
+    recorded.time.store(time, Ordering::Relaxed);
+    recorded
+        .event
+        .store(event.index() as u32, Ordering::Relaxed);
+    recorded.hwnd.store(
+        event.hwnd().map_or(0, |hwnd| hwnd.0 as isize),
+        Ordering::Relaxed,
+    );
+    recorded.sequence.store(sequence + 1, Ordering::Release);
+}
+
+// The recorded events that are still in the ring, oldest first, as (time, event, hwnd). Events
+// that are being written while we read them are left out.
+fn recent_events() -> Vec<(u64, u32, isize)> {
+    let next = NEXT_EVENT.load(Ordering::Acquire);
+
+    (next.saturating_sub(MAX_EVENTS)..next)
+        .filter_map(|sequence| {
+            let recorded = &RECENT_EVENTS[sequence % MAX_EVENTS];
+            if recorded.sequence.load(Ordering::Acquire) != sequence + 1 {
+                return None;
+            }
+            let event = (
+                recorded.time.load(Ordering::Relaxed),
+                recorded.event.load(Ordering::Relaxed),
+                recorded.hwnd.load(Ordering::Relaxed),
+            );
+            // Another event may have taken the spot while we were reading it
+            (recorded.sequence.load(Ordering::Acquire) == sequence + 1).then_some(event)
+        })
+        .collect()
+}
+
+/// The folder crash reports are written to.
+pub fn get_crash_dir() -> anyhow::Result<PathBuf> {
+    Ok(Config::get_config_dir()?.join("crashes"))
+}
+
+/// Whether there are crash reports the user hasn't opened yet.
+pub fn has_new_reports() -> bool {
+    HAS_NEW_REPORTS.load(Ordering::SeqCst)
+}
+
+/// How many crash reports this process has written. This goes up whenever there's a new report.
+pub fn report_count() -> usize {
+    REPORT_COUNT.load(Ordering::SeqCst)
+}
+
+/// Marks the crash reports as seen, e.g. after opening the crashes folder.
+pub fn mark_reports_seen() {
+    HAS_NEW_REPORTS.store(false, Ordering::SeqCst);
+    if let Ok(dir) = get_crash_dir() {
+        let _ = fs::remove_file(dir.join(NEW_REPORT_MARKER));
+    }
+}
+
+fn start_watchdog() -> anyhow::Result<()> {
+    unsafe {
+        let requested = CreateEventW(None, FALSE, FALSE, PCWSTR::null())?;
+        let written = CreateEventW(None, FALSE, FALSE, PCWSTR::null())?;
+        REPORT_REQUESTED.store(requested.0 as isize, Ordering::SeqCst);
+        REPORT_WRITTEN.store(written.0 as isize, Ordering::SeqCst);
+    }
+
+    thread::Builder::new()
+        .name("crash watchdog".to_string())
+        .spawn(|| unsafe {
+            let requested = HANDLE(REPORT_REQUESTED.load(Ordering::SeqCst) as _);
+            let written = HANDLE(REPORT_WRITTEN.load(Ordering::SeqCst) as _);
+            WaitForSingleObject(requested, INFINITE);
+
+            let thread_id = CRASHED_THREAD.load(Ordering::SeqCst);
+            let pointers = CRASH_POINTERS.load(Ordering::SeqCst);
+            let description = match pointers
+                .as_ref()
+                .and_then(|info| info.ExceptionRecord.as_ref())
+            {
+                Some(record) => format!(
+                    "exception {:#010x} at {:?}",
+                    record.ExceptionCode.0, record.ExceptionAddress
+                ),
+                None => "unknown exception".to_string(),
+            };
+            let _ = write_report(&description, thread_id, Some(pointers));
+
+            let _ = SetEvent(written);
+        })?;
+
+    Ok(())
+}
+
+// Only called for exceptions that nothing else handled, which take the process down right after
+unsafe extern "system" fn handle_exception(info: *const EXCEPTION_POINTERS) -> i32 {
+    if WROTE_EXCEPTION_REPORT.swap(true, Ordering::SeqCst) {
+        return EXCEPTION_CONTINUE_SEARCH;
+    }
+
+    // Nothing in here allocates or takes a lock, in case that's what broke
+    CRASHED_THREAD.store(GetCurrentThreadId(), Ordering::SeqCst);
+    CRASH_POINTERS.store(info as *mut _, Ordering::SeqCst);
+    let _ = SetEvent(HANDLE(REPORT_REQUESTED.load(Ordering::SeqCst) as _));
+    WaitForSingleObject(
+        HANDLE(REPORT_WRITTEN.load(Ordering::SeqCst) as _),
+        REPORT_TIMEOUT_MS,
+    );
+
+    EXCEPTION_CONTINUE_SEARCH
+}
+
+// Write the minidump and text report for the given thread, returning the path of the text report
+fn write_report(
+    description: &str,
+    thread_id: u32,
+    exception: Option<*mut EXCEPTION_POINTERS>,
+) -> anyhow::Result<PathBuf> {
+    let crash_dir = get_crash_dir()?;
+    fs::create_dir_all(&crash_dir)?;
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let report_path = crash_dir.join(format!("crash-{timestamp}.txt"));
+    let dump_path = crash_dir.join(format!("crash-{timestamp}.dmp"));
+
+    fs::write(&report_path, build_report(description, thread_id))?;
+
+    // The text report is the important part, so a failed minidump just gets noted in the log
+    if let Err(e) = write_minidump(&dump_path, thread_id, exception) {
+        error!("could not write minidump: {e:#}");
+    }
+
+    let _ = fs::write(crash_dir.join(NEW_REPORT_MARKER), "");
+    remove_old_reports(&crash_dir);
+
+    HAS_NEW_REPORTS.store(true, Ordering::SeqCst);
+    REPORT_COUNT.fetch_add(1, Ordering::SeqCst);
+
+    Ok(report_path)
+}
+
+fn build_report(description: &str, thread_id: u32) -> String {
+    let mut report = String::new();
+
+    let _ = writeln!(report, "tacky-borders v{}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(report, "thread {thread_id}");
+    let _ = writeln!(report, "\n{description}");
+
+    // Whatever crashed might be holding these locks, so we use try_lock to avoid hanging here
+    let _ = writeln!(report, "\n--- config ---");
+    match CONFIG.try_lock() {
+        Ok(config) => {
+            let _ = writeln!(
+                report,
+                "{}",
+                serde_yaml::to_string(&*config).unwrap_or_else(|e| format!("{e}"))
+            );
+        }
+        Err(_) => {
+            let _ = writeln!(report, "(config was locked)");
+        }
+    }
+
+    let _ = writeln!(report, "\n--- windows ---");
+    match BORDERS.try_entries() {
+        Some(borders) => {
+            for (key, border) in borders {
+                let border = border.0 as isize;
+                let _ = match key {
+                    BorderKey::Window(window) => {
+                        let hwnd = HWND(window as _);
+                        writeln!(
+                            report,
+                            "{window:#x} (border {border:#x}): {} | {} | {}",
+                            get_process_name(hwnd),
+                            get_window_class(hwnd),
+                            get_window_title(hwnd)
+                        )
+                    }
+                    key => writeln!(report, "{key:?} (border {border:#x})"),
+                };
+            }
+        }
+        None => {
+            let _ = writeln!(report, "(window list was locked)");
+        }
+    }
+
+    let _ = writeln!(report, "\n--- last {MAX_EVENTS} events ---");
+    for (time, event, hwnd) in recent_events() {
+        let name = WindowEvent::NAMES
+            .get(event as usize)
+            .copied()
+            .unwrap_or("other");
+        let _ = writeln!(report, "{time} {name} {hwnd:#x}");
+    }
+
+    let latency = event_hook::latency();
+    let _ = writeln!(report, "\n--- event latency ---");
+    let _ = writeln!(
+        report,
+        "{} events handled, {:.1}ms on average, {}ms at worst",
+        latency.handled,
+        latency.total_ms as f32 / latency.handled.max(1) as f32,
+        latency.worst_ms
+    );
+
+    report
+}
+
+fn write_minidump(
+    path: &Path,
+    thread_id: u32,
+    exception: Option<*mut EXCEPTION_POINTERS>,
+) -> anyhow::Result<()> {
+    let file = File::create(path)?;
+
+    let exception_info = exception.map(|pointers| MINIDUMP_EXCEPTION_INFORMATION {
+        ThreadId: thread_id,
+        ExceptionPointers: pointers,
+        ClientPointers: FALSE,
+    });
+
+    unsafe {
+        MiniDumpWriteDump(
+            GetCurrentProcess(),
+            GetCurrentProcessId(),
+            HANDLE(file.as_raw_handle()),
+            MiniDumpNormal,
+            exception_info
+                .as_ref()
+                .map(|info| info as *const MINIDUMP_EXCEPTION_INFORMATION),
+            None,
+            None,
+        )?;
+    }
+
+    Ok(())
+}
+
+fn remove_old_reports(crash_dir: &Path) {
+    let Ok(entries) = fs::read_dir(crash_dir) else {
+        return;
+    };
+
+    // The timestamps in the names sort the same way as the times they were written
+    let mut reports: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+        .collect();
+    reports.sort();
+
+    while reports.len() > MAX_REPORTS {
+        let report = reports.remove(0);
+        let _ = fs::remove_file(report.with_extension("dmp"));
+        let _ = fs::remove_file(report);
+    }
+}
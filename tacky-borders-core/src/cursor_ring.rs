@@ -0,0 +1,128 @@
+//! A small ring that follows the mouse cursor. It's drawn by a border window like any other, so it
+//! gets the same colors and animations, but it tracks the cursor instead of a window.
+//!
+//! The ring is configured with the `cursor_ring` section of the config (see [`CursorRingConfig`])
+//! and can be toggled with [`toggle`] or the `cursor_ring` hotkey.
+
+use serde::{Deserialize, Serialize};
+use std::thread;
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+use windows::Win32::UI::HiDpi::GetDpiForSystem;
+use windows::Win32::UI::WindowsAndMessaging::WM_NCDESTROY;
+
+use crate::animations::Animations;
+use crate::border_config::{BorderPlacement, CONFIG};
+use crate::colors::ColorConfig;
+use crate::registry::{BorderKey, BORDERS};
+use crate::utils::*;
+use crate::window_border::WindowBorder;
+
+/// The `cursor_ring` section of the config.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CursorRingConfig {
+    /// The ring's diameter in pixels.
+    #[serde(default = "default_size")]
+    pub size: f32,
+    #[serde(default = "default_width")]
+    pub border_width: f32,
+    pub color: ColorConfig,
+    pub animations: Option<Animations>,
+    /// Whether the ring shows up on startup, or only once it's toggled on.
+    #[serde(default = "default_true")]
+    pub show_on_start: bool,
+}
+
+fn default_size() -> f32 {
+    32.0
+}
+
+fn default_width() -> f32 {
+    2.0
+}
+
+fn default_true() -> bool {
+    true
+}
+
+// Stands in for the ring's tracking window, which it doesn't have. The registry knows the ring as
+// BorderKey::CursorRing, so this is never looked up.
+const RING_TRACKING_WINDOW: isize = isize::MIN;
+
+pub(crate) fn start() {
+    let show_on_start = CONFIG
+        .lock()
+        .unwrap()
+        .cursor_ring
+        .as_ref()
+        .is_some_and(|cursor_ring| cursor_ring.show_on_start);
+
+    if show_on_start {
+        show();
+    }
+}
+
+/// Whether the cursor ring is currently showing.
+pub fn is_showing() -> bool {
+    BORDERS.contains(BorderKey::CursorRing)
+}
+
+/// Shows the cursor ring. Does nothing if it's already showing or `cursor_ring` isn't in the
+/// config.
+pub fn show() {
+    if CONFIG.lock().unwrap().cursor_ring.is_none() || is_showing() {
+        return;
+    }
+
+    let _ = thread::spawn(|| match create_ring_struct() {
+        Ok(ring) => run_border(ring, None),
+        Err(e) => error!("could not create cursor ring: {e}"),
+    });
+}
+
+/// Hides the cursor ring.
+pub fn hide() {
+    let Some(ring_window) = BORDERS.get(BorderKey::CursorRing) else {
+        return;
+    };
+
+    let _ = post_message_w(ring_window, WM_NCDESTROY, WPARAM(0), LPARAM(0));
+}
+
+/// Shows the cursor ring if it's hidden, or hides it if it's showing.
+pub fn toggle() {
+    match is_showing() {
+        true => hide(),
+        false => show(),
+    }
+}
+
+fn create_ring_struct() -> anyhow::Result<WindowBorder> {
+    let config = CONFIG.lock().unwrap();
+    let Some(ring_config) = config.cursor_ring.clone() else {
+        return Err(anyhow::anyhow!("cursor_ring is not configured"));
+    };
+    let renderer_backend = config.global.renderer.unwrap_or_default();
+    drop(config);
+
+    let dpi = unsafe { GetDpiForSystem() } as f32;
+    let size = (ring_config.size * dpi / 96.0) as i32;
+    let border_width = ((ring_config.border_width * dpi / 96.0) as i32).min(size / 2);
+    let animations = ring_config.animations.unwrap_or_default();
+
+    Ok(WindowBorder {
+        tracking_window: HWND(RING_TRACKING_WINDOW as _),
+        cursor_ring_size: Some(size),
+        border_width,
+        // The radius is for the middle of the stroke, so this makes the rounded rect a circle
+        border_radius: (size - border_width) as f32 / 2.0,
+        placement: BorderPlacement::Inner,
+        active_color: ring_config.color.convert_to_color(true),
+        inactive_color: ring_config.color.convert_to_color(false),
+        comet_color: animations.comet.color(),
+        animations,
+        renderer_backend,
+        antialias: true,
+        supersampling: 1,
+        ..Default::default()
+    })
+}
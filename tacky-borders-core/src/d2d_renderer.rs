@@ -0,0 +1,330 @@
+use anyhow::{anyhow, Context};
+use std::sync::LazyLock;
+use windows::Win32::Foundation::{COLORREF, D2DERR_RECREATE_TARGET, FALSE, HWND, TRUE};
+use windows::Win32::Graphics::Direct2D::Common::{
+    D2D1_ALPHA_MODE_PREMULTIPLIED, D2D1_PIXEL_FORMAT, D2D_SIZE_F, D2D_SIZE_U,
+};
+use windows::Win32::Graphics::Direct2D::{
+    D2D1CreateFactory, ID2D1Bitmap, ID2D1BitmapRenderTarget, ID2D1Factory, ID2D1HwndRenderTarget,
+    ID2D1RenderTarget, D2D1_ANTIALIAS_MODE, D2D1_ANTIALIAS_MODE_ALIASED,
+    D2D1_BITMAP_INTERPOLATION_MODE_LINEAR, D2D1_COMPATIBLE_RENDER_TARGET_OPTIONS_NONE,
+    D2D1_FACTORY_TYPE_MULTI_THREADED, D2D1_HWND_RENDER_TARGET_PROPERTIES,
+    D2D1_PRESENT_OPTIONS_IMMEDIATELY, D2D1_PRESENT_OPTIONS_RETAIN_CONTENTS,
+    D2D1_RENDER_TARGET_PROPERTIES, D2D1_RENDER_TARGET_TYPE_DEFAULT,
+};
+use windows::Win32::Graphics::Dwm::{
+    DwmEnableBlurBehindWindow, DWM_BB_BLURREGION, DWM_BB_ENABLE, DWM_BLURBEHIND,
+};
+use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT_UNKNOWN;
+use windows::Win32::Graphics::Gdi::CreateRectRgn;
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetSystemMetrics, SetLayeredWindowAttributes, LWA_ALPHA, SM_CXVIRTUALSCREEN,
+};
+
+use crate::notifications::{self, NotificationKind};
+use crate::renderer::{BorderRenderer, DrawnArea, FrameState, RendererBackend};
+use crate::window_border::WindowBorder;
+
+pub(crate) static RENDER_FACTORY: LazyLock<ID2D1Factory> = unsafe {
+    LazyLock::new(|| {
+        match D2D1CreateFactory::<ID2D1Factory>(D2D1_FACTORY_TYPE_MULTI_THREADED, None) {
+            Ok(factory) => factory,
+            Err(e) => {
+                // Not sure how I can recover from this error so I'm just going to panic
+                error!("could not create ID2D1Factory: {e}");
+                notifications::notify(
+                    NotificationKind::Error,
+                    "Could not start Direct2D",
+                    &format!("Borders can't be drawn: {e}"),
+                );
+                panic!("could not create ID2D1Factory: {e}");
+            }
+        }
+    })
+};
+
+#[derive(Debug, Default)]
+pub struct D2DRenderer {
+    render_target: Option<ID2D1HwndRenderTarget>,
+    border_window: HWND,
+    antialias_mode: D2D1_ANTIALIAS_MODE,
+    pixel_size: D2D_SIZE_U,
+    // Offscreen render target used for supersampling, along with the pixel size it was made for
+    supersample_target: Option<((u32, u32), ID2D1BitmapRenderTarget)>,
+    noise_bitmap: Option<ID2D1Bitmap>,
+    // What the last frame drew to on each target, which is all that needs clearing for the next
+    // one. None means we don't know (e.g. after a resize), so everything gets cleared.
+    drawn_area: Option<DrawnArea>,
+    supersample_drawn_area: Option<DrawnArea>,
+}
+
+impl D2DRenderer {
+    fn get_supersample_target(
+        &mut self,
+        render_target: &ID2D1HwndRenderTarget,
+        supersampling: u32,
+    ) -> anyhow::Result<ID2D1BitmapRenderTarget> {
+        let size_key = (self.pixel_size.width, self.pixel_size.height);
+
+        // Reuse the existing supersample target if the border hasn't changed size
+        if let Some((cached_size, ref bitmap_target)) = self.supersample_target {
+            if cached_size == size_key {
+                return Ok(bitmap_target.clone());
+            }
+        }
+
+        // We keep the logical size the same but scale up the pixel size, so the draw calls don't
+        // need to know anything about supersampling
+        let logical_size = D2D_SIZE_F {
+            width: self.pixel_size.width as f32,
+            height: self.pixel_size.height as f32,
+        };
+        let scaled_size = D2D_SIZE_U {
+            width: self.pixel_size.width * supersampling,
+            height: self.pixel_size.height * supersampling,
+        };
+
+        let bitmap_target = unsafe {
+            let bitmap_target = render_target
+                .CreateCompatibleRenderTarget(
+                    Some(&logical_size),
+                    Some(&scaled_size),
+                    None,
+                    D2D1_COMPATIBLE_RENDER_TARGET_OPTIONS_NONE,
+                )
+                .context("could not create supersample render target")?;
+
+            bitmap_target.SetAntialiasMode(self.antialias_mode);
+            bitmap_target
+        };
+
+        self.supersample_target = Some((size_key, bitmap_target.clone()));
+        self.supersample_drawn_area = None;
+
+        Ok(bitmap_target)
+    }
+
+    fn recreate_render_target(&mut self) -> anyhow::Result<()> {
+        // These were created from the old render target, so they must be recreated too
+        self.supersample_target = None;
+        self.noise_bitmap = None;
+        self.drawn_area = None;
+
+        let render_target = create_render_target(self.border_window, self.antialias_mode)?;
+        unsafe { render_target.Resize(&self.pixel_size) }?;
+        self.render_target = Some(render_target);
+
+        Ok(())
+    }
+}
+
+impl BorderRenderer for D2DRenderer {
+    fn backend(&self) -> RendererBackend {
+        RendererBackend::Direct2D
+    }
+
+    fn init(&mut self, border: &WindowBorder) -> anyhow::Result<()> {
+        make_transparent(border.border_window)?;
+
+        self.border_window = border.border_window;
+        self.antialias_mode = border.antialias_mode();
+        self.render_target = Some(create_render_target(
+            self.border_window,
+            self.antialias_mode,
+        )?);
+
+        Ok(())
+    }
+
+    fn resize(&mut self, width: u32, height: u32) -> anyhow::Result<()> {
+        self.pixel_size = D2D_SIZE_U { width, height };
+
+        // Whatever was drawn before is gone (or stretched) after resizing
+        self.drawn_area = None;
+
+        if let Some(ref render_target) = self.render_target {
+            unsafe { render_target.Resize(&self.pixel_size) }
+                .context("could not resize render_target")?;
+        }
+
+        Ok(())
+    }
+
+    fn draw(&mut self, frame: &FrameState) -> anyhow::Result<()> {
+        let Some(render_target) = self.render_target.clone() else {
+            return Err(anyhow!("render_target has not been created yet"));
+        };
+        if let Some(noise) = frame.noise {
+            if self.noise_bitmap.is_none() {
+                self.noise_bitmap = noise
+                    .create_bitmap(&render_target)
+                    .inspect_err(|e| error!("could not create noise bitmap: {e}"))
+                    .ok();
+            }
+        }
+
+        let size = D2D_SIZE_F {
+            width: self.pixel_size.width as f32,
+            height: self.pixel_size.height as f32,
+        };
+
+        unsafe {
+            render_target.BeginDraw();
+            let clear_area = self.drawn_area.map(|last| last.union(frame.drawn_area));
+            clear(&render_target, clear_area, size);
+            self.drawn_area = Some(frame.drawn_area);
+
+            let supersampling = frame.supersampling;
+            match (frame.visible, supersampling > 1) {
+                (false, _) => {}
+                (true, true) => {
+                    // Draw the border at a higher resolution, then scale it down onto the window
+                    let bitmap_target =
+                        self.get_supersample_target(&render_target, supersampling)?;
+
+                    bitmap_target.BeginDraw();
+                    let clear_area = self
+                        .supersample_drawn_area
+                        .map(|last| last.union(frame.drawn_area));
+                    clear(&bitmap_target, clear_area, size);
+                    self.supersample_drawn_area = Some(frame.drawn_area);
+                    frame
+                        .painter
+                        .paint(&bitmap_target, self.noise_bitmap.as_ref())?;
+                    bitmap_target
+                        .EndDraw(None, None)
+                        .context("could not draw to supersample render target")?;
+
+                    let bitmap = bitmap_target
+                        .GetBitmap()
+                        .context("could not retrieve supersample bitmap")?;
+
+                    // The rest of the bitmap is transparent, so there's no need to scale it down
+                    match frame.drawn_area.rects(size.width, size.height) {
+                        Some(rects) => {
+                            for rect in rects.iter() {
+                                render_target
+                                    .PushAxisAlignedClip(rect, D2D1_ANTIALIAS_MODE_ALIASED);
+                                render_target.DrawBitmap(
+                                    &bitmap,
+                                    None,
+                                    1.0,
+                                    D2D1_BITMAP_INTERPOLATION_MODE_LINEAR,
+                                    None,
+                                );
+                                render_target.PopAxisAlignedClip();
+                            }
+                        }
+                        None => render_target.DrawBitmap(
+                            &bitmap,
+                            None,
+                            1.0,
+                            D2D1_BITMAP_INTERPOLATION_MODE_LINEAR,
+                            None,
+                        ),
+                    }
+                }
+                (true, false) => frame
+                    .painter
+                    .paint(&render_target, self.noise_bitmap.as_ref())?,
+            }
+
+            match render_target.EndDraw(None, None) {
+                Ok(_) => {}
+                Err(e) if e.code() == D2DERR_RECREATE_TARGET => {
+                    // D2DERR_RECREATE_TARGET is recoverable if we just recreate the render target.
+                    // This error can be caused by things like waking up from sleep, updating GPU
+                    // drivers, changing screen resolution, etc.
+                    warn!("render_target has been lost; attempting to recreate");
+
+                    self.recreate_render_target()
+                        .context("could not recreate render_target")?;
+
+                    info!("successfully recreated render_target; resuming thread");
+                }
+                Err(other) => {
+                    return Err(other).context("render_target.EndDraw() failed");
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn release(&mut self) {
+        self.supersample_target = None;
+        self.noise_bitmap = None;
+        self.render_target = None;
+    }
+}
+
+// Clearing the whole surface every frame costs a lot on big windows, when all that ever changes is
+// a thin band along the edges. So if we know what was drawn, we only clear that.
+unsafe fn clear(render_target: &ID2D1RenderTarget, area: Option<DrawnArea>, size: D2D_SIZE_F) {
+    match area.and_then(|area| area.rects(size.width, size.height)) {
+        Some(rects) => {
+            for rect in rects.iter() {
+                render_target.PushAxisAlignedClip(rect, D2D1_ANTIALIAS_MODE_ALIASED);
+                render_target.Clear(None);
+                render_target.PopAxisAlignedClip();
+            }
+        }
+        None => render_target.Clear(None),
+    }
+}
+
+// Lets a window show what's drawn on it with per-pixel transparency
+pub(crate) fn make_transparent(window: HWND) -> anyhow::Result<()> {
+    unsafe {
+        // Make the window transparent (stole the code from PowerToys; dunno how it works).
+        let pos: i32 = -GetSystemMetrics(SM_CXVIRTUALSCREEN) - 8;
+        let hrgn = CreateRectRgn(pos, 0, pos + 1, 1);
+        let mut bh: DWM_BLURBEHIND = Default::default();
+        if !hrgn.is_invalid() {
+            bh = DWM_BLURBEHIND {
+                dwFlags: DWM_BB_ENABLE | DWM_BB_BLURREGION,
+                fEnable: TRUE,
+                hRgnBlur: hrgn,
+                fTransitionOnMaximized: FALSE,
+            };
+        }
+        // These functions below are pretty important, so if they fail, just return an Error
+        DwmEnableBlurBehindWindow(window, &bh).context("could not make window transparent")?;
+
+        SetLayeredWindowAttributes(window, COLORREF(0x00000000), 255, LWA_ALPHA)
+            .context("could not set LWA_ALPHA")?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn create_render_target(
+    border_window: HWND,
+    antialias_mode: D2D1_ANTIALIAS_MODE,
+) -> windows::core::Result<ID2D1HwndRenderTarget> {
+    let render_target_properties = D2D1_RENDER_TARGET_PROPERTIES {
+        r#type: D2D1_RENDER_TARGET_TYPE_DEFAULT,
+        pixelFormat: D2D1_PIXEL_FORMAT {
+            format: DXGI_FORMAT_UNKNOWN,
+            alphaMode: D2D1_ALPHA_MODE_PREMULTIPLIED,
+        },
+        dpiX: 96.0,
+        dpiY: 96.0,
+        ..Default::default()
+    };
+    let hwnd_render_target_properties = D2D1_HWND_RENDER_TARGET_PROPERTIES {
+        hwnd: border_window,
+        pixelSize: Default::default(),
+        // Keep the last frame around so we only have to redraw the parts of it that change
+        presentOptions: D2D1_PRESENT_OPTIONS_IMMEDIATELY | D2D1_PRESENT_OPTIONS_RETAIN_CONTENTS,
+    };
+
+    unsafe {
+        let render_target = RENDER_FACTORY
+            .CreateHwndRenderTarget(&render_target_properties, &hwnd_render_target_properties)?;
+
+        render_target.SetAntialiasMode(antialias_mode);
+
+        Ok(render_target)
+    }
+}
@@ -0,0 +1,142 @@
+//! Follows Windows' do not disturb setting (called Focus Assist on Windows 10). While it's on,
+//! borders can switch to a minimal style (thin, static, and without any effects) or pause entirely,
+//! depending on the `do_not_disturb` section's `action`.
+
+use serde::{Deserialize, Serialize};
+use std::ffi::c_void;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::Once;
+use std::thread;
+use std::time;
+
+const POLL_INTERVAL: time::Duration = time::Duration::from_secs(2);
+
+// Windows doesn't have a documented way to read do not disturb, so we read the same notification
+// state that the shell itself publishes whenever it changes. It's 0 while do not disturb is off.
+const WNF_SHEL_QUIETHOURS_ACTIVE_PROFILE_CHANGED: u64 = 0x0d83063ea3bf1c75;
+
+#[link(name = "ntdll")]
+extern "system" {
+    fn NtQueryWnfStateData(
+        state_name: *const u64,
+        type_id: *const c_void,
+        explicit_scope: *const c_void,
+        change_stamp: *mut u32,
+        buffer: *mut c_void,
+        buffer_size: *mut u32,
+    ) -> i32;
+}
+
+// Whether do not disturb is on right now
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+// The action from the last config that loaded, as a DoNotDisturbAction discriminant. Borders are
+// created while CONFIG is locked, so they can't read it from there.
+static ACTION: AtomicU8 = AtomicU8::new(DoNotDisturbAction::Ignore as u8);
+
+static POLLER: Once = Once::new();
+
+/// What borders do while do not disturb is on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum DoNotDisturbAction {
+    /// Keep drawing borders like normal.
+    #[default]
+    #[serde(alias = "ignore")]
+    Ignore,
+    /// Draw thin borders without animations or effects.
+    #[serde(alias = "minimal")]
+    Minimal,
+    /// Remove every border until do not disturb is turned off.
+    #[serde(alias = "pause")]
+    Pause,
+}
+
+/// The `do_not_disturb` section of the config.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DoNotDisturbConfig {
+    #[serde(default)]
+    pub action: DoNotDisturbAction,
+    /// The border width used by the minimal style.
+    #[serde(default = "default_border_width")]
+    pub border_width: f32,
+}
+
+impl Default for DoNotDisturbConfig {
+    fn default() -> Self {
+        Self {
+            action: DoNotDisturbAction::default(),
+            border_width: default_border_width(),
+        }
+    }
+}
+
+fn default_border_width() -> f32 {
+    1.0
+}
+
+/// Whether borders are drawn in the minimal style right now.
+pub fn is_minimal() -> bool {
+    ACTIVE.load(Ordering::SeqCst)
+        && ACTION.load(Ordering::SeqCst) == DoNotDisturbAction::Minimal as u8
+}
+
+/// Whether borders are paused for do not disturb right now.
+pub fn is_paused() -> bool {
+    ACTIVE.load(Ordering::SeqCst)
+        && ACTION.load(Ordering::SeqCst) == DoNotDisturbAction::Pause as u8
+}
+
+// Called whenever the config is loaded. Borders get recreated after a reload anyway, so this
+// doesn't need to touch them.
+pub(crate) fn reset(config: &DoNotDisturbConfig) {
+    ACTION.store(config.action as u8, Ordering::SeqCst);
+}
+
+pub(crate) fn start() {
+    POLLER.call_once(|| {
+        ACTIVE.store(is_do_not_disturb_on(), Ordering::SeqCst);
+
+        let _ = thread::spawn(|| loop {
+            thread::sleep(POLL_INTERVAL);
+            poll();
+        });
+    });
+}
+
+fn poll() {
+    let active = is_do_not_disturb_on();
+    if ACTIVE.swap(active, Ordering::SeqCst) == active {
+        return;
+    }
+
+    info!(
+        "do not disturb was turned {}",
+        if active { "on" } else { "off" }
+    );
+
+    // Nothing to re-apply if borders look the same either way
+    if ACTION.load(Ordering::SeqCst) != DoNotDisturbAction::Ignore as u8 {
+        crate::reload_borders();
+    }
+}
+
+fn is_do_not_disturb_on() -> bool {
+    let mut change_stamp = 0u32;
+    let mut profile = 0u32;
+    let mut size = size_of::<u32>() as u32;
+
+    let status = unsafe {
+        NtQueryWnfStateData(
+            &WNF_SHEL_QUIETHOURS_ACTIVE_PROFILE_CHANGED,
+            ptr::null(),
+            ptr::null(),
+            &mut change_stamp,
+            ptr::addr_of_mut!(profile) as *mut c_void,
+            &mut size,
+        )
+    };
+
+    // A negative NTSTATUS means it failed, e.g. on versions of Windows without do not disturb
+    status >= 0 && profile != 0
+}
@@ -0,0 +1,392 @@
+use serde::{Deserialize, Serialize};
+use windows::Win32::Foundation::RECT;
+use windows::Win32::Graphics::Direct2D::Common::{D2D1_GRADIENT_STOP, D2D_POINT_2F, D2D_RECT_F};
+use windows::Win32::Graphics::Direct2D::{
+    ID2D1Brush, ID2D1RenderTarget, D2D1_BRUSH_PROPERTIES, D2D1_EXTEND_MODE_CLAMP, D2D1_GAMMA_2_2,
+    D2D1_LINEAR_GRADIENT_BRUSH_PROPERTIES, D2D1_ROUNDED_RECT,
+};
+
+use crate::colors::{Color, ColorConfig};
+
+// Different colors for each edge of the focused window's border, e.g. a lighter top and a darker
+// bottom for a beveled look. Edges that are left out keep the border's active color.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EdgeColorsConfig {
+    pub top: Option<ColorConfig>,
+    pub right: Option<ColorConfig>,
+    pub bottom: Option<ColorConfig>,
+    pub left: Option<ColorConfig>,
+}
+
+// Presets that derive the edges' colors from the active color. Colors set in edge_colors still
+// win over the preset's.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum BorderStyle {
+    #[default]
+    #[serde(alias = "flat")]
+    Flat,
+    // Lighter on the top and left, darker on the bottom and right, like a raised frame
+    #[serde(alias = "bevel")]
+    Bevel,
+    // The other way around, like a sunken frame
+    #[serde(alias = "inset")]
+    Inset,
+}
+
+// How much the bevel presets move the OKLAB lightness of each edge
+const BEVEL_SHADE: f32 = 0.18;
+
+impl BorderStyle {
+    // The shade of each edge, clockwise from the top
+    fn shades(&self) -> [f32; 4] {
+        match self {
+            BorderStyle::Flat => [0.0; 4],
+            BorderStyle::Bevel => [BEVEL_SHADE, -BEVEL_SHADE, -BEVEL_SHADE, BEVEL_SHADE],
+            BorderStyle::Inset => [-BEVEL_SHADE, BEVEL_SHADE, BEVEL_SHADE, -BEVEL_SHADE],
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EdgeColors {
+    // Clockwise from the top, in the same order as the edges' segments
+    pub colors: [Option<Color>; 4],
+    pub style: BorderStyle,
+}
+
+impl EdgeColors {
+    // Flat borders without any edge colors are drawn the usual way, so there's nothing to set up
+    pub fn new(config: Option<&EdgeColorsConfig>, style: BorderStyle) -> Option<Self> {
+        let config = match (config, style) {
+            (None, BorderStyle::Flat) => return None,
+            (config, _) => config.cloned().unwrap_or_default(),
+        };
+
+        let convert = |color: &Option<ColorConfig>| {
+            color
+                .as_ref()
+                .map(|color_config| color_config.convert_to_color(true))
+        };
+
+        Some(Self {
+            colors: [
+                convert(&config.top),
+                convert(&config.right),
+                convert(&config.bottom),
+                convert(&config.left),
+            ],
+            style,
+        })
+    }
+
+    // Every edge's color, with the active color (shaded for the style) standing in for the ones
+    // that aren't set. They all fade along with the active color.
+    pub fn resolve(&self, active_color: &Color) -> [Color; 4] {
+        let shades = self.style.shades();
+
+        std::array::from_fn(|edge| {
+            let mut color = match (&self.colors[edge], shades[edge]) {
+                (Some(color), _) => color.clone(),
+                (None, 0.0) => active_color.clone(),
+                (None, shade) => active_color.shade(shade),
+            };
+            color.set_fade_opacity(active_color.fade_opacity());
+            color
+        })
+    }
+}
+
+// One piece of the border. Edges start and end with the same color, while corners blend from one
+// edge's color into the next one's.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EdgeSegment {
+    // The part of the border's stroke that belongs to this segment. Neighboring segments' clips
+    // meet exactly, so drawing the whole stroke once per segment (with aliased clips) leaves no
+    // seams at the corners, unlike drawing open pieces that each get antialiased on their own.
+    pub clip: D2D_RECT_F,
+    pub start: D2D_POINT_2F,
+    pub end: D2D_POINT_2F,
+    // Indices into EdgeColors::resolve()
+    pub from: usize,
+    pub to: usize,
+}
+
+// Split the rounded rect into its four edges and four corners, going clockwise from the top edge.
+// Square corners still get a short corner segment (as long as half the stroke is wide) so the
+// colors blend around them too.
+pub fn edge_segments(rounded_rect: &D2D1_ROUNDED_RECT, stroke_width: f32) -> [EdgeSegment; 8] {
+    let rect = rounded_rect.rect;
+    let radius = rounded_rect
+        .radiusX
+        .min((rect.right - rect.left) / 2.0)
+        .min((rect.bottom - rect.top) / 2.0)
+        .max(0.0);
+    let half_stroke = (stroke_width / 2.0)
+        .min((rect.right - rect.left) / 2.0)
+        .min((rect.bottom - rect.top) / 2.0)
+        .max(0.0);
+    let corner = match radius > 0.0 {
+        true => radius,
+        false => half_stroke,
+    };
+    // The corners' clips reach at least half the stroke in, so the edges' clips never overlap with
+    // the stroke along the next edge
+    let corner_clip = corner.max(half_stroke);
+    let point = |x: f32, y: f32| D2D_POINT_2F { x, y };
+    let clip = |left: f32, top: f32, right: f32, bottom: f32| D2D_RECT_F {
+        left,
+        top,
+        right,
+        bottom,
+    };
+
+    // Nothing gets drawn further out than this
+    let margin = stroke_width.max(0.0) + 1.0;
+    let (outer_left, outer_top) = (rect.left - margin, rect.top - margin);
+    let (outer_right, outer_bottom) = (rect.right + margin, rect.bottom + margin);
+    let center_x = (rect.left + rect.right) / 2.0;
+    let center_y = (rect.top + rect.bottom) / 2.0;
+    let (inner_left, inner_right) = (rect.left + corner_clip, rect.right - corner_clip);
+    let (inner_top, inner_bottom) = (rect.top + corner_clip, rect.bottom - corner_clip);
+
+    // Each corner goes from the end of one edge, around the rect's corner, to the start of the next
+    let corners = [
+        (
+            point(rect.right - corner, rect.top),
+            point(rect.right, rect.top + corner),
+            clip(inner_right, outer_top, outer_right, inner_top),
+        ),
+        (
+            point(rect.right, rect.bottom - corner),
+            point(rect.right - corner, rect.bottom),
+            clip(inner_right, inner_bottom, outer_right, outer_bottom),
+        ),
+        (
+            point(rect.left + corner, rect.bottom),
+            point(rect.left, rect.bottom - corner),
+            clip(outer_left, inner_bottom, inner_left, outer_bottom),
+        ),
+        (
+            point(rect.left, rect.top + corner),
+            point(rect.left + corner, rect.top),
+            clip(outer_left, outer_top, inner_left, inner_top),
+        ),
+    ];
+    // Clockwise from the top, each edge takes its half of the rect up to the corners' clips
+    let edge_clips = [
+        clip(inner_left, outer_top, inner_right, center_y),
+        clip(center_x, inner_top, outer_right, inner_bottom),
+        clip(inner_left, center_y, inner_right, outer_bottom),
+        clip(outer_left, inner_top, center_x, inner_bottom),
+    ];
+
+    std::array::from_fn(|index| {
+        let edge = index / 2;
+        let (corner_start, corner_end, corner_clip) = corners[edge];
+
+        match index % 2 {
+            // The edge leading into this corner starts where the last corner ended
+            0 => EdgeSegment {
+                clip: edge_clips[edge],
+                start: corners[(edge + 3) % 4].1,
+                end: corner_start,
+                from: edge,
+                to: edge,
+            },
+            _ => EdgeSegment {
+                clip: corner_clip,
+                start: corner_start,
+                end: corner_end,
+                from: edge,
+                to: (edge + 1) % 4,
+            },
+        }
+    })
+}
+
+// The segments and brushes that draw_edges() last drew with. Building them is the expensive part,
+// so they're kept until the colors, the border's shape, or the render target change. The fade and
+// the brush transform change from frame to frame, so those go on the brushes right before drawing.
+#[derive(Debug, Default)]
+pub struct EdgeCache {
+    key: Option<EdgeCacheKey>,
+    segments: Vec<EdgeSegment>,
+    brushes: Vec<ID2D1Brush>,
+}
+
+#[derive(Debug, PartialEq)]
+struct EdgeCacheKey {
+    render_target: ID2D1RenderTarget,
+    window_rect: RECT,
+    rounded_rect: D2D1_ROUNDED_RECT,
+    stroke_width: f32,
+    colors: [Color; 4],
+}
+
+impl EdgeCache {
+    // Build the segments and brushes again if anything they depend on changed. 'colors' are the
+    // ones from EdgeColors::resolve(), at full fade opacity.
+    pub fn update(
+        &mut self,
+        render_target: &ID2D1RenderTarget,
+        window_rect: &RECT,
+        rounded_rect: &D2D1_ROUNDED_RECT,
+        stroke_width: f32,
+        colors: [Color; 4],
+        brush_properties: &D2D1_BRUSH_PROPERTIES,
+    ) -> windows::core::Result<()> {
+        let key = EdgeCacheKey {
+            render_target: render_target.clone(),
+            window_rect: *window_rect,
+            rounded_rect: *rounded_rect,
+            stroke_width,
+            colors,
+        };
+        if self.key.as_ref() == Some(&key) {
+            return Ok(());
+        }
+
+        let segments = edge_segments(rounded_rect, stroke_width);
+        let brushes = segments
+            .iter()
+            .map(|segment| match segment.from == segment.to {
+                true => key.colors[segment.from].create_brush(
+                    render_target,
+                    window_rect,
+                    brush_properties,
+                ),
+                false => create_corner_brush(
+                    render_target,
+                    segment,
+                    &key.colors[segment.from],
+                    &key.colors[segment.to],
+                    brush_properties,
+                ),
+            })
+            .collect::<windows::core::Result<Vec<_>>>()?;
+
+        self.key = Some(key);
+        self.segments = segments.to_vec();
+        self.brushes = brushes;
+
+        Ok(())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&EdgeSegment, &ID2D1Brush)> {
+        self.segments.iter().zip(self.brushes.iter())
+    }
+}
+
+// A gradient across the corner from one edge's color to the next's. Gradients can't blend into
+// other gradients, so those stand in with their average color here.
+pub fn create_corner_brush(
+    render_target: &ID2D1RenderTarget,
+    segment: &EdgeSegment,
+    from: &Color,
+    to: &Color,
+    brush_properties: &D2D1_BRUSH_PROPERTIES,
+) -> windows::core::Result<ID2D1Brush> {
+    let gradient_stops = [
+        D2D1_GRADIENT_STOP {
+            position: 0.0,
+            color: from.flatten(),
+        },
+        D2D1_GRADIENT_STOP {
+            position: 1.0,
+            color: to.flatten(),
+        },
+    ];
+
+    unsafe {
+        let gradient_stop_collection = render_target.CreateGradientStopCollection(
+            &gradient_stops,
+            D2D1_GAMMA_2_2,
+            D2D1_EXTEND_MODE_CLAMP,
+        )?;
+
+        let brush = render_target.CreateLinearGradientBrush(
+            &D2D1_LINEAR_GRADIENT_BRUSH_PROPERTIES {
+                startPoint: segment.start,
+                endPoint: segment.end,
+            },
+            Some(brush_properties),
+            &gradient_stop_collection,
+        )?;
+
+        Ok(brush.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress;
+
+    fn rounded_rect(radius: f32) -> D2D1_ROUNDED_RECT {
+        D2D1_ROUNDED_RECT {
+            rect: D2D_RECT_F {
+                left: 10.0,
+                top: 10.0,
+                right: 210.0,
+                bottom: 110.0,
+            },
+            radiusX: radius,
+            radiusY: radius,
+        }
+    }
+
+    fn contains(clip: &D2D_RECT_F, point: &D2D_POINT_2F) -> bool {
+        clip.left <= point.x && point.x < clip.right && clip.top <= point.y && point.y < clip.bottom
+    }
+
+    #[test]
+    fn edge_segments_go_clockwise() {
+        let froms: Vec<_> = edge_segments(&rounded_rect(8.0), 4.0)
+            .iter()
+            .map(|segment| (segment.from, segment.to))
+            .collect();
+        assert_eq!(
+            froms,
+            [
+                (0, 0),
+                (0, 1),
+                (1, 1),
+                (1, 2),
+                (2, 2),
+                (2, 3),
+                (3, 3),
+                (3, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn edge_segment_clips_cover_the_stroke_once() {
+        // Square corners, corners rounder than the stroke is wide, and ones that aren't
+        for (radius, stroke_width) in [(0.0, 4.0), (8.0, 4.0), (1.0, 6.0), (0.0, 1.0)] {
+            let rounded_rect = rounded_rect(radius);
+            let segments = edge_segments(&rounded_rect, stroke_width);
+
+            // Points all along the stroke: on the outline and near both of its sides
+            for step in 0..997 {
+                let fraction = (step as f32 + 0.5) / 997.0;
+                let (point, normal) = progress::point_on_perimeter(&rounded_rect, fraction);
+
+                for offset in [-0.45, 0.0, 0.45] {
+                    let point = D2D_POINT_2F {
+                        x: point.x + normal.x * stroke_width * offset,
+                        y: point.y + normal.y * stroke_width * offset,
+                    };
+                    let count = segments
+                        .iter()
+                        .filter(|segment| contains(&segment.clip, &point))
+                        .count();
+                    assert_eq!(
+                        count, 1,
+                        "{point:?} (radius {radius}, width {stroke_width})"
+                    );
+                }
+            }
+        }
+    }
+}
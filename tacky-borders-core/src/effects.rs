@@ -0,0 +1,909 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::thread;
+use std::time;
+use windows::Foundation::Numerics::Matrix3x2;
+use windows::Win32::Foundation::{HWND, LPARAM, POINT, RECT, WPARAM};
+use windows::Win32::Graphics::Direct2D::Common::{
+    D2D1_ALPHA_MODE_PREMULTIPLIED, D2D1_COLOR_F, D2D1_PIXEL_FORMAT, D2D_POINT_2F, D2D_SIZE_U,
+};
+use windows::Win32::Graphics::Direct2D::{
+    ID2D1Bitmap, ID2D1Brush, ID2D1RenderTarget, D2D1_BITMAP_BRUSH_PROPERTIES,
+    D2D1_BITMAP_INTERPOLATION_MODE_NEAREST_NEIGHBOR, D2D1_BITMAP_PROPERTIES, D2D1_ELLIPSE,
+    D2D1_EXTEND_MODE_WRAP, D2D1_ROUNDED_RECT,
+};
+use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT_B8G8R8A8_UNORM;
+use windows::Win32::Graphics::Gdi::{GetDC, GetPixel, ReleaseDC, CLR_INVALID};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetAncestor, GetCursorPos, WindowFromPoint, GA_ROOT,
+};
+
+use crate::colors::{relative_luminance, Color, ColorConfig};
+use crate::progress;
+use crate::utils::{get_border_from_window, post_message_w, WM_APP_HOVER};
+
+// Width and height of the noise bitmap. It gets tiled along the border, so it can be pretty small.
+const NOISE_TILE_SIZE: u32 = 64;
+
+// No matter what the config asks for, this many particles (and this many new ones a second) is
+// plenty
+const MAX_PARTICLE_BUDGET: u32 = 500;
+const MAX_PARTICLE_RATE: f32 = 1000.0;
+// How fast (in radians per second) sparkles twinkle and snow sways from side to side, and how far
+// snow sways compared to how fast it falls
+const SPARKLE_TWINKLE_SPEED: f32 = 9.0;
+const SNOW_SWAY_SPEED: f32 = 3.0;
+const SNOW_SWAY_AMOUNT: f32 = 0.4;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NoiseConfig {
+    #[serde(default = "default_noise_intensity")]
+    pub intensity: f32,
+    #[serde(default = "default_noise_speed")]
+    pub speed: f32,
+}
+
+fn default_noise_intensity() -> f32 {
+    0.25
+}
+
+fn default_noise_speed() -> f32 {
+    24.0
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContrastGuardConfig {
+    // The minimum WCAG contrast ratio between the border and whatever is behind it (1 to 21)
+    #[serde(default = "default_min_contrast")]
+    pub min_contrast: f32,
+    // How often (in milliseconds) to sample the background
+    #[serde(default = "default_contrast_interval")]
+    pub interval: u32,
+}
+
+fn default_min_contrast() -> f32 {
+    3.0
+}
+
+fn default_contrast_interval() -> u32 {
+    1000
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotRespondingConfig {
+    // The color to use for the border while the window isn't responding
+    #[serde(default = "default_not_responding_color")]
+    pub color: ColorConfig,
+    #[serde(default = "default_true")]
+    pub dashed: bool,
+    // How often (in milliseconds) to check if the window is responding
+    #[serde(default = "default_not_responding_interval")]
+    pub interval: u32,
+}
+
+fn default_not_responding_color() -> ColorConfig {
+    ColorConfig::SolidConfig("#808080".to_string())
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_not_responding_interval() -> u32 {
+    2000
+}
+
+// Windows only calls a window "Not Responding" after about 5s, so checking more often than this
+// doesn't notice it any sooner
+pub const NOT_RESPONDING_MIN_INTERVAL_MS: u32 = 1000;
+
+// The style borders switch to while their window isn't responding
+#[derive(Debug, Clone)]
+pub struct NotResponding {
+    pub color: Color,
+    pub dashed: bool,
+    pub interval: u32,
+}
+
+impl NotResponding {
+    pub fn new(config: &NotRespondingConfig) -> Self {
+        Self {
+            color: config.color.convert_to_color(true),
+            dashed: config.dashed,
+            interval: config.interval.max(NOT_RESPONDING_MIN_INTERVAL_MS),
+        }
+    }
+
+    // Get the not responding color with the opacity of the color it is replacing
+    pub fn color_with_opacity(&self, opacity: f32) -> Color {
+        let mut color = self.color.clone();
+        color.set_fade_opacity(opacity);
+        color
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RippleConfig {
+    // How far (in pixels) the ripple travels away from the border
+    #[serde(default = "default_ripple_distance")]
+    pub distance: f32,
+    // How long (in milliseconds) the ripple lasts
+    #[serde(default = "default_ripple_duration")]
+    pub duration: u64,
+}
+
+fn default_ripple_distance() -> f32 {
+    16.0
+}
+
+fn default_ripple_duration() -> u64 {
+    400
+}
+
+// A ring that expands out from the border and fades away when its window gets focus
+#[derive(Debug, Clone)]
+pub struct Ripple {
+    pub distance: i32,
+    pub duration: time::Duration,
+    pub start: Option<time::Instant>,
+}
+
+impl Ripple {
+    pub fn new(config: &RippleConfig, dpi: f32) -> Self {
+        Self {
+            distance: (config.distance.max(0.0) * dpi / 96.0) as i32,
+            duration: time::Duration::from_millis(config.duration.max(1)),
+            start: None,
+        }
+    }
+
+    // How far along the ripple is (eased, from 0.0 to 1.0), or None if it isn't running
+    pub fn progress(&self) -> Option<f32> {
+        let elapsed = self.start?.elapsed().as_secs_f32() / self.duration.as_secs_f32();
+        match elapsed < 1.0 {
+            // Ease out so the ripple starts fast and slows down as it fades
+            true => Some(1.0 - (1.0 - elapsed).powi(3)),
+            false => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HoverPeekConfig {
+    // How long (in milliseconds) the cursor has to rest on an inactive window before its border shows
+    #[serde(default = "default_hover_delay")]
+    pub delay: u64,
+    // How long (in milliseconds) the border takes to fade in or out
+    #[serde(default = "default_hover_fade")]
+    pub fade: u64,
+}
+
+fn default_hover_delay() -> u64 {
+    300
+}
+
+fn default_hover_fade() -> u64 {
+    150
+}
+
+// How often the shared hover poll checks which window the cursor is on
+const HOVER_POLL_INTERVAL: time::Duration = time::Duration::from_millis(50);
+
+// The border windows that have hover_peek. It's None while the poll isn't running, which it only
+// does while there's at least one of them.
+static HOVER_BORDERS: Mutex<Option<HashSet<isize>>> = Mutex::new(None);
+
+// Hides inactive borders until the cursor rests on their window for a bit
+#[derive(Debug, Clone)]
+pub struct HoverPeek {
+    pub delay: time::Duration,
+    pub fade: time::Duration,
+    // Whether the cursor is on the window, as last heard from the hover poll
+    pub hovered: bool,
+    // When the cursor moved onto the window, if it's still there
+    pub hover_start: Option<time::Instant>,
+    // How visible the inactive border is, from 0.0 to 1.0
+    pub opacity: f32,
+    // The last tick, while the border is fading or waiting out the delay
+    pub last_update: Option<time::Instant>,
+}
+
+impl HoverPeek {
+    pub fn new(config: &HoverPeekConfig) -> Self {
+        Self {
+            delay: time::Duration::from_millis(config.delay),
+            fade: time::Duration::from_millis(config.fade.max(1)),
+            hovered: false,
+            hover_start: None,
+            opacity: 0.0,
+            last_update: None,
+        }
+    }
+
+    // Called when the cursor moves onto or off of the window
+    pub fn set_hovered(&mut self, hovered: bool, now: time::Instant) {
+        self.hovered = hovered;
+        self.hover_start = match hovered {
+            true => self.hover_start.or(Some(now)),
+            false => None,
+        };
+    }
+
+    // Called when the window loses focus. Its border was fully shown until now, so it only fades
+    // out if the cursor isn't already resting on the window.
+    pub fn reset_shown(&mut self, now: time::Instant) {
+        self.opacity = 1.0;
+        self.last_update = None;
+        self.hover_start = match self.hovered {
+            true => Some(now.checked_sub(self.delay).unwrap_or(now)),
+            false => None,
+        };
+    }
+
+    fn is_shown(&self, now: time::Instant) -> bool {
+        self.hover_start
+            .is_some_and(|start| now.duration_since(start) >= self.delay)
+    }
+
+    // Whether the border needs ticks, i.e. it's fading or waiting out the delay before fading in
+    pub fn is_changing(&self, now: time::Instant) -> bool {
+        let shown = self.is_shown(now);
+        let target = match shown {
+            true => 1.0,
+            false => 0.0,
+        };
+        let waiting = self.hover_start.is_some() && !shown;
+        self.opacity != target || waiting
+    }
+
+    // Move the opacity towards where it should be. Returns true while the border still needs ticks.
+    pub fn update(&mut self, now: time::Instant) -> bool {
+        let elapsed = self
+            .last_update
+            .map_or(time::Duration::ZERO, |last| now.duration_since(last));
+        let step = elapsed.as_secs_f32() / self.fade.as_secs_f32();
+
+        self.opacity = match self.is_shown(now) {
+            true => (self.opacity + step).min(1.0),
+            false => (self.opacity - step).max(0.0),
+        };
+
+        let is_changing = self.is_changing(now);
+        self.last_update = is_changing.then_some(now);
+        is_changing
+    }
+}
+
+// Every border with hover_peek hears about the cursor from this one poll (as WM_APP_HOVER, with a
+// wparam of 1 for the window it moved onto and 0 for the one it left), instead of each of them
+// checking where the cursor is on a timer of its own. It stops once the last of those borders
+// calls stop_hover_poll.
+pub(crate) fn start_hover_poll(border_window: HWND) {
+    let mut hover_borders = HOVER_BORDERS.lock().unwrap();
+    if hover_borders.is_none() {
+        let _ = thread::spawn(run_hover_poll);
+    }
+    hover_borders
+        .get_or_insert_with(HashSet::new)
+        .insert(border_window.0 as isize);
+}
+
+pub(crate) fn stop_hover_poll(border_window: HWND) {
+    if let Some(hover_borders) = HOVER_BORDERS.lock().unwrap().as_mut() {
+        hover_borders.remove(&(border_window.0 as isize));
+    }
+}
+
+fn run_hover_poll() {
+    let mut last_hovered: Option<isize> = None;
+    loop {
+        // Checked under the lock so a border that calls start_hover_poll right now either keeps
+        // this thread going or starts a new one
+        {
+            let mut hover_borders = HOVER_BORDERS.lock().unwrap();
+            if hover_borders
+                .as_ref()
+                .is_none_or(|borders| borders.is_empty())
+            {
+                *hover_borders = None;
+                return;
+            }
+        }
+
+        let hovered = get_window_under_cursor().map(|hwnd| hwnd.0 as isize);
+        if hovered != last_hovered {
+            for (window, is_hovered) in [(last_hovered, false), (hovered, true)] {
+                let border = window.and_then(|window| get_border_from_window(HWND(window as _)));
+                if let Some(border) = border {
+                    let _ = post_message_w(
+                        border,
+                        WM_APP_HOVER,
+                        WPARAM(is_hovered as usize),
+                        LPARAM(0),
+                    );
+                }
+            }
+            last_hovered = hovered;
+        }
+        thread::sleep(HOVER_POLL_INTERVAL);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ParticleKind {
+    // Twinkling specks that drift away from the border
+    #[default]
+    #[serde(alias = "sparkle")]
+    Sparkle,
+    // Flakes that fall off the border, swaying from side to side
+    #[serde(alias = "snow")]
+    Snow,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParticlesConfig {
+    #[serde(default)]
+    pub kind: ParticleKind,
+    // How many particles spawn every second
+    #[serde(default = "default_particle_rate")]
+    pub rate: f32,
+    // The most particles there can be at once
+    #[serde(default = "default_particle_budget")]
+    pub budget: u32,
+    // How big (in pixels) the particles are across
+    #[serde(default = "default_particle_size")]
+    pub size: f32,
+    // How far (in pixels) the particles drift before they've faded away
+    #[serde(default = "default_particle_distance")]
+    pub distance: f32,
+    // How long (in milliseconds) each particle lasts
+    #[serde(default = "default_particle_lifetime")]
+    pub lifetime: u64,
+    // Defaults to the border's active color
+    pub color: Option<ColorConfig>,
+}
+
+fn default_particle_rate() -> f32 {
+    30.0
+}
+
+fn default_particle_budget() -> u32 {
+    60
+}
+
+fn default_particle_size() -> f32 {
+    3.0
+}
+
+fn default_particle_distance() -> f32 {
+    14.0
+}
+
+fn default_particle_lifetime() -> u64 {
+    1000
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Particle {
+    position: D2D_POINT_2F,
+    // In pixels per second
+    velocity: D2D_POINT_2F,
+    // In seconds
+    age: f32,
+    lifetime: f32,
+    // Keeps particles from twinkling or swaying in lockstep
+    phase: f32,
+}
+
+// Particles that spawn along the focused window's border and drift away from it. They're simulated
+// here on the CPU, and drawn as one ellipse each with the same brush.
+#[derive(Debug, Clone)]
+pub struct Particles {
+    pub kind: ParticleKind,
+    pub rate: f32,
+    pub budget: usize,
+    pub size: f32,
+    pub distance: i32,
+    pub lifetime: f32,
+    pub color: Option<Color>,
+    // When the particles were last moved, while the particle timer is running
+    pub last_update: Option<time::Instant>,
+    particles: Vec<Particle>,
+    // Particles that were due to spawn but didn't add up to a whole one yet
+    pending: f32,
+    random_state: u32,
+}
+
+impl Particles {
+    pub fn new(config: &ParticlesConfig, dpi: f32) -> Self {
+        let budget = config.budget.min(MAX_PARTICLE_BUDGET) as usize;
+
+        Self {
+            kind: config.kind,
+            rate: match config.rate.is_finite() {
+                true => config.rate.clamp(0.0, MAX_PARTICLE_RATE),
+                false => 0.0,
+            },
+            budget,
+            size: config.size.max(0.0) * dpi / 96.0,
+            distance: (config.distance.max(0.0) * dpi / 96.0) as i32,
+            lifetime: config.lifetime.max(1) as f32 / 1000.0,
+            color: config.color.as_ref().map(|color| {
+                let mut color = color.convert_to_color(true);
+                color.set_fade_opacity(1.0);
+                color
+            }),
+            last_update: None,
+            particles: Vec::with_capacity(budget),
+            pending: 0.0,
+            random_state: 0x2545F491,
+        }
+    }
+
+    // Move the particles along, getting rid of the ones that have faded away, and spawn new ones
+    // along the border if 'spawning'. Returns false once there's nothing left to animate.
+    pub fn step(
+        &mut self,
+        rounded_rect: &D2D1_ROUNDED_RECT,
+        spawning: bool,
+        now: time::Instant,
+    ) -> bool {
+        // Don't let particles jump ahead after the timer was held up (e.g. by a window drag)
+        let elapsed = self
+            .last_update
+            .map_or(0.0, |last| {
+                now.saturating_duration_since(last).as_secs_f32()
+            })
+            .min(0.1);
+        self.last_update = Some(now);
+
+        let fall_speed = self.distance as f32 / self.lifetime;
+        for particle in self.particles.iter_mut() {
+            particle.age += elapsed;
+            particle.position.x += particle.velocity.x * elapsed;
+            particle.position.y += particle.velocity.y * elapsed;
+
+            if self.kind == ParticleKind::Snow {
+                let sway = (particle.phase + particle.age * SNOW_SWAY_SPEED).cos();
+                particle.velocity.x = sway * fall_speed * SNOW_SWAY_AMOUNT;
+            }
+        }
+        self.particles
+            .retain(|particle| particle.age < particle.lifetime);
+
+        match spawning {
+            true => {
+                self.pending += self.rate * elapsed;
+                let due = self.pending.floor();
+                self.pending -= due;
+
+                // Whatever doesn't fit in the budget is dropped
+                let count = (due as usize).min(self.budget.saturating_sub(self.particles.len()));
+                for _ in 0..count {
+                    self.spawn(rounded_rect);
+                }
+            }
+            false => self.pending = 0.0,
+        }
+
+        spawning || !self.particles.is_empty()
+    }
+
+    fn spawn(&mut self, rounded_rect: &D2D1_ROUNDED_RECT) {
+        let (position, normal) = progress::point_on_perimeter(rounded_rect, self.random());
+        let lifetime = self.lifetime * (0.6 + 0.4 * self.random());
+        let speed = self.distance as f32 / lifetime;
+
+        let velocity = match self.kind {
+            // Mostly straight out, give or take a bit to either side
+            ParticleKind::Sparkle => {
+                let spread = (self.random() - 0.5) * speed;
+                let speed = speed * (0.5 + 0.5 * self.random());
+                D2D_POINT_2F {
+                    x: normal.x * speed - normal.y * spread,
+                    y: normal.y * speed + normal.x * spread,
+                }
+            }
+            // Mostly straight down, with a little push away from the border
+            ParticleKind::Snow => D2D_POINT_2F {
+                x: normal.x * speed * 0.3,
+                y: speed + normal.y * speed * 0.3,
+            },
+        };
+
+        let phase = self.random() * std::f32::consts::TAU;
+        self.particles.push(Particle {
+            position,
+            velocity,
+            age: 0.0,
+            lifetime,
+            phase,
+        });
+    }
+
+    // From 0.0 to 1.0
+    fn random(&mut self) -> f32 {
+        xorshift(&mut self.random_state) as f32 / u32::MAX as f32
+    }
+
+    pub fn draw(&self, render_target: &ID2D1RenderTarget, brush: &ID2D1Brush) {
+        let radius = self.size / 2.0;
+        let opacity = unsafe { brush.GetOpacity() };
+
+        for particle in self.particles.iter() {
+            let fade = 1.0 - particle.age / particle.lifetime;
+            let particle_opacity = match self.kind {
+                ParticleKind::Sparkle => {
+                    let twinkle = (particle.phase + particle.age * SPARKLE_TWINKLE_SPEED).sin();
+                    fade * (0.6 + 0.4 * twinkle)
+                }
+                ParticleKind::Snow => fade,
+            };
+
+            let ellipse = D2D1_ELLIPSE {
+                point: particle.position,
+                radiusX: radius,
+                radiusY: radius,
+            };
+            unsafe {
+                brush.SetOpacity(opacity * particle_opacity);
+                render_target.FillEllipse(&ellipse, brush);
+            }
+        }
+
+        unsafe { brush.SetOpacity(opacity) };
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    // How much room the particles need outside of the border
+    pub fn extent(&self) -> i32 {
+        self.distance + self.size.ceil() as i32
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AdaptiveQualityConfig {
+    // Cut back on quality once frames take longer than this (in milliseconds) on average
+    #[serde(default = "default_slow_frame")]
+    pub slow_frame: f32,
+    // Go back up once frames take less than this (in milliseconds) on average
+    #[serde(default = "default_fast_frame")]
+    pub fast_frame: f32,
+    // Animations never get slowed down below this fps
+    #[serde(default = "default_min_fps")]
+    pub min_fps: i32,
+}
+
+fn default_slow_frame() -> f32 {
+    8.0
+}
+
+fn default_fast_frame() -> f32 {
+    3.0
+}
+
+fn default_min_fps() -> i32 {
+    20
+}
+
+// The lowest quality level. Level 1 halves the animation fps and turns off supersampling, and level
+// 2 also stops drawing noise.
+const MIN_QUALITY_LEVEL: u32 = 2;
+// Wait a bit between changes so one slow frame (or one fast one) doesn't flip the quality around
+const QUALITY_DOWN_COOLDOWN: time::Duration = time::Duration::from_secs(2);
+const QUALITY_UP_COOLDOWN: time::Duration = time::Duration::from_secs(10);
+
+// Lowers a border's animation quality while its frames take too long to draw (e.g. with lots of
+// borders animating at once), and raises it again once things calm down
+#[derive(Debug, Clone)]
+pub struct AdaptiveQuality {
+    pub slow_frame: f32,
+    pub fast_frame: f32,
+    pub min_fps: i32,
+    // Moving average of how long frames take to draw, in milliseconds
+    pub frame_time: f32,
+    // 0 is full quality, and higher levels are lower quality
+    pub level: u32,
+    pub last_change: Option<time::Instant>,
+}
+
+impl AdaptiveQuality {
+    pub fn new(config: &AdaptiveQualityConfig) -> Self {
+        Self {
+            slow_frame: config.slow_frame,
+            fast_frame: config.fast_frame.min(config.slow_frame),
+            min_fps: config.min_fps.max(1),
+            frame_time: 0.0,
+            level: 0,
+            last_change: None,
+        }
+    }
+
+    // Add a frame to the average. Returns true if the quality level changed.
+    pub fn record_frame(&mut self, frame_time: time::Duration, now: time::Instant) -> bool {
+        self.frame_time = self.frame_time * 0.9 + frame_time.as_secs_f32() * 1000.0 * 0.1;
+
+        let since_change = self
+            .last_change
+            .map_or(time::Duration::MAX, |last| now.duration_since(last));
+        let level = if self.frame_time > self.slow_frame && since_change >= QUALITY_DOWN_COOLDOWN {
+            (self.level + 1).min(MIN_QUALITY_LEVEL)
+        } else if self.frame_time < self.fast_frame && since_change >= QUALITY_UP_COOLDOWN {
+            self.level.saturating_sub(1)
+        } else {
+            self.level
+        };
+
+        if level == self.level {
+            return false;
+        }
+
+        self.level = level;
+        self.last_change = Some(now);
+
+        true
+    }
+
+    pub fn fps(&self, fps: i32) -> i32 {
+        match self.level {
+            0 => fps,
+            _ => (fps / 2).max(self.min_fps).min(fps),
+        }
+    }
+
+    pub fn allows_supersampling(&self) -> bool {
+        self.level < 1
+    }
+
+    pub fn allows_noise(&self) -> bool {
+        self.level < 2
+    }
+}
+
+// Whether the mouse cursor is over the window (and not some other window on top of it)
+pub fn is_cursor_over(hwnd: HWND) -> bool {
+    get_window_under_cursor() == Some(hwnd)
+}
+
+// The top-level window the cursor is on
+fn get_window_under_cursor() -> Option<HWND> {
+    let mut point = POINT::default();
+    unsafe { GetCursorPos(&mut point) }.ok()?;
+
+    // Borders are disabled windows, so WindowFromPoint looks right through them
+    let window = unsafe { WindowFromPoint(point) };
+    match window.is_invalid() {
+        true => None,
+        false => Some(unsafe { GetAncestor(window, GA_ROOT) }),
+    }
+}
+
+// Points along the edges (as fractions of the width and height) that we sample the background at
+const CONTRAST_SAMPLE_POINTS: [(f32, f32); 8] = [
+    (0.0, 0.0),
+    (0.5, 0.0),
+    (1.0, 0.0),
+    (1.0, 0.5),
+    (1.0, 1.0),
+    (0.5, 1.0),
+    (0.0, 1.0),
+    (0.0, 0.5),
+];
+
+// Sample a handful of screen pixels just outside of the given rect and return their average
+// relative luminance. Full desktop duplication would need a D3D11 device per border, but reading
+// a few pixels through GDI is cheap enough at the low rate we do it at. GDI also leaves out layered
+// windows, so we never end up sampling our own border.
+pub fn sample_background_luminance(rect: &RECT) -> Option<f32> {
+    // How many pixels outside of the rect to sample at
+    const MARGIN: i32 = 2;
+
+    let width = (rect.right - rect.left) as f32;
+    let height = (rect.bottom - rect.top) as f32;
+
+    unsafe {
+        let hdc = GetDC(HWND::default());
+        if hdc.is_invalid() {
+            return None;
+        }
+
+        let luminances: Vec<f32> = CONTRAST_SAMPLE_POINTS
+            .iter()
+            .filter_map(|(fx, fy)| {
+                let x = rect.left + (fx * width) as i32 + ((fx * 2.0 - 1.0) as i32) * MARGIN;
+                let y = rect.top + (fy * height) as i32 + ((fy * 2.0 - 1.0) as i32) * MARGIN;
+
+                // Off-screen points come back as CLR_INVALID
+                let pixel = GetPixel(hdc, x, y);
+                if pixel.0 == CLR_INVALID {
+                    return None;
+                }
+
+                // COLORREFs are 0x00BBGGRR
+                let color = D2D1_COLOR_F {
+                    r: (pixel.0 & 0xFF) as f32 / 255.0,
+                    g: ((pixel.0 >> 8) & 0xFF) as f32 / 255.0,
+                    b: ((pixel.0 >> 16) & 0xFF) as f32 / 255.0,
+                    a: 1.0,
+                };
+                Some(relative_luminance(&color))
+            })
+            .collect();
+
+        ReleaseDC(HWND::default(), hdc);
+
+        match luminances.is_empty() {
+            true => None,
+            false => Some(luminances.iter().sum::<f32>() / luminances.len() as f32),
+        }
+    }
+}
+
+// Film grain drawn over the border stroke using a precomputed noise bitmap. The grain is animated
+// by shifting the tiled bitmap to a random offset 'speed' times per second. The bitmap is a
+// device-dependent resource, so it belongs to the renderer rather than to this struct.
+#[derive(Debug, Clone)]
+pub struct Noise {
+    pub intensity: f32,
+    pub speed: f32,
+    start_time: time::Instant,
+}
+
+impl Noise {
+    pub fn new(config: &NoiseConfig) -> Self {
+        Self {
+            intensity: config.intensity.clamp(0.0, 1.0),
+            speed: config.speed.max(0.0),
+            start_time: time::Instant::now(),
+        }
+    }
+
+    pub fn is_animated(&self) -> bool {
+        self.speed > 0.0 && self.intensity > 0.0
+    }
+
+    pub fn create_bitmap(
+        &self,
+        render_target: &ID2D1RenderTarget,
+    ) -> windows::core::Result<ID2D1Bitmap> {
+        let seed = self.start_time.elapsed().subsec_nanos() | 1;
+        let pixels = generate_noise_pixels(seed);
+
+        let bitmap_properties = D2D1_BITMAP_PROPERTIES {
+            pixelFormat: D2D1_PIXEL_FORMAT {
+                format: DXGI_FORMAT_B8G8R8A8_UNORM,
+                alphaMode: D2D1_ALPHA_MODE_PREMULTIPLIED,
+            },
+            dpiX: 96.0,
+            dpiY: 96.0,
+        };
+
+        unsafe {
+            render_target.CreateBitmap(
+                D2D_SIZE_U {
+                    width: NOISE_TILE_SIZE,
+                    height: NOISE_TILE_SIZE,
+                },
+                Some(pixels.as_ptr() as _),
+                NOISE_TILE_SIZE * 4,
+                &bitmap_properties,
+            )
+        }
+    }
+
+    pub fn create_brush(
+        &self,
+        render_target: &ID2D1RenderTarget,
+        bitmap: &ID2D1Bitmap,
+        opacity: f32,
+    ) -> windows::core::Result<ID2D1Brush> {
+        let bitmap_brush_properties = D2D1_BITMAP_BRUSH_PROPERTIES {
+            extendModeX: D2D1_EXTEND_MODE_WRAP,
+            extendModeY: D2D1_EXTEND_MODE_WRAP,
+            interpolationMode: D2D1_BITMAP_INTERPOLATION_MODE_NEAREST_NEIGHBOR,
+        };
+
+        // Pick a pseudo-random offset for the current grain frame
+        let frame = (self.start_time.elapsed().as_secs_f32() * self.speed) as u32;
+        let mut state = frame.wrapping_mul(0x9E3779B9) | 1;
+        let offset_x = (xorshift(&mut state) % NOISE_TILE_SIZE) as f32;
+        let offset_y = (xorshift(&mut state) % NOISE_TILE_SIZE) as f32;
+
+        unsafe {
+            let brush =
+                render_target.CreateBitmapBrush(bitmap, Some(&bitmap_brush_properties), None)?;
+
+            brush.SetTransform(&Matrix3x2::translation(offset_x, offset_y));
+            brush.SetOpacity(self.intensity * opacity);
+
+            Ok(brush.into())
+        }
+    }
+}
+
+// Generate premultiplied BGRA pixels made up of light and dark specks with random opacity
+fn generate_noise_pixels(seed: u32) -> Vec<u8> {
+    let mut state = seed;
+    let mut pixels = Vec::with_capacity((NOISE_TILE_SIZE * NOISE_TILE_SIZE * 4) as usize);
+
+    for _ in 0..NOISE_TILE_SIZE * NOISE_TILE_SIZE {
+        let random = xorshift(&mut state);
+        let alpha = (random & 0xFF) as u8;
+        let channel = match random & 0x100 != 0 {
+            true => alpha,
+            false => 0,
+        };
+
+        pixels.extend_from_slice(&[channel, channel, channel, alpha]);
+    }
+
+    pixels
+}
+
+fn xorshift(state: &mut u32) -> u32 {
+    let mut x = *state;
+    x ^= x << 13;
+    x ^= x >> 17;
+    x ^= x << 5;
+    *state = x;
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hover_peek_waits_then_fades() {
+        let config = HoverPeekConfig {
+            delay: 300,
+            fade: 100,
+        };
+        let mut hover_peek = HoverPeek::new(&config);
+        let start = time::Instant::now();
+        let at = |ms: u64| start + time::Duration::from_millis(ms);
+
+        assert!(!hover_peek.is_changing(at(0)));
+
+        hover_peek.set_hovered(true, at(0));
+        // (time, still changing, opacity)
+        let cases = [
+            (0, true, 0.0),
+            (290, true, 0.0),
+            (300, true, 0.1),
+            (350, true, 0.6),
+            (400, false, 1.0),
+        ];
+        for (ms, is_changing, opacity) in cases {
+            assert_eq!(hover_peek.update(at(ms)), is_changing, "at {ms}ms");
+            assert!((hover_peek.opacity - opacity).abs() < 0.001, "at {ms}ms");
+        }
+
+        hover_peek.set_hovered(false, at(1000));
+        assert!(hover_peek.update(at(1000)));
+        assert!(!hover_peek.update(at(1100)));
+        assert_eq!(hover_peek.opacity, 0.0);
+    }
+
+    #[test]
+    fn hover_peek_stays_shown_under_the_cursor_after_losing_focus() {
+        let config = HoverPeekConfig {
+            delay: 300,
+            fade: 100,
+        };
+        let now = time::Instant::now();
+
+        let mut hover_peek = HoverPeek::new(&config);
+        hover_peek.set_hovered(true, now);
+        hover_peek.reset_shown(now);
+        assert!(!hover_peek.is_changing(now));
+
+        hover_peek.set_hovered(false, now);
+        hover_peek.reset_shown(now);
+        assert!(hover_peek.is_changing(now));
+    }
+}
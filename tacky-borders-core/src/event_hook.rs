@@ -0,0 +1,960 @@
+use anyhow::{anyhow, Context};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{LazyLock, Mutex};
+use std::thread;
+use std::time;
+use windows::Win32::Foundation::{GetLastError, HWND, LPARAM, WPARAM};
+use windows::Win32::System::SystemInformation::GetTickCount;
+use windows::Win32::UI::Accessibility::{
+    SetWinEventHook, UnhookWinEvent, HWINEVENTHOOK, WINEVENTPROC,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetAncestor, EVENT_OBJECT_CLOAKED, EVENT_OBJECT_DESTROY, EVENT_OBJECT_FOCUS, EVENT_OBJECT_HIDE,
+    EVENT_OBJECT_LOCATIONCHANGE, EVENT_OBJECT_NAMECHANGE, EVENT_OBJECT_REORDER, EVENT_OBJECT_SHOW,
+    EVENT_OBJECT_UNCLOAKED, EVENT_SYSTEM_MENUPOPUPEND, EVENT_SYSTEM_MINIMIZEEND,
+    EVENT_SYSTEM_MINIMIZESTART, GA_ROOT, OBJID_CLIENT, OBJID_CURSOR, OBJID_WINDOW,
+    WINEVENT_OUTOFCONTEXT, WINEVENT_SKIPOWNPROCESS,
+};
+
+use crate::border_config::CONFIG;
+use crate::crash;
+use crate::registry::{BorderKey, BORDERS};
+use crate::retry;
+use crate::rule_engine;
+use crate::state;
+use crate::tracking::{WindowEvent, WindowTracker};
+use crate::{log_if_err, utils::*};
+
+// An event on its way to the event worker. 'time' is when it happened, in the same milliseconds
+// as GetTickCount().
+struct QueuedEvent {
+    event: WindowEvent,
+    time: u32,
+}
+
+// Events that took longer than this from happening to being handled get logged, at most once every
+// SLOW_EVENT_LOG_INTERVAL
+const SLOW_EVENT_MS: u32 = 100;
+const SLOW_EVENT_LOG_INTERVAL: time::Duration = time::Duration::from_secs(10);
+
+// How long events take from happening to being handled, for the log and crash reports
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct EventLatency {
+    pub handled: u64,
+    pub total_ms: u64,
+    pub worst_ms: u32,
+    last_logged: Option<time::Instant>,
+}
+
+static LATENCY: Mutex<EventLatency> = Mutex::new(EventLatency {
+    handled: 0,
+    total_ms: 0,
+    worst_ms: 0,
+    last_logged: None,
+});
+
+// Windows waits for the hook callback to return before it delivers the next event (to us and, for
+// some events, to everyone else too), so the callback only queues events up. They're handled in
+// order on this worker, which is where anything slow (like creating a border) happens.
+static EVENT_QUEUE: LazyLock<Sender<QueuedEvent>> = LazyLock::new(|| {
+    let (sender, receiver) = mpsc::channel::<QueuedEvent>();
+
+    let _ = thread::Builder::new()
+        .name("event worker".to_string())
+        .spawn(move || {
+            let mut desktop = Win32Desktop::default();
+            for queued in receiver {
+                handle_event(&mut desktop, queued.event, queued.time);
+                record_latency(unsafe { GetTickCount() }.wrapping_sub(queued.time));
+            }
+        });
+
+    sender
+});
+
+// Task View is MultitaskingViewFrame on Windows 10. Windows 11 uses a XAML island for it (and for
+// Alt+Tab, which shows window previews too).
+const TASK_VIEW_CLASSES: [&str; 2] = ["MultitaskingViewFrame", "XamlExplorerHostIslandWindow"];
+
+static TASK_VIEW_OPEN: AtomicBool = AtomicBool::new(false);
+
+// Some windows change their title many times a second (like terminals showing the running command,
+// or download progress in a browser), and each change can run the rules and the script. A window's
+// title changes are handled at most this often, with the last one being handled late instead of
+// dropped.
+const NAME_CHANGE_INTERVAL_MS: u32 = 250;
+
+#[derive(Debug, PartialEq)]
+enum NameChange {
+    Handle,
+    // Handle it again after this many milliseconds
+    Defer(u32),
+    // It's already going to be handled later
+    Skip,
+}
+
+#[derive(Default)]
+struct NameChangeThrottle {
+    // When each window's title change was last handled. Only the recent ones are kept.
+    last_handled: HashMap<isize, u32>,
+    deferred: HashSet<isize>,
+}
+
+impl NameChangeThrottle {
+    fn check(&mut self, hwnd: HWND, time: u32) -> NameChange {
+        let key = hwnd.0 as isize;
+        self.last_handled
+            .retain(|_, last_handled| time.wrapping_sub(*last_handled) < NAME_CHANGE_INTERVAL_MS);
+
+        let Some(&last_handled) = self.last_handled.get(&key) else {
+            self.last_handled.insert(key, time);
+            self.deferred.remove(&key);
+            return NameChange::Handle;
+        };
+
+        match self.deferred.insert(key) {
+            true => NameChange::Defer(NAME_CHANGE_INTERVAL_MS - time.wrapping_sub(last_handled)),
+            false => NameChange::Skip,
+        }
+    }
+}
+
+// Every WinEvent that to_window_event() understands. The hooks are installed for just these events,
+// so Windows doesn't have to call us for all of the ones we'd ignore (which is most of them).
+const WIN_EVENTS: &[u32] = &[
+    EVENT_SYSTEM_MENUPOPUPEND,
+    EVENT_SYSTEM_MINIMIZESTART,
+    EVENT_SYSTEM_MINIMIZEEND,
+    EVENT_OBJECT_DESTROY,
+    EVENT_OBJECT_SHOW,
+    EVENT_OBJECT_HIDE,
+    EVENT_OBJECT_REORDER,
+    EVENT_OBJECT_FOCUS,
+    EVENT_OBJECT_LOCATIONCHANGE,
+    EVENT_OBJECT_NAMECHANGE,
+    EVENT_OBJECT_CLOAKED,
+    EVENT_OBJECT_UNCLOAKED,
+];
+
+// Install one hook for each run of consecutive events in WIN_EVENTS. Returns the hooks that could
+// be installed.
+pub(crate) fn set_event_hooks(callback: WINEVENTPROC, flags: u32) -> Vec<HWINEVENTHOOK> {
+    let mut events: Vec<u32> = WIN_EVENTS.to_vec();
+    events.sort_unstable();
+    events.dedup();
+
+    let mut ranges: Vec<(u32, u32)> = Vec::new();
+    for event in events {
+        match ranges.last_mut() {
+            Some((_, max)) if *max + 1 == event => *max = event,
+            _ => ranges.push((event, event)),
+        }
+    }
+
+    ranges
+        .into_iter()
+        .filter_map(|(min, max)| {
+            let hook = unsafe { SetWinEventHook(min, max, None, callback, 0, 0, flags) };
+            match hook.is_invalid() {
+                true => {
+                    error!("could not install event hook for events {min:#x}..={max:#x}");
+                    None
+                }
+                false => Some(hook),
+            }
+        })
+        .collect()
+}
+
+// The usual tracker, which gets events from WinEvent hooks installed on start()'s thread
+#[derive(Default)]
+pub(crate) struct WinEventTracker {
+    hooks: Vec<HWINEVENTHOOK>,
+}
+
+impl WindowTracker for WinEventTracker {
+    fn name(&self) -> &'static str {
+        "WinEvent hook"
+    }
+
+    fn start(&mut self) -> anyhow::Result<()> {
+        self.hooks = set_event_hooks(
+            Some(handle_win_event),
+            WINEVENT_OUTOFCONTEXT | WINEVENT_SKIPOWNPROCESS,
+        );
+        match self.hooks.is_empty() {
+            true => Err(anyhow!("could not install event hook: {:?}", unsafe {
+                GetLastError()
+            })),
+            false => Ok(()),
+        }
+    }
+
+    fn stop(&mut self) -> bool {
+        self.hooks
+            .retain(|hook| !unsafe { UnhookWinEvent(*hook) }.as_bool());
+        self.hooks.is_empty()
+    }
+}
+
+pub extern "system" fn handle_win_event(
+    _h_win_event_hook: HWINEVENTHOOK,
+    _event: u32,
+    _hwnd: HWND,
+    _id_object: i32,
+    _id_child: i32,
+    _dw_event_thread: u32,
+    _dwms_event_time: u32,
+) {
+    // Paused borders get recreated from scratch once they're resumed
+    if _id_object == OBJID_CURSOR.0 || crate::is_suspended() {
+        return;
+    }
+
+    if let Some(event) = to_window_event(_event, _hwnd, _id_object) {
+        queue_event(event, _dwms_event_time);
+    }
+}
+
+// What the WinEvent means for borders, if anything. Most of them are only about the window itself
+// and not about the things inside of it (like scrollbars or the caret).
+fn to_window_event(event: u32, hwnd: HWND, id_object: i32) -> Option<WindowEvent> {
+    let is_window = id_object == OBJID_WINDOW.0;
+
+    Some(match event {
+        EVENT_SYSTEM_MENUPOPUPEND => WindowEvent::MenuClosed,
+        EVENT_SYSTEM_MINIMIZESTART => WindowEvent::Minimized(hwnd),
+        EVENT_SYSTEM_MINIMIZEEND => WindowEvent::Restored(hwnd),
+        EVENT_OBJECT_DESTROY if is_window || id_object == OBJID_CLIENT.0 => {
+            WindowEvent::Destroyed(hwnd)
+        }
+        EVENT_OBJECT_SHOW if is_window => WindowEvent::Shown(hwnd),
+        EVENT_OBJECT_HIDE if is_window => WindowEvent::Hidden(hwnd),
+        EVENT_OBJECT_CLOAKED if is_window => WindowEvent::Cloaked(hwnd),
+        EVENT_OBJECT_UNCLOAKED if is_window => WindowEvent::Uncloaked(hwnd),
+        EVENT_OBJECT_REORDER => WindowEvent::Reordered(hwnd),
+        EVENT_OBJECT_FOCUS => WindowEvent::FocusChanged(hwnd),
+        EVENT_OBJECT_LOCATIONCHANGE => WindowEvent::Moved(hwnd),
+        EVENT_OBJECT_NAMECHANGE if is_window => WindowEvent::TitleChanged(hwnd),
+        _ => return None,
+    })
+}
+
+// Hand the event to the event worker. Every tracker's events come through here.
+pub(crate) fn queue_event(event: WindowEvent, time: u32) {
+    crash::record_event(event);
+
+    if EVENT_QUEUE.send(QueuedEvent { event, time }).is_err() {
+        error!("could not queue {event:?}: the event worker has stopped");
+    }
+}
+
+// Everything the event worker needs to know about windows, and everything it does to their
+// borders. Win32Desktop is the real one, and the tests feed handle_event() made-up events with a
+// fake one instead.
+trait Desktop {
+    fn has_filtered_style(&self, hwnd: HWND) -> bool;
+    fn is_tool_window(&self, hwnd: HWND) -> bool;
+    // The top-level window that 'hwnd' is a part of
+    fn root_window(&self, hwnd: HWND) -> HWND;
+    fn is_task_view(&self, hwnd: HWND) -> bool;
+    fn is_visible(&self, hwnd: HWND) -> bool;
+    fn has_script(&self) -> bool;
+    // Whether any window rule looks at titles
+    fn rules_use_title(&self) -> bool;
+    fn name_changes(&mut self) -> &mut NameChangeThrottle;
+    fn border_for(&self, hwnd: HWND) -> Option<HWND>;
+    // Every (key, border window) pair
+    fn borders(&self) -> Vec<(BorderKey, HWND)>;
+    // Returns whether Task View was open before
+    fn swap_task_view_open(&mut self, open: bool) -> bool;
+
+    fn show_border(&mut self, hwnd: HWND);
+    fn hide_border(&mut self, hwnd: HWND);
+    fn destroy_border(&mut self, hwnd: HWND);
+    fn post(&mut self, border_window: HWND, message: u32, wparam: usize, context: &'static str);
+    // Sent messages are handled before posted ones, so this one jumps the border's queue
+    fn send_notify(&mut self, border_window: HWND, message: u32, context: &'static str);
+    // Forget everything about a window that's been destroyed
+    fn forget_window(&mut self, hwnd: HWND);
+    // Queue the event up again after 'delay_ms'
+    fn defer(&mut self, event: WindowEvent, delay_ms: u32);
+}
+
+#[derive(Default)]
+struct Win32Desktop {
+    name_changes: NameChangeThrottle,
+}
+
+impl Desktop for Win32Desktop {
+    fn has_filtered_style(&self, hwnd: HWND) -> bool {
+        has_filtered_style(hwnd)
+    }
+
+    fn is_tool_window(&self, hwnd: HWND) -> bool {
+        is_tool_window(hwnd)
+    }
+
+    fn root_window(&self, hwnd: HWND) -> HWND {
+        unsafe { GetAncestor(hwnd, GA_ROOT) }
+    }
+
+    fn is_task_view(&self, hwnd: HWND) -> bool {
+        TASK_VIEW_CLASSES.contains(&get_window_class(hwnd).as_str())
+    }
+
+    fn is_visible(&self, hwnd: HWND) -> bool {
+        is_window_visible(hwnd)
+    }
+
+    fn has_script(&self) -> bool {
+        CONFIG.lock().unwrap().global.script.is_some()
+    }
+
+    fn rules_use_title(&self) -> bool {
+        CONFIG
+            .lock()
+            .unwrap()
+            .window_rules
+            .iter()
+            .any(rule_engine::uses_title)
+    }
+
+    fn name_changes(&mut self) -> &mut NameChangeThrottle {
+        &mut self.name_changes
+    }
+
+    fn border_for(&self, hwnd: HWND) -> Option<HWND> {
+        get_border_from_window(hwnd)
+    }
+
+    fn borders(&self) -> Vec<(BorderKey, HWND)> {
+        BORDERS.entries()
+    }
+
+    fn swap_task_view_open(&mut self, open: bool) -> bool {
+        TASK_VIEW_OPEN.swap(open, Ordering::SeqCst)
+    }
+
+    fn show_border(&mut self, hwnd: HWND) {
+        show_border_for_window(hwnd);
+    }
+
+    fn hide_border(&mut self, hwnd: HWND) {
+        hide_border_for_window(hwnd);
+    }
+
+    fn destroy_border(&mut self, hwnd: HWND) {
+        destroy_border_for_window(hwnd);
+    }
+
+    fn post(&mut self, border_window: HWND, message: u32, wparam: usize, context: &'static str) {
+        log_if_err!(
+            post_message_w(border_window, message, WPARAM(wparam), LPARAM(0)).context(context)
+        );
+    }
+
+    fn send_notify(&mut self, border_window: HWND, message: u32, context: &'static str) {
+        log_if_err!(
+            send_notify_message_w(border_window, message, WPARAM(0), LPARAM(0)).context(context)
+        );
+    }
+
+    fn forget_window(&mut self, hwnd: HWND) {
+        state::set_toggled_off(hwnd, false);
+        retry::forget(hwnd);
+    }
+
+    fn defer(&mut self, event: WindowEvent, delay_ms: u32) {
+        let _ = thread::spawn(move || {
+            thread::sleep(time::Duration::from_millis(delay_ms as u64));
+            queue_event(event, unsafe { GetTickCount() });
+        });
+    }
+}
+
+fn handle_event(desktop: &mut impl Desktop, event: WindowEvent, time: u32) {
+    match event {
+        WindowEvent::Created(hwnd) | WindowEvent::Shown(hwnd) | WindowEvent::Uncloaked(hwnd) => {
+            on_show(desktop, hwnd)
+        }
+        WindowEvent::Hidden(hwnd) | WindowEvent::Cloaked(hwnd) => on_hide(desktop, hwnd),
+        WindowEvent::Destroyed(hwnd) => on_destroy(desktop, hwnd),
+        WindowEvent::Moved(hwnd) => on_location_change(desktop, hwnd),
+        WindowEvent::FocusChanged(hwnd) => on_focus(desktop, hwnd, time),
+        WindowEvent::TitleChanged(hwnd) => on_name_change(desktop, hwnd, time),
+        WindowEvent::Minimized(hwnd) => on_minimize_start(desktop, hwnd),
+        WindowEvent::Restored(hwnd) => on_minimize_end(desktop, hwnd),
+        WindowEvent::Reordered(hwnd) => on_reorder(desktop, hwnd),
+        // Menus are topmost and can leave borders in the wrong spot in the z-order after they
+        // close, so we treat that like a reorder too
+        WindowEvent::MenuClosed => post_reorder(desktop),
+    }
+}
+
+fn record_latency(latency_ms: u32) {
+    let Ok(mut latency) = LATENCY.lock() else {
+        return;
+    };
+
+    latency.handled += 1;
+    latency.total_ms += latency_ms as u64;
+    latency.worst_ms = latency.worst_ms.max(latency_ms);
+
+    if latency_ms >= SLOW_EVENT_MS
+        && latency
+            .last_logged
+            .is_none_or(|last_logged| last_logged.elapsed() >= SLOW_EVENT_LOG_INTERVAL)
+    {
+        latency.last_logged = Some(time::Instant::now());
+        warn!(
+            "a window event took {latency_ms}ms to handle (average {:.1}ms over {} events)",
+            latency.total_ms as f32 / latency.handled as f32,
+            latency.handled
+        );
+    }
+}
+
+pub(crate) fn latency() -> EventLatency {
+    LATENCY.lock().map(|latency| *latency).unwrap_or_default()
+}
+
+fn on_location_change(desktop: &mut impl Desktop, hwnd: HWND) {
+    // Filtered windows can still have a border if they follow their owner's, so we only skip them
+    // if they don't
+    if desktop.has_filtered_style(hwnd) && !desktop.is_tool_window(hwnd) {
+        return;
+    }
+
+    if let Some(border) = desktop.border_for(hwnd) {
+        desktop.send_notify(border, WM_APP_LOCATIONCHANGE, "EVENT_OBJECT_LOCATIONCHANGE");
+    }
+}
+
+fn on_reorder(desktop: &mut impl Desktop, hwnd: HWND) {
+    if desktop.has_filtered_style(hwnd) {
+        return;
+    }
+
+    post_reorder(desktop);
+}
+
+// Send reorder messages to all the border windows
+fn post_reorder(desktop: &mut impl Desktop) {
+    for (_, border_window) in desktop.borders() {
+        if desktop.is_visible(border_window) {
+            desktop.post(border_window, WM_APP_REORDER, 0, "EVENT_OBJECT_REORDER");
+        }
+    }
+}
+
+fn on_focus(desktop: &mut impl Desktop, hwnd: HWND, time: u32) {
+    // This event can send a child window for its hwnd, so we have to find its parent
+    let parent = desktop.root_window(hwnd);
+
+    if desktop.has_filtered_style(parent) {
+        return;
+    }
+
+    // Send focus messages to all the border windows
+    for (key, border_window) in desktop.borders() {
+        // Some apps like Flow Launcher can become focused even if they aren't visible yet, so I
+        // also need to check if 'key' is equal to 'parent' (the focused window)
+        if desktop.is_visible(border_window) || key == BorderKey::window(parent) {
+            desktop.post(
+                border_window,
+                WM_APP_FOCUS,
+                time as usize,
+                "EVENT_OBJECT_FOCUS",
+            );
+        }
+    }
+}
+
+// Created / Shown / Uncloaked
+fn on_show(desktop: &mut impl Desktop, hwnd: HWND) {
+    match desktop.is_task_view(hwnd) {
+        true => set_task_view_open(desktop, true),
+        false => {
+            desktop.show_border(hwnd);
+
+            // A new window can open right above a bordered one without a reorder event, so the
+            // borders check whether it ended up between them and their windows
+            post_reorder(desktop);
+        }
+    }
+}
+
+// Hidden / Cloaked
+fn on_hide(desktop: &mut impl Desktop, hwnd: HWND) {
+    match desktop.is_task_view(hwnd) {
+        true => set_task_view_open(desktop, false),
+        false => desktop.hide_border(hwnd),
+    }
+}
+
+pub(crate) fn is_task_view_open() -> bool {
+    TASK_VIEW_OPEN.load(Ordering::SeqCst)
+}
+
+// Hide every border while Task View is open, and bring them back once it closes
+fn set_task_view_open(desktop: &mut impl Desktop, open: bool) {
+    if desktop.swap_task_view_open(open) == open {
+        return;
+    }
+
+    for (_, border_window) in desktop.borders() {
+        desktop.post(
+            border_window,
+            WM_APP_TASKVIEW,
+            open as usize,
+            "set_task_view_open",
+        );
+    }
+}
+
+fn on_minimize_start(desktop: &mut impl Desktop, hwnd: HWND) {
+    if let Some(border) = desktop.border_for(hwnd) {
+        desktop.post(
+            border,
+            WM_APP_MINIMIZESTART,
+            0,
+            "EVENT_SYSTEM_MINIMIZESTART",
+        );
+    }
+}
+
+fn on_minimize_end(desktop: &mut impl Desktop, hwnd: HWND) {
+    if let Some(border) = desktop.border_for(hwnd) {
+        desktop.post(border, WM_APP_MINIMIZEEND, 0, "EVENT_SYSTEM_MINIMIZEEND");
+    }
+}
+
+fn on_name_change(desktop: &mut impl Desktop, hwnd: HWND, time: u32) {
+    if desktop.has_filtered_style(hwnd) {
+        return;
+    }
+
+    // Titles are only used by script hooks once the border has been created. If there's no border
+    // yet, a rule or the script may have disabled it for the old title, so we give the window
+    // another chance.
+    let border = desktop.border_for(hwnd);
+    let title_matters = match border {
+        Some(_) => desktop.has_script(),
+        None => desktop.has_script() || desktop.rules_use_title(),
+    };
+    if !title_matters {
+        return;
+    }
+
+    match desktop.name_changes().check(hwnd, time) {
+        NameChange::Handle => {}
+        NameChange::Defer(delay_ms) => {
+            desktop.defer(WindowEvent::TitleChanged(hwnd), delay_ms);
+            return;
+        }
+        NameChange::Skip => return,
+    }
+
+    match border {
+        Some(border) => desktop.post(border, WM_APP_TITLECHANGE, 0, "EVENT_OBJECT_NAMECHANGE"),
+        None => desktop.show_border(hwnd),
+    }
+}
+
+fn on_destroy(desktop: &mut impl Desktop, hwnd: HWND) {
+    if !desktop.has_filtered_style(hwnd) || desktop.is_tool_window(hwnd) {
+        desktop.destroy_border(hwnd);
+        // HWNDs get reused, so whatever gets this one next shouldn't start out toggled off, or be
+        // given up on because this one's border couldn't be created
+        desktop.forget_window(hwnd);
+    }
+}
+
+// Used by Border::attach() when start() hasn't installed the main hook. Borders are only ever
+// created through attach() in that case, so events that would create a new border are dropped.
+pub extern "system" fn handle_attached_win_event(
+    _h_win_event_hook: HWINEVENTHOOK,
+    _event: u32,
+    _hwnd: HWND,
+    _id_object: i32,
+    _id_child: i32,
+    _dw_event_thread: u32,
+    _dwms_event_time: u32,
+) {
+    match _event {
+        EVENT_OBJECT_SHOW | EVENT_OBJECT_UNCLOAKED | EVENT_OBJECT_NAMECHANGE
+            if get_border_from_window(_hwnd).is_none() => {}
+        _ => handle_win_event(
+            _h_win_event_hook,
+            _event,
+            _hwnd,
+            _id_object,
+            _id_child,
+            _dw_event_thread,
+            _dwms_event_time,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[derive(Debug, Clone, Copy, Default)]
+    struct FakeWindow {
+        filtered: bool,
+        tool_window: bool,
+        task_view: bool,
+        // For child windows
+        root: Option<isize>,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Call {
+        Show(isize),
+        Hide(isize),
+        Destroy(isize),
+        Post(isize, u32, usize),
+        SendNotify(isize, u32),
+        ForgetWindow(isize),
+        Defer(WindowEvent, u32),
+    }
+
+    // Borders are made up as the window's key + 1000, and start out visible
+    #[derive(Default)]
+    struct FakeDesktop {
+        windows: HashMap<isize, FakeWindow>,
+        borders: Vec<isize>,
+        hidden_borders: Vec<isize>,
+        script: bool,
+        title_rules: bool,
+        task_view_open: bool,
+        name_changes: NameChangeThrottle,
+        calls: Vec<Call>,
+    }
+
+    fn hwnd(key: isize) -> HWND {
+        HWND(key as _)
+    }
+
+    fn border_of(key: isize) -> isize {
+        key + 1000
+    }
+
+    impl FakeDesktop {
+        fn window(&self, hwnd: HWND) -> FakeWindow {
+            self.windows
+                .get(&(hwnd.0 as isize))
+                .copied()
+                .unwrap_or_default()
+        }
+
+        fn with_border(mut self, key: isize) -> Self {
+            self.borders.push(key);
+            self
+        }
+
+        fn run(&mut self, events: &[WindowEvent], time: u32) -> Vec<Call> {
+            for event in events {
+                handle_event(self, *event, time);
+            }
+            std::mem::take(&mut self.calls)
+        }
+    }
+
+    impl Desktop for FakeDesktop {
+        fn has_filtered_style(&self, hwnd: HWND) -> bool {
+            self.window(hwnd).filtered
+        }
+
+        fn is_tool_window(&self, hwnd: HWND) -> bool {
+            self.window(hwnd).tool_window
+        }
+
+        fn root_window(&self, hwnd: HWND) -> HWND {
+            self.window(hwnd).root.map_or(hwnd, |root| HWND(root as _))
+        }
+
+        fn is_task_view(&self, hwnd: HWND) -> bool {
+            self.window(hwnd).task_view
+        }
+
+        fn is_visible(&self, hwnd: HWND) -> bool {
+            !self.hidden_borders.contains(&(hwnd.0 as isize))
+        }
+
+        fn has_script(&self) -> bool {
+            self.script
+        }
+
+        fn rules_use_title(&self) -> bool {
+            self.title_rules
+        }
+
+        fn name_changes(&mut self) -> &mut NameChangeThrottle {
+            &mut self.name_changes
+        }
+
+        fn border_for(&self, hwnd: HWND) -> Option<HWND> {
+            let key = hwnd.0 as isize;
+            self.borders
+                .contains(&key)
+                .then(|| HWND(border_of(key) as _))
+        }
+
+        fn borders(&self) -> Vec<(BorderKey, HWND)> {
+            self.borders
+                .iter()
+                .map(|key| (BorderKey::Window(*key), HWND(border_of(*key) as _)))
+                .collect()
+        }
+
+        fn swap_task_view_open(&mut self, open: bool) -> bool {
+            std::mem::replace(&mut self.task_view_open, open)
+        }
+
+        fn show_border(&mut self, hwnd: HWND) {
+            let key = hwnd.0 as isize;
+            self.calls.push(Call::Show(key));
+            if !self.borders.contains(&key) {
+                self.borders.push(key);
+            }
+        }
+
+        fn hide_border(&mut self, hwnd: HWND) {
+            self.calls.push(Call::Hide(hwnd.0 as isize));
+        }
+
+        fn destroy_border(&mut self, hwnd: HWND) {
+            let key = hwnd.0 as isize;
+            self.calls.push(Call::Destroy(key));
+            self.borders.retain(|border| *border != key);
+        }
+
+        fn post(&mut self, border_window: HWND, message: u32, wparam: usize, _: &'static str) {
+            self.calls
+                .push(Call::Post(border_window.0 as isize, message, wparam));
+        }
+
+        fn send_notify(&mut self, border_window: HWND, message: u32, _: &'static str) {
+            self.calls
+                .push(Call::SendNotify(border_window.0 as isize, message));
+        }
+
+        fn forget_window(&mut self, hwnd: HWND) {
+            self.calls.push(Call::ForgetWindow(hwnd.0 as isize));
+        }
+
+        fn defer(&mut self, event: WindowEvent, delay_ms: u32) {
+            self.calls.push(Call::Defer(event, delay_ms));
+        }
+    }
+
+    #[test]
+    fn window_lifetime() {
+        let mut desktop = FakeDesktop::default();
+
+        let calls = desktop.run(
+            &[
+                WindowEvent::Created(hwnd(1)),
+                WindowEvent::Moved(hwnd(1)),
+                WindowEvent::Minimized(hwnd(1)),
+                WindowEvent::Restored(hwnd(1)),
+                WindowEvent::Hidden(hwnd(1)),
+                WindowEvent::Destroyed(hwnd(1)),
+                // Nothing has a border anymore, so there's nobody to tell about this
+                WindowEvent::Moved(hwnd(1)),
+            ],
+            0,
+        );
+
+        assert_eq!(
+            calls,
+            [
+                Call::Show(1),
+                Call::Post(1001, WM_APP_REORDER, 0),
+                Call::SendNotify(1001, WM_APP_LOCATIONCHANGE),
+                Call::Post(1001, WM_APP_MINIMIZESTART, 0),
+                Call::Post(1001, WM_APP_MINIMIZEEND, 0),
+                Call::Hide(1),
+                Call::Destroy(1),
+                Call::ForgetWindow(1),
+            ]
+        );
+    }
+
+    #[test]
+    fn filtered_windows_are_left_alone() {
+        let mut desktop = FakeDesktop::default();
+        desktop.windows.insert(
+            2,
+            FakeWindow {
+                filtered: true,
+                ..Default::default()
+            },
+        );
+        let mut desktop = desktop.with_border(2);
+
+        let calls = desktop.run(
+            &[
+                WindowEvent::Moved(hwnd(2)),
+                WindowEvent::Reordered(hwnd(2)),
+                WindowEvent::FocusChanged(hwnd(2)),
+                WindowEvent::TitleChanged(hwnd(2)),
+                WindowEvent::Destroyed(hwnd(2)),
+            ],
+            0,
+        );
+
+        assert!(calls.is_empty());
+    }
+
+    #[test]
+    fn tool_windows_follow_their_group() {
+        let mut desktop = FakeDesktop::default();
+        desktop.windows.insert(
+            3,
+            FakeWindow {
+                filtered: true,
+                tool_window: true,
+                ..Default::default()
+            },
+        );
+        let mut desktop = desktop.with_border(3);
+
+        let calls = desktop.run(
+            &[WindowEvent::Moved(hwnd(3)), WindowEvent::Destroyed(hwnd(3))],
+            0,
+        );
+
+        assert_eq!(
+            calls,
+            [
+                Call::SendNotify(1003, WM_APP_LOCATIONCHANGE),
+                Call::Destroy(3),
+                Call::ForgetWindow(3),
+            ]
+        );
+    }
+
+    #[test]
+    fn focus_goes_to_visible_borders_and_the_focused_window() {
+        let mut desktop = FakeDesktop::default()
+            .with_border(1)
+            .with_border(2)
+            .with_border(3);
+        // A child of window 2, whose border hasn't been shown yet
+        desktop.windows.insert(
+            20,
+            FakeWindow {
+                root: Some(2),
+                ..Default::default()
+            },
+        );
+        desktop.hidden_borders = vec![border_of(2), border_of(3)];
+
+        let calls = desktop.run(&[WindowEvent::FocusChanged(hwnd(20))], 42);
+
+        assert_eq!(
+            calls,
+            [
+                Call::Post(1001, WM_APP_FOCUS, 42),
+                Call::Post(1002, WM_APP_FOCUS, 42),
+            ]
+        );
+    }
+
+    #[test]
+    fn task_view_hides_every_border_once() {
+        let mut desktop = FakeDesktop::default().with_border(1).with_border(2);
+        desktop.windows.insert(
+            9,
+            FakeWindow {
+                task_view: true,
+                ..Default::default()
+            },
+        );
+
+        let calls = desktop.run(
+            &[
+                WindowEvent::Shown(hwnd(9)),
+                WindowEvent::Uncloaked(hwnd(9)),
+                WindowEvent::Hidden(hwnd(9)),
+                WindowEvent::Cloaked(hwnd(9)),
+            ],
+            0,
+        );
+
+        assert_eq!(
+            calls,
+            [
+                Call::Post(1001, WM_APP_TASKVIEW, 1),
+                Call::Post(1002, WM_APP_TASKVIEW, 1),
+                Call::Post(1001, WM_APP_TASKVIEW, 0),
+                Call::Post(1002, WM_APP_TASKVIEW, 0),
+            ]
+        );
+        assert!(!desktop.task_view_open);
+    }
+
+    #[test]
+    fn title_changes_only_matter_to_scripts_and_title_rules() {
+        let mut desktop = FakeDesktop::default().with_border(1);
+        let events = [
+            WindowEvent::TitleChanged(hwnd(1)),
+            WindowEvent::TitleChanged(hwnd(2)),
+        ];
+
+        assert!(desktop.run(&events, 0).is_empty());
+
+        // A title rule may want a border for the new title, but it can't change an existing one
+        desktop.title_rules = true;
+        assert_eq!(desktop.run(&events, 1000), [Call::Show(2)]);
+
+        let mut desktop = FakeDesktop::default().with_border(1);
+        desktop.script = true;
+        assert_eq!(
+            desktop.run(&events, 0),
+            [
+                Call::Post(1001, WM_APP_TITLECHANGE, 0),
+                // The script may want a border for the new title
+                Call::Show(2),
+            ]
+        );
+    }
+
+    #[test]
+    fn title_changes_are_throttled() {
+        let mut desktop = FakeDesktop::default().with_border(1);
+        desktop.script = true;
+        let title_changed = WindowEvent::TitleChanged(hwnd(1));
+
+        // (time, calls)
+        let cases = [
+            (1000, vec![Call::Post(1001, WM_APP_TITLECHANGE, 0)]),
+            // The last change in a burst comes back once the interval is up
+            (1100, vec![Call::Defer(title_changed, 150)]),
+            (1200, vec![]),
+            (1250, vec![Call::Post(1001, WM_APP_TITLECHANGE, 0)]),
+            (2000, vec![Call::Post(1001, WM_APP_TITLECHANGE, 0)]),
+        ];
+
+        for (time, calls) in cases {
+            assert_eq!(desktop.run(&[title_changed], time), calls, "at {time}");
+        }
+    }
+
+    #[test]
+    fn menus_closing_reorder_visible_borders() {
+        let mut desktop = FakeDesktop::default().with_border(1).with_border(2);
+        desktop.hidden_borders = vec![border_of(1)];
+
+        let calls = desktop.run(&[WindowEvent::MenuClosed], 0);
+
+        assert_eq!(calls, [Call::Post(1002, WM_APP_REORDER, 0)]);
+    }
+}
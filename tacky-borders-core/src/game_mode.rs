@@ -0,0 +1,177 @@
+//! Hides the borders on a monitor while a fullscreen game (or any other fullscreen app, like a
+//! video player) is in the foreground there, so borders aren't drawn over it and don't cost it any
+//! frames.
+//!
+//! Only the borders on the fullscreen app's monitor are hidden, and they come back once it goes
+//! away. Borders on other monitors (and the cursor ring) keep going like normal. Apps in the
+//! `allow` list never hide anything. Game mode is off unless it's turned on in the config.
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicIsize, Ordering};
+use std::sync::{Mutex, Once};
+use std::thread;
+use std::time;
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+use windows::Win32::Graphics::Gdi::{MonitorFromWindow, HMONITOR, MONITOR_DEFAULTTONEAREST};
+use windows::Win32::UI::Shell::{SHQueryUserNotificationState, QUNS_RUNNING_D3D_FULL_SCREEN};
+use windows::Win32::UI::WindowsAndMessaging::GetForegroundWindow;
+
+use crate::border_config::CONFIG;
+use crate::log_if_err;
+use crate::registry::{BorderKey, BORDERS};
+use crate::utils::*;
+
+const POLL_INTERVAL: time::Duration = time::Duration::from_secs(1);
+
+// The desktop covers the whole monitor too, but it's not something to pause for
+const SHELL_CLASSES: [&str; 2] = ["Progman", "WorkerW"];
+
+// The monitor that has a fullscreen app on it, or 0 if there's none
+static SUSPENDED_MONITOR: AtomicIsize = AtomicIsize::new(0);
+
+// The border windows that the poller has hidden
+static HIDDEN_BORDERS: Mutex<Option<HashSet<isize>>> = Mutex::new(None);
+
+static POLLER: Once = Once::new();
+
+/// The `game_mode` section of the config.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameModeConfig {
+    /// Whether to hide borders for fullscreen apps at all.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Processes (e.g. `mpv.exe`) that keep their borders even when they're fullscreen.
+    #[serde(default)]
+    pub allow: Vec<String>,
+}
+
+impl Default for GameModeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            allow: Vec::new(),
+        }
+    }
+}
+
+// Maximized borderless browsers and video players cover the whole monitor too, so this is opt-in
+fn default_enabled() -> bool {
+    false
+}
+
+/// Whether the borders on some monitor are hidden for a fullscreen app right now.
+pub fn is_suspended() -> bool {
+    SUSPENDED_MONITOR.load(Ordering::SeqCst) != 0
+}
+
+// The poller keeps running after the config changes, and just picks up the new options
+pub(crate) fn start() {
+    POLLER.call_once(|| {
+        let _ = thread::spawn(|| loop {
+            poll();
+            thread::sleep(POLL_INTERVAL);
+        });
+    });
+}
+
+fn poll() {
+    let config = CONFIG.lock().unwrap().game_mode.clone();
+
+    let fullscreen_window = match config.enabled {
+        true => get_fullscreen_window(),
+        false => None,
+    };
+    let fullscreen_process = fullscreen_window.map(get_process_name);
+    let suspend = fullscreen_process.as_ref().is_some_and(|process| {
+        !config.allow.iter().any(|allowed| {
+            allowed.eq_ignore_ascii_case(process)
+                || allowed.eq_ignore_ascii_case(process.trim_end_matches(".exe"))
+        })
+    });
+
+    let monitor = fullscreen_window
+        .filter(|_| suspend)
+        .map(|hwnd| unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST) });
+    let monitor_key = monitor.map_or(0, |monitor| monitor.0 as isize);
+
+    if SUSPENDED_MONITOR.swap(monitor_key, Ordering::SeqCst) != monitor_key {
+        match monitor {
+            Some(monitor) => info!(
+                "hiding borders on {monitor:?} while {} is fullscreen",
+                fullscreen_process.unwrap_or_default()
+            ),
+            None => info!("showing borders again"),
+        }
+    }
+
+    // Windows can move between monitors (and new borders can show up) while we're suspended, so
+    // this runs on every poll and not just when the fullscreen app comes or goes
+    update_hidden_borders(monitor);
+}
+
+// Hide the borders on 'monitor' and show the ones that aren't on it (anymore)
+fn update_hidden_borders(monitor: Option<HMONITOR>) {
+    let mut hidden_borders = HIDDEN_BORDERS.lock().unwrap();
+    let last_hidden = hidden_borders.take().unwrap_or_default();
+
+    let hidden: HashSet<isize> = match monitor {
+        Some(monitor) => BORDERS
+            .entries()
+            .into_iter()
+            .filter(|(key, _)| get_border_monitor(*key) == Some(monitor))
+            .map(|(_, border_window)| border_window.0 as isize)
+            .collect(),
+        None => HashSet::new(),
+    };
+
+    for border_window in hidden.difference(&last_hidden) {
+        log_if_err!(post_message_w(
+            HWND(*border_window as _),
+            WM_APP_FULLSCREEN,
+            WPARAM(1),
+            LPARAM(0)
+        )
+        .context("game_mode::update_hidden_borders"));
+    }
+    // Some of these may have been destroyed in the meantime, which is fine
+    for border_window in last_hidden.difference(&hidden) {
+        let _ = post_message_w(
+            HWND(*border_window as _),
+            WM_APP_FULLSCREEN,
+            WPARAM(0),
+            LPARAM(0),
+        );
+    }
+
+    *hidden_borders = Some(hidden);
+}
+
+// The monitor a border's window is on. The cursor ring follows the cursor wherever it goes, so
+// it's never hidden.
+fn get_border_monitor(key: BorderKey) -> Option<HMONITOR> {
+    match key {
+        BorderKey::Window(hwnd) => {
+            Some(unsafe { MonitorFromWindow(HWND(hwnd as _), MONITOR_DEFAULTTONEAREST) })
+        }
+        BorderKey::Monitor(monitor) => Some(HMONITOR(monitor as _)),
+        BorderKey::CursorRing => None,
+    }
+}
+
+fn get_fullscreen_window() -> Option<HWND> {
+    let foreground = unsafe { GetForegroundWindow() };
+    if foreground.is_invalid() || SHELL_CLASSES.contains(&get_window_class(foreground).as_str()) {
+        return None;
+    }
+
+    // This catches exclusive fullscreen, but borderless fullscreen games just look like a window
+    // that happens to cover the whole monitor, so we check for that too
+    let state = unsafe { SHQueryUserNotificationState() }.unwrap_or_default();
+    if state == QUNS_RUNNING_D3D_FULL_SCREEN || covers_monitor(foreground) {
+        return Some(foreground);
+    }
+
+    None
+}
@@ -0,0 +1,239 @@
+use anyhow::{anyhow, Context};
+use std::ffi::c_void;
+use std::{mem, ptr, slice};
+use windows::Win32::Foundation::{COLORREF, FALSE, HANDLE, HWND, POINT, SIZE};
+use windows::Win32::Graphics::Direct2D::Common::D2D1_COLOR_F;
+use windows::Win32::Graphics::Direct2D::D2D1_ROUNDED_RECT;
+use windows::Win32::Graphics::Dwm::{DwmEnableBlurBehindWindow, DWM_BB_ENABLE, DWM_BLURBEHIND};
+use windows::Win32::Graphics::Gdi::{
+    CreateCompatibleDC, CreateDIBSection, DeleteDC, DeleteObject, SelectObject, AC_SRC_ALPHA,
+    AC_SRC_OVER, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, BLENDFUNCTION, DIB_RGB_COLORS, HBITMAP, HDC,
+    HGDIOBJ,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetWindowLongPtrW, SetWindowLongPtrW, UpdateLayeredWindow, GWL_EXSTYLE, ULW_ALPHA,
+    WS_EX_LAYERED,
+};
+
+use crate::renderer::{BorderRenderer, FrameState, RendererBackend};
+use crate::window_border::WindowBorder;
+
+// Draws borders with plain GDI, for systems where Direct2D doesn't work well (VMs, old GPUs). It
+// only draws a solid rounded rect: gradients are flattened into one color, and effects, badges,
+// etc. are left out.
+//
+// There's not much here that could fail on a bad driver: we fill in the pixels of a DIB section
+// ourselves and hand them to the window manager with UpdateLayeredWindow.
+#[derive(Debug, Default)]
+pub struct GdiRenderer {
+    border_window: HWND,
+    mem_dc: HDC,
+    bitmap: HBITMAP,
+    old_bitmap: HGDIOBJ,
+    // The DIB section's pixels, which belong to the bitmap
+    bits: Option<ptr::NonNull<u32>>,
+    width: i32,
+    height: i32,
+}
+
+impl GdiRenderer {
+    fn release_bitmap(&mut self) {
+        if self.bitmap.is_invalid() {
+            return;
+        }
+
+        unsafe {
+            SelectObject(self.mem_dc, self.old_bitmap);
+            let _ = DeleteObject(self.bitmap);
+        }
+        self.bitmap = HBITMAP::default();
+        self.bits = None;
+    }
+}
+
+impl BorderRenderer for GdiRenderer {
+    fn backend(&self) -> RendererBackend {
+        RendererBackend::Gdi
+    }
+
+    fn init(&mut self, border: &WindowBorder) -> anyhow::Result<()> {
+        self.border_window = border.border_window;
+
+        unsafe {
+            // UpdateLayeredWindow fails on windows that have been given SetLayeredWindowAttributes
+            // (i.e. by the Direct2D renderer), and removing WS_EX_LAYERED is the only way to undo it
+            let ex_style = GetWindowLongPtrW(self.border_window, GWL_EXSTYLE);
+            SetWindowLongPtrW(
+                self.border_window,
+                GWL_EXSTYLE,
+                ex_style & !(WS_EX_LAYERED.0 as isize),
+            );
+            SetWindowLongPtrW(self.border_window, GWL_EXSTYLE, ex_style);
+
+            // The bitmap already has the transparency we want, so we don't need DWM's trick
+            let bh = DWM_BLURBEHIND {
+                dwFlags: DWM_BB_ENABLE,
+                fEnable: FALSE,
+                ..Default::default()
+            };
+            DwmEnableBlurBehindWindow(self.border_window, &bh)
+                .context("could not disable blur behind")?;
+
+            self.mem_dc = CreateCompatibleDC(HDC::default());
+            if self.mem_dc.is_invalid() {
+                return Err(anyhow!("could not create memory DC"));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn resize(&mut self, width: u32, height: u32) -> anyhow::Result<()> {
+        self.release_bitmap();
+        self.width = width as i32;
+        self.height = height as i32;
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+
+        let bitmap_info = BITMAPINFO {
+            bmiHeader: BITMAPINFOHEADER {
+                biSize: mem::size_of::<BITMAPINFOHEADER>() as u32,
+                biWidth: self.width,
+                // Negative so that the rows go from top to bottom
+                biHeight: -self.height,
+                biPlanes: 1,
+                biBitCount: 32,
+                biCompression: BI_RGB.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        unsafe {
+            let mut bits: *mut c_void = ptr::null_mut();
+            self.bitmap = CreateDIBSection(
+                self.mem_dc,
+                &bitmap_info,
+                DIB_RGB_COLORS,
+                &mut bits,
+                HANDLE::default(),
+                0,
+            )
+            .context("could not create DIB section")?;
+            self.bits = ptr::NonNull::new(bits as *mut u32);
+            self.old_bitmap = SelectObject(self.mem_dc, self.bitmap);
+        }
+
+        Ok(())
+    }
+
+    fn draw(&mut self, frame: &FrameState) -> anyhow::Result<()> {
+        let Some(bits) = self.bits else {
+            // There's nothing to draw on a window with no size
+            return Ok(());
+        };
+
+        let pixels = unsafe {
+            slice::from_raw_parts_mut(bits.as_ptr(), (self.width * self.height) as usize)
+        };
+        fill_stroke(
+            pixels,
+            self.width,
+            self.height,
+            &frame.rounded_rect,
+            frame.stroke_width,
+            &frame.color.flatten(),
+        );
+
+        let blend = BLENDFUNCTION {
+            BlendOp: AC_SRC_OVER as u8,
+            BlendFlags: 0,
+            SourceConstantAlpha: match frame.visible {
+                true => (frame.opacity.clamp(0.0, 1.0) * 255.0).round() as u8,
+                false => 0,
+            },
+            AlphaFormat: AC_SRC_ALPHA as u8,
+        };
+
+        unsafe {
+            UpdateLayeredWindow(
+                self.border_window,
+                HDC::default(),
+                Some(&POINT {
+                    x: frame.window_rect.left,
+                    y: frame.window_rect.top,
+                }),
+                Some(&SIZE {
+                    cx: self.width,
+                    cy: self.height,
+                }),
+                self.mem_dc,
+                Some(&POINT::default()),
+                COLORREF(0),
+                Some(&blend),
+                ULW_ALPHA,
+            )
+        }
+        .context("could not update layered window")
+    }
+
+    fn release(&mut self) {
+        self.release_bitmap();
+
+        if !self.mem_dc.is_invalid() {
+            let _ = unsafe { DeleteDC(self.mem_dc) };
+            self.mem_dc = HDC::default();
+        }
+    }
+}
+
+fn fill_stroke(
+    pixels: &mut [u32],
+    width: i32,
+    height: i32,
+    rounded_rect: &D2D1_ROUNDED_RECT,
+    stroke_width: f32,
+    color: &D2D1_COLOR_F,
+) {
+    let rect = &rounded_rect.rect;
+    let center_x = (rect.left + rect.right) / 2.0;
+    let center_y = (rect.top + rect.bottom) / 2.0;
+    let half_width = (rect.right - rect.left) / 2.0;
+    let half_height = (rect.bottom - rect.top) / 2.0;
+    let radius = rounded_rect
+        .radiusX
+        .min(half_width)
+        .min(half_height)
+        .max(0.0);
+    let half_stroke = stroke_width / 2.0;
+
+    for y in 0..height {
+        for x in 0..width {
+            // Signed distance from the middle of the pixel to the edge of the rounded rect
+            let dx = (x as f32 + 0.5 - center_x).abs() - (half_width - radius);
+            let dy = (y as f32 + 0.5 - center_y).abs() - (half_height - radius);
+            let outside = (dx.max(0.0).powi(2) + dy.max(0.0).powi(2)).sqrt();
+            let distance = outside + dx.max(dy).min(0.0) - radius;
+
+            // Antialias the edges by how much of the pixel the stroke covers
+            let coverage = (half_stroke - distance.abs() + 0.5).clamp(0.0, 1.0);
+
+            pixels[(y * width + x) as usize] = match coverage > 0.0 {
+                true => premultiply(color, coverage),
+                false => 0,
+            };
+        }
+    }
+}
+
+// UpdateLayeredWindow expects premultiplied BGRA
+fn premultiply(color: &D2D1_COLOR_F, coverage: f32) -> u32 {
+    let alpha = color.a * coverage;
+    let channel = |value: f32| ((value * alpha).clamp(0.0, 1.0) * 255.0).round() as u32;
+
+    ((alpha.clamp(0.0, 1.0) * 255.0).round() as u32) << 24
+        | channel(color.r) << 16
+        | channel(color.g) << 8
+        | channel(color.b)
+}
@@ -0,0 +1,184 @@
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Once;
+use std::thread;
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+use windows::Win32::System::Threading::GetCurrentThreadId;
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    RegisterHotKey, UnregisterHotKey, HOT_KEY_MODIFIERS, MOD_ALT, MOD_CONTROL, MOD_NOREPEAT,
+    MOD_SHIFT, MOD_WIN, VK_DELETE, VK_DOWN, VK_END, VK_ESCAPE, VK_F1, VK_HOME, VK_INSERT, VK_LEFT,
+    VK_NEXT, VK_PRIOR, VK_RETURN, VK_RIGHT, VK_SPACE, VK_TAB, VK_UP,
+};
+use windows::Win32::UI::WindowsAndMessaging::{GetMessageW, PostThreadMessageW, MSG, WM_HOTKEY};
+
+use crate::accessibility;
+use crate::border_config::CONFIG;
+use crate::cursor_ring;
+use crate::ipc;
+use crate::timer;
+use crate::utils::WM_APP_RELOAD_HOTKEYS;
+
+static HOTKEY_THREAD: Once = Once::new();
+static HOTKEY_THREAD_ID: AtomicU32 = AtomicU32::new(0);
+
+// Hotkeys are written like "ctrl+alt+t" or "win+shift+f5"
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Hotkeys {
+    // Start or stop a timer
+    pub timer: Option<String>,
+    // Show or hide the cursor ring
+    pub cursor_ring: Option<String>,
+    // Pulse the focused window's border so it's easy to find
+    pub locate: Option<String>,
+    // Turn accessibility mode on or off
+    pub accessibility: Option<String>,
+    // Turn the focused window's border off until the window is closed, or back on
+    pub toggle: Option<String>,
+}
+
+// The ids we register each hotkey with
+const HOTKEY_TIMER: i32 = 1;
+const HOTKEY_CURSOR_RING: i32 = 2;
+const HOTKEY_LOCATE: i32 = 3;
+const HOTKEY_ACCESSIBILITY: i32 = 4;
+const HOTKEY_TOGGLE: i32 = 5;
+
+// Hotkeys are registered on their own thread, since WM_HOTKEY is posted to the thread that
+// registered it and we don't control the message loop of the thread that calls start()
+pub(crate) fn start() {
+    HOTKEY_THREAD.call_once(|| {
+        let _ = thread::spawn(|| unsafe {
+            HOTKEY_THREAD_ID.store(GetCurrentThreadId(), Ordering::SeqCst);
+            let mut registered = register_hotkeys();
+
+            let mut message = MSG::default();
+            while GetMessageW(&mut message, HWND::default(), 0, 0).into() {
+                match message.message {
+                    WM_HOTKEY => handle_hotkey(message.wParam.0 as i32),
+                    WM_APP_RELOAD_HOTKEYS => {
+                        for id in registered.drain(..) {
+                            let _ = UnregisterHotKey(HWND::default(), id);
+                        }
+                        registered = register_hotkeys();
+                    }
+                    _ => {}
+                }
+            }
+        });
+    });
+}
+
+// Re-register the hotkeys from the config (e.g. after it has been reloaded)
+pub(crate) fn reload() {
+    let thread_id = HOTKEY_THREAD_ID.load(Ordering::SeqCst);
+    if thread_id != 0 {
+        let _ =
+            unsafe { PostThreadMessageW(thread_id, WM_APP_RELOAD_HOTKEYS, WPARAM(0), LPARAM(0)) };
+    }
+}
+
+fn register_hotkeys() -> Vec<i32> {
+    let hotkeys = CONFIG.lock().unwrap().hotkeys.clone();
+    let mut registered = Vec::new();
+
+    for (id, hotkey) in [
+        (HOTKEY_TIMER, hotkeys.timer),
+        (HOTKEY_CURSOR_RING, hotkeys.cursor_ring),
+        (HOTKEY_LOCATE, hotkeys.locate),
+        (HOTKEY_ACCESSIBILITY, hotkeys.accessibility),
+        (HOTKEY_TOGGLE, hotkeys.toggle),
+    ] {
+        let Some(hotkey) = hotkey else {
+            continue;
+        };
+
+        let result = parse_hotkey(&hotkey).and_then(|(modifiers, key)| unsafe {
+            RegisterHotKey(HWND::default(), id, modifiers | MOD_NOREPEAT, key)
+                .map_err(|e| anyhow!("{e} (it may already be used by another app)"))
+        });
+        match result {
+            Ok(_) => registered.push(id),
+            Err(e) => error!("could not register hotkey '{hotkey}': {e:#}"),
+        }
+    }
+
+    registered
+}
+
+fn handle_hotkey(id: i32) {
+    match id {
+        HOTKEY_TIMER => timer::toggle(),
+        HOTKEY_CURSOR_RING => cursor_ring::toggle(),
+        HOTKEY_LOCATE => {
+            if let Err(e) = ipc::locate("focused") {
+                debug!("could not locate the focused window: {e}");
+            }
+        }
+        HOTKEY_ACCESSIBILITY => accessibility::toggle(),
+        HOTKEY_TOGGLE => {
+            if let Err(e) = ipc::toggle("focused") {
+                debug!("could not toggle the focused window's border: {e}");
+            }
+        }
+        _ => {}
+    }
+}
+
+fn parse_hotkey(hotkey: &str) -> anyhow::Result<(HOT_KEY_MODIFIERS, u32)> {
+    let mut modifiers = HOT_KEY_MODIFIERS(0);
+    let mut key = None;
+
+    for part in hotkey.split('+').map(|part| part.trim().to_lowercase()) {
+        match part.as_str() {
+            "ctrl" | "control" => modifiers |= MOD_CONTROL,
+            "alt" => modifiers |= MOD_ALT,
+            "shift" => modifiers |= MOD_SHIFT,
+            "win" | "super" => modifiers |= MOD_WIN,
+            name if key.is_none() => key = Some(parse_key(name)?),
+            _ => return Err(anyhow!("hotkeys can only have one non-modifier key")),
+        }
+    }
+
+    match key {
+        Some(key) => Ok((modifiers, key)),
+        None => Err(anyhow!("hotkey is missing a key")),
+    }
+}
+
+fn parse_key(name: &str) -> anyhow::Result<u32> {
+    let named_key = match name {
+        "space" => Some(VK_SPACE),
+        "tab" => Some(VK_TAB),
+        "enter" | "return" => Some(VK_RETURN),
+        "escape" | "esc" => Some(VK_ESCAPE),
+        "insert" => Some(VK_INSERT),
+        "delete" => Some(VK_DELETE),
+        "home" => Some(VK_HOME),
+        "end" => Some(VK_END),
+        "pageup" => Some(VK_PRIOR),
+        "pagedown" => Some(VK_NEXT),
+        "up" => Some(VK_UP),
+        "down" => Some(VK_DOWN),
+        "left" => Some(VK_LEFT),
+        "right" => Some(VK_RIGHT),
+        _ => None,
+    };
+    if let Some(key) = named_key {
+        return Ok(key.0 as u32);
+    }
+
+    // F1 to F24 are consecutive
+    if let Some(number) = name.strip_prefix('f').and_then(|n| n.parse::<u32>().ok()) {
+        if (1..=24).contains(&number) {
+            return Ok(VK_F1.0 as u32 + number - 1);
+        }
+    }
+
+    // Letters and digits use their uppercase ASCII codes
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_alphanumeric() => Ok(c.to_ascii_uppercase() as u32),
+        _ => Err(anyhow!("unknown key '{name}'")),
+    }
+}
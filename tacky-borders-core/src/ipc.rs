@@ -0,0 +1,685 @@
+//! Commands that other processes can send to a running instance over a named pipe.
+//!
+//! Each connection sends a single line like `set-progress focused 50` and gets a reply back, either
+//! `ok` (optionally followed by a message) or `error <message>`, after which the pipe is closed.
+//! Messages are a single line, except for `export`'s, and commands can be at most
+//! [`MAX_COMMAND_LENGTH`] bytes. Arguments with spaces can be wrapped in double quotes, inside of
+//! which `\"` is a quote and `\\` is a backslash.
+//!
+//! Commands:
+//! - `set-progress <window> <0-100|none>`: draws a progress ring along the border
+//! - `timer [minutes|stop]`: starts a countdown ring on the focused border (see [`timer`](crate::timer))
+//! - `locate [window]`: briefly pulses and flashes a border (the focused one by default) so it's
+//!   easy to spot
+//! - `set-color <window> <color|reset>`: replaces a border's colors until it's reset
+//! - `disable <window>` / `enable <window>`: turns a window's border off or back on
+//! - `toggle [window]`: turns a border (the focused one by default) off until its window is closed,
+//!   or back on if it was toggled off
+//! - `theme [name|none|default]`: switches to a theme (see [`themes`](crate::themes)), turns
+//!   themes off, or goes back to the config's theme. Prints the current theme without an argument.
+//! - `accessibility [on|off]`: toggles (or turns on or off) the high-visibility mode (see
+//!   [`accessibility`](crate::accessibility))
+//! - `export`: prints the config the instance is using, as
+//!   [`Config::export_effective`](crate::border_config::Config::export_effective) writes it
+//!
+//! Changes made with `set-color`, `disable`, and `theme` are saved (see [`state`](crate::state)), so they
+//! survive restarts and config reloads.
+//!
+//! Instances started with `--config` listen on their own pipe (see [`pipe_name`]), so several can
+//! run side by side with different configs. Commands sent with the same `--config` go to the
+//! matching instance.
+//!
+//! `<window>` can be `focused`, `all`, an HWND like `0x1a2b`, or `process:<name>`, `class:<name>`,
+//! or `title:<text>` (titles only need to contain the text).
+
+use anyhow::{anyhow, Context};
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::windows::io::FromRawHandle;
+use std::sync::Once;
+use std::thread;
+use std::time;
+use windows::core::HSTRING;
+use windows::Win32::Foundation::{CloseHandle, ERROR_PIPE_CONNECTED, HWND, LPARAM, WPARAM};
+use windows::Win32::Storage::FileSystem::PIPE_ACCESS_DUPLEX;
+use windows::Win32::System::Pipes::{
+    ConnectNamedPipe, CreateNamedPipeW, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS,
+    PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+};
+use windows::Win32::UI::WindowsAndMessaging::GetForegroundWindow;
+
+use crate::accessibility;
+use crate::border_config::{Config, MatchStrategy, CONFIG};
+use crate::colors::ColorConfig;
+use crate::log_if_err;
+use crate::progress;
+use crate::registry::{BorderKey, BORDERS};
+use crate::rule_engine::match_string;
+use crate::state;
+use crate::themes;
+use crate::timer;
+use crate::utils::*;
+use crate::SendHWND;
+
+/// The pipe that [`send`] connects to when there's no `--config`.
+pub const PIPE_NAME: &str = r"\\.\pipe\tacky-borders";
+
+/// The longest command line the server reads. Anything longer gets an error back.
+pub const MAX_COMMAND_LENGTH: usize = 8192;
+
+static SERVER: Once = Once::new();
+
+/// The pipe name for the config that's in use. Instances started with `--config` get a name derived
+/// from the config's path, so each one can be reached separately.
+pub fn pipe_name() -> String {
+    let Some(config_path) = Config::get_config_path_override() else {
+        return PIPE_NAME.to_string();
+    };
+
+    // Paths are case-insensitive on Windows. The hash is stable between builds, so a newer CLI can
+    // still find an older instance.
+    let hash = stable_hash(&config_path.to_string_lossy().to_lowercase());
+
+    format!("{PIPE_NAME}-{hash:016x}")
+}
+
+/// Sends a command to the running instance and returns its reply.
+pub fn send(args: &[String]) -> anyhow::Result<String> {
+    let mut pipe = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(pipe_name())
+        .context("could not connect to tacky-borders (is it running?)")?;
+
+    let command_line = args
+        .iter()
+        .map(|arg| quote_arg(arg))
+        .collect::<Vec<_>>()
+        .join(" ");
+    writeln!(pipe, "{command_line}").context("could not send command")?;
+
+    // The reply is everything up to the server closing the pipe
+    let mut reply = String::new();
+    pipe.read_to_string(&mut reply)
+        .context("could not read reply")?;
+    let reply = reply.trim_end();
+
+    match reply.split_once(' ').unwrap_or((reply, "")) {
+        ("ok", message) => Ok(message.to_string()),
+        ("error", message) => Err(anyhow!("{message}")),
+        _ => Err(anyhow!("invalid reply: {reply}")),
+    }
+}
+
+pub(crate) fn start_server() {
+    SERVER.call_once(|| {
+        let _ = thread::spawn(|| {
+            if let Err(e) = run_server() {
+                error!("ipc server stopped: {e:#}");
+            }
+        });
+    });
+}
+
+fn run_server() -> anyhow::Result<()> {
+    let pipe_name = pipe_name();
+    debug!("listening for ipc commands on {pipe_name}");
+    let pipe_name = HSTRING::from(pipe_name);
+
+    loop {
+        // Each client gets its own pipe instance, so we create a new one after every connection
+        let pipe = unsafe {
+            CreateNamedPipeW(
+                &pipe_name,
+                PIPE_ACCESS_DUPLEX,
+                PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                PIPE_UNLIMITED_INSTANCES,
+                4096,
+                4096,
+                0,
+                None,
+            )
+        };
+        if pipe.is_invalid() {
+            return Err(anyhow!("could not create named pipe {pipe_name}"));
+        }
+
+        // ERROR_PIPE_CONNECTED just means the client connected before we started waiting
+        if let Err(e) = unsafe { ConnectNamedPipe(pipe, None) } {
+            if e.code() != ERROR_PIPE_CONNECTED.to_hresult() {
+                error!("could not accept ipc connection: {e}");
+                let _ = unsafe { CloseHandle(pipe) };
+                continue;
+            }
+        }
+
+        // The File takes ownership of the handle and closes it when dropped. Each client is handled
+        // on its own thread, so one that never sends its command can't hold up everyone else.
+        let client = unsafe { File::from_raw_handle(pipe.0) };
+        let _ = thread::spawn(move || log_if_err!(handle_client(client)));
+    }
+}
+
+fn handle_client(mut client: File) -> anyhow::Result<()> {
+    // Read one byte past the limit, so we can tell a command that's too long from one that's just
+    // missing its newline
+    let mut line = String::new();
+    BufReader::new((&client).take(MAX_COMMAND_LENGTH as u64 + 1))
+        .read_line(&mut line)
+        .context("could not read ipc command")?;
+
+    let result = match line.len() > MAX_COMMAND_LENGTH && !line.ends_with('\n') {
+        true => Err(anyhow!("command is longer than {MAX_COMMAND_LENGTH} bytes")),
+        false => {
+            let args = split_args(line.trim());
+            debug!("received ipc command: {args:?}");
+            run_command(&args)
+        }
+    };
+
+    let reply = match result {
+        Ok(message) if message.is_empty() => "ok".to_string(),
+        Ok(message) => format!("ok {message}"),
+        Err(e) => format!("error {e:#}"),
+    };
+
+    writeln!(client, "{reply}").context("could not send ipc reply")?;
+    // Make sure the client gets the reply before the pipe is closed
+    let _ = client.sync_all();
+
+    Ok(())
+}
+
+fn run_command(args: &[String]) -> anyhow::Result<String> {
+    let Some((command, args)) = args.split_first() else {
+        return Err(anyhow!("no command given"));
+    };
+
+    match command.as_str() {
+        "set-progress" => {
+            let [query, value] = args else {
+                return Err(anyhow!("usage: set-progress <window> <0-100|none>"));
+            };
+
+            let progress = match value.as_str() {
+                "none" | "clear" => None,
+                value => {
+                    let percent = value
+                        .trim_end_matches('%')
+                        .parse::<f32>()
+                        .map_err(|_| anyhow!("invalid progress '{value}'"))?;
+                    Some(percent / 100.0)
+                }
+            };
+
+            let borders = find_borders(query)?;
+            for (_, border_window) in borders.iter() {
+                progress::set_border_progress(*border_window, progress)
+                    .context("could not send progress to border")?;
+            }
+
+            Ok(format!("updated {} border(s)", borders.len()))
+        }
+        "timer" => match args {
+            [] => {
+                let duration = timer::start(None);
+                Ok(format!(
+                    "started a {} minute timer",
+                    duration.as_secs_f32() / 60.0
+                ))
+            }
+            [arg] if arg == "stop" => {
+                timer::stop();
+                Ok(String::new())
+            }
+            [minutes] => {
+                let minutes = minutes
+                    .parse::<f32>()
+                    .map_err(|_| anyhow!("invalid number of minutes '{minutes}'"))?;
+                timer::start(Some(timer::duration_from_minutes(minutes)?));
+                Ok(format!("started a {minutes} minute timer"))
+            }
+            _ => Err(anyhow!("usage: timer [minutes|stop]")),
+        },
+        "set-color" => {
+            let [query, color] = args else {
+                return Err(anyhow!("usage: set-color <window> <color|reset>"));
+            };
+
+            // A bad color would otherwise be saved, and come back after every restart
+            let color = match color.as_str() {
+                "reset" => None,
+                color => {
+                    let color = ColorConfig::SolidConfig(color.to_string());
+                    color.validate()?;
+                    Some(color)
+                }
+            };
+
+            let borders = find_borders(query)?;
+            for (tracking_window, border_window) in borders.iter() {
+                match color {
+                    None => {
+                        state::set_color(*tracking_window, None);
+                        recreate_border(*tracking_window);
+                    }
+                    Some(ref color) => {
+                        state::set_color(*tracking_window, Some(color.clone()));
+                        set_border_color(*border_window, color.clone())
+                            .context("could not send color to border")?;
+                    }
+                }
+            }
+
+            Ok(format!("updated {} border(s)", borders.len()))
+        }
+        "disable" => {
+            let [query] = args else {
+                return Err(anyhow!("usage: disable <window>"));
+            };
+
+            let borders = find_borders(query)?;
+            for (tracking_window, _) in borders.iter() {
+                state::set_disabled(*tracking_window, true);
+                destroy_border_for_window(*tracking_window);
+            }
+
+            Ok(format!("turned off {} border(s)", borders.len()))
+        }
+        "enable" => {
+            let [query] = args else {
+                return Err(anyhow!("usage: enable <window>"));
+            };
+
+            let windows = find_unbordered_windows(query)?;
+            for tracking_window in windows.iter() {
+                state::set_disabled(*tracking_window, false);
+                state::set_toggled_off(*tracking_window, false);
+                create_border_for_window(*tracking_window);
+            }
+
+            Ok(format!("turned on {} border(s)", windows.len()))
+        }
+        "toggle" => {
+            let query = match args {
+                [] => "focused",
+                [query] => query.as_str(),
+                _ => return Err(anyhow!("usage: toggle [window]")),
+            };
+
+            let (off, on) = toggle(query)?;
+            Ok(format!("turned off {off} border(s) and turned on {on}"))
+        }
+        "locate" => {
+            let query = match args {
+                [] => "focused",
+                [query] => query.as_str(),
+                _ => return Err(anyhow!("usage: locate [window]")),
+            };
+
+            let borders = locate(query)?;
+            Ok(format!("located {borders} border(s)"))
+        }
+        "theme" => {
+            let mut choices = state::tray_choices();
+            let theme = match args {
+                [] => {
+                    let theme = choices
+                        .theme
+                        .or_else(|| CONFIG.lock().unwrap().global.theme.clone())
+                        .filter(|name| !name.is_empty());
+                    return Ok(match theme {
+                        Some(name) => format!("using theme '{name}'"),
+                        None => "not using a theme".to_string(),
+                    });
+                }
+                [arg] if arg == "default" => None,
+                [arg] if arg == "none" => Some(String::new()),
+                [name] => {
+                    // Check it up front, so a typo gets an error instead of an unthemed reload
+                    themes::load_theme(name)?;
+                    Some(name.clone())
+                }
+                _ => return Err(anyhow!("usage: theme [name|none|default]")),
+            };
+
+            choices.theme = theme;
+            let save = CONFIG
+                .lock()
+                .unwrap()
+                .global
+                .remember_tray_choices
+                .unwrap_or(true);
+            state::set_tray_choices(choices, save);
+            crate::reload();
+
+            Ok("reloaded borders with the new theme".to_string())
+        }
+        "accessibility" => {
+            let enabled = match args {
+                [] => !accessibility::is_enabled(),
+                [arg] if arg == "on" => true,
+                [arg] if arg == "off" => false,
+                _ => return Err(anyhow!("usage: accessibility [on|off]")),
+            };
+
+            accessibility::set_enabled(enabled);
+            Ok(format!(
+                "accessibility mode is {}",
+                if enabled { "on" } else { "off" }
+            ))
+        }
+        "export" => match args {
+            [] => {
+                let config = CONFIG.lock().unwrap().clone();
+                config.export_effective()
+            }
+            _ => Err(anyhow!("usage: export")),
+        },
+        _ => Err(anyhow!("unknown command '{command}'")),
+    }
+}
+
+// Play the locate effect on every border that matches the query, returning how many there were
+pub(crate) fn locate(query: &str) -> anyhow::Result<usize> {
+    let borders = find_borders(query)?;
+    for (_, border_window) in borders.iter() {
+        post_message_w(*border_window, WM_APP_LOCATE, WPARAM(0), LPARAM(0))
+            .context("could not send locate to border")?;
+    }
+
+    Ok(borders.len())
+}
+
+// Turn off the border of every matching window until the window is closed, and turn back on the ones
+// that were toggled off before. Unlike 'disable', nothing gets saved. Returns how many borders were
+// turned off and on.
+pub(crate) fn toggle(query: &str) -> anyhow::Result<(usize, usize)> {
+    // A bad query should say so, instead of just matching nothing
+    WindowQuery::parse(query)?;
+
+    let bordered = find_borders(query).unwrap_or_default();
+    let toggled_off: Vec<HWND> = find_unbordered_windows(query)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|hwnd| state::is_toggled_off(*hwnd))
+        .collect();
+    if bordered.is_empty() && toggled_off.is_empty() {
+        return Err(anyhow!("no window matches '{query}'"));
+    }
+
+    for (tracking_window, _) in bordered.iter() {
+        state::set_toggled_off(*tracking_window, true);
+        destroy_border_for_window(*tracking_window);
+    }
+    for tracking_window in toggled_off.iter() {
+        state::set_toggled_off(*tracking_window, false);
+        create_border_for_window(*tracking_window);
+    }
+
+    Ok((bordered.len(), toggled_off.len()))
+}
+
+// A window picked by an IPC command
+enum WindowQuery {
+    All,
+    Focused,
+    Hwnd(isize),
+    Field(fn(HWND) -> String, String, MatchStrategy),
+}
+
+impl WindowQuery {
+    fn parse(query: &str) -> anyhow::Result<Self> {
+        if let Some((field, pattern)) = query.split_once(':') {
+            let (get_field, strategy): (fn(HWND) -> String, _) = match field {
+                "process" => (get_process_name, MatchStrategy::Equals),
+                "class" => (get_window_class, MatchStrategy::Equals),
+                "title" => (get_window_title, MatchStrategy::Contains),
+                _ => return Err(anyhow!("unknown window query '{field}'")),
+            };
+            return Ok(WindowQuery::Field(get_field, pattern.to_string(), strategy));
+        }
+
+        match query {
+            "all" => Ok(WindowQuery::All),
+            "focused" | "active" => Ok(WindowQuery::Focused),
+            query => match query.strip_prefix("0x") {
+                Some(hex) => isize::from_str_radix(hex, 16),
+                None => query.parse::<isize>(),
+            }
+            .map(WindowQuery::Hwnd)
+            .map_err(|_| anyhow!("invalid window query '{query}'")),
+        }
+    }
+
+    fn matches(&self, hwnd: HWND) -> bool {
+        match self {
+            WindowQuery::All => true,
+            WindowQuery::Focused => hwnd == unsafe { GetForegroundWindow() },
+            WindowQuery::Hwnd(query) => hwnd.0 as isize == *query,
+            WindowQuery::Field(get_field, pattern, strategy) => {
+                match_string(&get_field(hwnd), pattern, strategy)
+            }
+        }
+    }
+}
+
+// Find the (tracking window, border window) pairs that match a window query
+pub(crate) fn find_borders(query: &str) -> anyhow::Result<Vec<(HWND, HWND)>> {
+    let window_query = WindowQuery::parse(query)?;
+
+    // Copy the borders out first since getting window titles can block. Monitor frames and the
+    // cursor ring don't track a real window, so we leave them out.
+    let borders: Vec<(HWND, HWND)> = BORDERS
+        .entries()
+        .into_iter()
+        .filter_map(|(key, border)| Some((key.hwnd()?, border)))
+        .collect();
+
+    let matches: Vec<(HWND, HWND)> = borders
+        .into_iter()
+        .filter(|(tracking, _)| window_query.matches(*tracking))
+        .collect();
+
+    match matches.is_empty() {
+        true => Err(anyhow!("no bordered window matches '{query}'")),
+        false => Ok(matches),
+    }
+}
+
+// Find the windows without a border that match a window query, e.g. ones that were turned off
+fn find_unbordered_windows(query: &str) -> anyhow::Result<Vec<HWND>> {
+    let window_query = WindowQuery::parse(query)?;
+    let bordered: HashSet<BorderKey> = BORDERS.entries().into_iter().map(|(key, _)| key).collect();
+
+    let matches: Vec<HWND> = get_top_level_windows()
+        .into_iter()
+        .filter(|hwnd| {
+            !bordered.contains(&BorderKey::window(*hwnd))
+                && !has_filtered_style(*hwnd)
+                && is_window_visible(*hwnd)
+                && !is_cloaked(*hwnd)
+        })
+        .filter(|hwnd| window_query.matches(*hwnd))
+        .collect();
+
+    match matches.is_empty() {
+        true => Err(anyhow!("no window without a border matches '{query}'")),
+        false => Ok(matches),
+    }
+}
+
+// Destroy a border and create it again once its thread has cleaned up, so it picks up its rule's
+// options again
+fn recreate_border(tracking_window: HWND) {
+    destroy_border_for_window(tracking_window);
+
+    let window = SendHWND(tracking_window);
+    let _ = thread::spawn(move || {
+        let window_sent = window;
+        let key = BorderKey::window(window_sent.0);
+
+        for _ in 0..50 {
+            if !BORDERS.contains(key) {
+                create_border_for_window(window_sent.0);
+                return;
+            }
+            thread::sleep(time::Duration::from_millis(10));
+        }
+        error!("timed out recreating border for {:?}", window_sent.0);
+    });
+}
+
+// Quote an argument for split_args if it needs it
+fn quote_arg(arg: &str) -> String {
+    match arg.is_empty() || arg.contains(|c: char| c.is_whitespace() || c == '"') {
+        true => format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\"")),
+        false => arg.to_string(),
+    }
+}
+
+// Split a command line on whitespace, keeping "quoted strings" together. Inside quotes, \" and \\
+// are a quote and a backslash, and any other backslash is kept as it is (for paths).
+fn split_args(line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_arg = false;
+
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if in_quotes && matches!(chars.peek(), Some('"' | '\\')) => {
+                current.extend(chars.next());
+            }
+            '"' => {
+                in_quotes = !in_quotes;
+                has_arg = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if has_arg {
+                    args.push(std::mem::take(&mut current));
+                    has_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                has_arg = true;
+            }
+        }
+    }
+    if has_arg {
+        args.push(current);
+    }
+
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_args_table() {
+        let cases: [(&str, &[&str]); 12] = [
+            ("set-color focused red", &["set-color", "focused", "red"]),
+            ("  timer \t 5  ", &["timer", "5"]),
+            ("", &[]),
+            (
+                r#"set-color "title:My App" red"#,
+                &["set-color", "title:My App", "red"],
+            ),
+            (r#"a "" b"#, &["a", "", "b"]),
+            (r#""""#, &[""]),
+            (r#"a"b c"d"#, &["ab cd"]),
+            // A quote that's never closed runs to the end of the line
+            (r#"locate "title:a b"#, &["locate", "title:a b"]),
+            (r#""say \"hi\"""#, &[r#"say "hi""#]),
+            (r#""C:\dir\\""#, &[r"C:\dir\"]),
+            // Backslashes are only special before a quote or another backslash, and only in quotes
+            (r#""C:\dir\file""#, &[r"C:\dir\file"]),
+            (r"C:\dir\\file", &[r"C:\dir\\file"]),
+        ];
+
+        for (line, expected) in cases {
+            assert_eq!(split_args(line), expected, "{line}");
+        }
+    }
+
+    #[test]
+    fn quote_arg_round_trips() {
+        let args = [
+            "set-color",
+            "",
+            "title:My App",
+            r#"say "hi""#,
+            r"C:\dir\",
+            r#"C:\dir\"quoted""#,
+            "tab\there",
+        ]
+        .map(String::from);
+
+        let line = args
+            .iter()
+            .map(|arg| quote_arg(arg))
+            .collect::<Vec<_>>()
+            .join(" ");
+        assert_eq!(split_args(&line), args, "{line}");
+    }
+
+    #[test]
+    fn window_query_parse_table() {
+        assert!(matches!(WindowQuery::parse("all"), Ok(WindowQuery::All)));
+        assert!(matches!(
+            WindowQuery::parse("focused"),
+            Ok(WindowQuery::Focused)
+        ));
+        assert!(matches!(
+            WindowQuery::parse("active"),
+            Ok(WindowQuery::Focused)
+        ));
+
+        let hwnds = [
+            ("0x1a2b", 0x1a2b),
+            ("0x1A2B", 0x1a2b),
+            ("6699", 6699),
+            ("-1", -1),
+        ];
+        for (query, expected) in hwnds {
+            match WindowQuery::parse(query) {
+                Ok(WindowQuery::Hwnd(hwnd)) => assert_eq!(hwnd, expected, "{query}"),
+                _ => panic!("{query} should be an HWND"),
+            }
+        }
+
+        let fields = [
+            ("process:code.exe", "code.exe", true),
+            ("class:Chrome_WidgetWin_1", "Chrome_WidgetWin_1", true),
+            // Only the first ':' separates the field
+            ("title:a: b", "a: b", false),
+            ("title:", "", false),
+        ];
+        for (query, expected, equals) in fields {
+            match WindowQuery::parse(query) {
+                Ok(WindowQuery::Field(_, pattern, strategy)) => {
+                    assert_eq!(pattern, expected, "{query}");
+                    assert_eq!(matches!(strategy, MatchStrategy::Equals), equals, "{query}");
+                }
+                _ => panic!("{query} should be a field query"),
+            }
+        }
+
+        for query in [
+            "",
+            "pid:1234",
+            "Process:code.exe",
+            "0x",
+            "0xzz",
+            "12ab",
+            "focus",
+        ] {
+            assert!(WindowQuery::parse(query).is_err(), "{query}");
+        }
+    }
+}
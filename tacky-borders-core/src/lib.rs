@@ -0,0 +1,277 @@
+//! Border rendering for Windows, used by the tacky-borders app.
+//!
+//! This crate has everything needed to draw borders around other windows: the border window and
+//! its Direct2D renderer, the animation engine, and the rule engine that picks options for each
+//! window. The tacky-borders binary is just a tray icon and a message loop on top of it, so window
+//! managers, launchers, etc. can embed the same borders.
+//!
+//! Borders are driven by a WinEvent hook, which delivers events to the thread that installed it.
+//! That thread has to pump messages, e.g.:
+//!
+//! ```no_run
+//! use windows::Win32::Foundation::HWND;
+//! use windows::Win32::UI::WindowsAndMessaging::{DispatchMessageW, GetMessageW, TranslateMessage, MSG};
+//!
+//! tacky_borders_core::start();
+//!
+//! unsafe {
+//!     let mut message = MSG::default();
+//!     while GetMessageW(&mut message, HWND::default(), 0, 0).into() {
+//!         let _ = TranslateMessage(&message);
+//!         DispatchMessageW(&message);
+//!     }
+//! }
+//! ```
+//!
+//! Options are read from `~/.config/tacky-borders/config.yaml` by default (see
+//! [`border_config::Config::get_config_path`] for the other places it can be), and [`reload`]
+//! picks up any changes to it.
+
+#[macro_use]
+extern crate log;
+
+use anyhow::Context;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex, Once};
+use windows::core::w;
+use windows::Win32::Foundation::{GetLastError, BOOL, HINSTANCE, HWND, LPARAM, TRUE, WPARAM};
+use windows::Win32::System::SystemServices::IMAGE_DOS_HEADER;
+use windows::Win32::UI::WindowsAndMessaging::{
+    EnumWindows, LoadCursorW, RegisterClassExW, IDC_ARROW, WM_NCDESTROY, WNDCLASSEXW,
+};
+
+pub mod accessibility;
+mod anim_timer;
+pub mod animations;
+pub mod badges;
+pub mod border;
+pub mod border_config;
+pub mod colors;
+mod config_diff;
+pub mod crash;
+pub mod cursor_ring;
+mod d2d_renderer;
+pub mod do_not_disturb;
+pub mod edges;
+pub mod effects;
+mod event_hook;
+pub mod game_mode;
+mod gdi_renderer;
+pub mod hotkeys;
+pub mod ipc;
+pub mod lifecycle;
+mod migrations;
+pub mod monitor_frames;
+pub mod notifications;
+mod overlay;
+pub mod picker;
+pub mod plugins;
+pub mod presentation;
+pub mod progress;
+mod registry;
+pub mod renderer;
+mod retry;
+pub mod rule_engine;
+mod scripting;
+pub mod seasons;
+pub mod settings_window;
+mod shell_exclusion;
+mod shell_hook;
+pub mod simulation;
+pub mod state;
+mod strict;
+pub mod strokes;
+mod style_watch;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
+pub mod themes;
+pub mod timer;
+mod tracking;
+mod uia_tracker;
+pub mod utils;
+pub mod window_border;
+
+use crate::border_config::Config;
+use crate::registry::BORDERS;
+use crate::utils::*;
+
+extern "C" {
+    static __ImageBase: IMAGE_DOS_HEADER;
+}
+
+static INITIAL_WINDOWS: LazyLock<Mutex<Vec<isize>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+
+// Whether start() has installed the event hooks. Border::attach() uses this to decide whether it
+// needs a hook of its own.
+static EVENT_HOOK_INSTALLED: AtomicBool = AtomicBool::new(false);
+
+static WINDOW_CLASS: Once = Once::new();
+
+// This is used to send HWNDs across threads even though HWND doesn't implement Send and Sync.
+struct SendHWND(HWND);
+unsafe impl Send for SendHWND {}
+unsafe impl Sync for SendHWND {}
+
+/// Installs the event hooks on the calling thread, registers the border window class, creates
+/// borders for every window that's already open, and starts listening for [`ipc`] commands and
+/// hotkeys.
+///
+/// The calling thread must run a message loop afterwards, or no events will be delivered.
+pub fn start() {
+    tracking::start();
+    EVENT_HOOK_INSTALLED.store(true, Ordering::SeqCst);
+    border::remove_attach_hook();
+    register_window_class_once();
+    // Borders need to know whether do not disturb is on before they're created
+    do_not_disturb::start();
+    log_if_err!(enum_windows());
+    monitor_frames::create_frames();
+    cursor_ring::start();
+    game_mode::start();
+    presentation::start();
+    seasons::start();
+    style_watch::start();
+    ipc::start_server();
+    hotkeys::start();
+}
+
+/// Removes the event hooks installed by [`start`] (and stops whatever else tracks windows, like
+/// the shell hook or UI Automation). Must be called from the same thread.
+///
+/// Returns false if any of the hooks could not be removed.
+pub fn stop() -> bool {
+    let unhooked = tracking::stop();
+    if unhooked {
+        EVENT_HOOK_INSTALLED.store(false, Ordering::SeqCst);
+    }
+    unhooked
+}
+
+/// Re-reads the config file and applies the new options. Borders are only recreated if their
+/// options changed and can't be updated in place, so e.g. a new color doesn't restart every
+/// border's animations.
+pub fn reload() {
+    let snapshot = config_diff::snapshot();
+    Config::reload_config();
+    plugins::forget_failed_plugins();
+    config_diff::apply(snapshot);
+    hotkeys::reload();
+}
+
+/// Changes the loaded config without touching the config file, and recreates the borders with the
+/// change. It lasts until the next [`reload`], which reads the file again.
+pub fn update_config(f: impl FnOnce(&mut Config)) {
+    f(&mut border_config::CONFIG.lock().unwrap());
+    reload_borders();
+}
+
+/// How many borders are running in safe mode, i.e. drawn with plain GDI because Direct2D kept
+/// failing for them.
+pub fn safe_mode_count() -> usize {
+    renderer::safe_mode_count()
+}
+
+/// How many open windows have no border because creating it kept failing, even after retrying.
+pub fn failed_border_count() -> usize {
+    retry::failed_count()
+}
+
+// Both start() and Border::attach() need the window class, so it's only registered by whichever
+// gets called first
+fn register_window_class_once() {
+    WINDOW_CLASS.call_once(|| log_if_err!(register_window_class()));
+}
+
+fn register_window_class() -> windows::core::Result<()> {
+    unsafe {
+        let hinstance: HINSTANCE = std::mem::transmute(&__ImageBase);
+
+        let window_class = WNDCLASSEXW {
+            cbSize: size_of::<WNDCLASSEXW>() as u32,
+            lpfnWndProc: Some(window_border::WindowBorder::s_wnd_proc),
+            hInstance: hinstance,
+            lpszClassName: w!("border"),
+            hCursor: LoadCursorW(None, IDC_ARROW)?,
+            ..Default::default()
+        };
+
+        let result = RegisterClassExW(&window_class);
+        if result == 0 {
+            let last_error = GetLastError();
+            error!("could not register window class: {last_error:?}");
+        }
+    }
+
+    Ok(())
+}
+
+fn enum_windows() -> windows::core::Result<()> {
+    unsafe {
+        EnumWindows(Some(enum_windows_callback), LPARAM::default())?;
+    }
+    debug!(
+        "windows have been enumerated! adopted {} existing windows",
+        INITIAL_WINDOWS.lock().unwrap().len()
+    );
+    Ok(())
+}
+
+fn reload_borders() {
+    // Send destroy messages to all the border windows
+    for border_window in BORDERS.take_all() {
+        log_if_err!(
+            post_message_w(border_window, WM_NCDESTROY, WPARAM(0), LPARAM(0))
+                .context("reload_borders")
+        );
+    }
+
+    // Clear the initial windows list
+    INITIAL_WINDOWS.lock().unwrap().clear();
+    retry::reset();
+
+    // Borders come back once whatever paused them is over
+    if is_suspended() {
+        return;
+    }
+
+    log_if_err!(enum_windows());
+    monitor_frames::create_frames();
+    cursor_ring::start();
+}
+
+// Whether every border is paused, e.g. for do not disturb. Game mode only hides the borders on one
+// monitor, so it doesn't count.
+fn is_suspended() -> bool {
+    do_not_disturb::is_paused() || presentation::is_paused()
+}
+
+unsafe extern "system" fn enum_windows_callback(_hwnd: HWND, _lparam: LPARAM) -> BOOL {
+    adopt_window(_hwnd);
+    TRUE
+}
+
+// Give a window that's already open its border, without the initialize delay
+fn adopt_window(_hwnd: HWND) {
+    if !has_filtered_style(_hwnd) {
+        // Add currently open windows to the intial windows list so we can keep track of them. This
+        // has to happen before the border thread is spawned, otherwise it can race us and apply
+        // the initialize delay to a window that's already open.
+        let mut initial_windows = INITIAL_WINDOWS.lock().unwrap();
+        if !initial_windows.contains(&(_hwnd.0 as isize)) {
+            initial_windows.push(_hwnd.0 as isize);
+        }
+        drop(initial_windows);
+
+        // Hidden and cloaked windows (e.g. on other virtual desktops) get their border once they
+        // are shown, and since they're in the list above, it shows up without a delay
+        if is_window_visible(_hwnd) && !is_cloaked(_hwnd) && !is_frameless_fullscreen(_hwnd) {
+            create_border_for_window(_hwnd);
+        }
+    } else if is_tool_window(_hwnd) && is_window_visible(_hwnd) && !is_cloaked(_hwnd) {
+        // Tool windows are usually above their owner in the z-order, so the owner's border may not
+        // exist yet. That's fine since get_group_leader() only needs the owner itself.
+        if let Some(leader) = get_group_leader(_hwnd) {
+            create_group_border_for_window(_hwnd, leader);
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! The lifecycle every border goes through, as a state machine:
+//!
+//! ```text
+//! Creating → Visible → Focused ⇄ Unfocused → Hiding → Visible → ...
+//! ```
+//!
+//! Borders can be destroyed from any state. Each transition can run an animation, which is set in
+//! the `animations` section's `transitions` list. Borders are hidden as soon as they enter
+//! `Hiding`, so only transitions into `Visible`, `Focused`, and `Unfocused` get animated.
+
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_yaml::Value;
+
+use crate::animations::{self, AnimationType};
+
+/// Where a border is in its lifecycle.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LifecycleState {
+    /// The border window exists but hasn't been shown yet.
+    #[default]
+    Creating,
+    /// The border is appearing. It settles into `Focused` or `Unfocused` once the transition's
+    /// animation is done.
+    Visible,
+    Focused,
+    Unfocused,
+    /// The tracking window was minimized, cloaked, or hidden (e.g. for Task View).
+    Hiding,
+    Destroyed,
+}
+
+impl LifecycleState {
+    /// Whether a border can go straight from this state to `to`.
+    pub fn can_transition_to(self, to: LifecycleState) -> bool {
+        use LifecycleState::*;
+
+        match (self, to) {
+            (Destroyed, _) => false,
+            (_, Destroyed) => true,
+            (Creating | Hiding, Visible) => true,
+            (Visible | Focused | Unfocused, Focused | Unfocused) => true,
+            (Creating | Visible | Focused | Unfocused, Hiding) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the border is on screen in this state.
+    pub fn is_shown(self) -> bool {
+        matches!(
+            self,
+            LifecycleState::Visible | LifecycleState::Focused | LifecycleState::Unfocused
+        )
+    }
+}
+
+/// The animation a transition runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransitionAnimation {
+    /// Snap straight to the new colors.
+    #[serde(alias = "none")]
+    None,
+    /// Fade to the new colors (or in from nothing, when the border becomes visible).
+    #[serde(alias = "fade")]
+    Fade,
+}
+
+/// An animation to run when a border goes from one state to another.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransitionHook {
+    /// The state to come from. Any state matches if this is left out.
+    #[serde(default)]
+    pub from: Option<LifecycleState>,
+    pub to: LifecycleState,
+    pub animation: TransitionAnimation,
+    /// Works like the Fade speed in `active` and `inactive`, presets and durations included.
+    /// Defaults to the Fade animation's speed (or 200 if there is none).
+    #[serde(default, deserialize_with = "fade_speed")]
+    pub speed: Option<f32>,
+}
+
+fn fade_speed<'de, D>(deserializer: D) -> Result<Option<f32>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Value::deserialize(deserializer)? {
+        Value::Number(n) => Ok(n.as_f64().map(|f| f as f32)),
+        Value::String(s) => animations::parse_speed(&AnimationType::Fade, &s)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+        _ => Ok(None),
+    }
+}
+
+impl TransitionHook {
+    fn matches(&self, from: LifecycleState, to: LifecycleState) -> bool {
+        self.to == to && self.from.is_none_or(|hook_from| hook_from == from)
+    }
+}
+
+/// A transition's animation, with the speed it runs at.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RunningTransition {
+    pub animation: TransitionAnimation,
+    pub speed: f32,
+}
+
+// Hooks that name the state they come from win over the ones that don't
+pub(crate) fn find_hook(
+    hooks: &[TransitionHook],
+    from: LifecycleState,
+    to: LifecycleState,
+) -> Option<&TransitionHook> {
+    hooks
+        .iter()
+        .find(|hook| hook.from.is_some() && hook.matches(from, to))
+        .or_else(|| hooks.iter().find(|hook| hook.matches(from, to)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use LifecycleState::*;
+
+    fn hook(from: Option<LifecycleState>, to: LifecycleState, speed: f32) -> TransitionHook {
+        TransitionHook {
+            from,
+            to,
+            animation: TransitionAnimation::Fade,
+            speed: Some(speed),
+        }
+    }
+
+    #[test]
+    fn can_transition_to_table() {
+        let cases = [
+            (Creating, Visible, true),
+            (Creating, Hiding, true),
+            (Creating, Focused, false),
+            (Creating, Destroyed, true),
+            (Visible, Focused, true),
+            (Visible, Unfocused, true),
+            (Visible, Visible, false),
+            (Visible, Creating, false),
+            (Focused, Unfocused, true),
+            (Unfocused, Focused, true),
+            (Focused, Focused, true),
+            (Focused, Hiding, true),
+            (Focused, Visible, false),
+            (Hiding, Visible, true),
+            (Hiding, Focused, false),
+            (Hiding, Hiding, false),
+            (Hiding, Destroyed, true),
+            (Destroyed, Visible, false),
+            (Destroyed, Destroyed, false),
+        ];
+
+        for (from, to, expected) in cases {
+            assert_eq!(from.can_transition_to(to), expected, "{from:?} -> {to:?}");
+        }
+    }
+
+    #[test]
+    fn find_hook_prefers_named_states() {
+        let hooks = [
+            hook(None, Focused, 1.0),
+            hook(Some(Hiding), Visible, 2.0),
+            hook(Some(Unfocused), Focused, 3.0),
+            hook(None, Visible, 4.0),
+        ];
+
+        let cases = [
+            // Hooks that name their state win, even when they're written after one that doesn't
+            (Unfocused, Focused, Some(3.0)),
+            (Visible, Focused, Some(1.0)),
+            (Hiding, Visible, Some(2.0)),
+            (Creating, Visible, Some(4.0)),
+            (Focused, Unfocused, None),
+            (Focused, Hiding, None),
+        ];
+
+        for (from, to, expected) in cases {
+            let speed = super::find_hook(&hooks, from, to).and_then(|hook| hook.speed);
+            assert_eq!(speed, expected, "{from:?} -> {to:?}");
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use anyhow::anyhow;
+use serde_yaml::{Mapping, Value};
+
+// Bump this (and add a migration below) whenever a config option is renamed or restructured. Configs
+// without a 'version' key are from before versioning, which we call version 1.
+pub const CURRENT_VERSION: u32 = 2;
+
+struct Migration {
+    // The version this migration upgrades the config to, from the one before it
+    to: u32,
+    // Returns a description of every change it made
+    apply: fn(&mut Mapping) -> Vec<String>,
+}
+
+const MIGRATIONS: &[Migration] = &[Migration {
+    to: 2,
+    apply: migrate_to_v2,
+}];
+
+// Upgrade the config to CURRENT_VERSION in place, returning a description of every change. Configs
+// from a newer version are left alone, since we have no idea what changed.
+pub fn migrate(config: &mut Value) -> anyhow::Result<Vec<String>> {
+    let Some(root) = config.as_mapping_mut() else {
+        return Err(anyhow!("expected the config to be a mapping of options"));
+    };
+
+    let version = match root.get("version") {
+        None => 1,
+        Some(value) => match value.as_u64() {
+            Some(version) => version as u32,
+            None => {
+                return Err(anyhow!(
+                    "expected 'version' to be a number, found {value:?}"
+                ))
+            }
+        },
+    };
+
+    if version > CURRENT_VERSION {
+        warn!(
+            "config is from a newer version of tacky-borders (version {version}, expected \
+             {CURRENT_VERSION} or lower); some options may not work"
+        );
+        return Ok(Vec::new());
+    }
+
+    let mut changes = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|migration| migration.to > version) {
+        changes.extend(
+            (migration.apply)(root)
+                .into_iter()
+                .map(|change| format!("(version {}) {change}", migration.to)),
+        );
+    }
+
+    root.insert(Value::from("version"), Value::from(CURRENT_VERSION));
+
+    Ok(changes)
+}
+
+// Version 1 is the config format from before versioning, which already took 'init_delay' and
+// 'restore_delay' as short names for the delays. Version 2 only writes the long names, so a
+// rewritten config doesn't end up with both spellings.
+fn migrate_to_v2(root: &mut Mapping) -> Vec<String> {
+    const RENAMED: &[(&str, &str)] = &[
+        ("init_delay", "initialize_delay"),
+        ("restore_delay", "unminimize_delay"),
+    ];
+
+    let mut changes = Vec::new();
+
+    let mut migrate_section = |section: &mut Mapping, path: &str| {
+        for (old_key, new_key) in RENAMED {
+            rename_key(section, path, old_key, new_key, &mut changes);
+        }
+    };
+
+    if let Some(global) = root.get_mut("global").and_then(Value::as_mapping_mut) {
+        migrate_section(global, "global");
+    }
+
+    if let Some(rules) = root
+        .get_mut("window_rules")
+        .and_then(Value::as_sequence_mut)
+    {
+        for (i, rule) in rules.iter_mut().enumerate() {
+            if let Some(rule) = rule.as_mapping_mut() {
+                migrate_section(rule, &format!("window_rules[{i}]"));
+            }
+        }
+    }
+
+    changes
+}
+
+fn rename_key(
+    section: &mut Mapping,
+    path: &str,
+    old_key: &str,
+    new_key: &str,
+    changes: &mut Vec<String>,
+) {
+    let Some(value) = section.remove(old_key) else {
+        return;
+    };
+
+    // Having both used to be a duplicate field error, so just keep the one with the new name
+    match section.contains_key(new_key) {
+        true => changes.push(format!(
+            "removed {path}.{old_key} since {path}.{new_key} is also set"
+        )),
+        false => {
+            section.insert(Value::from(new_key), value);
+            changes.push(format!("renamed {path}.{old_key} to {path}.{new_key}"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::border_config::Config;
+
+    // The parts of the default config from before versioning that migrations care about, plus a
+    // rule that uses the short delay names
+    const V1_CONFIG: &str = "
+global:
+  border_width: 3
+  border_offset: -1
+  border_radius: -1
+  active_color:
+    colors: ['#6274e7', '#8752a3']
+    direction: 45deg
+  inactive_color: '#30304f'
+  init_delay: 200
+  restore_delay: 150
+  animations:
+    active: { ReverseSpiral, Fade }
+    inactive: { Spiral: 100, Fade: 100 }
+    fps: 60
+window_rules:
+  - match: 'Class'
+    name: 'Windows.UI.Core.CoreWindow'
+    enabled: false
+  - match: 'Title'
+    strategy: 'Contains'
+    name: 'Firefox'
+    init_delay: 0
+    restore_delay: 0
+";
+
+    const V2_CONFIG: &str = "
+global:
+  border_width: 3
+  border_offset: -1
+  border_radius: -1
+  active_color:
+    colors: ['#6274e7', '#8752a3']
+    direction: 45deg
+  inactive_color: '#30304f'
+  initialize_delay: 200
+  unminimize_delay: 150
+  animations:
+    active: { ReverseSpiral, Fade }
+    inactive: { Spiral: 100, Fade: 100 }
+    fps: 60
+window_rules:
+  - match: 'Class'
+    name: 'Windows.UI.Core.CoreWindow'
+    enabled: false
+  - match: 'Title'
+    strategy: 'Contains'
+    name: 'Firefox'
+    initialize_delay: 0
+    unminimize_delay: 0
+version: 2
+";
+
+    fn migrated(contents: &str) -> (Value, Vec<String>) {
+        let mut config: Value = serde_yaml::from_str(contents).unwrap();
+        let changes = migrate(&mut config).unwrap();
+        (config, changes)
+    }
+
+    #[test]
+    fn v1_config_migrates_to_v2() {
+        let (config, changes) = migrated(V1_CONFIG);
+        let expected: Value = serde_yaml::from_str(V2_CONFIG).unwrap();
+        assert_eq!(config, expected);
+        assert_eq!(
+            changes,
+            [
+                "(version 2) renamed global.init_delay to global.initialize_delay",
+                "(version 2) renamed global.restore_delay to global.unminimize_delay",
+                "(version 2) renamed window_rules[1].init_delay to window_rules[1].initialize_delay",
+                "(version 2) renamed window_rules[1].restore_delay to window_rules[1].unminimize_delay",
+            ]
+        );
+
+        let config: Config = serde_yaml::from_value(config).unwrap();
+        assert_eq!(config.global.initialize_delay, Some(200));
+        assert_eq!(config.window_rules[1].unminimize_delay, Some(0));
+    }
+
+    #[test]
+    fn v2_config_is_left_alone() {
+        let (config, changes) = migrated(V2_CONFIG);
+        let expected: Value = serde_yaml::from_str(V2_CONFIG).unwrap();
+        assert_eq!(config, expected);
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn new_name_wins_over_old_name() {
+        let (config, changes) = migrated("global: { init_delay: 200, initialize_delay: 50 }");
+        assert_eq!(config["global"]["initialize_delay"], Value::from(50));
+        assert!(config["global"].get("init_delay").is_none());
+        assert_eq!(
+            changes,
+            ["(version 2) removed global.init_delay since global.initialize_delay is also set"]
+        );
+    }
+
+    #[test]
+    fn newer_config_is_left_alone() {
+        let (config, changes) = migrated("version: 3\nglobal: { init_delay: 200 }");
+        assert_eq!(config["version"], Value::from(3));
+        assert_eq!(config["global"]["init_delay"], Value::from(200));
+        assert!(changes.is_empty());
+    }
+}
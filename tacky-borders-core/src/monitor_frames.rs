@@ -0,0 +1,144 @@
+//! Decorative frames drawn around each monitor's work area. They aren't tied to any window, so
+//! they're stored in the border registry under a key made from the monitor's handle instead.
+//!
+//! Frames are configured with the `monitor_frames` section of the config (see
+//! [`MonitorFrameConfig`]). If it has an `active_color`, the frame around the active monitor (see
+//! [`ActiveMonitor`]) uses that instead, so multi-monitor setups can see which display has focus.
+
+use serde::{Deserialize, Serialize};
+use std::thread;
+use windows::Win32::Foundation::{HWND, POINT};
+use windows::Win32::Graphics::Gdi::{
+    MonitorFromPoint, MonitorFromWindow, HMONITOR, MONITOR_DEFAULTTONEAREST,
+};
+use windows::Win32::UI::HiDpi::{GetDpiForMonitor, MDT_EFFECTIVE_DPI};
+use windows::Win32::UI::WindowsAndMessaging::{GetCursorPos, GetForegroundWindow};
+
+use crate::animations::Animations;
+use crate::border_config::{BorderPlacement, CONFIG};
+use crate::colors::ColorConfig;
+use crate::registry::{BorderKey, BORDERS};
+use crate::utils::*;
+use crate::window_border::WindowBorder;
+use crate::SendHWND;
+
+/// The `monitor_frames` section of the config.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonitorFrameConfig {
+    #[serde(default = "default_width")]
+    pub border_width: f32,
+    #[serde(default)]
+    pub border_radius: f32,
+    pub color: ColorConfig,
+    // The color of the frame around the active monitor. Leave it out to draw every frame the same.
+    pub active_color: Option<ColorConfig>,
+    #[serde(default)]
+    pub active_monitor: ActiveMonitor,
+    pub animations: Option<Animations>,
+}
+
+/// How the active monitor is picked for `active_color`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ActiveMonitor {
+    /// The monitor with the focused window on it.
+    #[default]
+    #[serde(alias = "focus")]
+    Focus,
+    /// The monitor with the mouse cursor on it.
+    #[serde(alias = "cursor")]
+    Cursor,
+}
+
+impl ActiveMonitor {
+    pub fn current(&self) -> HMONITOR {
+        unsafe {
+            match self {
+                ActiveMonitor::Focus => {
+                    MonitorFromWindow(GetForegroundWindow(), MONITOR_DEFAULTTONEAREST)
+                }
+                ActiveMonitor::Cursor => {
+                    let mut point = POINT::default();
+                    let _ = GetCursorPos(&mut point);
+                    MonitorFromPoint(point, MONITOR_DEFAULTTONEAREST)
+                }
+            }
+        }
+    }
+}
+
+fn default_width() -> f32 {
+    2.0
+}
+
+// Stands in for the frame's tracking window, which it doesn't have. The registry knows frames by
+// their monitor (see BorderKey::Monitor), so this is never looked up.
+fn frame_tracking_window(monitor: HMONITOR) -> HWND {
+    HWND(-(monitor.0 as isize) as _)
+}
+
+/// Creates a frame for every monitor that doesn't have one yet. Does nothing if `monitor_frames`
+/// isn't in the config.
+pub fn create_frames() {
+    if CONFIG.lock().unwrap().monitor_frames.is_none() {
+        return;
+    }
+
+    for monitor in get_monitors() {
+        if BORDERS.contains(BorderKey::monitor(monitor)) {
+            continue;
+        }
+
+        // HMONITOR isn't Send either, so we smuggle it across as an HWND
+        let monitor = SendHWND(HWND(monitor.0));
+        let _ = thread::spawn(move || {
+            let monitor_sent = monitor;
+            let monitor = HMONITOR(monitor_sent.0 .0);
+
+            match create_frame_struct(monitor) {
+                Ok(frame) => run_border(frame, None),
+                Err(e) => error!("could not create monitor frame: {e}"),
+            }
+        });
+    }
+}
+
+fn create_frame_struct(monitor: HMONITOR) -> anyhow::Result<WindowBorder> {
+    let config = CONFIG.lock().unwrap();
+    let Some(frame_config) = config.monitor_frames.clone() else {
+        return Err(anyhow::anyhow!("monitor_frames is not configured"));
+    };
+    let renderer_backend = config.global.renderer.unwrap_or_default();
+    drop(config);
+
+    let (mut dpi_x, mut dpi_y) = (0, 0);
+    unsafe { GetDpiForMonitor(monitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y) }?;
+    let dpi = dpi_x as f32;
+
+    let border_width = (frame_config.border_width * dpi / 96.0) as i32;
+    let animations = frame_config.animations.unwrap_or_default();
+
+    Ok(WindowBorder {
+        tracking_window: frame_tracking_window(monitor),
+        monitor: Some(monitor),
+        border_width,
+        border_radius: frame_config.border_radius * dpi / 96.0,
+        // The frame sits inside the work area so it doesn't spill onto other monitors
+        placement: BorderPlacement::Inner,
+        monitor_highlight: frame_config
+            .active_color
+            .is_some()
+            .then_some(frame_config.active_monitor),
+        active_color: frame_config
+            .active_color
+            .as_ref()
+            .unwrap_or(&frame_config.color)
+            .convert_to_color(true),
+        inactive_color: frame_config.color.convert_to_color(false),
+        comet_color: animations.comet.color(),
+        animations,
+        renderer_backend,
+        antialias: true,
+        supersampling: 1,
+        ..Default::default()
+    })
+}
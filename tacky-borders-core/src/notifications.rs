@@ -0,0 +1,258 @@
+//! Small popups in the corner of the screen for things the user should know about without having to
+//! dig through the log, like the config failing to reload.
+//!
+//! Which popups show up is controlled by the `notifications` option (see [`NotificationLevel`]).
+//! Only one popup is shown at a time; a new one replaces the old one. Popups go away on their own
+//! after a few seconds, or when they're clicked.
+
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicIsize, AtomicU8, Ordering};
+use std::sync::Once;
+use std::thread;
+use windows::core::w;
+use windows::Win32::Foundation::{COLORREF, HINSTANCE, HWND, LPARAM, LRESULT, POINT, RECT, WPARAM};
+use windows::Win32::Graphics::Gdi::{
+    BeginPaint, CreateSolidBrush, DeleteObject, DrawTextW, EndPaint, FillRect, GetStockObject,
+    MonitorFromPoint, SelectObject, SetBkMode, SetTextColor, DEFAULT_GUI_FONT, DT_END_ELLIPSIS,
+    DT_SINGLELINE, DT_WORDBREAK, MONITOR_DEFAULTTOPRIMARY, PAINTSTRUCT, TRANSPARENT,
+};
+use windows::Win32::UI::HiDpi::GetDpiForSystem;
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW, GetClientRect, GetMessageW,
+    LoadCursorW, PostQuitMessage, RegisterClassExW, SetTimer, ShowWindow, TranslateMessage,
+    IDC_ARROW, MSG, SW_SHOWNOACTIVATE, WM_CLOSE, WM_DESTROY, WM_LBUTTONUP, WM_PAINT, WM_TIMER,
+    WNDCLASSEXW, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW, WS_EX_TOPMOST, WS_POPUP,
+};
+
+use crate::log_if_err;
+use crate::utils::*;
+
+/// The `notifications` option.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum NotificationLevel {
+    /// Only show errors, like the config failing to load.
+    #[default]
+    #[serde(alias = "errors_only", alias = "ErrorsOnly")]
+    Errors,
+    /// Also show things like the config reloading successfully.
+    #[serde(alias = "all")]
+    All,
+    #[serde(alias = "off")]
+    Off,
+}
+
+/// How important a notification is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NotificationKind {
+    Info,
+    Error,
+    /// The answer to something the user just asked for (like picking a window), which shows up
+    /// unless notifications are off.
+    Result,
+}
+
+// The level from the last config that loaded, as a NotificationLevel discriminant. This can't just
+// read CONFIG, since we show notifications while it's being loaded.
+static LEVEL: AtomicU8 = AtomicU8::new(NotificationLevel::Errors as u8);
+
+// The popup that's currently showing, if any
+static CURRENT_POPUP: AtomicIsize = AtomicIsize::new(0);
+
+static POPUP_CLASS: Once = Once::new();
+
+const POPUP_WIDTH: i32 = 360;
+const POPUP_HEIGHT: i32 = 84;
+const POPUP_MARGIN: i32 = 12;
+const POPUP_TIMER_ID: usize = 1;
+const INFO_DURATION_MS: u32 = 3000;
+const ERROR_DURATION_MS: u32 = 8000;
+
+thread_local! {
+    // Each popup has its own thread, so this is the text for that thread's popup
+    static POPUP_TEXT: RefCell<(NotificationKind, Vec<u16>, Vec<u16>)> =
+        const { RefCell::new((NotificationKind::Info, Vec::new(), Vec::new())) };
+}
+
+/// Sets which notifications are shown. This is called whenever the config is loaded.
+pub fn set_level(level: NotificationLevel) {
+    LEVEL.store(level as u8, Ordering::SeqCst);
+}
+
+fn should_show(kind: NotificationKind) -> bool {
+    let level = LEVEL.load(Ordering::SeqCst);
+    match kind {
+        NotificationKind::Info => level == NotificationLevel::All as u8,
+        NotificationKind::Error | NotificationKind::Result => level != NotificationLevel::Off as u8,
+    }
+}
+
+/// Shows a popup, unless the `notifications` option says not to. Returns right away.
+pub fn notify(kind: NotificationKind, title: &str, message: &str) {
+    if !should_show(kind) {
+        return;
+    }
+
+    let to_wide = |text: &str| text.encode_utf16().collect::<Vec<u16>>();
+    let title = to_wide(title);
+    let message = to_wide(message);
+
+    let _ = thread::spawn(move || {
+        POPUP_TEXT.replace((kind, title, message));
+        log_if_err!(show_popup(kind));
+    });
+}
+
+fn show_popup(kind: NotificationKind) -> anyhow::Result<()> {
+    register_popup_class_once();
+
+    unsafe {
+        let hinstance: HINSTANCE = std::mem::transmute(&crate::__ImageBase);
+
+        // Popups go in the bottom-right corner of the primary monitor, above the taskbar
+        let scale = |value: i32| value * GetDpiForSystem() as i32 / 96;
+        let monitor = MonitorFromPoint(POINT::default(), MONITOR_DEFAULTTOPRIMARY);
+        let work_area = get_monitor_work_area(monitor).unwrap_or_default();
+        let (width, height) = (scale(POPUP_WIDTH), scale(POPUP_HEIGHT));
+
+        let popup = CreateWindowExW(
+            WS_EX_TOPMOST | WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE,
+            w!("tacky-borders-popup"),
+            w!("tacky-borders"),
+            WS_POPUP,
+            work_area.right - width - scale(POPUP_MARGIN),
+            work_area.bottom - height - scale(POPUP_MARGIN),
+            width,
+            height,
+            None,
+            None,
+            hinstance,
+            None,
+        )?;
+
+        // Replace whatever popup was showing before
+        let previous = CURRENT_POPUP.swap(popup.0 as isize, Ordering::SeqCst);
+        if previous != 0 {
+            let _ = post_message_w(HWND(previous as _), WM_CLOSE, WPARAM(0), LPARAM(0));
+        }
+
+        let duration = match kind {
+            NotificationKind::Info => INFO_DURATION_MS,
+            NotificationKind::Error | NotificationKind::Result => ERROR_DURATION_MS,
+        };
+        SetTimer(popup, POPUP_TIMER_ID, duration, None);
+        let _ = ShowWindow(popup, SW_SHOWNOACTIVATE);
+
+        let mut message = MSG::default();
+        while GetMessageW(&mut message, HWND::default(), 0, 0).into() {
+            let _ = TranslateMessage(&message);
+            DispatchMessageW(&message);
+        }
+
+        let _ =
+            CURRENT_POPUP.compare_exchange(popup.0 as isize, 0, Ordering::SeqCst, Ordering::SeqCst);
+    }
+
+    Ok(())
+}
+
+fn register_popup_class_once() {
+    POPUP_CLASS.call_once(|| unsafe {
+        let hinstance: HINSTANCE = std::mem::transmute(&crate::__ImageBase);
+
+        let window_class = WNDCLASSEXW {
+            cbSize: size_of::<WNDCLASSEXW>() as u32,
+            lpfnWndProc: Some(popup_wnd_proc),
+            hInstance: hinstance,
+            lpszClassName: w!("tacky-borders-popup"),
+            hCursor: LoadCursorW(None, IDC_ARROW).unwrap_or_default(),
+            ..Default::default()
+        };
+
+        if RegisterClassExW(&window_class) == 0 {
+            error!("could not register popup window class");
+        }
+    });
+}
+
+unsafe extern "system" fn popup_wnd_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    match message {
+        WM_PAINT => {
+            paint_popup(window);
+            LRESULT(0)
+        }
+        WM_TIMER | WM_LBUTTONUP | WM_CLOSE => {
+            let _ = DestroyWindow(window);
+            LRESULT(0)
+        }
+        WM_DESTROY => {
+            PostQuitMessage(0);
+            LRESULT(0)
+        }
+        _ => DefWindowProcW(window, message, wparam, lparam),
+    }
+}
+
+unsafe fn paint_popup(window: HWND) {
+    let mut paint = PAINTSTRUCT::default();
+    let hdc = BeginPaint(window, &mut paint);
+
+    let mut client_rect = RECT::default();
+    let _ = GetClientRect(window, &mut client_rect);
+
+    // COLORREFs are 0x00BBGGRR
+    let (background, accent) = POPUP_TEXT.with_borrow(|(kind, _, _)| match kind {
+        NotificationKind::Info | NotificationKind::Result => (0x00302b2b, 0x00e7a46f),
+        NotificationKind::Error => (0x00302b2b, 0x007b70f3),
+    });
+
+    let background_brush = CreateSolidBrush(COLORREF(background));
+    FillRect(hdc, &client_rect, background_brush);
+    let _ = DeleteObject(background_brush);
+
+    // A strip down the left side that says what kind of notification this is
+    let scale = |value: i32| value * GetDpiForSystem() as i32 / 96;
+    let accent_rect = RECT {
+        right: client_rect.left + scale(4),
+        ..client_rect
+    };
+    let accent_brush = CreateSolidBrush(COLORREF(accent));
+    FillRect(hdc, &accent_rect, accent_brush);
+    let _ = DeleteObject(accent_brush);
+
+    SelectObject(hdc, GetStockObject(DEFAULT_GUI_FONT));
+    SetBkMode(hdc, TRANSPARENT);
+
+    let padding = scale(12);
+    let mut title_rect = RECT {
+        left: client_rect.left + padding + scale(4),
+        top: client_rect.top + padding,
+        right: client_rect.right - padding,
+        bottom: client_rect.top + padding + scale(18),
+    };
+    let mut message_rect = RECT {
+        top: title_rect.bottom + scale(4),
+        bottom: client_rect.bottom - padding,
+        ..title_rect
+    };
+
+    POPUP_TEXT.with_borrow_mut(|(_, title, message)| {
+        SetTextColor(hdc, COLORREF(0x00ffffff));
+        DrawTextW(hdc, title, &mut title_rect, DT_SINGLELINE | DT_END_ELLIPSIS);
+
+        SetTextColor(hdc, COLORREF(0x00d0d0d0));
+        DrawTextW(
+            hdc,
+            message,
+            &mut message_rect,
+            DT_WORDBREAK | DT_END_ELLIPSIS,
+        );
+    });
+
+    let _ = EndPaint(window, &paint);
+}
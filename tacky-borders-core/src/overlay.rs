@@ -0,0 +1,526 @@
+use anyhow::{anyhow, Context};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::mem::ManuallyDrop;
+use std::sync::{mpsc, LazyLock, Mutex, Once};
+use std::thread;
+use windows::core::w;
+use windows::Foundation::Numerics::Matrix3x2;
+use windows::Win32::Foundation::{
+    D2DERR_RECREATE_TARGET, HINSTANCE, HWND, LPARAM, LRESULT, RECT, WPARAM,
+};
+use windows::Win32::Graphics::Direct2D::Common::{D2D_RECT_F, D2D_SIZE_U};
+use windows::Win32::Graphics::Direct2D::{
+    ID2D1HwndRenderTarget, ID2D1Layer, D2D1_ANTIALIAS_MODE_ALIASED,
+    D2D1_ANTIALIAS_MODE_PER_PRIMITIVE, D2D1_BRUSH_PROPERTIES, D2D1_LAYER_OPTIONS_NONE,
+    D2D1_LAYER_PARAMETERS, D2D1_ROUNDED_RECT,
+};
+use windows::Win32::Graphics::Gdi::{
+    MonitorFromRect, ValidateRect, HMONITOR, MONITOR_DEFAULTTONEAREST,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW, GetMessageW, LoadCursorW,
+    PostQuitMessage, RegisterClassExW, SetWindowPos, ShowWindow, TranslateMessage, HWND_TOPMOST,
+    IDC_ARROW, MSG, SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOSIZE, SW_SHOWNA, WM_DESTROY,
+    WM_DISPLAYCHANGE, WM_PAINT, WNDCLASSEXW, WS_DISABLED, WS_EX_LAYERED, WS_EX_NOACTIVATE,
+    WS_EX_TOOLWINDOW, WS_EX_TOPMOST, WS_EX_TRANSPARENT, WS_POPUP,
+};
+
+use crate::colors::Color;
+use crate::d2d_renderer::{create_render_target, make_transparent};
+use crate::log_if_err;
+use crate::renderer::{BorderRenderer, FrameState, RendererBackend};
+use crate::utils::*;
+use crate::window_border::{self, WindowBorder};
+use crate::SendHWND;
+
+// EXPERIMENTAL: draws borders onto one full-screen, click-through overlay window per monitor,
+// instead of onto each border's own window. The idea is that DWM only has to present one layered
+// window per monitor, but this isn't there yet, so it's only used when the config asks for it:
+// - The border windows are still created, since they're what get the border's messages and follow
+//   the tracking window around. They're just never given any content (no
+//   SetLayeredWindowAttributes or UpdateLayeredWindow), so DWM never draws them.
+// - The overlay is HWND_TOPMOST, so borders show through windows that cover their tracking window
+//   (unless occlusion_clipping is on).
+// - Any border changing redraws the whole monitor, not just the part that changed.
+// - Like GDI, only the main stroke is drawn: effects, badges, the other clipping, etc. are left out.
+#[derive(Debug, Default)]
+pub struct OverlayRenderer {
+    border_window: HWND,
+    // The last frame, which gets moved along with the border window until the next one
+    last_item: Option<OverlayItem>,
+}
+
+// Everything the overlay needs to draw one border. It's all plain data, so it can be handed over
+// to the overlay's thread.
+#[derive(Debug, Clone)]
+struct OverlayItem {
+    window_rect: RECT,
+    rounded_rect: D2D1_ROUNDED_RECT,
+    stroke_width: f32,
+    color: Color,
+    brush_transform: Matrix3x2,
+    // The screen rects covered by windows above the tracking window, with occlusion_clipping
+    occluding_rects: Vec<RECT>,
+}
+
+struct OverlayWindow {
+    window: SendHWND,
+    rect: RECT,
+    // Set while a redraw is waiting in the overlay's queue, so that any other borders that change
+    // before then don't queue up redraws of their own
+    redraw_pending: bool,
+}
+
+// What's on each border's part of the overlays, keyed by border window
+static ITEMS: LazyLock<Mutex<HashMap<isize, OverlayItem>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// The overlay on each monitor, keyed by the monitor's handle
+static OVERLAYS: LazyLock<Mutex<HashMap<isize, OverlayWindow>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+static OVERLAY_CLASS: Once = Once::new();
+
+thread_local! {
+    // Each overlay has its own thread, so this is that thread's overlay
+    static OVERLAY: RefCell<Option<Overlay>> = const { RefCell::new(None) };
+}
+
+impl OverlayRenderer {
+    fn key(&self) -> isize {
+        self.border_window.0 as isize
+    }
+
+    // Hidden borders stay in last_item, so they can come back without waiting for a new frame
+    fn submit(&self) -> anyhow::Result<()> {
+        let item = self
+            .last_item
+            .clone()
+            .filter(|_| is_window_visible(self.border_window));
+        update_item(self.key(), item)
+    }
+}
+
+impl BorderRenderer for OverlayRenderer {
+    fn backend(&self) -> RendererBackend {
+        RendererBackend::Overlay
+    }
+
+    fn init(&mut self, border: &WindowBorder) -> anyhow::Result<()> {
+        self.border_window = border.border_window;
+        Ok(())
+    }
+
+    // The overlays are always the size of their monitor
+    fn resize(&mut self, _width: u32, _height: u32) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn draw(&mut self, frame: &FrameState) -> anyhow::Result<()> {
+        self.last_item = frame.visible.then(|| OverlayItem {
+            window_rect: frame.window_rect,
+            rounded_rect: frame.rounded_rect,
+            stroke_width: frame.stroke_width,
+            color: frame.color.clone(),
+            brush_transform: frame.brush_transform,
+            occluding_rects: frame.occluding_rects.to_vec(),
+        });
+        self.submit()
+    }
+
+    fn window_changed(&mut self, window_rect: RECT) {
+        if let Some(ref mut item) = self.last_item {
+            item.window_rect = window_rect;
+        }
+        log_if_err!(self.submit());
+    }
+
+    fn release(&mut self) {
+        self.last_item = None;
+        log_if_err!(update_item(self.key(), None));
+    }
+}
+
+// Puts a border on the overlays (or takes it off them with None), then redraws the overlays it
+// was or is on
+fn update_item(key: isize, item: Option<OverlayItem>) -> anyhow::Result<()> {
+    let old_item = {
+        let mut items = ITEMS.lock().unwrap();
+        match item {
+            Some(ref item) => items.insert(key, item.clone()),
+            None => items.remove(&key),
+        }
+    };
+
+    let mut overlays = OVERLAYS.lock().unwrap();
+    if let Some(ref item) = item {
+        let monitor = unsafe { MonitorFromRect(&item.window_rect, MONITOR_DEFAULTTONEAREST) };
+
+        // Overlays are only created once there's something to draw on their monitor
+        if !overlays.contains_key(&(monitor.0 as isize)) {
+            for monitor in get_monitors() {
+                if overlays.contains_key(&(monitor.0 as isize)) {
+                    continue;
+                }
+                match spawn_overlay(monitor) {
+                    Ok(overlay) => {
+                        overlays.insert(monitor.0 as isize, overlay);
+                    }
+                    Err(e) => error!("could not create overlay for {monitor:?}: {e:#}"),
+                }
+            }
+
+            if !overlays.contains_key(&(monitor.0 as isize)) {
+                return Err(anyhow!("there is no overlay to draw on"));
+            }
+        }
+    }
+
+    let damaged: Vec<RECT> = old_item
+        .iter()
+        .chain(item.iter())
+        .map(|item| item.window_rect)
+        .collect();
+    for overlay in overlays.values_mut() {
+        if overlay.redraw_pending || !damaged.iter().any(|rect| overlaps(rect, &overlay.rect)) {
+            continue;
+        }
+
+        let window = overlay.window.0;
+        overlay.redraw_pending =
+            post_message_w(window, WM_APP_OVERLAY_REDRAW, WPARAM(0), LPARAM(0))
+                .context("could not request an overlay redraw")
+                .inspect_err(|e| error!("{e:#}"))
+                .is_ok();
+    }
+
+    Ok(())
+}
+
+fn overlaps(a: &RECT, b: &RECT) -> bool {
+    a.left < b.right && b.left < a.right && a.top < b.bottom && b.top < a.bottom
+}
+
+// Starts the overlay's thread and waits for it to create its window
+fn spawn_overlay(monitor: HMONITOR) -> anyhow::Result<OverlayWindow> {
+    let rect = get_monitor_rect(monitor).context("could not get the monitor's rect")?;
+    let (sender, receiver) = mpsc::channel();
+
+    // HMONITOR isn't Send either, so we smuggle it across as an HWND
+    let monitor = SendHWND(HWND(monitor.0));
+    let _ = thread::spawn(move || {
+        let monitor_sent = monitor;
+        let monitor = HMONITOR(monitor_sent.0 .0);
+
+        match Overlay::create(monitor, rect) {
+            Ok(overlay) => {
+                let _ = sender.send(Ok(SendHWND(overlay.window)));
+                overlay.run();
+            }
+            Err(e) => {
+                let _ = sender.send(Err(e));
+            }
+        }
+    });
+
+    let window = receiver.recv().context("overlay thread exited early")??;
+
+    Ok(OverlayWindow {
+        window,
+        rect,
+        redraw_pending: false,
+    })
+}
+
+// The overlay window itself, which lives on its own thread
+struct Overlay {
+    window: HWND,
+    monitor: HMONITOR,
+    rect: RECT,
+    render_target: Option<ID2D1HwndRenderTarget>,
+}
+
+impl Overlay {
+    fn create(monitor: HMONITOR, rect: RECT) -> anyhow::Result<Overlay> {
+        register_overlay_class_once();
+
+        let window = unsafe {
+            let hinstance: HINSTANCE = std::mem::transmute(&crate::__ImageBase);
+
+            CreateWindowExW(
+                WS_EX_LAYERED
+                    | WS_EX_TOPMOST
+                    | WS_EX_TOOLWINDOW
+                    | WS_EX_TRANSPARENT
+                    | WS_EX_NOACTIVATE,
+                w!("tacky-borders-overlay"),
+                w!("tacky-borders overlay"),
+                WS_POPUP | WS_DISABLED,
+                rect.left,
+                rect.top,
+                rect.right - rect.left,
+                rect.bottom - rect.top,
+                None,
+                None,
+                hinstance,
+                None,
+            )?
+        };
+
+        make_transparent(window)?;
+        let _ = unsafe { ShowWindow(window, SW_SHOWNA) };
+
+        Ok(Overlay {
+            window,
+            monitor,
+            rect,
+            render_target: None,
+        })
+    }
+
+    fn run(self) {
+        OVERLAY.replace(Some(self));
+
+        unsafe {
+            let mut message = MSG::default();
+            while GetMessageW(&mut message, HWND::default(), 0, 0).into() {
+                let _ = TranslateMessage(&message);
+                DispatchMessageW(&message);
+            }
+        }
+
+        OVERLAY.take();
+    }
+
+    fn size(&self) -> D2D_SIZE_U {
+        D2D_SIZE_U {
+            width: (self.rect.right - self.rect.left) as u32,
+            height: (self.rect.bottom - self.rect.top) as u32,
+        }
+    }
+
+    fn redraw(&mut self) -> anyhow::Result<()> {
+        // Anything that changes from here on needs another redraw
+        if let Some(overlay) = OVERLAYS.lock().unwrap().get_mut(&(self.monitor.0 as isize)) {
+            overlay.redraw_pending = false;
+        }
+
+        let items: Vec<OverlayItem> = ITEMS
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|item| overlaps(&item.window_rect, &self.rect))
+            .cloned()
+            .collect();
+
+        if self.render_target.is_none() {
+            let render_target =
+                create_render_target(self.window, D2D1_ANTIALIAS_MODE_PER_PRIMITIVE)?;
+            unsafe { render_target.Resize(&self.size()) }?;
+            self.render_target = Some(render_target);
+        }
+        let Some(render_target) = self.render_target.clone() else {
+            return Ok(());
+        };
+
+        unsafe {
+            render_target.BeginDraw();
+            render_target.Clear(None);
+
+            for item in items.iter() {
+                // Each border is drawn in its own window's coordinates, shifted to where it is on
+                // the monitor
+                render_target.SetTransform(&Matrix3x2::translation(
+                    (item.window_rect.left - self.rect.left) as f32,
+                    (item.window_rect.top - self.rect.top) as f32,
+                ));
+
+                // The overlay is above everything, so the windows that cover the tracking window
+                // have to be cut out by hand
+                let mask = window_border::create_exclusion_geometry(
+                    &item.window_rect,
+                    &item.occluding_rects,
+                )
+                .inspect_err(|e| error!("could not create overlay mask: {e}"))
+                .ok()
+                .flatten();
+                if let Some(ref mask) = mask {
+                    let mut layer_parameters = D2D1_LAYER_PARAMETERS {
+                        contentBounds: D2D_RECT_F {
+                            left: f32::MIN,
+                            top: f32::MIN,
+                            right: f32::MAX,
+                            bottom: f32::MAX,
+                        },
+                        geometricMask: ManuallyDrop::new(Some(mask.clone())),
+                        maskAntialiasMode: D2D1_ANTIALIAS_MODE_ALIASED,
+                        maskTransform: Matrix3x2::identity(),
+                        opacity: 1.0,
+                        opacityBrush: ManuallyDrop::new(None),
+                        layerOptions: D2D1_LAYER_OPTIONS_NONE,
+                    };
+                    render_target.PushLayer(&layer_parameters, None::<&ID2D1Layer>);
+                    // PushLayer holds its own reference to the mask
+                    ManuallyDrop::drop(&mut layer_parameters.geometricMask);
+                }
+
+                let brush_properties = D2D1_BRUSH_PROPERTIES {
+                    opacity: 1.0,
+                    transform: item.brush_transform,
+                };
+                match item
+                    .color
+                    .create_brush(&render_target, &item.window_rect, &brush_properties)
+                {
+                    Ok(brush) => render_target.DrawRoundedRectangle(
+                        &item.rounded_rect,
+                        &brush,
+                        item.stroke_width,
+                        None,
+                    ),
+                    Err(e) => error!("could not create brush for overlay: {e}"),
+                }
+
+                if mask.is_some() {
+                    render_target.PopLayer();
+                }
+            }
+            render_target.SetTransform(&Matrix3x2::identity());
+
+            match render_target.EndDraw(None, None) {
+                Ok(_) => {}
+                // Same as in D2DRenderer, except the next redraw creates the new one
+                Err(e) if e.code() == D2DERR_RECREATE_TARGET => {
+                    warn!("overlay render_target has been lost; recreating it");
+                    self.render_target = None;
+                    let _ =
+                        post_message_w(self.window, WM_APP_OVERLAY_REDRAW, WPARAM(0), LPARAM(0));
+                }
+                Err(other) => {
+                    return Err(other).context("overlay render_target.EndDraw() failed");
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Follows the monitor to its new size and position. Returns false if the monitor is gone.
+    fn update_rect(&mut self) -> anyhow::Result<bool> {
+        let Some(rect) = get_monitor_rect(self.monitor) else {
+            return Ok(false);
+        };
+        if rect == self.rect {
+            return Ok(true);
+        }
+
+        self.rect = rect;
+        if let Some(overlay) = OVERLAYS.lock().unwrap().get_mut(&(self.monitor.0 as isize)) {
+            overlay.rect = rect;
+        }
+
+        unsafe {
+            SetWindowPos(
+                self.window,
+                HWND_TOPMOST,
+                rect.left,
+                rect.top,
+                rect.right - rect.left,
+                rect.bottom - rect.top,
+                SWP_NOACTIVATE,
+            )
+            .context("could not move overlay")?;
+
+            if let Some(ref render_target) = self.render_target {
+                render_target
+                    .Resize(&self.size())
+                    .context("could not resize overlay render_target")?;
+            }
+        }
+
+        self.redraw()?;
+        Ok(true)
+    }
+}
+
+fn register_overlay_class_once() {
+    OVERLAY_CLASS.call_once(|| unsafe {
+        let hinstance: HINSTANCE = std::mem::transmute(&crate::__ImageBase);
+
+        let window_class = WNDCLASSEXW {
+            cbSize: size_of::<WNDCLASSEXW>() as u32,
+            lpfnWndProc: Some(overlay_wnd_proc),
+            hInstance: hinstance,
+            lpszClassName: w!("tacky-borders-overlay"),
+            hCursor: LoadCursorW(None, IDC_ARROW).unwrap_or_default(),
+            ..Default::default()
+        };
+
+        if RegisterClassExW(&window_class) == 0 {
+            error!("could not register overlay window class");
+        }
+    });
+}
+
+unsafe extern "system" fn overlay_wnd_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    match message {
+        WM_APP_OVERLAY_REDRAW | WM_PAINT => {
+            if message == WM_PAINT {
+                let _ = ValidateRect(window, None);
+            }
+            OVERLAY.with_borrow_mut(|overlay| {
+                if let Some(overlay) = overlay {
+                    log_if_err!(overlay.redraw());
+                }
+            });
+            LRESULT(0)
+        }
+        WM_DISPLAYCHANGE => {
+            let monitor_exists = OVERLAY.with_borrow_mut(|overlay| match overlay {
+                Some(overlay) => overlay
+                    .update_rect()
+                    .inspect_err(|e| error!("could not update overlay: {e:#}"))
+                    .unwrap_or(true),
+                None => true,
+            });
+
+            // The next border to be drawn on this monitor's replacement (if any) makes a new one
+            if !monitor_exists {
+                let _ = DestroyWindow(window);
+            }
+            LRESULT(0)
+        }
+        // Explorer restarting can knock the overlay out of the topmost band
+        message if message != 0 && message == taskbar_created_message() => {
+            let _ = SetWindowPos(
+                window,
+                HWND_TOPMOST,
+                0,
+                0,
+                0,
+                0,
+                SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
+            );
+            LRESULT(0)
+        }
+        WM_DESTROY => {
+            OVERLAY.with_borrow(|overlay| {
+                if let Some(overlay) = overlay {
+                    OVERLAYS
+                        .lock()
+                        .unwrap()
+                        .remove(&(overlay.monitor.0 as isize));
+                }
+            });
+            PostQuitMessage(0);
+            LRESULT(0)
+        }
+        _ => DefWindowProcW(window, message, wparam, lparam),
+    }
+}
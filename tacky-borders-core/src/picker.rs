@@ -0,0 +1,189 @@
+//! Finds out what a window is by clicking on it, for writing window rules. This is what
+//! `tacky-borders pick` and the tray menu's "Pick Window" use.
+//!
+//! [`pick_window`] waits for a click, [`rule_snippet`] turns the picked window into a rule that can
+//! be pasted into the config, and [`copy_to_clipboard`] copies it.
+
+use anyhow::{anyhow, Context};
+use std::cell::Cell;
+use windows::Win32::Foundation::{
+    GlobalFree, HANDLE, HINSTANCE, HWND, LPARAM, LRESULT, POINT, WPARAM,
+};
+use windows::Win32::System::DataExchange::{
+    CloseClipboard, EmptyClipboard, OpenClipboard, SetClipboardData,
+};
+use windows::Win32::System::Memory::{GlobalAlloc, GlobalLock, GlobalUnlock, GMEM_MOVEABLE};
+use windows::Win32::System::Ole::CF_UNICODETEXT;
+use windows::Win32::UI::Input::KeyboardAndMouse::VK_ESCAPE;
+use windows::Win32::UI::WindowsAndMessaging::{
+    CallNextHookEx, DispatchMessageW, GetAncestor, GetMessageW, KillTimer, PostQuitMessage,
+    SetTimer, SetWindowsHookExW, TranslateMessage, UnhookWindowsHookEx, WindowFromPoint, GA_ROOT,
+    HC_ACTION, HHOOK, KBDLLHOOKSTRUCT, MSG, MSLLHOOKSTRUCT, WH_KEYBOARD_LL, WH_MOUSE_LL,
+    WM_KEYDOWN, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_RBUTTONDOWN, WM_RBUTTONUP, WM_TIMER,
+};
+
+use crate::rule_engine::WindowInfo;
+
+// Nobody is going to wait longer than this to click something
+const PICK_TIMEOUT_MS: u32 = 30_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PickState {
+    Waiting,
+    Picked(POINT),
+    Cancelled,
+}
+
+thread_local! {
+    // Low-level hooks run on the thread that installed them, which is the one waiting in
+    // pick_window()
+    static PICK: Cell<PickState> = const { Cell::new(PickState::Waiting) };
+}
+
+/// Waits for the next left click and returns the top-level window that was clicked. The click
+/// itself never reaches the window. Pressing Escape, right-clicking, or waiting 30 seconds cancels
+/// it, in which case this returns None.
+pub fn pick_window() -> anyhow::Result<Option<HWND>> {
+    PICK.set(PickState::Waiting);
+
+    unsafe {
+        let hinstance: HINSTANCE = std::mem::transmute(&crate::__ImageBase);
+
+        let mouse_hook = SetWindowsHookExW(WH_MOUSE_LL, Some(mouse_hook_proc), hinstance, 0)
+            .context("could not install mouse hook")?;
+        let keyboard_hook =
+            match SetWindowsHookExW(WH_KEYBOARD_LL, Some(keyboard_hook_proc), hinstance, 0) {
+                Ok(hook) => hook,
+                Err(e) => {
+                    let _ = UnhookWindowsHookEx(mouse_hook);
+                    return Err(e).context("could not install keyboard hook");
+                }
+            };
+
+        // Without a window, this posts WM_TIMER to the thread's message queue
+        let timeout_timer = SetTimer(None, 0, PICK_TIMEOUT_MS, None);
+
+        let mut message = MSG::default();
+        while GetMessageW(&mut message, HWND::default(), 0, 0).into() {
+            if message.message == WM_TIMER && message.wParam.0 == timeout_timer {
+                PICK.set(PickState::Cancelled);
+                break;
+            }
+
+            let _ = TranslateMessage(&message);
+            DispatchMessageW(&message);
+        }
+
+        let _ = KillTimer(None, timeout_timer);
+        let _ = UnhookWindowsHookEx(keyboard_hook);
+        let _ = UnhookWindowsHookEx(mouse_hook);
+    }
+
+    match PICK.get() {
+        PickState::Picked(point) => unsafe {
+            // Border windows are click-through, so this finds whatever is under them
+            let window = GetAncestor(WindowFromPoint(point), GA_ROOT);
+            Ok((!window.is_invalid()).then_some(window))
+        },
+        _ => Ok(None),
+    }
+}
+
+// Swallows the click (both halves of it, so the window doesn't get a stray button up either) and
+// stops pick_window()'s message loop once it's over
+unsafe extern "system" fn mouse_hook_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code == HC_ACTION as i32 {
+        let info = &*(lparam.0 as *const MSLLHOOKSTRUCT);
+
+        match (wparam.0 as u32, PICK.get()) {
+            (WM_LBUTTONDOWN, PickState::Waiting) => {
+                PICK.set(PickState::Picked(info.pt));
+                return LRESULT(1);
+            }
+            (WM_RBUTTONDOWN, PickState::Waiting) => {
+                PICK.set(PickState::Cancelled);
+                return LRESULT(1);
+            }
+            (WM_LBUTTONUP, PickState::Picked(_)) | (WM_RBUTTONUP, PickState::Cancelled) => {
+                PostQuitMessage(0);
+                return LRESULT(1);
+            }
+            _ => {}
+        }
+    }
+
+    CallNextHookEx(HHOOK::default(), code, wparam, lparam)
+}
+
+unsafe extern "system" fn keyboard_hook_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code == HC_ACTION as i32 && wparam.0 as u32 == WM_KEYDOWN {
+        let info = &*(lparam.0 as *const KBDLLHOOKSTRUCT);
+
+        if info.vkCode == VK_ESCAPE.0 as u32 && PICK.get() == PickState::Waiting {
+            PICK.set(PickState::Cancelled);
+            PostQuitMessage(0);
+            return LRESULT(1);
+        }
+    }
+
+    CallNextHookEx(HHOOK::default(), code, wparam, lparam)
+}
+
+/// A window rule that matches the window by its process and class, ready to paste under
+/// `window_rules`. The title is left in a comment, since it usually changes.
+pub fn rule_snippet(window: &WindowInfo) -> String {
+    format!(
+        "  # title: {}\n  - when: {{ process: {}, class: {} }}\n    enabled: true\n",
+        quote(&window.title),
+        quote(&window.process),
+        quote(&window.class)
+    )
+}
+
+// Quote the text as a YAML string, escaping whatever needs it
+fn quote(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Puts the text on the clipboard.
+pub fn copy_to_clipboard(text: &str) -> anyhow::Result<()> {
+    let wide: Vec<u16> = text.encode_utf16().chain([0]).collect();
+
+    unsafe {
+        OpenClipboard(HWND::default()).context("could not open clipboard")?;
+
+        let result = (|| -> anyhow::Result<()> {
+            EmptyClipboard().context("could not empty clipboard")?;
+
+            let memory = GlobalAlloc(GMEM_MOVEABLE, wide.len() * size_of::<u16>())
+                .context("could not allocate clipboard memory")?;
+            let pointer = GlobalLock(memory) as *mut u16;
+            if pointer.is_null() {
+                let _ = GlobalFree(memory);
+                return Err(anyhow!("could not lock clipboard memory"));
+            }
+            pointer.copy_from_nonoverlapping(wide.as_ptr(), wide.len());
+            let _ = GlobalUnlock(memory);
+
+            // The clipboard owns the memory once this works, so it's only ours to free if it didn't
+            if let Err(e) = SetClipboardData(CF_UNICODETEXT.0 as u32, HANDLE(memory.0)) {
+                let _ = GlobalFree(memory);
+                return Err(e).context("could not set clipboard data");
+            }
+            Ok(())
+        })();
+
+        let _ = CloseClipboard();
+        result
+    }
+}
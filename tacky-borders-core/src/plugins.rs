@@ -0,0 +1,198 @@
+use anyhow::{anyhow, Context};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time;
+use windows::core::{s, HSTRING};
+use windows::Foundation::Numerics::Matrix3x2;
+use windows::Win32::Foundation::{FreeLibrary, HMODULE};
+use windows::Win32::System::LibraryLoader::{GetProcAddress, LoadLibraryW};
+
+use crate::animations::AnimationDelta;
+
+// Plugins are native libraries (e.g. a Rust cdylib) that export the following functions:
+//
+//   extern "C" fn tacky_plugin_abi_version(host_version: u32) -> u32
+//   extern "C" fn tacky_plugin_on_frame(state: *const PluginBorderState, params: *mut PluginBrushParams)
+//
+// tacky_plugin_abi_version receives the host's ABI version and returns the version the plugin
+// implements. If they don't match, the plugin is rejected. tacky_plugin_on_frame is called on
+// every animation frame and can modify the brush parameters, which start out at their defaults.
+//
+// Note: plugin functions must never unwind (panic) across the FFI boundary.
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+// Loaded plugins are cached here and never unloaded so their function pointers stay valid. So are
+// the ones that failed to load (as their error), so every border doesn't try them again until the
+// next reload.
+static PLUGINS: LazyLock<Mutex<HashMap<PathBuf, Result<Plugin, String>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+type AbiVersionFn = unsafe extern "C" fn(host_version: u32) -> u32;
+type OnFrameFn =
+    unsafe extern "C" fn(state: *const PluginBorderState, params: *mut PluginBrushParams);
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PluginBorderState {
+    // Size of the border window in pixels
+    pub width: f32,
+    pub height: f32,
+    pub border_width: f32,
+    pub border_radius: f32,
+    // 1 if the tracking window is the foreground window, 0 otherwise
+    pub is_active: u32,
+    // Seconds since the plugin started running on this border
+    pub elapsed: f32,
+    // Seconds since the previous frame
+    pub delta: f32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct PluginBrushParams {
+    // Multiplied with the opacity of the border's colors
+    pub opacity: f32,
+    // 3x2 matrix [m11, m12, m21, m22, m31, m32] applied to the brushes after built-in animations
+    pub transform: [f32; 6],
+}
+
+impl Default for PluginBrushParams {
+    fn default() -> Self {
+        Self {
+            opacity: 1.0,
+            transform: [1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+        }
+    }
+}
+
+impl PluginBrushParams {
+    // Plugins go through the same merge as the built-in animations
+    pub fn delta(&self) -> AnimationDelta {
+        AnimationDelta {
+            transform: self.matrix(),
+            opacity: self.opacity,
+            ..Default::default()
+        }
+    }
+
+    pub fn matrix(&self) -> Matrix3x2 {
+        let [m11, m12, m21, m22, m31, m32] = self.transform;
+        Matrix3x2 {
+            M11: m11,
+            M12: m12,
+            M21: m21,
+            M22: m22,
+            M31: m31,
+            M32: m32,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Plugin {
+    on_frame: OnFrameFn,
+}
+
+// A plugin running on a specific border
+#[derive(Debug, Clone)]
+pub struct PluginInstance {
+    plugin: Plugin,
+    start_time: time::Instant,
+    last_frame_time: time::Instant,
+}
+
+impl PluginInstance {
+    pub fn new(plugin: Plugin) -> Self {
+        let now = time::Instant::now();
+        Self {
+            plugin,
+            start_time: now,
+            last_frame_time: now,
+        }
+    }
+
+    pub fn on_frame(
+        &mut self,
+        mut state: PluginBorderState,
+        now: time::Instant,
+    ) -> PluginBrushParams {
+        state.elapsed = now.saturating_duration_since(self.start_time).as_secs_f32();
+        state.delta = now
+            .saturating_duration_since(self.last_frame_time)
+            .as_secs_f32();
+        self.last_frame_time = now;
+
+        let mut params = PluginBrushParams::default();
+        unsafe { (self.plugin.on_frame)(&state, &mut params) };
+
+        params
+    }
+}
+
+// Load the plugin at the given path, or return it (or the error from loading it) from the cache if
+// it has been loaded before
+pub fn load_plugin(path: &Path) -> anyhow::Result<Plugin> {
+    let mut plugins = PLUGINS.lock().unwrap();
+
+    if let Some(plugin) = plugins.get(path) {
+        return plugin.clone().map_err(|e| anyhow!("{e}"));
+    }
+
+    let plugin = unsafe { load_library(path) };
+    if plugin.is_ok() {
+        info!("loaded plugin {}", path.display());
+    }
+    plugins.insert(
+        path.to_path_buf(),
+        plugin.as_ref().copied().map_err(|e| format!("{e:#}")),
+    );
+
+    plugin
+}
+
+// Forget the plugins that failed to load, so they're tried again (e.g. after being fixed)
+pub fn forget_failed_plugins() {
+    PLUGINS.lock().unwrap().retain(|_, plugin| plugin.is_ok());
+}
+
+unsafe fn load_library(path: &Path) -> anyhow::Result<Plugin> {
+    let module = LoadLibraryW(&HSTRING::from(path.as_os_str()))
+        .with_context(|| format!("could not load plugin {}", path.display()))?;
+
+    // A plugin we can't use is unloaded again, since nothing holds on to its functions
+    let plugin = get_plugin(module, path);
+    if plugin.is_err() {
+        let _ = FreeLibrary(module);
+    }
+
+    plugin
+}
+
+unsafe fn get_plugin(module: HMODULE, path: &Path) -> anyhow::Result<Plugin> {
+    let Some(abi_version) = GetProcAddress(module, s!("tacky_plugin_abi_version")) else {
+        return Err(anyhow!(
+            "plugin {} does not export tacky_plugin_abi_version",
+            path.display()
+        ));
+    };
+    let Some(on_frame) = GetProcAddress(module, s!("tacky_plugin_on_frame")) else {
+        return Err(anyhow!(
+            "plugin {} does not export tacky_plugin_on_frame",
+            path.display()
+        ));
+    };
+
+    let abi_version: AbiVersionFn = std::mem::transmute(abi_version);
+    let on_frame: OnFrameFn = std::mem::transmute(on_frame);
+
+    let plugin_version = abi_version(PLUGIN_ABI_VERSION);
+    if plugin_version != PLUGIN_ABI_VERSION {
+        return Err(anyhow!(
+            "plugin {} uses abi version {plugin_version}, but only version {PLUGIN_ABI_VERSION} is supported",
+            path.display()
+        ));
+    }
+
+    Ok(Plugin { on_frame })
+}
@@ -0,0 +1,227 @@
+//! Switches to a presentation profile while presenting, i.e. while the displays are duplicated,
+//! Windows' presentation settings are on, or a slideshow or screen share window is open. The profile
+//! that was in use before comes back once the presentation is over.
+//!
+//! Without a `profile`, every border is removed while presenting instead.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, Once};
+use std::thread;
+use std::time;
+use windows::Win32::Devices::Display::{
+    GetDisplayConfigBufferSizes, QueryDisplayConfig, DISPLAYCONFIG_MODE_INFO,
+    DISPLAYCONFIG_PATH_INFO, DISPLAYCONFIG_TOPOLOGY_CLONE, DISPLAYCONFIG_TOPOLOGY_ID,
+    QDC_DATABASE_CURRENT,
+};
+use windows::Win32::Foundation::{ERROR_SUCCESS, HWND};
+use windows::Win32::Graphics::Gdi::{MonitorFromWindow, HMONITOR, MONITOR_DEFAULTTONULL};
+use windows::Win32::UI::Shell::{SHQueryUserNotificationState, QUNS_PRESENTATION_MODE};
+use windows::Win32::UI::WindowsAndMessaging::IsZoomed;
+
+use crate::border_config::MatchStrategy;
+use crate::rule_engine::{Condition, WindowInfo};
+use crate::utils::*;
+
+const POLL_INTERVAL: time::Duration = time::Duration::from_secs(3);
+
+static PRESENTING: AtomicBool = AtomicBool::new(false);
+
+// The settings that presentations are detected with. These stay the same while presenting, since
+// the presentation profile might not have a 'presentation' section of its own.
+static SETTINGS: Mutex<Option<PresentationConfig>> = Mutex::new(None);
+
+static POLLER: Once = Once::new();
+
+// The windows the poller saw last time. A window's class and process never change, so only the rest
+// of its info is looked up again on each poll.
+static WINDOW_INFO: Mutex<Option<HashMap<isize, WindowInfo>>> = Mutex::new(None);
+
+/// The `presentation` section of the config.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresentationConfig {
+    /// Whether to look for presentations at all.
+    #[serde(default)]
+    pub enabled: bool,
+    /// The profile to switch to while presenting. Every border is removed if this is left out.
+    pub profile: Option<String>,
+    /// Count duplicated displays as presenting.
+    #[serde(default = "default_duplicate_displays")]
+    pub duplicate_displays: bool,
+    /// Windows that mean a presentation is going on, like a slideshow or a screen share's toolbar.
+    /// Titles and classes only need to contain the given text.
+    #[serde(default = "default_windows")]
+    pub windows: Vec<Condition>,
+}
+
+impl Default for PresentationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            profile: None,
+            duplicate_displays: default_duplicate_displays(),
+            windows: default_windows(),
+        }
+    }
+}
+
+fn default_duplicate_displays() -> bool {
+    true
+}
+
+// PowerPoint's slideshow and the toolbar Teams shows while sharing the screen
+fn default_windows() -> Vec<Condition> {
+    vec![
+        Condition {
+            class: Some("screenClass".to_string()),
+            ..Default::default()
+        },
+        Condition {
+            title: Some("Sharing control bar".to_string()),
+            ..Default::default()
+        },
+    ]
+}
+
+/// Whether a presentation is going on right now.
+pub fn is_presenting() -> bool {
+    PRESENTING.load(Ordering::SeqCst)
+}
+
+/// The profile to load instead of the one picked from the tray menu, if we're presenting.
+pub fn profile_override() -> Option<String> {
+    match is_presenting() {
+        true => SETTINGS.lock().unwrap().as_ref()?.profile.clone(),
+        false => None,
+    }
+}
+
+// Whether borders are removed for the presentation, since there's no profile to switch to
+pub(crate) fn is_paused() -> bool {
+    is_presenting() && profile_override().is_none()
+}
+
+// Called whenever the config is loaded, except while presenting
+pub(crate) fn reset(config: &PresentationConfig) {
+    if !is_presenting() {
+        *SETTINGS.lock().unwrap() = Some(config.clone());
+    }
+}
+
+pub(crate) fn start() {
+    POLLER.call_once(|| {
+        let _ = thread::spawn(|| loop {
+            poll();
+            thread::sleep(POLL_INTERVAL);
+        });
+    });
+}
+
+fn poll() {
+    let Some(settings) = SETTINGS.lock().unwrap().clone() else {
+        return;
+    };
+
+    let presenting = settings.enabled && detect_presentation(&settings);
+    if PRESENTING.swap(presenting, Ordering::SeqCst) == presenting {
+        return;
+    }
+
+    info!(
+        "presentation {}",
+        if presenting { "started" } else { "ended" }
+    );
+
+    match settings.profile {
+        // Load the presentation profile (or go back to the old one)
+        Some(_) => crate::reload(),
+        // reload_borders() only destroys the borders while they're paused
+        None => crate::reload_borders(),
+    }
+}
+
+fn detect_presentation(settings: &PresentationConfig) -> bool {
+    if settings.duplicate_displays && is_display_duplicated() {
+        return true;
+    }
+
+    let state = unsafe { SHQueryUserNotificationState() }.unwrap_or_default();
+    if state == QUNS_PRESENTATION_MODE {
+        return true;
+    }
+
+    !settings.windows.is_empty()
+        && get_visible_windows().iter().any(|window| {
+            settings
+                .windows
+                .iter()
+                .any(|condition| condition.evaluate(window, &MatchStrategy::Contains))
+        })
+}
+
+fn get_visible_windows() -> Vec<WindowInfo> {
+    let mut cache = WINDOW_INFO.lock().unwrap();
+    let mut last_info = cache.take().unwrap_or_default();
+    let monitors = get_monitors();
+
+    // Windows that are gone drop out of the cache here, since only the ones we find are put back
+    let info: HashMap<isize, WindowInfo> = get_top_level_windows()
+        .into_iter()
+        .filter(|&hwnd| is_window_visible(hwnd) && !is_cloaked(hwnd))
+        .map(|hwnd| {
+            let key = hwnd.0 as isize;
+            let mut window = last_info.remove(&key).unwrap_or_else(|| WindowInfo {
+                class: get_window_class(hwnd),
+                process: get_process_name(hwnd),
+                ..Default::default()
+            });
+            window.title = get_window_title(hwnd);
+            window.monitor = get_monitor_number(hwnd, &monitors);
+            window.maximized = unsafe { IsZoomed(hwnd).as_bool() };
+            (key, window)
+        })
+        .collect();
+
+    let windows = info.values().cloned().collect();
+    *cache = Some(info);
+    windows
+}
+
+// The same as get_monitor_index(), but without enumerating the monitors again for every window
+fn get_monitor_number(hwnd: HWND, monitors: &[HMONITOR]) -> Option<u32> {
+    let monitor = unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTONULL) };
+    monitors
+        .iter()
+        .position(|&hmonitor| !monitor.is_invalid() && hmonitor == monitor)
+        .map(|index| index as u32 + 1)
+}
+
+// Whether the displays are set to 'Duplicate' (e.g. from Win+P)
+fn is_display_duplicated() -> bool {
+    let (mut path_count, mut mode_count) = (0u32, 0u32);
+    if unsafe {
+        GetDisplayConfigBufferSizes(QDC_DATABASE_CURRENT, &mut path_count, &mut mode_count)
+    } != ERROR_SUCCESS
+    {
+        return false;
+    }
+
+    let mut paths = vec![DISPLAYCONFIG_PATH_INFO::default(); path_count as usize];
+    let mut modes = vec![DISPLAYCONFIG_MODE_INFO::default(); mode_count as usize];
+    let mut topology = DISPLAYCONFIG_TOPOLOGY_ID::default();
+    let result = unsafe {
+        QueryDisplayConfig(
+            QDC_DATABASE_CURRENT,
+            &mut path_count,
+            paths.as_mut_ptr(),
+            &mut mode_count,
+            modes.as_mut_ptr(),
+            Some(&mut topology),
+        )
+    };
+
+    // 'Second screen only' (DISPLAYCONFIG_TOPOLOGY_EXTERNAL) is also what a laptop docked with its
+    // lid closed looks like, so only 'Duplicate' counts
+    result == ERROR_SUCCESS && topology == DISPLAYCONFIG_TOPOLOGY_CLONE
+}
@@ -0,0 +1,262 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+use windows::Win32::Graphics::Direct2D::Common::{
+    D2D1_FIGURE_BEGIN_HOLLOW, D2D1_FIGURE_END_OPEN, D2D_POINT_2F, D2D_SIZE_F,
+};
+use windows::Win32::Graphics::Direct2D::{
+    ID2D1Factory, ID2D1PathGeometry, D2D1_ARC_SEGMENT, D2D1_ARC_SIZE_SMALL, D2D1_ROUNDED_RECT,
+    D2D1_SWEEP_DIRECTION_CLOCKWISE,
+};
+
+use crate::utils::{post_message_w, WM_APP_PROGRESS};
+
+// Progress is sent to border windows as parts per PROGRESS_SCALE in the WPARAM, and
+// PROGRESS_CLEAR removes it
+const PROGRESS_SCALE: f32 = 10000.0;
+const PROGRESS_CLEAR: usize = usize::MAX;
+
+// Note: there's no public API to read the progress that other apps show on their taskbar buttons
+// (ITaskbarList3 only lets an app set its own), so progress has to be sent to us over IPC.
+pub fn set_border_progress(
+    border_window: HWND,
+    progress: Option<f32>,
+) -> windows::core::Result<()> {
+    let wparam = match progress {
+        Some(progress) => (progress.clamp(0.0, 1.0) * PROGRESS_SCALE) as usize,
+        None => PROGRESS_CLEAR,
+    };
+
+    post_message_w(border_window, WM_APP_PROGRESS, WPARAM(wparam), LPARAM(0))
+}
+
+pub fn progress_from_wparam(wparam: WPARAM) -> Option<f32> {
+    match wparam.0 {
+        PROGRESS_CLEAR => None,
+        value => Some(value as f32 / PROGRESS_SCALE),
+    }
+}
+
+enum Segment {
+    Line {
+        start: D2D_POINT_2F,
+        end: D2D_POINT_2F,
+    },
+    // A quarter circle going clockwise from 'start_angle' (in radians)
+    Arc {
+        center: D2D_POINT_2F,
+        start_angle: f32,
+    },
+}
+
+// Build a path that follows the rounded rect clockwise from the middle of its top edge, stopping
+// once it has covered 'progress' (0.0 to 1.0) of the perimeter
+pub fn create_progress_geometry(
+    factory: &ID2D1Factory,
+    rounded_rect: &D2D1_ROUNDED_RECT,
+    progress: f32,
+) -> windows::core::Result<ID2D1PathGeometry> {
+    create_perimeter_geometry(factory, rounded_rect, 0.0, progress.clamp(0.0, 1.0))
+}
+
+// Build a path that follows the rounded rect clockwise from 'start' to 'end', both as fractions of
+// the perimeter starting from the middle of its top edge. 'end' can go past 1.0 to wrap around the
+// top, as long as the path doesn't go around more than once.
+pub fn create_perimeter_geometry(
+    factory: &ID2D1Factory,
+    rounded_rect: &D2D1_ROUNDED_RECT,
+    start: f32,
+    end: f32,
+) -> windows::core::Result<ID2D1PathGeometry> {
+    let (radius, segments) = perimeter_segments(rounded_rect);
+    let perimeter = perimeter_length(rounded_rect);
+    let start = start.max(0.0);
+    let (start, end) = (start * perimeter, end.min(start + 1.0) * perimeter);
+
+    unsafe {
+        let geometry = factory.CreatePathGeometry()?;
+        let sink = geometry.Open()?;
+
+        // Going over the segments twice covers paths that wrap around the top
+        let mut offset = 0.0;
+        let mut started = false;
+        for segment in segments.iter().chain(segments.iter()) {
+            let length = segment_length(segment, radius);
+            let (from, to) = (start.max(offset), end.min(offset + length));
+            let segment_offset = offset;
+            offset += length;
+
+            if length == 0.0 || to <= from {
+                continue;
+            }
+
+            if !started {
+                let fraction = (from - segment_offset) / length;
+                sink.BeginFigure(
+                    point_along(segment, radius, fraction),
+                    D2D1_FIGURE_BEGIN_HOLLOW,
+                );
+                started = true;
+            }
+
+            let to_point = point_along(segment, radius, (to - segment_offset) / length);
+            match segment {
+                Segment::Line { .. } => sink.AddLine(to_point),
+                Segment::Arc { .. } => sink.AddArc(&D2D1_ARC_SEGMENT {
+                    point: to_point,
+                    size: D2D_SIZE_F {
+                        width: radius,
+                        height: radius,
+                    },
+                    rotationAngle: 0.0,
+                    sweepDirection: D2D1_SWEEP_DIRECTION_CLOCKWISE,
+                    arcSize: D2D1_ARC_SIZE_SMALL,
+                }),
+            }
+        }
+
+        if started {
+            sink.EndFigure(D2D1_FIGURE_END_OPEN);
+        }
+        sink.Close()?;
+
+        Ok(geometry)
+    }
+}
+
+// The point 'fraction' (0.0 to 1.0) of the way around the rounded rect, measured the same way as
+// create_perimeter_geometry(), along with the direction pointing straight out of the rect there
+pub fn point_on_perimeter(
+    rounded_rect: &D2D1_ROUNDED_RECT,
+    fraction: f32,
+) -> (D2D_POINT_2F, D2D_POINT_2F) {
+    let (radius, segments) = perimeter_segments(rounded_rect);
+    let perimeter = perimeter_length(rounded_rect);
+    let mut remaining = perimeter * fraction.rem_euclid(1.0);
+
+    for segment in segments.iter() {
+        let length = segment_length(segment, radius);
+        if length == 0.0 || remaining > length {
+            remaining -= length;
+            continue;
+        }
+
+        let fraction = remaining / length;
+        let normal = match segment {
+            // Going clockwise, the outside is always on the left
+            Segment::Line { start, end } => D2D_POINT_2F {
+                x: (end.y - start.y) / length,
+                y: -(end.x - start.x) / length,
+            },
+            Segment::Arc { start_angle, .. } => {
+                let angle = start_angle + FRAC_PI_2 * fraction;
+                D2D_POINT_2F {
+                    x: angle.cos(),
+                    y: angle.sin(),
+                }
+            }
+        };
+        return (point_along(segment, radius, fraction), normal);
+    }
+
+    // Only empty rects have nothing to land on
+    let rect = rounded_rect.rect;
+    (
+        D2D_POINT_2F {
+            x: (rect.left + rect.right) / 2.0,
+            y: rect.top,
+        },
+        D2D_POINT_2F { x: 0.0, y: -1.0 },
+    )
+}
+
+// How far it is around the rounded rect, in pixels
+pub fn perimeter_length(rounded_rect: &D2D1_ROUNDED_RECT) -> f32 {
+    let (radius, segments) = perimeter_segments(rounded_rect);
+    segments
+        .iter()
+        .map(|segment| segment_length(segment, radius))
+        .sum()
+}
+
+// The outline of the rounded rect going clockwise from the middle of its top edge, along with the
+// radius of its corners
+fn perimeter_segments(rounded_rect: &D2D1_ROUNDED_RECT) -> (f32, [Segment; 9]) {
+    let rect = rounded_rect.rect;
+    let radius = rounded_rect
+        .radiusX
+        .min((rect.right - rect.left) / 2.0)
+        .min((rect.bottom - rect.top) / 2.0)
+        .max(0.0);
+    let point = |x: f32, y: f32| D2D_POINT_2F { x, y };
+    let center_x = (rect.left + rect.right) / 2.0;
+
+    let segments = [
+        Segment::Line {
+            start: point(center_x, rect.top),
+            end: point(rect.right - radius, rect.top),
+        },
+        Segment::Arc {
+            center: point(rect.right - radius, rect.top + radius),
+            start_angle: -FRAC_PI_2,
+        },
+        Segment::Line {
+            start: point(rect.right, rect.top + radius),
+            end: point(rect.right, rect.bottom - radius),
+        },
+        Segment::Arc {
+            center: point(rect.right - radius, rect.bottom - radius),
+            start_angle: 0.0,
+        },
+        Segment::Line {
+            start: point(rect.right - radius, rect.bottom),
+            end: point(rect.left + radius, rect.bottom),
+        },
+        Segment::Arc {
+            center: point(rect.left + radius, rect.bottom - radius),
+            start_angle: FRAC_PI_2,
+        },
+        Segment::Line {
+            start: point(rect.left, rect.bottom - radius),
+            end: point(rect.left, rect.top + radius),
+        },
+        Segment::Arc {
+            center: point(rect.left + radius, rect.top + radius),
+            start_angle: PI,
+        },
+        Segment::Line {
+            start: point(rect.left + radius, rect.top),
+            end: point(center_x, rect.top),
+        },
+    ];
+
+    (radius, segments)
+}
+
+fn segment_length(segment: &Segment, radius: f32) -> f32 {
+    match segment {
+        Segment::Line { start, end } => {
+            ((end.x - start.x).powi(2) + (end.y - start.y).powi(2)).sqrt()
+        }
+        Segment::Arc { .. } => radius * FRAC_PI_2,
+    }
+}
+
+// The point 'fraction' (0.0 to 1.0) of the way along the segment
+fn point_along(segment: &Segment, radius: f32, fraction: f32) -> D2D_POINT_2F {
+    match segment {
+        Segment::Line { start, end } => D2D_POINT_2F {
+            x: start.x + (end.x - start.x) * fraction,
+            y: start.y + (end.y - start.y) * fraction,
+        },
+        Segment::Arc {
+            center,
+            start_angle,
+        } => {
+            let angle = start_angle + FRAC_PI_2 * fraction;
+            D2D_POINT_2F {
+                x: center.x + radius * angle.cos(),
+                y: center.y + radius * angle.sin(),
+            }
+        }
+    }
+}
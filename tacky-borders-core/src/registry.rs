@@ -0,0 +1,286 @@
+use anyhow::anyhow;
+use std::collections::{HashMap, HashSet};
+use std::sync::{LazyLock, RwLock};
+use windows::Win32::Foundation::HWND;
+use windows::Win32::Graphics::Gdi::HMONITOR;
+
+// Every border that's running, keyed by what it follows. Everything goes through BorderRegistry's
+// methods, which never hand out the lock itself, so nobody can end up posting messages to border
+// threads while holding it.
+pub(crate) static BORDERS: LazyLock<BorderRegistry> = LazyLock::new(BorderRegistry::default);
+
+// What a border is registered under. Most borders follow a window, but monitor frames follow a
+// monitor, and there's only ever one cursor ring.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum BorderKey {
+    Window(isize),
+    Monitor(isize),
+    CursorRing,
+}
+
+impl BorderKey {
+    pub(crate) fn window(hwnd: HWND) -> Self {
+        BorderKey::Window(hwnd.0 as isize)
+    }
+
+    pub(crate) fn monitor(monitor: HMONITOR) -> Self {
+        BorderKey::Monitor(monitor.0 as isize)
+    }
+
+    // The window the border follows, if it follows one
+    pub(crate) fn hwnd(self) -> Option<HWND> {
+        match self {
+            BorderKey::Window(hwnd) => Some(HWND(hwnd as _)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct BorderHandle {
+    // HWND isn't Send or Sync, so we keep it as an isize
+    border_window: isize,
+    // The window group the tracking window belongs to, if any
+    group: Option<String>,
+}
+
+impl BorderHandle {
+    pub(crate) fn border_window(&self) -> HWND {
+        HWND(self.border_window as _)
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct BorderRegistry {
+    borders: RwLock<Borders>,
+}
+
+#[derive(Debug, Default)]
+struct Borders {
+    handles: HashMap<BorderKey, BorderHandle>,
+    // Keys that are reserved while their border window is being created. Creating the window
+    // sends it messages, so it happens without the lock.
+    pending: HashSet<BorderKey>,
+}
+
+impl BorderRegistry {
+    pub(crate) fn get(&self, key: BorderKey) -> Option<HWND> {
+        self.read()
+            .handles
+            .get(&key)
+            .map(BorderHandle::border_window)
+    }
+
+    pub(crate) fn contains(&self, key: BorderKey) -> bool {
+        self.read().handles.contains_key(&key)
+    }
+
+    pub(crate) fn group(&self, key: BorderKey) -> Option<String> {
+        self.read()
+            .handles
+            .get(&key)
+            .and_then(|handle| handle.group.clone())
+    }
+
+    // Create a border window with 'create' and register it under 'key', unless there's already a
+    // border there (or one on the way). The key is reserved while 'create' runs, so two threads
+    // racing to border the same window can't both win. If the reservation is taken away before
+    // 'create' finishes, e.g. by a reload, the new window is handed to 'discard' instead.
+    pub(crate) fn register(
+        &self,
+        key: BorderKey,
+        group: Option<String>,
+        create: impl FnOnce() -> anyhow::Result<HWND>,
+        discard: impl FnOnce(HWND),
+    ) -> anyhow::Result<HWND> {
+        {
+            let mut borders = self.borders.write().unwrap();
+            if borders.handles.contains_key(&key) || !borders.pending.insert(key) {
+                return Err(anyhow!("{key:?} already has a border"));
+            }
+        }
+
+        let created = create();
+
+        let mut borders = self.borders.write().unwrap();
+        let reserved = borders.pending.remove(&key);
+        let border_window = match (created, reserved) {
+            (Ok(border_window), true) => border_window,
+            (Ok(border_window), false) => {
+                drop(borders);
+                discard(border_window);
+                return Err(anyhow!(
+                    "{key:?} was unregistered while its border was created"
+                ));
+            }
+            (Err(e), _) => return Err(e),
+        };
+        borders.handles.insert(
+            key,
+            BorderHandle {
+                border_window: border_window.0 as isize,
+                group,
+            },
+        );
+
+        Ok(border_window)
+    }
+
+    // Remove the border, but only if it's still the one registered under 'key'. After a reload, a
+    // border that's on its way out would otherwise unregister the new border for the same window.
+    pub(crate) fn unregister(&self, key: BorderKey, border_window: HWND) {
+        let mut borders = self.borders.write().unwrap();
+        if borders
+            .handles
+            .get(&key)
+            .is_some_and(|handle| handle.border_window() == border_window)
+        {
+            borders.handles.remove(&key);
+        }
+    }
+
+    // Remove the border, returning its window so it can be told to close. A border that's still
+    // being created loses its reservation, so it closes itself once it's done.
+    pub(crate) fn take(&self, key: BorderKey) -> Option<HWND> {
+        let mut borders = self.borders.write().unwrap();
+        borders.pending.remove(&key);
+        borders
+            .handles
+            .remove(&key)
+            .map(|handle| handle.border_window())
+    }
+
+    // Remove every border, returning their windows so they can be told to close
+    pub(crate) fn take_all(&self) -> Vec<HWND> {
+        let mut borders = self.borders.write().unwrap();
+        borders.pending.clear();
+        borders
+            .handles
+            .drain()
+            .map(|(_, handle)| handle.border_window())
+            .collect()
+    }
+
+    // A copy of every (key, border window) pair, to loop over without holding the lock
+    pub(crate) fn entries(&self) -> Vec<(BorderKey, HWND)> {
+        self.read()
+            .handles
+            .iter()
+            .map(|(key, handle)| (*key, handle.border_window()))
+            .collect()
+    }
+
+    // Same as entries(), but gives up instead of waiting if the registry is locked. The crash
+    // handler uses this, since whatever crashed may have been holding the lock.
+    pub(crate) fn try_entries(&self) -> Option<Vec<(BorderKey, HWND)>> {
+        let borders = self.borders.try_read().ok()?;
+        Some(
+            borders
+                .handles
+                .iter()
+                .map(|(key, handle)| (*key, handle.border_window()))
+                .collect(),
+        )
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, Borders> {
+        self.borders.read().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_dont_collide() {
+        let registry = BorderRegistry::default();
+        let keys = [
+            BorderKey::Window(5),
+            BorderKey::Monitor(5),
+            BorderKey::Monitor(-5),
+            BorderKey::CursorRing,
+        ];
+
+        for (index, key) in keys.into_iter().enumerate() {
+            let border_window = HWND((index + 100) as _);
+            assert_eq!(
+                registry
+                    .register(key, None, || Ok(border_window), |_| {})
+                    .unwrap(),
+                border_window
+            );
+        }
+        for (index, key) in keys.into_iter().enumerate() {
+            assert_eq!(registry.get(key), Some(HWND((index + 100) as _)));
+            assert!(registry
+                .register(key, None, || Ok(HWND::default()), |_| {})
+                .is_err());
+        }
+
+        // Only the border that's registered under a key can unregister it
+        registry.unregister(BorderKey::Window(5), HWND(101 as _));
+        assert!(registry.contains(BorderKey::Window(5)));
+        registry.unregister(BorderKey::Window(5), HWND(100 as _));
+        assert!(!registry.contains(BorderKey::Window(5)));
+        assert!(registry.contains(BorderKey::Monitor(5)));
+
+        assert_eq!(registry.take(BorderKey::CursorRing), Some(HWND(103 as _)));
+        assert_eq!(registry.take_all().len(), 2);
+    }
+
+    #[test]
+    fn creating_doesnt_hold_the_lock() {
+        let registry = BorderRegistry::default();
+        let key = BorderKey::Window(5);
+
+        // Someone else trying to border the same window while we create ours loses
+        let mut discarded = None;
+        let border_window = registry.register(
+            key,
+            None,
+            || {
+                assert!(!registry.contains(key));
+                assert!(registry
+                    .register(key, None, || Ok(HWND(200 as _)), |_| {})
+                    .is_err());
+                Ok(HWND(100 as _))
+            },
+            |border_window| discarded = Some(border_window),
+        );
+        assert_eq!(border_window.unwrap(), HWND(100 as _));
+        assert_eq!(discarded, None);
+        assert_eq!(registry.get(key), Some(HWND(100 as _)));
+
+        // A reload while we create ours means ours is thrown away
+        let key = BorderKey::Window(6);
+        let border_window = registry.register(
+            key,
+            None,
+            || {
+                registry.take_all();
+                Ok(HWND(101 as _))
+            },
+            |border_window| discarded = Some(border_window),
+        );
+        assert!(border_window.is_err());
+        assert_eq!(discarded, Some(HWND(101 as _)));
+        assert!(!registry.contains(key));
+
+        // A failed border frees up its key
+        let key = BorderKey::Window(7);
+        assert!(registry
+            .register(key, None, || Err(anyhow!("no window")), |_| {})
+            .is_err());
+        assert!(registry
+            .register(key, None, || Ok(HWND(102 as _)), |_| {})
+            .is_ok());
+    }
+
+    #[test]
+    fn only_window_keys_have_windows() {
+        assert_eq!(BorderKey::Window(7).hwnd(), Some(HWND(7 as _)));
+        assert_eq!(BorderKey::Monitor(7).hwnd(), None);
+        assert_eq!(BorderKey::CursorRing.hwnd(), None);
+    }
+}
@@ -0,0 +1,395 @@
+//! The backends that turn a border's state into pixels on its window.
+//!
+//! Borders are drawn with Direct2D ([`D2DRenderer`]) unless the `renderer` option says otherwise,
+//! or the system has no hardware acceleration to speak of (VMs, old GPUs). In that case they use
+//! [`GdiRenderer`], which is much simpler but only draws a solid border. [`OverlayRenderer`] draws
+//! every border on a monitor onto one shared overlay window instead. It's experimental: it doesn't
+//! save any windows yet, draws over windows that cover a border, and is never picked on its own.
+//!
+//! A border also switches to GDI on its own if Direct2D keeps failing for it. This is called
+//! "safe mode", and [`safe_mode_count`] says how many borders are in it.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{LazyLock, Once};
+use windows::Foundation::Numerics::Matrix3x2;
+use windows::Win32::Foundation::RECT;
+use windows::Win32::Graphics::Direct2D::Common::D2D_RECT_F;
+use windows::Win32::Graphics::Direct2D::{ID2D1Bitmap, ID2D1RenderTarget, D2D1_ROUNDED_RECT};
+use windows::Win32::Graphics::Dxgi::{
+    CreateDXGIFactory1, IDXGIFactory1, DXGI_ADAPTER_FLAG_SOFTWARE,
+};
+
+use crate::colors::Color;
+pub use crate::d2d_renderer::D2DRenderer;
+use crate::effects::Noise;
+pub use crate::gdi_renderer::GdiRenderer;
+pub use crate::overlay::OverlayRenderer;
+use crate::window_border::WindowBorder;
+
+/// Something that can draw a [`WindowBorder`] onto its border window.
+///
+/// Renderers are created with [`create_renderer`], which also calls [`init`](Self::init). The
+/// border then calls [`resize`](Self::resize) whenever its window changes size (and before the
+/// first frame), [`draw`](Self::draw) for every frame, and [`release`](Self::release) once it's
+/// done with the renderer.
+pub trait BorderRenderer: fmt::Debug {
+    /// Which backend this is.
+    fn backend(&self) -> RendererBackend;
+
+    /// Sets up the border window and creates whatever resources the backend needs.
+    fn init(&mut self, border: &WindowBorder) -> anyhow::Result<()>;
+
+    /// Makes room for a border window of the given size in pixels.
+    fn resize(&mut self, width: u32, height: u32) -> anyhow::Result<()>;
+
+    /// Draws one frame.
+    fn draw(&mut self, frame: &FrameState) -> anyhow::Result<()>;
+
+    /// Called whenever the border window moves, shows up, or hides. Backends that draw onto the
+    /// border window itself don't need to do anything here, so that's the default.
+    fn window_changed(&mut self, _window_rect: RECT) {}
+
+    /// Frees the backend's resources. The renderer won't be used again afterwards.
+    fn release(&mut self);
+}
+
+/// Everything a renderer needs to draw one frame. It's all plain data, apart from the
+/// [`painter`](Self::painter) that backends supporting every effect hand their render target to.
+pub struct FrameState<'a> {
+    /// Where the border window is on screen.
+    pub window_rect: RECT,
+    /// The middle of the stroke, relative to the border window.
+    pub rounded_rect: D2D1_ROUNDED_RECT,
+    pub stroke_width: f32,
+    /// The color on top, i.e. the active color if the window is focused, with the not responding
+    /// style, Night Light compensation, and contrast guard already applied.
+    pub color: Color,
+    /// False while the border is blinked off by a flash (or completely faded out by hover peek).
+    pub visible: bool,
+    /// How visible the whole border is, from 0.0 to 1.0, while hover peek fades it in or out.
+    /// Backends that draw through the [`painter`](Self::painter) get this applied already.
+    pub opacity: f32,
+    /// The part of the border window this frame can draw to, so backends that keep the last frame
+    /// around only have to clear that part.
+    pub drawn_area: DrawnArea,
+    /// How many times the window's resolution to draw at before scaling down (1 for none).
+    pub supersampling: u32,
+    /// The film grain to draw over the border, if it has any.
+    pub noise: Option<&'a Noise>,
+    /// The transform gradient brushes are drawn with, which spiral animations rotate.
+    pub brush_transform: Matrix3x2,
+    /// Where windows above the tracking window cover it on screen, with `occlusion_clipping`.
+    pub occluding_rects: &'a [RECT],
+    /// Draws the whole border, effects and all.
+    pub painter: &'a dyn BorderPainter,
+}
+
+/// Draws a whole border onto a Direct2D render target. [`WindowBorder`] is the one that's used
+/// outside of tests.
+pub trait BorderPainter {
+    /// Draws the border, with the noise bitmap made from [`FrameState::noise`] if there is one.
+    fn paint(
+        &self,
+        render_target: &ID2D1RenderTarget,
+        noise_bitmap: Option<&ID2D1Bitmap>,
+    ) -> anyhow::Result<()>;
+}
+
+/// The part of a border window that a border can draw to: a band along each edge, plus a square in
+/// each corner (which rounded corners curve into). Nothing is ever drawn in the rest of it, so it
+/// stays transparent from one frame to the next.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DrawnArea {
+    /// How far in from the edges the bands go.
+    pub edge: f32,
+    /// The size of the corner squares.
+    pub corner: f32,
+}
+
+impl DrawnArea {
+    /// The area covered by either one.
+    pub fn union(self, other: DrawnArea) -> DrawnArea {
+        DrawnArea {
+            edge: self.edge.max(other.edge),
+            corner: self.corner.max(other.corner),
+        }
+    }
+
+    /// The rectangles making up this area on a border window of the given size, or None if they'd
+    /// cover all of it anyway.
+    pub fn rects(&self, width: f32, height: f32) -> Option<[D2D_RECT_F; 8]> {
+        let (edge, corner) = (self.edge.ceil(), self.corner.max(self.edge).ceil());
+        if 2.0 * corner >= width.min(height) {
+            return None;
+        }
+
+        let rect = |left, top, right, bottom| D2D_RECT_F {
+            left,
+            top,
+            right,
+            bottom,
+        };
+        Some([
+            // The edges, between the corners
+            rect(corner, 0.0, width - corner, edge),
+            rect(corner, height - edge, width - corner, height),
+            rect(0.0, corner, edge, height - corner),
+            rect(width - edge, corner, width, height - corner),
+            // The corners
+            rect(0.0, 0.0, corner, corner),
+            rect(width - corner, 0.0, width, corner),
+            rect(0.0, height - corner, corner, height),
+            rect(width - corner, height - corner, width, height),
+        ])
+    }
+}
+
+/// The `renderer` option.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum RendererBackend {
+    /// Direct2D, unless there's no hardware acceleration.
+    #[default]
+    #[serde(alias = "auto")]
+    Auto,
+    #[serde(alias = "direct2d", alias = "d2d")]
+    Direct2D,
+    #[serde(alias = "gdi")]
+    Gdi,
+    /// One overlay window per monitor that every border is drawn onto (see [`OverlayRenderer`]).
+    /// Experimental, and only used when it's asked for.
+    #[serde(alias = "overlay")]
+    Overlay,
+}
+
+impl RendererBackend {
+    /// Picks a backend for [`Auto`](RendererBackend::Auto). Other backends are returned as is.
+    pub fn resolve(self) -> RendererBackend {
+        match self {
+            RendererBackend::Auto => match *HARDWARE_ACCELERATED {
+                true => RendererBackend::Direct2D,
+                false => RendererBackend::Gdi,
+            },
+            backend => backend,
+        }
+    }
+}
+
+// Checked once, since the GPUs aren't going anywhere
+static HARDWARE_ACCELERATED: LazyLock<bool> = LazyLock::new(|| {
+    let accelerated = has_hardware_adapter();
+    if !accelerated {
+        warn!("no hardware graphics adapter found; borders will be drawn with GDI");
+    }
+    accelerated
+});
+
+// Direct2D falls back to software rendering without a real GPU, which is slow enough with our
+// animations that GDI is the better choice
+fn has_hardware_adapter() -> bool {
+    unsafe {
+        let factory: IDXGIFactory1 = match CreateDXGIFactory1() {
+            Ok(factory) => factory,
+            Err(e) => {
+                error!("could not create IDXGIFactory1: {e}");
+                return false;
+            }
+        };
+
+        let mut index = 0;
+        while let Ok(adapter) = factory.EnumAdapters1(index) {
+            index += 1;
+
+            let Ok(desc) = adapter.GetDesc1() else {
+                continue;
+            };
+
+            // The Microsoft Basic Render Driver doesn't always set the software flag
+            let is_basic_render_driver = desc.VendorId == 0x1414 && desc.DeviceId == 0x8c;
+            if desc.Flags & DXGI_ADAPTER_FLAG_SOFTWARE.0 as u32 == 0 && !is_basic_render_driver {
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+static OVERLAY_WARNING: Once = Once::new();
+
+/// Creates and initializes a renderer for the given backend.
+pub fn create_renderer(
+    backend: RendererBackend,
+    border: &WindowBorder,
+) -> anyhow::Result<Box<dyn BorderRenderer>> {
+    let mut renderer: Box<dyn BorderRenderer> = match backend.resolve() {
+        RendererBackend::Gdi => Box::<GdiRenderer>::default(),
+        RendererBackend::Overlay => {
+            OVERLAY_WARNING.call_once(|| {
+                warn!(
+                    "the Overlay renderer is experimental; borders may show through other windows"
+                )
+            });
+            Box::<OverlayRenderer>::default()
+        }
+        _ => Box::<D2DRenderer>::default(),
+    };
+    renderer.init(border)?;
+
+    Ok(renderer)
+}
+
+static SAFE_MODE_BORDERS: AtomicUsize = AtomicUsize::new(0);
+
+/// How many borders are in safe mode, i.e. drawn with GDI because Direct2D kept failing for them.
+pub fn safe_mode_count() -> usize {
+    SAFE_MODE_BORDERS.load(Ordering::SeqCst)
+}
+
+pub(crate) fn set_safe_mode(enabled: bool) {
+    match enabled {
+        true => SAFE_MODE_BORDERS.fetch_add(1, Ordering::SeqCst),
+        false => SAFE_MODE_BORDERS.fetch_sub(1, Ordering::SeqCst),
+    };
+}
+
+// A renderer that only remembers what it was asked to do, so borders can be tested without a
+// window to draw on
+#[cfg(test)]
+pub(crate) mod mock {
+    use super::*;
+    use windows::Win32::Graphics::Direct2D::Common::D2D1_COLOR_F;
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub(crate) enum RenderCall {
+        Init,
+        Resize(u32, u32),
+        Draw(DrawnFrame),
+        WindowChanged(RECT),
+        Release,
+    }
+
+    // The parts of a FrameState worth checking
+    #[derive(Debug, Clone, PartialEq)]
+    pub(crate) struct DrawnFrame {
+        pub window_rect: RECT,
+        pub stroke_width: f32,
+        pub color: D2D1_COLOR_F,
+        pub visible: bool,
+        pub drawn_area: DrawnArea,
+        pub supersampling: u32,
+    }
+
+    #[derive(Debug, Default)]
+    pub(crate) struct RecordingRenderer {
+        pub calls: Vec<RenderCall>,
+    }
+
+    impl RecordingRenderer {
+        pub(crate) fn frames(&self) -> Vec<&DrawnFrame> {
+            self.calls
+                .iter()
+                .filter_map(|call| match call {
+                    RenderCall::Draw(frame) => Some(frame),
+                    _ => None,
+                })
+                .collect()
+        }
+    }
+
+    impl BorderRenderer for RecordingRenderer {
+        // There's no backend for it, and Direct2D is what borders get by default
+        fn backend(&self) -> RendererBackend {
+            RendererBackend::Direct2D
+        }
+
+        fn init(&mut self, _border: &WindowBorder) -> anyhow::Result<()> {
+            self.calls.push(RenderCall::Init);
+            Ok(())
+        }
+
+        fn resize(&mut self, width: u32, height: u32) -> anyhow::Result<()> {
+            self.calls.push(RenderCall::Resize(width, height));
+            Ok(())
+        }
+
+        fn draw(&mut self, frame: &FrameState) -> anyhow::Result<()> {
+            self.calls.push(RenderCall::Draw(DrawnFrame {
+                window_rect: frame.window_rect,
+                stroke_width: frame.stroke_width,
+                color: frame.color.flatten(),
+                visible: frame.visible,
+                drawn_area: frame.drawn_area,
+                supersampling: frame.supersampling,
+            }));
+            Ok(())
+        }
+
+        fn window_changed(&mut self, window_rect: RECT) {
+            self.calls.push(RenderCall::WindowChanged(window_rect));
+        }
+
+        fn release(&mut self) {
+            self.calls.push(RenderCall::Release);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(edge: f32, corner: f32) -> DrawnArea {
+        DrawnArea { edge, corner }
+    }
+
+    #[test]
+    fn drawn_area_rects_table() {
+        let as_tuple = |rect: &D2D_RECT_F| (rect.left, rect.top, rect.right, rect.bottom);
+
+        let cases = [
+            // (area, width, height, top edge, bottom right corner)
+            (
+                area(4.0, 10.0),
+                100.0,
+                50.0,
+                Some((10.0, 0.0, 90.0, 4.0)),
+                Some((90.0, 40.0, 100.0, 50.0)),
+            ),
+            // Fractions round up, and the corners are never smaller than the edges
+            (
+                area(5.2, 2.0),
+                100.0,
+                50.0,
+                Some((6.0, 0.0, 94.0, 6.0)),
+                Some((94.0, 44.0, 100.0, 50.0)),
+            ),
+            (
+                area(4.0, 24.0),
+                100.0,
+                50.0,
+                Some((24.0, 0.0, 76.0, 4.0)),
+                Some((76.0, 26.0, 100.0, 50.0)),
+            ),
+            // Corners that meet in the middle cover the whole window
+            (area(4.0, 25.0), 100.0, 50.0, None, None),
+            (area(30.0, 0.0), 100.0, 50.0, None, None),
+            (area(0.0, 0.0), 0.0, 0.0, None, None),
+        ];
+
+        for (area, width, height, top_edge, corner) in cases {
+            let rects = area.rects(width, height);
+            assert_eq!(
+                rects.map(|rects| (as_tuple(&rects[0]), as_tuple(&rects[7]))),
+                top_edge.zip(corner),
+                "{area:?} on {width}x{height}"
+            );
+        }
+    }
+
+    #[test]
+    fn drawn_area_union_takes_the_larger_of_each() {
+        assert_eq!(area(4.0, 2.0).union(area(1.0, 8.0)), area(4.0, 8.0));
+    }
+}
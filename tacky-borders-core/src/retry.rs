@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::thread;
+use std::time;
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::WindowsAndMessaging::IsWindow;
+
+use crate::utils::*;
+use crate::SendHWND;
+
+// Creating a border can fail for a while when lots of windows show up at once (e.g. right after
+// logging in, while DWM and the GPU driver are still starting up). Instead of leaving those windows
+// without a border, we try again with exponential backoff, and give up after MAX_ATTEMPTS tries.
+const FIRST_DELAY: time::Duration = time::Duration::from_millis(500);
+const MAX_DELAY: time::Duration = time::Duration::from_secs(30);
+const MAX_ATTEMPTS: u32 = 8;
+
+#[derive(Debug, Default)]
+struct Failure {
+    attempts: u32,
+    gave_up: bool,
+}
+
+// Windows whose border couldn't be created, keyed by tracking window
+static FAILURES: LazyLock<Mutex<HashMap<isize, Failure>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// Tries to create the window's border again later, unless it has already failed too many times
+pub(crate) fn schedule(tracking_window: HWND, error: &anyhow::Error) {
+    let key = tracking_window.0 as isize;
+
+    let attempts = {
+        let mut failures = FAILURES.lock().unwrap();
+        let failure = failures.entry(key).or_default();
+        if failure.gave_up {
+            return;
+        }
+
+        failure.attempts += 1;
+        if failure.attempts > MAX_ATTEMPTS {
+            failure.gave_up = true;
+            error!(
+                "giving up on the border for {tracking_window:?} after {MAX_ATTEMPTS} retries: {error:#}"
+            );
+            return;
+        }
+        failure.attempts
+    };
+
+    let delay = FIRST_DELAY
+        .saturating_mul(1 << (attempts - 1).min(16))
+        .min(MAX_DELAY);
+    warn!("could not create border for {tracking_window:?} ({error:#}); retrying in {delay:?}");
+
+    let window = SendHWND(tracking_window);
+    let _ = thread::spawn(move || {
+        let window_sent = window;
+        thread::sleep(delay);
+
+        // Nothing to retry if the window has closed in the meantime. While borders are paused,
+        // they're all recreated once the pause is over anyways.
+        if !unsafe { IsWindow(window_sent.0) }.as_bool() || crate::is_suspended() {
+            succeeded(window_sent.0);
+            return;
+        }
+
+        // This also skips windows that have been hidden or have gotten a border since
+        show_border_for_window(window_sent.0);
+    });
+}
+
+// Called once a border is up and running, so its window starts over if it ever fails again
+pub(crate) fn succeeded(tracking_window: HWND) {
+    FAILURES
+        .lock()
+        .unwrap()
+        .remove(&(tracking_window.0 as isize));
+}
+
+// Called once the window is destroyed, since its HWND can be reused by a window that deserves a
+// fresh start (even if we had given up on this one)
+pub(crate) fn forget(tracking_window: HWND) {
+    FAILURES
+        .lock()
+        .unwrap()
+        .remove(&(tracking_window.0 as isize));
+}
+
+// Every border gets recreated after a reload, so they all get another chance
+pub(crate) fn reset() {
+    FAILURES.lock().unwrap().clear();
+}
+
+// How many open windows we gave up on
+pub(crate) fn failed_count() -> usize {
+    FAILURES
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(&key, failure)| failure.gave_up && unsafe { IsWindow(HWND(key as _)) }.as_bool())
+        .count()
+}
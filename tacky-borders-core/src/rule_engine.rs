@@ -0,0 +1,480 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+
+use crate::border_config::{MatchKind, MatchStrategy, RuleMerge, WindowRule};
+
+// The properties of a window that window rules (and scripts) can match against
+#[derive(Debug, Clone, Default)]
+pub struct WindowInfo {
+    pub title: String,
+    pub class: String,
+    pub process: String,
+    // 1-based index of the monitor the window is on, in the order that Windows enumerates them
+    pub monitor: Option<u32>,
+    pub maximized: bool,
+}
+
+// A declarative condition like { process: "code.exe", monitor: 2, maximized: false }. Every field
+// that is present must match, and conditions can be combined using 'all', 'any', and 'not'.
+// 'strategy' overrides the window rule's match strategy for this condition and the ones inside it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Condition {
+    pub title: Option<String>,
+    pub class: Option<String>,
+    pub process: Option<String>,
+    pub monitor: Option<u32>,
+    pub maximized: Option<bool>,
+    pub strategy: Option<MatchStrategy>,
+    pub all: Option<Vec<Condition>>,
+    pub any: Option<Vec<Condition>>,
+    pub not: Option<Box<Condition>>,
+}
+
+impl Condition {
+    // String fields are compared using the window rule's match strategy
+    pub fn evaluate(&self, window: &WindowInfo, strategy: &MatchStrategy) -> bool {
+        let strategy = self.strategy.as_ref().unwrap_or(strategy);
+
+        let string_matches = |pattern: &Option<String>, value: &str| {
+            pattern
+                .as_ref()
+                .is_none_or(|pattern| match_string(value, pattern, strategy))
+        };
+
+        string_matches(&self.title, &window.title)
+            && string_matches(&self.class, &window.class)
+            && string_matches(&self.process, &window.process)
+            && self
+                .monitor
+                .is_none_or(|monitor| window.monitor == Some(monitor))
+            && self
+                .maximized
+                .is_none_or(|maximized| window.maximized == maximized)
+            && self.all.as_ref().is_none_or(|conditions| {
+                conditions
+                    .iter()
+                    .all(|condition| condition.evaluate(window, strategy))
+            })
+            && self.any.as_ref().is_none_or(|conditions| {
+                conditions
+                    .iter()
+                    .any(|condition| condition.evaluate(window, strategy))
+            })
+            && self
+                .not
+                .as_ref()
+                .is_none_or(|condition| !condition.evaluate(window, strategy))
+    }
+}
+
+pub fn match_string(value: &str, pattern: &str, strategy: &MatchStrategy) -> bool {
+    match strategy {
+        MatchStrategy::Equals => value.to_lowercase().eq(&pattern.to_lowercase()),
+        MatchStrategy::Contains => value.to_lowercase().contains(&pattern.to_lowercase()),
+        MatchStrategy::Regex => get_regex(pattern).is_some_and(|regex| regex.is_match(value)),
+    }
+}
+
+// Rules are matched against every window on every event, so each pattern is only compiled once.
+// Invalid patterns are remembered as None, which also means their error is only logged once.
+fn get_regex(pattern: &str) -> Option<Regex> {
+    static REGEXES: LazyLock<Mutex<HashMap<String, Option<Regex>>>> =
+        LazyLock::new(|| Mutex::new(HashMap::new()));
+
+    let mut regexes = REGEXES.lock().unwrap();
+    if let Some(regex) = regexes.get(pattern) {
+        return regex.clone();
+    }
+
+    let regex = match Regex::new(pattern) {
+        Ok(regex) => Some(regex),
+        Err(e) => {
+            error!("invalid regex '{pattern}' in window rule: {e}");
+            None
+        }
+    };
+    regexes.insert(pattern.to_string(), regex.clone());
+
+    regex
+}
+
+// Whether the rule could stop (or start) matching a window when its title changes
+pub fn uses_title(rule: &WindowRule) -> bool {
+    fn condition_uses_title(condition: &Condition) -> bool {
+        condition.title.is_some()
+            || condition
+                .all
+                .iter()
+                .chain(condition.any.iter())
+                .flatten()
+                .chain(condition.not.as_deref())
+                .any(condition_uses_title)
+    }
+
+    matches!(rule.kind, Some(MatchKind::Title))
+        || rule
+            .when
+            .iter()
+            .chain(rule.not.iter())
+            .any(condition_uses_title)
+}
+
+// Check whether the window rule applies to the given window. A rule needs a 'match' and 'name',
+// a 'when' condition, a 'not' condition, or any mix of them. A rule with just 'not' applies to
+// every window that doesn't match it.
+pub fn rule_matches(rule: &WindowRule, window: &WindowInfo) -> bool {
+    let strategy = rule.strategy.clone().unwrap_or(MatchStrategy::Equals);
+
+    if rule.kind.is_none() && rule.when.is_none() && rule.not.is_none() {
+        error!("expected 'match', 'when', or 'not' for window rule but none found!");
+        return false;
+    }
+
+    if let Some(ref kind) = rule.kind {
+        let window_name = match kind {
+            MatchKind::Title => &window.title,
+            MatchKind::Class => &window.class,
+        };
+
+        let Some(match_name) = &rule.name else {
+            error!("expected `name` for window rule but none found!");
+            return false;
+        };
+
+        if !match_string(window_name, match_name, &strategy) {
+            return false;
+        }
+    }
+
+    rule.when
+        .as_ref()
+        .is_none_or(|condition| condition.evaluate(window, &strategy))
+        && rule
+            .not
+            .as_ref()
+            .is_none_or(|condition| !condition.evaluate(window, &strategy))
+}
+
+// Find the window rules that apply to the window, returning their indices in 'window_rules' in
+// order of precedence. Rules go by 'priority' and then by where they are in the config. Anything
+// after the first rule that doesn't overlay the next one is left out, since it isn't used.
+pub fn find_rules(rules: &[WindowRule], window: &WindowInfo) -> Vec<usize> {
+    let mut matching: Vec<usize> = (0..rules.len())
+        .filter(|&i| rule_matches(&rules[i], window))
+        .collect();
+
+    // This is a stable sort, so ties stay in the order they're written
+    matching.sort_by_key(|&i| Reverse(rules[i].priority.unwrap_or(0)));
+
+    if let Some(last) = matching
+        .iter()
+        .position(|&i| rules[i].merge.unwrap_or_default() == RuleMerge::Replace)
+    {
+        matching.truncate(last + 1);
+    }
+
+    matching
+}
+
+// Get the options for the window from the window rules that apply to it, or the default rule if
+// none do
+pub fn get_rule(rules: &[WindowRule], window: &WindowInfo) -> WindowRule {
+    let matching = find_rules(rules, window);
+
+    // Start from the lowest precedence rule and put each higher one on top of it
+    let mut matching = matching.iter().rev().map(|&i| &rules[i]);
+    let Some(base) = matching.next() else {
+        return WindowRule::default();
+    };
+
+    matching.fold(base.clone(), |merged, rule| overlay_rule(rule, &merged))
+}
+
+// Every option that's set in 'top' replaces the one in 'base'. We go through YAML so this doesn't
+// need to be updated every time an option is added.
+fn overlay_rule(top: &WindowRule, base: &WindowRule) -> WindowRule {
+    let (Ok(Value::Mapping(mut merged)), Ok(Value::Mapping(top_options))) =
+        (serde_yaml::to_value(base), serde_yaml::to_value(top))
+    else {
+        error!("could not merge window rules");
+        return top.clone();
+    };
+
+    for (key, value) in top_options {
+        if !value.is_null() {
+            merged.insert(key, value);
+        }
+    }
+
+    serde_yaml::from_value(Value::Mapping(merged)).unwrap_or_else(|e| {
+        error!("could not merge window rules: {e}");
+        top.clone()
+    })
+}
+
+// Problems with a window rule that would keep it from ever matching
+pub fn validate_rule(rule: &WindowRule) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if rule.kind.is_none() && rule.when.is_none() && rule.not.is_none() {
+        problems.push("expected 'match', 'when', or 'not'".to_string());
+    }
+    if rule.kind.is_some() && rule.name.is_none() {
+        problems.push("expected 'name' to go with 'match'".to_string());
+    }
+
+    let strategy = rule.strategy.clone().unwrap_or(MatchStrategy::Equals);
+    let mut patterns: Vec<&String> = Vec::new();
+    if let MatchStrategy::Regex = strategy {
+        patterns.extend(rule.name.iter());
+    }
+    for condition in rule.when.iter().chain(rule.not.iter()) {
+        collect_regex_patterns(condition, &strategy, &mut patterns);
+    }
+
+    for pattern in patterns {
+        if let Err(e) = Regex::new(pattern) {
+            problems.push(format!("invalid regex '{pattern}': {e}"));
+        }
+    }
+
+    problems
+}
+
+fn collect_regex_patterns<'a>(
+    condition: &'a Condition,
+    strategy: &MatchStrategy,
+    patterns: &mut Vec<&'a String>,
+) {
+    let strategy = condition.strategy.as_ref().unwrap_or(strategy);
+
+    if let MatchStrategy::Regex = strategy {
+        patterns.extend(
+            [&condition.title, &condition.class, &condition.process]
+                .into_iter()
+                .flatten(),
+        );
+    }
+
+    let children = condition.all.iter().chain(condition.any.iter()).flatten();
+    for child in children.chain(condition.not.as_deref()) {
+        collect_regex_patterns(child, strategy, patterns);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_rules(yaml: &str) -> Vec<WindowRule> {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn window(title: &str, process: &str) -> WindowInfo {
+        WindowInfo {
+            title: title.to_string(),
+            class: "Window".to_string(),
+            process: process.to_string(),
+            monitor: Some(1),
+            maximized: false,
+        }
+    }
+
+    #[test]
+    fn evaluate_table() {
+        let window = WindowInfo {
+            maximized: true,
+            ..window("main.rs - Visual Studio Code", "Code.exe")
+        };
+
+        let cases = [
+            ("{}", true),
+            ("{ process: code.exe }", true),
+            ("{ process: code.exe, monitor: 2 }", false),
+            ("{ process: code.exe, monitor: 1, maximized: true }", true),
+            ("{ maximized: false }", false),
+            // The rule's strategy is used unless the condition has its own
+            ("{ title: Visual Studio }", false),
+            ("{ title: Visual Studio, strategy: Contains }", true),
+            ("{ all: [{ process: code.exe }, { monitor: 1 }] }", true),
+            ("{ all: [{ process: code.exe }, { monitor: 2 }] }", false),
+            ("{ any: [{ process: notepad.exe }, { monitor: 1 }] }", true),
+            ("{ any: [{ process: notepad.exe }, { monitor: 2 }] }", false),
+            ("{ all: [] }", true),
+            ("{ any: [] }", false),
+            ("{ not: { process: code.exe } }", false),
+            (
+                "{ not: { any: [{ monitor: 2 }, { maximized: false }] } }",
+                true,
+            ),
+            // Nested conditions inherit the strategy
+            ("{ strategy: Regex, any: [{ title: '^main\\.rs' }] }", true),
+            ("{ strategy: Regex, any: [{ title: '^Visual' }] }", false),
+        ];
+
+        for (yaml, expected) in cases {
+            let condition: Condition = serde_yaml::from_str(yaml).unwrap();
+            assert_eq!(
+                condition.evaluate(&window, &MatchStrategy::Equals),
+                expected,
+                "{yaml}"
+            );
+        }
+    }
+
+    #[test]
+    fn find_rules_table() {
+        let rules = parse_rules(
+            "
+            - { when: { process: code.exe }, border_width: 1 }
+            - { match: Title, name: notes, strategy: Contains, merge: Overlay, border_width: 2 }
+            - { when: { process: code.exe }, priority: 5, merge: Overlay, border_width: 3 }
+            - { match: Class, name: Window, border_width: 4 }
+            - { when: { maximized: true }, priority: -1, border_width: 5 }",
+        );
+
+        let cases = [
+            // The overlay at priority 5 goes first, and nothing's used after the plain rule
+            (window("main.rs", "code.exe"), vec![2, 0]),
+            // Ties go in the order they're written, so the plain rule cuts off the title overlay
+            (window("notes.md", "code.exe"), vec![2, 0]),
+            (window("notes.txt", "notepad.exe"), vec![1, 3]),
+            (window("Calculator", "calc.exe"), vec![3]),
+        ];
+
+        for (window, expected) in cases {
+            assert_eq!(find_rules(&rules, &window), expected, "{window:?}");
+        }
+
+        // The rule at priority -1 would come last, but the plain rule cuts it off
+        let maximized = WindowInfo {
+            maximized: true,
+            ..window("Calculator", "calc.exe")
+        };
+        assert_eq!(find_rules(&rules, &maximized), vec![3]);
+        assert_eq!(find_rules(&rules[4..], &maximized), vec![0]);
+        assert!(find_rules(&rules[..1], &maximized).is_empty());
+
+        // Overlays go on top of the rule they stop at
+        let merged = get_rule(&rules, &window("notes.md", "code.exe"));
+        assert_eq!(merged.border_width, Some(3.0));
+    }
+
+    #[test]
+    fn overlay_rule_table() {
+        let rules = parse_rules(
+            "
+            - { border_width: 2, border_offset: -1 }
+            - { border_width: 6, border_radius: 8 }
+            - {}",
+        );
+
+        let cases = [
+            // (top, base, width, offset, radius)
+            (0, 1, Some(2.0), Some(-1), Some(8.0)),
+            (1, 0, Some(6.0), Some(-1), Some(8.0)),
+            (2, 1, Some(6.0), None, Some(8.0)),
+            (1, 2, Some(6.0), None, Some(8.0)),
+        ];
+
+        for (top, base, width, offset, radius) in cases {
+            let merged = overlay_rule(&rules[top], &rules[base]);
+            assert_eq!(
+                (
+                    merged.border_width,
+                    merged.border_offset,
+                    merged.border_radius
+                ),
+                (width, offset, radius),
+                "{top} over {base}"
+            );
+        }
+    }
+
+    #[test]
+    fn not_table() {
+        let rules = parse_rules(
+            "
+            - { when: { process: chrome.exe }, not: { title: Picture-in-Picture } }
+            - { not: { process: chrome.exe } }
+            - { match: Class, name: Window, not: { any: [{ monitor: 2 }, { maximized: true }] } }",
+        );
+
+        let cases = [
+            (0, window("GitHub", "chrome.exe"), true),
+            (0, window("Picture-in-Picture", "chrome.exe"), false),
+            (0, window("Picture-in-Picture", "firefox.exe"), false),
+            // A rule with only 'not' matches everything else
+            (1, window("GitHub", "chrome.exe"), false),
+            (1, window("GitHub", "firefox.exe"), true),
+            (2, window("GitHub", "firefox.exe"), true),
+            (
+                2,
+                WindowInfo {
+                    monitor: Some(2),
+                    ..window("GitHub", "firefox.exe")
+                },
+                false,
+            ),
+            (
+                2,
+                WindowInfo {
+                    maximized: true,
+                    ..window("GitHub", "firefox.exe")
+                },
+                false,
+            ),
+        ];
+
+        for (rule, window, expected) in cases {
+            assert_eq!(
+                rule_matches(&rules[rule], &window),
+                expected,
+                "rule {rule} on {window:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn match_string_table() {
+        let cases = [
+            ("Firefox", "firefox", MatchStrategy::Equals, true),
+            ("Mozilla Firefox", "firefox", MatchStrategy::Contains, true),
+            ("Mozilla Firefox", "^Mozilla", MatchStrategy::Regex, true),
+            ("Mozilla Firefox", "^Firefox", MatchStrategy::Regex, false),
+            // Invalid patterns never match, even the second time when they come from the cache
+            ("(", "(", MatchStrategy::Regex, false),
+            ("(", "(", MatchStrategy::Regex, false),
+        ];
+
+        for (value, pattern, strategy, expected) in cases {
+            assert_eq!(
+                match_string(value, pattern, &strategy),
+                expected,
+                "'{value}' {strategy:?} '{pattern}'"
+            );
+        }
+    }
+
+    #[test]
+    fn uses_title_table() {
+        let rules = parse_rules(
+            "
+            - { match: Title, name: notes }
+            - { match: Class, name: Notepad }
+            - { when: { process: code.exe } }
+            - { when: { any: [{ process: code.exe }, { not: { title: prod } }] } }
+            - { match: Class, name: Notepad, not: { all: [{ title: scratch }] } }",
+        );
+
+        let expected = [true, false, false, true, true];
+        for (rule, expected) in rules.iter().zip(expected) {
+            assert_eq!(uses_title(rule), expected, "{rule:?}");
+        }
+    }
+}
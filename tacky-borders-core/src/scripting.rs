@@ -0,0 +1,178 @@
+use anyhow::anyhow;
+use mlua::{HookTriggers, LuaOptions, LuaSerdeExt, StdLib, Value};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time;
+
+use crate::colors::ColorConfig;
+use crate::rule_engine::WindowInfo;
+
+// Scripts get a fixed amount of time and memory to run each hook, so badly written scripts can't
+// freeze or bloat the border threads
+const SCRIPT_TIME_LIMIT: time::Duration = time::Duration::from_millis(50);
+const SCRIPT_MEMORY_LIMIT: usize = 16 * 1024 * 1024;
+
+// How often (in Lua VM instructions) to check whether a script has exceeded its time limit
+const SCRIPT_HOOK_INTERVAL: u32 = 1000;
+
+// Lua states can't be sent across threads, so each border thread lazily creates its own engine
+thread_local! {
+    static ENGINE: RefCell<Option<ScriptEngine>> = const { RefCell::new(None) };
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedColors {
+    pub active: Option<ColorConfig>,
+    pub inactive: Option<ColorConfig>,
+}
+
+struct ScriptEngine {
+    source: String,
+    lua: mlua::Lua,
+    deadline: Rc<Cell<time::Instant>>,
+}
+
+impl ScriptEngine {
+    fn new(source: &str) -> anyhow::Result<Self> {
+        // Only load the libraries that can't touch the filesystem or the rest of the system
+        let lua = mlua::Lua::new_with(
+            StdLib::STRING | StdLib::TABLE | StdLib::MATH,
+            LuaOptions::default(),
+        )
+        .map_err(|e| anyhow!("could not create lua state: {e}"))?;
+
+        lua.set_memory_limit(SCRIPT_MEMORY_LIMIT)
+            .map_err(|e| anyhow!("could not set script memory limit: {e}"))?;
+
+        let deadline = Rc::new(Cell::new(time::Instant::now() + SCRIPT_TIME_LIMIT));
+        let hook_deadline = deadline.clone();
+        lua.set_hook(
+            HookTriggers::new().every_nth_instruction(SCRIPT_HOOK_INTERVAL),
+            move |_, _| match time::Instant::now() > hook_deadline.get() {
+                true => Err(mlua::Error::RuntimeError(
+                    "script exceeded its time limit".to_string(),
+                )),
+                false => Ok(()),
+            },
+        );
+
+        // Run the script once so that it defines its hook functions
+        lua.load(source)
+            .exec()
+            .map_err(|e| anyhow!("could not load script: {e}"))?;
+
+        Ok(Self {
+            source: source.to_string(),
+            lua,
+            deadline,
+        })
+    }
+
+    // Call the global function named 'hook' with the window table. Returns nil if the script
+    // doesn't define the hook.
+    fn call_hook(&self, hook: &str, window: &WindowInfo) -> anyhow::Result<Value<'_>> {
+        let globals = self.lua.globals();
+        let Some(function) = globals
+            .get::<_, Option<mlua::Function>>(hook)
+            .map_err(|e| anyhow!("{hook} is not a function: {e}"))?
+        else {
+            return Ok(Value::Nil);
+        };
+
+        let window_table = self
+            .lua
+            .create_table()
+            .and_then(|table| {
+                table.set("title", window.title.as_str())?;
+                table.set("class", window.class.as_str())?;
+                table.set("process", window.process.as_str())?;
+                table.set("monitor", window.monitor)?;
+                table.set("maximized", window.maximized)?;
+                Ok(table)
+            })
+            .map_err(|e| anyhow!("could not create window table: {e}"))?;
+
+        self.deadline.set(time::Instant::now() + SCRIPT_TIME_LIMIT);
+
+        function
+            .call::<_, Value>(window_table)
+            .map_err(|e| anyhow!("error in script hook {hook}: {e}"))
+    }
+
+    fn call_bool_hook(&self, hook: &str, window: &WindowInfo) -> anyhow::Result<Option<bool>> {
+        match self.call_hook(hook, window)? {
+            Value::Nil => Ok(None),
+            Value::Boolean(value) => Ok(Some(value)),
+            other => Err(anyhow!(
+                "script hook {hook} should return a boolean but returned a {}",
+                other.type_name()
+            )),
+        }
+    }
+
+    fn call_color_hook(
+        &self,
+        hook: &str,
+        window: &WindowInfo,
+    ) -> anyhow::Result<Option<ColorConfig>> {
+        // Hooks can return either a string or a gradient table, so we let serde sort it out
+        match self.call_hook(hook, window)? {
+            Value::Nil => Ok(None),
+            value => self
+                .lua
+                .from_value::<ColorConfig>(value)
+                .map(Some)
+                .map_err(|e| anyhow!("script hook {hook} returned an invalid color: {e}")),
+        }
+    }
+}
+
+fn with_engine<T>(source: &str, callback: impl FnOnce(&ScriptEngine) -> T) -> anyhow::Result<T> {
+    ENGINE.with_borrow_mut(|engine| {
+        // (Re)create the engine if this thread doesn't have one yet or the script has changed
+        if engine.as_ref().is_none_or(|engine| engine.source != source) {
+            *engine = Some(ScriptEngine::new(source)?);
+        }
+
+        match engine.as_ref() {
+            Some(engine) => Ok(callback(engine)),
+            None => Err(anyhow!("script engine has not been created")),
+        }
+    })
+}
+
+// Evaluate the 'active_color' and 'inactive_color' hooks
+pub fn eval_colors(source: &str, window: &WindowInfo) -> ScriptedColors {
+    let result = with_engine(source, |engine| {
+        let mut colors = ScriptedColors::default();
+
+        match engine.call_color_hook("active_color", window) {
+            Ok(color) => colors.active = color,
+            Err(e) => error!("{e}"),
+        }
+        match engine.call_color_hook("inactive_color", window) {
+            Ok(color) => colors.inactive = color,
+            Err(e) => error!("{e}"),
+        }
+
+        colors
+    });
+
+    result.unwrap_or_else(|e| {
+        error!("{e}");
+        ScriptedColors::default()
+    })
+}
+
+// Evaluate the 'enabled' hook
+pub fn eval_enabled(source: &str, window: &WindowInfo) -> Option<bool> {
+    let result = with_engine(source, |engine| engine.call_bool_hook("enabled", window));
+
+    match result {
+        Ok(Ok(enabled)) => enabled,
+        Ok(Err(e)) | Err(e) => {
+            error!("{e}");
+            None
+        }
+    }
+}
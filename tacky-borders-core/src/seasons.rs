@@ -0,0 +1,283 @@
+//! Looks that borders wear on certain days of the year, like candy cane stripes in December.
+//!
+//! With `seasonal: true` in the config's `global` section, the first [`Season`] whose dates
+//! include today replaces the colors (and adds the dashes and particles) it sets, on top of any
+//! theme. The built-in seasons can be replaced by listing seasons with the same names under
+//! `seasons`, and new ones can be added there too. Seasons from the config are checked before the
+//! built-in ones. The date is checked whenever the config loads, and again whenever the day
+//! changes, in which case the config is reloaded if it's a different season now.
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::sync::Once;
+use std::thread;
+use std::time;
+use windows::Win32::System::SystemInformation::GetLocalTime;
+
+use crate::border_config::{Global, CONFIG};
+use crate::colors::ColorConfig;
+use crate::effects::ParticlesConfig;
+
+const POLL_INTERVAL: time::Duration = time::Duration::from_secs(60);
+
+// Days in each month. February gets its leap day, since seasons don't know the year.
+const DAYS_IN_MONTH: [u16; 12] = [31, 29, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+
+static POLLER: Once = Once::new();
+
+const BUILT_IN_SEASONS: &str = r##"
+- name: new_year
+  from: 12-31
+  to: 01-01
+  active_color: "#ffd700"
+  inactive_color: "#5c4b16"
+  particles: { kind: sparkle, color: "#ffd700" }
+- name: valentines
+  from: 02-14
+  to: 02-14
+  active_color: "#ff4d8d"
+  inactive_color: "#5c2a3d"
+- name: halloween
+  from: 10-24
+  to: 10-31
+  active_color: "#ff7518"
+  inactive_color: "#3d2b4f"
+- name: december
+  from: 12-01
+  to: 12-30
+  active_color: "#ffffff"
+  inactive_color: "#6b1a22"
+  dash_color: "#d6001c"
+  particles: { kind: snow, color: "#ffffff" }
+"##;
+
+/// A look for the days from `from` to `to` (both included). Anything left out keeps the config's
+/// value.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Season {
+    pub name: String,
+    /// The first day of the season, as month and day (like `12-01`). The season wraps around the
+    /// new year if this comes after `to`.
+    pub from: String,
+    /// The last day of the season.
+    pub to: String,
+    pub active_color: Option<ColorConfig>,
+    pub inactive_color: Option<ColorConfig>,
+    /// Dashes drawn over the border in this color.
+    pub dash_color: Option<ColorConfig>,
+    pub particles: Option<ParticlesConfig>,
+}
+
+impl Season {
+    /// Whether today's month and day fall within the season.
+    pub fn includes(&self, month: u16, day: u16) -> anyhow::Result<bool> {
+        let from = parse_month_day(&self.from)?;
+        let to = parse_month_day(&self.to)?;
+        let today = (month, day);
+
+        Ok(match from <= to {
+            true => from <= today && today <= to,
+            false => today >= from || today <= to,
+        })
+    }
+
+    /// Replaces the options in `global` that the season sets.
+    pub fn apply(&self, global: &mut Global) {
+        // A palette in the config would otherwise win over the season's own colors
+        if self.active_color.is_some() || self.inactive_color.is_some() {
+            global.palette_preset = None;
+        }
+        if let Some(ref active_color) = self.active_color {
+            global.active_color = active_color.clone();
+        }
+        if let Some(ref inactive_color) = self.inactive_color {
+            global.inactive_color = inactive_color.clone();
+        }
+
+        if self.dash_color.is_some() {
+            global.dash_color = self.dash_color.clone();
+        }
+        if self.particles.is_some() {
+            global.particles = self.particles.clone();
+        }
+    }
+}
+
+/// The built-in seasons.
+pub fn built_in_seasons() -> Vec<Season> {
+    serde_yaml::from_str(BUILT_IN_SEASONS).expect("the built-in seasons should parse")
+}
+
+/// The season that borders should wear today, if any. `seasons` are the ones from the config.
+pub fn current_season(seasons: &[Season]) -> Option<Season> {
+    let now = unsafe { GetLocalTime() };
+
+    let built_in = built_in_seasons()
+        .into_iter()
+        .filter(|built_in| !seasons.iter().any(|season| season.name == built_in.name));
+
+    seasons.iter().cloned().chain(built_in).find(|season| {
+        match season.includes(now.wMonth, now.wDay) {
+            Ok(includes) => includes,
+            Err(e) => {
+                error!("could not check season '{}': {e:#}", season.name);
+                false
+            }
+        }
+    })
+}
+
+// Checks the date once a minute, and reloads the config when a new day brings a new season. The
+// poller keeps running after the config changes, and just picks up the new seasons.
+pub(crate) fn start() {
+    POLLER.call_once(|| {
+        let _ = thread::spawn(|| {
+            let mut last_day = today();
+            let mut last_season = season_name();
+
+            loop {
+                thread::sleep(POLL_INTERVAL);
+
+                let day = today();
+                if day == last_day {
+                    continue;
+                }
+                last_day = day;
+
+                let season = season_name();
+                if season != last_season {
+                    info!("changing season to {}", season.as_deref().unwrap_or("none"));
+                    last_season = season;
+                    crate::reload();
+                }
+            }
+        });
+    });
+}
+
+fn today() -> (u16, u16) {
+    let now = unsafe { GetLocalTime() };
+    (now.wMonth, now.wDay)
+}
+
+// The name of the season the config should be wearing today, if any
+fn season_name() -> Option<String> {
+    let (seasonal, seasons) = {
+        let config = CONFIG.lock().unwrap();
+        (
+            config.global.seasonal.unwrap_or(false),
+            config.global.seasons.clone().unwrap_or_default(),
+        )
+    };
+
+    match seasonal {
+        true => current_season(&seasons).map(|season| season.name),
+        false => None,
+    }
+}
+
+fn parse_month_day(date: &str) -> anyhow::Result<(u16, u16)> {
+    let invalid = || anyhow!("invalid date '{date}', expected a month and day like \"12-01\"");
+
+    let (month, day) = date.trim().split_once('-').ok_or_else(invalid)?;
+    let month: u16 = month.parse().map_err(|_| invalid())?;
+    let day: u16 = day.parse().map_err(|_| invalid())?;
+
+    let days_in_month = match month {
+        1..=12 => DAYS_IN_MONTH[month as usize - 1],
+        _ => return Err(invalid()),
+    };
+    match (1..=days_in_month).contains(&day) {
+        true => Ok((month, day)),
+        false => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn season(from: &str, to: &str) -> Season {
+        Season {
+            name: "test".to_string(),
+            from: from.to_string(),
+            to: to.to_string(),
+            active_color: None,
+            inactive_color: None,
+            dash_color: None,
+            particles: None,
+        }
+    }
+
+    #[test]
+    fn includes_table() {
+        let cases = [
+            // (from, to, month, day, included)
+            ("10-24", "10-31", 10, 24, true),
+            ("10-24", "10-31", 10, 31, true),
+            ("10-24", "10-31", 10, 23, false),
+            ("10-24", "10-31", 11, 1, false),
+            ("02-14", "02-14", 2, 14, true),
+            ("02-14", "02-14", 2, 15, false),
+            ("11-20", "01-05", 12, 25, true),
+            // Seasons that come after their end wrap around the new year
+            ("12-31", "01-01", 12, 31, true),
+            ("12-31", "01-01", 1, 1, true),
+            ("12-31", "01-01", 1, 2, false),
+            ("12-31", "01-01", 12, 30, false),
+            ("11-20", "01-05", 1, 5, true),
+            ("11-20", "01-05", 6, 1, false),
+        ];
+
+        for (from, to, month, day, expected) in cases {
+            assert_eq!(
+                season(from, to).includes(month, day).unwrap(),
+                expected,
+                "{month}-{day} in {from}..{to}"
+            );
+        }
+    }
+
+    #[test]
+    fn includes_rejects_invalid_dates() {
+        assert!(season("13-01", "12-31").includes(1, 1).is_err());
+        assert!(season("01-01", "nope").includes(1, 1).is_err());
+    }
+
+    #[test]
+    fn parse_month_day_table() {
+        let cases = [
+            ("12-01", Some((12, 1))),
+            (" 1-5 ", Some((1, 5))),
+            ("01-31", Some((1, 31))),
+            ("00-10", None),
+            ("13-01", None),
+            ("06-00", None),
+            ("06-32", None),
+            ("02-29", Some((2, 29))),
+            ("02-30", None),
+            ("04-31", None),
+            ("12-31", Some((12, 31))),
+            ("12/01", None),
+            ("12-", None),
+            ("-12-01", None),
+            ("december", None),
+            ("", None),
+        ];
+
+        for (date, expected) in cases {
+            assert_eq!(parse_month_day(date).ok(), expected, "'{date}'");
+        }
+    }
+
+    #[test]
+    fn built_in_seasons_parse() {
+        let seasons = built_in_seasons();
+        let names: Vec<_> = seasons.iter().map(|season| season.name.as_str()).collect();
+        assert_eq!(names, ["new_year", "valentines", "halloween", "december"]);
+
+        for season in seasons {
+            assert!(season.includes(1, 1).is_ok(), "{}", season.name);
+        }
+    }
+}
@@ -0,0 +1,631 @@
+//! A small settings window for the most common options, for anyone who would rather not edit the
+//! YAML by hand. The tray menu's "Settings..." item opens it.
+//!
+//! The window wears a border made from whatever is typed into it, so changes can be seen before
+//! they're used anywhere else. Apply uses them for every border until the next reload, and Save
+//! writes them to the config file as well. The animation checkboxes are the same toggles as the tray
+//! menu's, and take effect right away.
+
+use serde_yaml::Value;
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, AtomicIsize, Ordering};
+use std::sync::Once;
+use std::thread;
+use windows::core::{w, HSTRING, PCWSTR};
+use windows::Win32::Foundation::{FALSE, HINSTANCE, HWND, LPARAM, LRESULT, RECT, WPARAM};
+use windows::Win32::Graphics::Gdi::{GetStockObject, COLOR_BTNFACE, DEFAULT_GUI_FONT, HBRUSH};
+use windows::Win32::UI::HiDpi::GetDpiForSystem;
+use windows::Win32::UI::WindowsAndMessaging::{
+    AdjustWindowRectEx, CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW,
+    GetMessageW, GetWindowTextLengthW, GetWindowTextW, IsDialogMessageW, IsWindow, KillTimer,
+    LoadCursorW, PostQuitMessage, RegisterClassExW, SendMessageW, SetForegroundWindow, SetTimer,
+    SetWindowTextW, ShowWindow, TranslateMessage, BM_GETCHECK, BM_SETCHECK, BN_CLICKED,
+    BS_AUTOCHECKBOX, BS_PUSHBUTTON, CW_USEDEFAULT, EN_CHANGE, ES_AUTOHSCROLL, HMENU, IDCANCEL,
+    IDC_ARROW, MSG, SW_RESTORE, SW_SHOW, WINDOW_EX_STYLE, WINDOW_STYLE, WM_ACTIVATE, WM_COMMAND,
+    WM_DESTROY, WM_SETFONT, WM_TIMER, WNDCLASSEXW, WS_CAPTION, WS_CHILD, WS_EX_CLIENTEDGE,
+    WS_EX_CONTROLPARENT, WS_MINIMIZEBOX, WS_OVERLAPPED, WS_SYSMENU, WS_TABSTOP, WS_VISIBLE,
+};
+
+use crate::animations::AnimationType;
+use crate::border::{Border, BorderHandle, BorderOptions};
+use crate::border_config::{Config, WindowRule, CONFIG};
+use crate::colors::ColorConfig;
+use crate::state;
+use crate::utils::get_border_from_window;
+
+static SETTINGS_CLASS: Once = Once::new();
+
+// Only one settings window is open at a time. Opening it again brings the open one to the front.
+static OPEN: AtomicBool = AtomicBool::new(false);
+static OPEN_WINDOW: AtomicIsize = AtomicIsize::new(0);
+
+// The preview waits for typing to stop for a moment, since every change recreates its border
+const PREVIEW_TIMER: usize = 1;
+const PREVIEW_DELAY_MS: u32 = 300;
+// How often (and how many times) to check whether the old preview border is gone yet
+const PREVIEW_RETRY_MS: u32 = 20;
+const PREVIEW_MAX_RETRIES: u32 = 25;
+
+const ID_WIDTH: i32 = 101;
+const ID_RADIUS: i32 = 102;
+const ID_ACTIVE_COLOR: i32 = 103;
+const ID_INACTIVE_COLOR: i32 = 104;
+const ID_APPLY: i32 = 301;
+const ID_SAVE: i32 = 302;
+const ID_CLOSE: i32 = 303;
+// One checkbox per AnimationType::ALL, in the same order
+const ID_FIRST_ANIMATION: i32 = 201;
+
+thread_local! {
+    static SETTINGS: RefCell<Option<Settings>> = const { RefCell::new(None) };
+}
+
+/// Opens the settings window on its own thread, or brings it to the front if it's already open.
+/// `on_toggle` is called after an animation checkbox changes the tray menu's toggles, so the menu
+/// can be rebuilt.
+pub fn show(on_toggle: fn()) {
+    if OPEN.swap(true, Ordering::SeqCst) {
+        let window = HWND(OPEN_WINDOW.load(Ordering::SeqCst) as _);
+        if unsafe { IsWindow(window) }.as_bool() {
+            unsafe {
+                let _ = ShowWindow(window, SW_RESTORE);
+                let _ = SetForegroundWindow(window);
+            }
+        }
+        return;
+    }
+
+    let _ = thread::spawn(move || {
+        if let Err(e) = run(on_toggle) {
+            error!("could not open settings window: {e:#}");
+        }
+        OPEN_WINDOW.store(0, Ordering::SeqCst);
+        OPEN.store(false, Ordering::SeqCst);
+    });
+}
+
+// The values typed into the window. The colors are None unless they were changed, so colors that
+// can't be typed in (like gradients) are kept as they are.
+struct Values {
+    border_width: f32,
+    border_radius: f32,
+    active_color: Option<ColorConfig>,
+    inactive_color: Option<ColorConfig>,
+}
+
+struct Settings {
+    window: HWND,
+    width: HWND,
+    radius: HWND,
+    active_color: HWND,
+    inactive_color: HWND,
+    animations: Vec<HWND>,
+    status: HWND,
+    // The colors the window opened with, and the text they were shown as
+    initial_colors: [(String, ColorConfig); 2],
+    preview: Option<BorderHandle>,
+    // How many times the preview has waited for the old border to go away
+    preview_retries: u32,
+    on_toggle: fn(),
+}
+
+fn run(on_toggle: fn()) -> anyhow::Result<()> {
+    register_class_once();
+
+    let settings = unsafe { create_window(on_toggle)? };
+    let window = settings.window;
+    OPEN_WINDOW.store(window.0 as isize, Ordering::SeqCst);
+
+    SETTINGS.with_borrow_mut(|s| *s = Some(settings));
+    SETTINGS.with_borrow_mut(|s| {
+        if let Some(settings) = s {
+            settings.update_preview();
+        }
+    });
+
+    unsafe {
+        let _ = ShowWindow(window, SW_SHOW);
+        let _ = SetForegroundWindow(window);
+
+        let mut message = MSG::default();
+        while GetMessageW(&mut message, HWND::default(), 0, 0).into() {
+            // This is what makes Tab move between the fields
+            if IsDialogMessageW(window, &message).as_bool() {
+                continue;
+            }
+            let _ = TranslateMessage(&message);
+            DispatchMessageW(&message);
+        }
+    }
+
+    Ok(())
+}
+
+fn register_class_once() {
+    SETTINGS_CLASS.call_once(|| unsafe {
+        let hinstance: HINSTANCE = std::mem::transmute(&crate::__ImageBase);
+
+        let window_class = WNDCLASSEXW {
+            cbSize: size_of::<WNDCLASSEXW>() as u32,
+            lpfnWndProc: Some(settings_wnd_proc),
+            hInstance: hinstance,
+            lpszClassName: w!("tacky-borders-settings"),
+            hCursor: LoadCursorW(None, IDC_ARROW).unwrap_or_default(),
+            hbrBackground: HBRUSH((COLOR_BTNFACE.0 + 1) as usize as _),
+            ..Default::default()
+        };
+
+        if RegisterClassExW(&window_class) == 0 {
+            error!("could not register settings window class");
+        }
+    });
+}
+
+unsafe fn create_window(on_toggle: fn()) -> anyhow::Result<Settings> {
+    let hinstance: HINSTANCE = std::mem::transmute(&crate::__ImageBase);
+    let scale = |value: i32| value * GetDpiForSystem() as i32 / 96;
+
+    let (border_width, border_radius, initial_colors, theme) = {
+        let config = CONFIG.lock().unwrap();
+        let (active, inactive) = config.global.base_colors(&WindowRule::default());
+        let theme = state::tray_choices()
+            .theme
+            .or_else(|| config.global.theme.clone())
+            .filter(|name| !name.is_empty());
+
+        (
+            config.global.border_width,
+            config.global.border_radius,
+            [
+                (color_text(&active), active),
+                (color_text(&inactive), inactive),
+            ],
+            theme,
+        )
+    };
+
+    let style = WS_OVERLAPPED | WS_CAPTION | WS_SYSMENU | WS_MINIMIZEBOX;
+    let mut rect = RECT {
+        left: 0,
+        top: 0,
+        right: scale(330),
+        bottom: scale(288),
+    };
+    let _ = AdjustWindowRectEx(&mut rect, style, FALSE, WS_EX_CONTROLPARENT);
+
+    let window = CreateWindowExW(
+        WS_EX_CONTROLPARENT,
+        w!("tacky-borders-settings"),
+        w!("tacky-borders settings"),
+        style,
+        CW_USEDEFAULT,
+        CW_USEDEFAULT,
+        rect.right - rect.left,
+        rect.bottom - rect.top,
+        None,
+        None,
+        hinstance,
+        None,
+    )?;
+
+    let font = GetStockObject(DEFAULT_GUI_FONT);
+    let control = |ex_style: WINDOW_EX_STYLE,
+                   class: PCWSTR,
+                   text: &str,
+                   style: WINDOW_STYLE,
+                   id: i32,
+                   (x, y, width, height): (i32, i32, i32, i32)| {
+        let control = CreateWindowExW(
+            ex_style,
+            class,
+            &HSTRING::from(text),
+            WS_CHILD | WS_VISIBLE | style,
+            scale(x),
+            scale(y),
+            scale(width),
+            scale(height),
+            window,
+            HMENU(id as usize as _),
+            hinstance,
+            None,
+        )
+        .unwrap_or_default();
+        SendMessageW(control, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+        control
+    };
+    let label = |text: &str, y: i32| {
+        control(
+            WINDOW_EX_STYLE(0),
+            w!("STATIC"),
+            text,
+            WINDOW_STYLE(0),
+            0,
+            (12, y + 3, 110, 20),
+        )
+    };
+    let field = |text: &str, id: i32, y: i32| {
+        control(
+            WS_EX_CLIENTEDGE,
+            w!("EDIT"),
+            text,
+            WS_TABSTOP | WINDOW_STYLE(ES_AUTOHSCROLL as u32),
+            id,
+            (130, y, 188, 22),
+        )
+    };
+
+    label("Border width", 12);
+    let width = field(&border_width.to_string(), ID_WIDTH, 12);
+    label("Border radius", 42);
+    let radius = field(&border_radius.to_string(), ID_RADIUS, 42);
+    label("Active color", 72);
+    let active_color = field(&initial_colors[0].0, ID_ACTIVE_COLOR, 72);
+    label("Inactive color", 102);
+    let inactive_color = field(&initial_colors[1].0, ID_INACTIVE_COLOR, 102);
+
+    label("Animations", 136);
+    let disabled = state::tray_choices().disabled_animations;
+    let animations = AnimationType::ALL
+        .iter()
+        .enumerate()
+        .map(|(i, anim_type)| {
+            let checkbox = control(
+                WINDOW_EX_STYLE(0),
+                w!("BUTTON"),
+                &format!("{anim_type:?}"),
+                WS_TABSTOP | WINDOW_STYLE(BS_AUTOCHECKBOX as u32),
+                ID_FIRST_ANIMATION + i as i32,
+                (
+                    12 + 154 * (i as i32 % 2),
+                    158 + 22 * (i as i32 / 2),
+                    150,
+                    20,
+                ),
+            );
+            let checked = !disabled.contains(anim_type);
+            SendMessageW(checkbox, BM_SETCHECK, WPARAM(checked as usize), LPARAM(0));
+            checkbox
+        })
+        .collect();
+
+    let status = control(
+        WINDOW_EX_STYLE(0),
+        w!("STATIC"),
+        "",
+        WINDOW_STYLE(0),
+        0,
+        (12, 210, 306, 34),
+    );
+    if let Some(theme) = theme {
+        let _ = SetWindowTextW(
+            status,
+            &HSTRING::from(format!(
+                "The '{theme}' theme is in use, so its colors win over saved ones."
+            )),
+        );
+    }
+
+    let button = |text: &str, id: i32, x: i32, style: i32| {
+        control(
+            WINDOW_EX_STYLE(0),
+            w!("BUTTON"),
+            text,
+            WS_TABSTOP | WINDOW_STYLE(style as u32),
+            id,
+            (x, 252, 74, 26),
+        )
+    };
+    button("Apply", ID_APPLY, 84, BS_PUSHBUTTON);
+    button("Save", ID_SAVE, 164, BS_PUSHBUTTON);
+    button("Close", ID_CLOSE, 244, BS_PUSHBUTTON);
+
+    Ok(Settings {
+        window,
+        width,
+        radius,
+        active_color,
+        inactive_color,
+        animations,
+        status,
+        initial_colors,
+        preview: None,
+        preview_retries: 0,
+        on_toggle,
+    })
+}
+
+// Gradients don't fit in a text field, so they're only named
+fn color_text(color: &ColorConfig) -> String {
+    match color {
+        ColorConfig::SolidConfig(color) => color.clone(),
+        ColorConfig::GradientConfig(_) => "(gradient)".to_string(),
+    }
+}
+
+// The shortest text that gets back the same f32, since widening it to f64 makes e.g. 1.1 into
+// 1.100000023841858
+fn number(value: f32) -> Value {
+    value
+        .to_string()
+        .parse::<f64>()
+        .map_or(Value::Null, Value::from)
+}
+
+fn get_text(control: HWND) -> String {
+    unsafe {
+        let mut text = vec![0u16; GetWindowTextLengthW(control) as usize + 1];
+        let len = GetWindowTextW(control, &mut text);
+        String::from_utf16_lossy(&text[..len.max(0) as usize])
+    }
+}
+
+impl Settings {
+    fn read_values(&self) -> Result<Values, String> {
+        let border_width = get_text(self.width)
+            .trim()
+            .parse::<f32>()
+            .map_err(|_| "The border width has to be a number.".to_string())?;
+        if border_width < 0.0 {
+            return Err("The border width can't be negative.".to_string());
+        }
+        let border_radius = get_text(self.radius)
+            .trim()
+            .parse::<f32>()
+            .map_err(|_| "The border radius has to be a number (-1 picks one).".to_string())?;
+
+        Ok(Values {
+            border_width,
+            border_radius,
+            active_color: self.read_color(self.active_color, 0, "active")?,
+            inactive_color: self.read_color(self.inactive_color, 1, "inactive")?,
+        })
+    }
+
+    fn read_color(
+        &self,
+        control: HWND,
+        index: usize,
+        name: &str,
+    ) -> Result<Option<ColorConfig>, String> {
+        let text = get_text(control).trim().to_string();
+        if text == self.initial_colors[index].0 {
+            return Ok(None);
+        }
+        if text.is_empty() {
+            return Err(format!("The {name} color can't be empty."));
+        }
+
+        let color = ColorConfig::SolidConfig(text);
+        match color.validate() {
+            Ok(_) => Ok(Some(color)),
+            Err(_) => Err(format!(
+                "The {name} color has to be a hex code (like #ff8000), rgb(), hsl(), oklch(), or \
+                 accent."
+            )),
+        }
+    }
+
+    // The colors borders should end up with. When either one is changed, both are used, since a
+    // palette_preset would otherwise win over them.
+    fn colors(&self, values: &Values) -> (ColorConfig, ColorConfig) {
+        (
+            values
+                .active_color
+                .clone()
+                .unwrap_or_else(|| self.initial_colors[0].1.clone()),
+            values
+                .inactive_color
+                .clone()
+                .unwrap_or_else(|| self.initial_colors[1].1.clone()),
+        )
+    }
+
+    fn set_status(&self, text: &str) {
+        let _ = unsafe { SetWindowTextW(self.status, &HSTRING::from(text)) };
+    }
+
+    // Recreate the window's own border with the values in the fields
+    fn update_preview(&mut self) {
+        let _ = unsafe { KillTimer(self.window, PREVIEW_TIMER) };
+
+        let values = match self.read_values() {
+            Ok(values) => values,
+            Err(message) => {
+                self.set_status(&message);
+                return;
+            }
+        };
+
+        if let Some(preview) = self.preview.take() {
+            preview.detach();
+        }
+
+        // The old border is unregistered by its own thread, and attaching fails until it has been.
+        // Waiting for that here would freeze the window, so the timer checks back in a moment.
+        if get_border_from_window(self.window).is_some()
+            && self.preview_retries < PREVIEW_MAX_RETRIES
+        {
+            self.preview_retries += 1;
+            unsafe { SetTimer(self.window, PREVIEW_TIMER, PREVIEW_RETRY_MS, None) };
+            return;
+        }
+        self.preview_retries = 0;
+
+        let (active_color, inactive_color) = self.colors(&values);
+        let options = BorderOptions {
+            border_width: Some(values.border_width),
+            border_radius: Some(values.border_radius),
+            active_color: Some(active_color),
+            inactive_color: Some(inactive_color),
+            ..Default::default()
+        };
+        match Border::attach(self.window, options) {
+            Ok(preview) => self.preview = Some(preview),
+            Err(e) => error!("could not attach settings preview border: {e:#}"),
+        }
+    }
+
+    fn apply(&mut self) {
+        let values = match self.read_values() {
+            Ok(values) => values,
+            Err(message) => return self.set_status(&message),
+        };
+
+        let colors_changed = values.active_color.is_some() || values.inactive_color.is_some();
+        let (active_color, inactive_color) = self.colors(&values);
+        crate::update_config(|config: &mut Config| {
+            config.global.border_width = values.border_width;
+            config.global.border_radius = values.border_radius;
+            if colors_changed {
+                config.global.active_color = active_color;
+                config.global.inactive_color = inactive_color;
+                config.global.palette_preset = None;
+            }
+        });
+
+        self.update_preview();
+        self.set_status("Applied until the next reload.");
+    }
+
+    fn save(&mut self) {
+        let values = match self.read_values() {
+            Ok(values) => values,
+            Err(message) => return self.set_status(&message),
+        };
+
+        let mut options = vec![
+            ("border_width", number(values.border_width)),
+            ("border_radius", number(values.border_radius)),
+        ];
+
+        let has_palette = CONFIG.lock().unwrap().global.palette_preset.is_some();
+        if values.active_color.is_some() || values.inactive_color.is_some() {
+            // Unchanged colors only need writing when they came from the palette, which is then
+            // turned off. Those are always solid colors, so they fit on one line.
+            let (active_color, inactive_color) = self.colors(&values);
+            for (key, color, changed) in [
+                ("active_color", active_color, values.active_color.is_some()),
+                (
+                    "inactive_color",
+                    inactive_color,
+                    values.inactive_color.is_some(),
+                ),
+            ] {
+                if let (true, ColorConfig::SolidConfig(color)) = (changed || has_palette, color) {
+                    options.push((key, Value::String(color)));
+                }
+            }
+            if has_palette {
+                options.push(("palette_preset", Value::Null));
+            }
+        }
+
+        match Config::save_global_options(&options) {
+            Ok(path) => {
+                crate::reload();
+                self.update_preview();
+                self.set_status(&format!("Saved to {}.", path.display()));
+            }
+            Err(e) => {
+                error!("could not save settings: {e:#}");
+                self.set_status(&format!("Could not save: {e:#}"));
+            }
+        }
+    }
+
+    // Same as the tray menu's animation toggles, including whether they're remembered
+    fn toggle_animation(&mut self, index: usize) {
+        let Some(anim_type) = AnimationType::ALL.get(index).cloned() else {
+            return;
+        };
+        let checked =
+            unsafe { SendMessageW(self.animations[index], BM_GETCHECK, WPARAM(0), LPARAM(0)) }.0
+                == 1;
+
+        let save = CONFIG
+            .lock()
+            .unwrap()
+            .global
+            .remember_tray_choices
+            .unwrap_or(true);
+        let mut choices = state::tray_choices();
+        choices.disabled_animations.retain(|t| *t != anim_type);
+        if !checked {
+            choices.disabled_animations.push(anim_type);
+        }
+        state::set_tray_choices(choices, save);
+
+        crate::reload_borders();
+        self.update_preview();
+        (self.on_toggle)();
+    }
+}
+
+unsafe extern "system" fn settings_wnd_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    match message {
+        WM_COMMAND => {
+            let id = (wparam.0 & 0xffff) as i32;
+            let notification = ((wparam.0 >> 16) & 0xffff) as u32;
+
+            // Setting a field's text sends this too, before SETTINGS is filled in, so it can't
+            // touch SETTINGS
+            if notification == EN_CHANGE {
+                SetTimer(window, PREVIEW_TIMER, PREVIEW_DELAY_MS, None);
+                return LRESULT(0);
+            }
+            if notification != BN_CLICKED {
+                return DefWindowProcW(window, message, wparam, lparam);
+            }
+
+            // Escape clicks IDCANCEL
+            if id == ID_CLOSE || id == IDCANCEL.0 {
+                let _ = DestroyWindow(window);
+                return LRESULT(0);
+            }
+            SETTINGS.with_borrow_mut(|s| {
+                let Some(settings) = s else {
+                    return;
+                };
+                match id {
+                    ID_APPLY => settings.apply(),
+                    ID_SAVE => settings.save(),
+                    id if id >= ID_FIRST_ANIMATION => {
+                        settings.toggle_animation((id - ID_FIRST_ANIMATION) as usize)
+                    }
+                    _ => {}
+                }
+            });
+        }
+        WM_TIMER if wparam.0 == PREVIEW_TIMER => {
+            SETTINGS.with_borrow_mut(|s| {
+                if let Some(settings) = s {
+                    settings.update_preview();
+                }
+            });
+        }
+        // A reload from anywhere else takes the preview border with it
+        WM_ACTIVATE => {
+            SETTINGS.with_borrow_mut(|s| {
+                if let Some(settings) = s.as_mut().filter(|s| s.preview.is_some()) {
+                    if get_border_from_window(window).is_none() {
+                        settings.update_preview();
+                    }
+                }
+            });
+            return DefWindowProcW(window, message, wparam, lparam);
+        }
+        WM_DESTROY => {
+            if let Some(preview) = SETTINGS.take().and_then(|settings| settings.preview) {
+                preview.detach();
+            }
+            PostQuitMessage(0);
+        }
+        _ => return DefWindowProcW(window, message, wparam, lparam),
+    }
+    LRESULT(0)
+}
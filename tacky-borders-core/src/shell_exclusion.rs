@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time;
+use windows::core::{w, PCWSTR};
+use windows::Win32::Foundation::{HWND, RECT};
+use windows::Win32::UI::WindowsAndMessaging::{FindWindowExW, GetWindowRect};
+
+use crate::utils::*;
+
+// The Start menu, search, and notification flyouts are all CoreWindows, so we tell them apart from
+// other UWP windows by the process that owns them
+const SHELL_FLYOUT_PROCESSES: [&str; 4] = [
+    "StartMenuExperienceHost.exe",
+    "SearchHost.exe",
+    "SearchApp.exe",
+    "ShellExperienceHost.exe",
+];
+
+// Borders check this on every frame, and the Start menu can open at any time, so we only cache the
+// rects for a short while
+const CACHE_DURATION: time::Duration = time::Duration::from_millis(250);
+static EXCLUSION_CACHE: Mutex<Option<(time::Instant, Vec<RECT>)>> = Mutex::new(None);
+
+// Whether each CoreWindow we've found belongs to one of the SHELL_FLYOUT_PROCESSES. A window never
+// changes owners, so this saves opening its process every time the rects are checked.
+static FLYOUT_WINDOWS: Mutex<Option<HashMap<isize, bool>>> = Mutex::new(None);
+
+// Get the screen rects of the taskbars, Start menu, and notification flyouts that are currently
+// showing. Borders aren't allowed to draw over these.
+pub fn get_excluded_rects() -> Vec<RECT> {
+    let mut cache = EXCLUSION_CACHE.lock().unwrap();
+    if let Some((checked_at, ref rects)) = *cache {
+        if checked_at.elapsed() < CACHE_DURATION {
+            return rects.clone();
+        }
+    }
+
+    let mut windows = find_windows(w!("Shell_TrayWnd"));
+    windows.extend(find_windows(w!("Shell_SecondaryTrayWnd")));
+    windows.extend(find_flyout_windows());
+
+    let rects: Vec<RECT> = windows
+        .into_iter()
+        .filter(|hwnd| is_window_visible(*hwnd) && !is_cloaked(*hwnd))
+        .filter_map(|hwnd| {
+            let mut rect = RECT::default();
+            unsafe { GetWindowRect(hwnd, &mut rect) }.ok().map(|_| rect)
+        })
+        .collect();
+
+    *cache = Some((time::Instant::now(), rects.clone()));
+    rects
+}
+
+fn find_flyout_windows() -> Vec<HWND> {
+    let mut flyout_windows = FLYOUT_WINDOWS.lock().unwrap();
+    let mut last_flyout_windows = flyout_windows.take().unwrap_or_default();
+
+    // Only the CoreWindows that still exist are put back, so closed ones don't pile up
+    let core_windows: HashMap<isize, bool> = find_windows(w!("Windows.UI.Core.CoreWindow"))
+        .into_iter()
+        .map(|hwnd| {
+            let key = hwnd.0 as isize;
+            let is_flyout = last_flyout_windows.remove(&key).unwrap_or_else(|| {
+                SHELL_FLYOUT_PROCESSES.contains(&get_process_name(hwnd).as_str())
+            });
+            (key, is_flyout)
+        })
+        .collect();
+
+    let windows = core_windows
+        .iter()
+        .filter(|(_, is_flyout)| **is_flyout)
+        .map(|(key, _)| HWND(*key as _))
+        .collect();
+    *flyout_windows = Some(core_windows);
+    windows
+}
+
+// Find all the top-level windows with the given class name
+fn find_windows(class_name: PCWSTR) -> Vec<HWND> {
+    let mut windows = Vec::new();
+    let mut previous = HWND::default();
+
+    while let Ok(hwnd) =
+        unsafe { FindWindowExW(HWND::default(), previous, class_name, PCWSTR::null()) }
+    {
+        windows.push(hwnd);
+        previous = hwnd;
+    }
+
+    windows
+}
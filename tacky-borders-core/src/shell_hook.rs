@@ -0,0 +1,331 @@
+use anyhow::{anyhow, Context};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::{mpsc, LazyLock, Mutex, Once};
+use std::thread;
+use windows::core::w;
+use windows::Win32::Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, RECT, WPARAM};
+use windows::Win32::System::SystemInformation::GetTickCount;
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DeregisterShellHookWindow, DestroyWindow, DispatchMessageW,
+    GetMessageW, GetWindowRect, IsIconic, IsWindow, KillTimer, PostMessageW, PostQuitMessage,
+    RegisterClassExW, RegisterShellHookWindow, RegisterWindowMessageW, SetTimer, TranslateMessage,
+    HSHELL_HIGHBIT, HSHELL_REDRAW, HSHELL_WINDOWACTIVATED, HSHELL_WINDOWCREATED,
+    HSHELL_WINDOWDESTROYED, MSG, WM_CLOSE, WM_DESTROY, WM_TIMER, WNDCLASSEXW, WS_EX_TOOLWINDOW,
+    WS_POPUP,
+};
+
+use crate::event_hook;
+use crate::registry::BORDERS;
+use crate::tracking::{WindowEvent, WindowTracker};
+use crate::utils::*;
+use crate::SendHWND;
+
+// The shell hook is for when the WinEvent hook can't be used (some locked down or remote desktop
+// setups block it). The shell tells us about windows being created, destroyed, activated, and
+// retitled, but nothing tells us when windows move, minimize, or hide, so we poll every border's
+// window for that.
+const POLL_TIMER_ID: usize = 1;
+const POLL_INTERVAL_MS: u32 = 50;
+
+// Sent instead of HSHELL_WINDOWACTIVATED when a fullscreen window is around. The windows crate
+// doesn't have it.
+const HSHELL_RUDEAPPACTIVATED: u32 = HSHELL_WINDOWACTIVATED | HSHELL_HIGHBIT;
+
+static SHELL_HOOK_CLASS: Once = Once::new();
+
+// The hidden window that gets the shell's messages, while the shell hook is running
+static SHELL_HOOK_WINDOW: Mutex<Option<SendHWND>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct WindowState {
+    minimized: bool,
+    shown: bool,
+    rect: RECT,
+}
+
+thread_local! {
+    // What each border's window looked like at the last poll, keyed by tracking window
+    static WINDOW_STATES: RefCell<HashMap<isize, WindowState>> = RefCell::new(HashMap::new());
+}
+
+// The shell sends its messages with this ID. It's 0 if the message couldn't be registered.
+fn shell_hook_message() -> u32 {
+    static MESSAGE: LazyLock<u32> =
+        LazyLock::new(|| unsafe { RegisterWindowMessageW(w!("SHELLHOOK")) });
+    *MESSAGE
+}
+
+pub(crate) struct ShellHookTracker;
+
+impl WindowTracker for ShellHookTracker {
+    fn name(&self) -> &'static str {
+        "shell hook"
+    }
+
+    fn start(&mut self) -> anyhow::Result<()> {
+        start()
+    }
+
+    fn stop(&mut self) -> bool {
+        stop();
+        true
+    }
+}
+
+// Starts the shell hook's thread and waits for it to register its window
+fn start() -> anyhow::Result<()> {
+    if SHELL_HOOK_WINDOW.lock().unwrap().is_some() {
+        return Ok(());
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    let _ = thread::spawn(move || match create_window() {
+        Ok(window) => {
+            *SHELL_HOOK_WINDOW.lock().unwrap() = Some(SendHWND(window));
+            let _ = sender.send(Ok(()));
+
+            unsafe {
+                let mut message = MSG::default();
+                while GetMessageW(&mut message, HWND::default(), 0, 0).into() {
+                    let _ = TranslateMessage(&message);
+                    DispatchMessageW(&message);
+                }
+            }
+        }
+        Err(e) => {
+            let _ = sender.send(Err(e));
+        }
+    });
+
+    receiver.recv().context("shell hook thread exited early")?
+}
+
+// Closes the shell hook's window, which also ends its thread
+fn stop() {
+    if let Some(window) = SHELL_HOOK_WINDOW.lock().unwrap().take() {
+        let _ = unsafe { PostMessageW(window.0, WM_CLOSE, WPARAM(0), LPARAM(0)) };
+    }
+}
+
+fn create_window() -> anyhow::Result<HWND> {
+    register_shell_hook_class_once();
+
+    unsafe {
+        let hinstance: HINSTANCE = std::mem::transmute(&crate::__ImageBase);
+
+        // Message-only windows don't get shell hook messages, so this is a hidden top-level one
+        let window = CreateWindowExW(
+            WS_EX_TOOLWINDOW,
+            w!("tacky-borders-shell-hook"),
+            w!("tacky-borders shell hook"),
+            WS_POPUP,
+            0,
+            0,
+            0,
+            0,
+            None,
+            None,
+            hinstance,
+            None,
+        )
+        .context("could not create shell hook window")?;
+
+        if shell_hook_message() == 0 || !RegisterShellHookWindow(window).as_bool() {
+            let _ = DestroyWindow(window);
+            return Err(anyhow!("could not register shell hook window"));
+        }
+
+        SetTimer(window, POLL_TIMER_ID, POLL_INTERVAL_MS, None);
+        Ok(window)
+    }
+}
+
+fn register_shell_hook_class_once() {
+    SHELL_HOOK_CLASS.call_once(|| unsafe {
+        let hinstance: HINSTANCE = std::mem::transmute(&crate::__ImageBase);
+
+        let window_class = WNDCLASSEXW {
+            cbSize: size_of::<WNDCLASSEXW>() as u32,
+            lpfnWndProc: Some(shell_hook_wnd_proc),
+            hInstance: hinstance,
+            lpszClassName: w!("tacky-borders-shell-hook"),
+            ..Default::default()
+        };
+
+        if RegisterClassExW(&window_class) == 0 {
+            error!("could not register shell hook window class");
+        }
+    });
+}
+
+unsafe extern "system" fn shell_hook_wnd_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    match message {
+        message if message != 0 && message == shell_hook_message() => {
+            on_shell_event(wparam.0 as u32, HWND(lparam.0 as _));
+            LRESULT(0)
+        }
+        WM_TIMER if wparam.0 == POLL_TIMER_ID => {
+            poll_windows();
+            LRESULT(0)
+        }
+        WM_DESTROY => {
+            let _ = DeregisterShellHookWindow(window);
+            let _ = KillTimer(window, POLL_TIMER_ID);
+            PostQuitMessage(0);
+            LRESULT(0)
+        }
+        _ => DefWindowProcW(window, message, wparam, lparam),
+    }
+}
+
+fn on_shell_event(code: u32, hwnd: HWND) {
+    // Paused borders get recreated from scratch once they're resumed
+    if crate::is_suspended() {
+        return;
+    }
+
+    let time = unsafe { GetTickCount() };
+    let queue = |event: WindowEvent| event_hook::queue_event(event, time);
+
+    match code {
+        HSHELL_WINDOWCREATED => queue(WindowEvent::Created(hwnd)),
+        HSHELL_WINDOWDESTROYED => queue(WindowEvent::Destroyed(hwnd)),
+        // The activated window also comes to the front
+        HSHELL_WINDOWACTIVATED | HSHELL_RUDEAPPACTIVATED => {
+            queue(WindowEvent::FocusChanged(hwnd));
+            queue(WindowEvent::Reordered(hwnd));
+        }
+        // Sent when a window's title changes, among other things
+        HSHELL_REDRAW => queue(WindowEvent::TitleChanged(hwnd)),
+        _ => {}
+    }
+}
+
+// Queue up the events that the shell doesn't send for each border's window: minimizing, hiding,
+// and moving. Borders ignore location changes that don't change anything, so those are sent on
+// every poll.
+fn poll_windows() {
+    if crate::is_suspended() {
+        return;
+    }
+
+    let time = unsafe { GetTickCount() };
+
+    // Monitor frames and the cursor ring don't have a window to poll
+    let windows: Vec<HWND> = BORDERS
+        .entries()
+        .into_iter()
+        .filter_map(|(key, _)| key.hwnd())
+        .filter(|hwnd| unsafe { IsWindow(*hwnd) }.as_bool())
+        .collect();
+
+    WINDOW_STATES.with_borrow_mut(|states| {
+        states.retain(|key, _| windows.iter().any(|hwnd| hwnd.0 as isize == *key));
+
+        for hwnd in windows {
+            let mut rect = RECT::default();
+            let _ = unsafe { GetWindowRect(hwnd, &mut rect) };
+            let state = WindowState {
+                minimized: unsafe { IsIconic(hwnd) }.as_bool(),
+                shown: is_window_visible(hwnd) && !is_cloaked(hwnd),
+                rect,
+            };
+
+            // The first time we see a window is only for finding out what it looks like, since
+            // its border was just made to match it anyways
+            if let Some(last) = states.insert(hwnd.0 as isize, state) {
+                for event in state_changes(hwnd, &last, &state) {
+                    event_hook::queue_event(event, time);
+                }
+            }
+        }
+    });
+}
+
+// The events that get a border from 'last' to 'state'
+fn state_changes(hwnd: HWND, last: &WindowState, state: &WindowState) -> Vec<WindowEvent> {
+    let mut events = Vec::new();
+
+    match (last.minimized, state.minimized) {
+        (false, true) => events.push(WindowEvent::Minimized(hwnd)),
+        (true, false) => events.push(WindowEvent::Restored(hwnd)),
+        _ => {}
+    }
+    match (last.shown, state.shown) {
+        (true, false) => events.push(WindowEvent::Hidden(hwnd)),
+        (false, true) => events.push(WindowEvent::Shown(hwnd)),
+        _ => {}
+    }
+
+    if !state.minimized && state.rect != last.rect {
+        events.push(WindowEvent::Moved(hwnd));
+    }
+
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(minimized: bool, shown: bool, left: i32) -> WindowState {
+        WindowState {
+            minimized,
+            shown,
+            rect: RECT {
+                left,
+                top: 0,
+                right: left + 100,
+                bottom: 100,
+            },
+        }
+    }
+
+    #[test]
+    fn state_changes_table() {
+        let hwnd = HWND(1 as _);
+        let cases = [
+            // Nothing changed, so there's nothing to tell the border (not even a Moved)
+            (state(false, true, 0), state(false, true, 0), vec![]),
+            (
+                state(false, true, 0),
+                state(false, true, 10),
+                vec![WindowEvent::Moved(hwnd)],
+            ),
+            (
+                state(false, true, 0),
+                state(true, true, -32000),
+                vec![WindowEvent::Minimized(hwnd)],
+            ),
+            (
+                state(true, true, -32000),
+                state(false, true, 0),
+                vec![WindowEvent::Restored(hwnd), WindowEvent::Moved(hwnd)],
+            ),
+            (
+                state(false, true, 0),
+                state(false, false, 0),
+                vec![WindowEvent::Hidden(hwnd)],
+            ),
+            (
+                state(false, false, 0),
+                state(false, true, 20),
+                vec![WindowEvent::Shown(hwnd), WindowEvent::Moved(hwnd)],
+            ),
+        ];
+
+        for (last, state, expected) in cases {
+            assert_eq!(
+                state_changes(hwnd, &last, &state),
+                expected,
+                "{last:?} -> {state:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,462 @@
+//! A headless mode that plays window events from a script instead of listening for real ones.
+//!
+//! A [`Script`] describes some made-up windows and a timeline of events for them (creating,
+//! focusing, moving, minimizing, ...). [`run`] feeds those events through the same window rules and
+//! animation engine that real borders use, and records every change to a border's state (its rect,
+//! colors, and animation progress) as a [`Transition`]. Nothing is drawn and no windows are
+//! created, so this can run in CI to catch changes in how borders react to events.
+//!
+//! Scripts are YAML (or JSON, which YAML is a superset of):
+//!
+//! ```yaml
+//! windows:
+//!   - id: editor
+//!     process: Code.exe
+//!     rect: [100, 100, 900, 700]
+//! events:
+//!   - { at: 0, window: editor, event: Create }
+//!   - { at: 0, window: editor, event: Focus }
+//!   - { at: 500, window: editor, event: Move, rect: [200, 100, 1000, 700] }
+//!   - { at: 1000, window: editor, event: Minimize }
+//! ```
+//!
+//! Times are in milliseconds. Windows created at 0 are treated as already open when tacky-borders
+//! started, so they skip `initialize_delay` just like they would for real.
+//!
+//! Borders are made the same way as real ones, but things that need a real window or the rest of
+//! the system (Lua scripts, plugins, saved runtime state, DPI scaling, and so on) are left out:
+//! every window is at 96 DPI, and automatic corner radii are the Windows 11 default.
+
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time;
+use windows::Win32::Foundation::RECT;
+
+use crate::border_config::{Config, CONFIG};
+use crate::colors::Color;
+use crate::lifecycle::LifecycleState;
+use crate::rule_engine::{self, WindowInfo};
+use crate::utils::{build_border, expand_path, get_rule_colors, BorderInputs};
+use crate::window_border::WindowBorder;
+
+/// A timeline of window events to simulate.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Script {
+    /// A config to use instead of the user's config.yaml, relative to the script.
+    #[serde(default)]
+    pub config: Option<PathBuf>,
+    /// How often to step the animations, in milliseconds.
+    #[serde(default = "default_step_ms")]
+    pub step_ms: u64,
+    /// How long to run for. Defaults to one second after the last event.
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+    pub windows: Vec<ScriptWindow>,
+    pub events: Vec<ScriptEvent>,
+}
+
+fn default_step_ms() -> u64 {
+    16
+}
+
+/// A made-up window, with the properties that window rules can match against.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScriptWindow {
+    pub id: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub class: String,
+    #[serde(default)]
+    pub process: String,
+    #[serde(default)]
+    pub monitor: Option<u32>,
+    #[serde(default)]
+    pub maximized: bool,
+    /// The window's frame as [left, top, right, bottom].
+    pub rect: [i32; 4],
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScriptEvent {
+    /// When the event happens, in milliseconds from the start.
+    pub at: u64,
+    /// The id of the window it happens to.
+    pub window: String,
+    pub event: EventKind,
+    /// The new frame for `Move` events.
+    #[serde(default)]
+    pub rect: Option<[i32; 4]>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EventKind {
+    Create,
+    Destroy,
+    Focus,
+    Move,
+    Minimize,
+    Restore,
+}
+
+/// A border's state at some point in the simulation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BorderState {
+    pub visible: bool,
+    pub active: bool,
+    pub lifecycle: LifecycleState,
+    /// The border window as [left, top, right, bottom].
+    pub rect: [i32; 4],
+    pub stroke_width: f32,
+    pub active_color: String,
+    pub active_opacity: f32,
+    pub inactive_color: String,
+    pub inactive_opacity: f32,
+    pub fade_progress: f32,
+    pub spiral_angle: f32,
+}
+
+/// A border's state right after it changed.
+#[derive(Debug, Clone, Serialize)]
+pub struct Transition {
+    pub time_ms: u64,
+    pub window: String,
+    /// The event that caused the change, or None if it was an animation frame.
+    pub event: Option<EventKind>,
+    /// False once the border has been destroyed, in which case the state is its last one.
+    pub exists: bool,
+    #[serde(flatten)]
+    pub state: BorderState,
+}
+
+// Things that happen some time after the event that caused them, like real borders sleeping for
+// initialize_delay or unminimize_delay
+#[derive(Debug, Clone, Copy)]
+enum Pending {
+    Init,
+    Unminimize,
+}
+
+#[derive(Debug)]
+struct SimulatedBorder {
+    frame: [i32; 4],
+    border: WindowBorder,
+    visible: bool,
+    pending: Option<(u64, Pending)>,
+    last_state: Option<BorderState>,
+}
+
+/// Reads a script from a file.
+pub fn load_script(path: &Path) -> anyhow::Result<Script> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("could not read script {}", path.display()))?;
+    let mut script: Script = serde_yaml::from_str(&contents).context("could not parse script")?;
+
+    if let (Some(config_path), Some(script_dir)) = (script.config.as_mut(), path.parent()) {
+        let expanded = expand_path(&config_path.to_string_lossy());
+        *config_path = script_dir.join(expanded);
+    }
+
+    Ok(script)
+}
+
+/// Plays the script and returns every state change, in order.
+pub fn run(script: &Script) -> anyhow::Result<Vec<Transition>> {
+    match script.config {
+        Some(ref path) => {
+            let contents = fs::read_to_string(path)
+                .with_context(|| format!("could not read config {}", path.display()))?;
+            let config = Config::parse(&contents).context("could not parse config")?;
+            run_with_config(script, &config)
+        }
+        None => {
+            // A copy, so the config isn't locked (and every border stuck) for the whole run
+            let config = CONFIG.lock().unwrap().clone();
+            run_with_config(script, &config)
+        }
+    }
+}
+
+/// Plays the script against the given config instead of the user's.
+pub fn run_with_config(script: &Script, config: &Config) -> anyhow::Result<Vec<Transition>> {
+    let windows: HashMap<&str, &ScriptWindow> = script
+        .windows
+        .iter()
+        .map(|window| (window.id.as_str(), window))
+        .collect();
+
+    let mut events = script.events.clone();
+    for event in events.iter() {
+        if !windows.contains_key(event.window.as_str()) {
+            return Err(anyhow!(
+                "event at {}ms is for unknown window '{}'",
+                event.at,
+                event.window
+            ));
+        }
+    }
+    // A stable sort, so events at the same time keep the order they were written in
+    events.sort_by_key(|event| event.at);
+
+    let last_event = events.last().map_or(0, |event| event.at);
+    let duration = script.duration_ms.unwrap_or(last_event + 1000);
+    let step = script.step_ms.max(1);
+
+    let start = time::Instant::now();
+    let mut borders: HashMap<String, SimulatedBorder> = HashMap::new();
+    let mut focused: Option<String> = None;
+    let mut transitions = Vec::new();
+    let mut next_event = 0;
+
+    let mut now_ms = 0;
+    while now_ms <= duration {
+        let now = start + time::Duration::from_millis(now_ms);
+
+        while let Some(event) = events.get(next_event).filter(|event| event.at <= now_ms) {
+            next_event += 1;
+            let window = windows[event.window.as_str()];
+
+            match event.event {
+                EventKind::Create => {
+                    if borders.contains_key(&window.id) {
+                        continue;
+                    }
+                    let Some(border) = simulated_border(window, config, event.at == 0) else {
+                        debug!("border is disabled for simulated window '{}'", window.id);
+                        continue;
+                    };
+                    let delay = border.initialize_delay;
+                    borders.insert(
+                        window.id.clone(),
+                        SimulatedBorder {
+                            frame: window.rect,
+                            border,
+                            visible: false,
+                            pending: Some((now_ms + delay, Pending::Init)),
+                            last_state: None,
+                        },
+                    );
+                    continue;
+                }
+                EventKind::Destroy => {
+                    if let Some(mut sim) = borders.remove(&window.id) {
+                        sim.border.transition_to(LifecycleState::Destroyed, now);
+                        if focused.as_deref() == Some(window.id.as_str()) {
+                            focused = None;
+                        }
+                        transitions.push(Transition {
+                            time_ms: now_ms,
+                            window: window.id.clone(),
+                            event: Some(EventKind::Destroy),
+                            exists: false,
+                            state: state_of(&sim),
+                        });
+                    }
+                    continue;
+                }
+                EventKind::Focus => {
+                    focused = Some(window.id.clone());
+
+                    // Every border hears about focus changes, just like with WM_APP_FOCUS
+                    let focused_group = borders
+                        .get(&window.id)
+                        .and_then(|sim| sim.border.group.clone());
+                    for (id, sim) in borders.iter_mut() {
+                        // Borders that haven't been initialized yet will check for themselves
+                        if matches!(sim.pending, Some((_, Pending::Init))) {
+                            continue;
+                        }
+                        let is_active = *id == window.id
+                            || (sim.border.group.is_some() && sim.border.group == focused_group);
+                        sim.border.set_active(is_active, now);
+                    }
+                }
+                EventKind::Move => {
+                    let Some(rect) = event.rect else {
+                        return Err(anyhow!("Move event at {}ms has no rect", event.at));
+                    };
+                    if let Some(sim) = borders.get_mut(&window.id) {
+                        sim.frame = rect;
+                        sim.border.set_tracking_rect(to_rect(rect));
+                    }
+                }
+                EventKind::Minimize => {
+                    if let Some(sim) = borders.get_mut(&window.id) {
+                        sim.visible = false;
+                        sim.pending = None;
+                        sim.border.transition_to(LifecycleState::Hiding, now);
+                    }
+                }
+                EventKind::Restore => {
+                    if let Some(sim) = borders.get_mut(&window.id) {
+                        let delay = sim.border.unminimize_delay;
+                        sim.pending = Some((now_ms + delay, Pending::Unminimize));
+                    }
+                }
+            }
+
+            // Focus changes affect every border, not only the focused one
+            for (id, sim) in borders.iter_mut() {
+                if event.event == EventKind::Focus || *id == window.id {
+                    record(&mut transitions, now_ms, id, Some(event.event), sim);
+                }
+            }
+        }
+
+        for (id, sim) in borders.iter_mut() {
+            let cause = match sim.pending {
+                Some((at, pending)) if at <= now_ms => {
+                    sim.pending = None;
+                    finish_pending(sim, pending, focused.as_deref() == Some(id.as_str()), now);
+                    Some(match pending {
+                        Pending::Init => EventKind::Create,
+                        Pending::Unminimize => EventKind::Restore,
+                    })
+                }
+                _ => None,
+            };
+
+            if sim.visible && sim.pending.is_none() {
+                sim.border.step_animations(now);
+            }
+
+            record(&mut transitions, now_ms, id, cause, sim);
+        }
+
+        now_ms += step;
+    }
+
+    // HashMap iteration order is random, so put borders that changed at the same time in a
+    // predictable order
+    transitions.sort_by(|a, b| a.time_ms.cmp(&b.time_ms).then(a.window.cmp(&b.window)));
+
+    Ok(transitions)
+}
+
+fn finish_pending(
+    sim: &mut SimulatedBorder,
+    pending: Pending,
+    is_focused: bool,
+    now: time::Instant,
+) {
+    let border = &mut sim.border;
+    if matches!(pending, Pending::Init) {
+        border.is_active_window = is_focused;
+        border.animations.current = match border.is_active_window {
+            true => border.animations.active.clone(),
+            false => border.animations.inactive.clone(),
+        };
+    }
+    border.transition_to(LifecycleState::Visible, now);
+
+    border.set_tracking_rect(to_rect(sim.frame));
+    sim.visible = true;
+}
+
+// Only record a transition if something actually changed since the last one. Borders that are
+// still waiting for initialize_delay don't exist as far as anyone can see.
+fn record(
+    transitions: &mut Vec<Transition>,
+    time_ms: u64,
+    id: &str,
+    event: Option<EventKind>,
+    sim: &mut SimulatedBorder,
+) {
+    if matches!(sim.pending, Some((_, Pending::Init))) {
+        return;
+    }
+
+    let state = state_of(sim);
+    if sim.last_state.as_ref() == Some(&state) {
+        return;
+    }
+
+    sim.last_state = Some(state.clone());
+    transitions.push(Transition {
+        time_ms,
+        window: id.to_string(),
+        event,
+        exists: true,
+        state,
+    });
+}
+
+fn state_of(sim: &SimulatedBorder) -> BorderState {
+    let border = &sim.border;
+    let rect = border.window_rect;
+
+    BorderState {
+        visible: sim.visible,
+        active: border.is_active_window,
+        lifecycle: border.lifecycle,
+        rect: [rect.left, rect.top, rect.right, rect.bottom],
+        stroke_width: border.stroke_width(),
+        active_color: color_to_hex(&border.active_color),
+        active_opacity: border.active_color.fade_opacity(),
+        inactive_color: color_to_hex(&border.inactive_color),
+        inactive_opacity: border.inactive_color.fade_opacity(),
+        fade_progress: border.animations.fade_progress,
+        spiral_angle: border.animations.delta.rotation,
+    }
+}
+
+// Gradients are recorded as the average of their stops, which is enough to tell them apart
+fn color_to_hex(color: &Color) -> String {
+    let mut color = color.clone();
+    color.set_fade_opacity(1.0);
+    let flat = color.flatten();
+
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!(
+        "#{:02x}{:02x}{:02x}{:02x}",
+        channel(flat.r),
+        channel(flat.g),
+        channel(flat.b),
+        channel(flat.a)
+    )
+}
+
+fn to_rect([left, top, right, bottom]: [i32; 4]) -> RECT {
+    RECT {
+        left,
+        top,
+        right,
+        bottom,
+    }
+}
+
+// The same border a real window would get from the config, at 96 DPI. Simulations play the config
+// as it's written, so do not disturb, accessibility mode, and the tray menu's choices are left out.
+fn simulated_border(window: &ScriptWindow, config: &Config, initial: bool) -> Option<WindowBorder> {
+    let info = WindowInfo {
+        title: window.title.clone(),
+        class: window.class.clone(),
+        process: window.process.clone(),
+        monitor: window.monitor,
+        maximized: window.maximized,
+    };
+    let window_rule = rule_engine::get_rule(&config.window_rules, &info);
+
+    if window_rule.enabled == Some(false) {
+        return None;
+    }
+
+    let inputs = BorderInputs {
+        colors: get_rule_colors(&window_rule, config),
+        dpi: 96.0,
+        // What Windows 11 rounds windows by
+        window_radius: 8.0,
+        initial,
+        minimal: false,
+        accessibility: false,
+        disabled_animations: Vec::new(),
+    };
+
+    Some(build_border(config, &window_rule, inputs))
+}
@@ -0,0 +1,274 @@
+//! Changes made to borders at runtime (through [`ipc`](crate::ipc) commands like `set-color` or
+//! `disable`) that should stick around when tacky-borders restarts or reloads its config.
+//!
+//! Windows are remembered by a hash of their process name and class, so renaming a window (like a
+//! browser switching tabs) doesn't lose its override, and everything is stored in `state.yaml` next
+//! to the config. Overrides for windows that haven't been seen in [`STALE_AFTER_DAYS`] days are
+//! forgotten. The profile, theme, and animation toggles picked from the tray
+//! menu ([`TrayChoices`]) are kept here too. Borders toggled off with the `toggle` command are only
+//! remembered until their window closes, so those are never saved.
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{LazyLock, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use windows::Win32::Foundation::HWND;
+
+use crate::animations::AnimationType;
+use crate::border_config::Config;
+use crate::colors::ColorConfig;
+use crate::log_if_err;
+use crate::utils::*;
+
+static STATE: LazyLock<Mutex<RuntimeState>> = LazyLock::new(|| {
+    Mutex::new(match RuntimeState::load() {
+        Ok(state) => state,
+        Err(e) => {
+            error!("could not read state.yaml: {e:#}");
+            RuntimeState::default()
+        }
+    })
+});
+
+/// How long an override is kept after the last time a window it applies to got a border.
+pub const STALE_AFTER_DAYS: u64 = 90;
+
+// Tray choices made while remember_tray_choices is off. These win over the saved ones until the
+// next restart.
+static UNSAVED_TRAY_CHOICES: Mutex<Option<TrayChoices>> = Mutex::new(None);
+
+// Windows whose border was toggled off, by HWND since they're forgotten once the window is closed
+static TOGGLED_OFF: LazyLock<Mutex<HashSet<isize>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RuntimeState {
+    #[serde(default)]
+    windows: HashMap<String, WindowOverride>,
+    #[serde(default)]
+    tray: TrayChoices,
+}
+
+/// What was picked from the tray menu.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrayChoices {
+    /// The profile to load instead of config.yaml.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Animations that are turned off for every border.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disabled_animations: Vec<AnimationType>,
+    /// The theme to use instead of the config's. An empty name means no theme at all.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+}
+
+/// What was changed about a window's border at runtime.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WindowOverride {
+    /// Replaces both the active and inactive color.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<ColorConfig>,
+    /// The border was turned off.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub disabled: bool,
+    /// The last day (counted from the Unix epoch) a window this applies to was seen.
+    #[serde(default)]
+    pub last_seen: u64,
+}
+
+impl WindowOverride {
+    fn is_empty(&self) -> bool {
+        self.color.is_none() && !self.disabled
+    }
+}
+
+impl RuntimeState {
+    fn path() -> anyhow::Result<PathBuf> {
+        Ok(Config::get_config_dir()?.join("state.yaml"))
+    }
+
+    fn load() -> anyhow::Result<Self> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(&path).context("could not read state.yaml")?;
+        let mut state: Self = serde_yaml::from_str(&contents)?;
+
+        // This also drops the overrides from before they were keyed by class, which never have a
+        // last_seen and can't match anything anymore
+        let pruned = prune_stale(&mut state.windows, today());
+        if pruned > 0 {
+            info!("forgot {pruned} override(s) for windows that haven't been seen in a while");
+            log_if_err!(state.save());
+        }
+
+        Ok(state)
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        let contents = serde_yaml::to_string(self).context("could not serialize state")?;
+        fs::write(Self::path()?, contents).context("could not write state.yaml")
+    }
+}
+
+fn window_key(hwnd: HWND) -> String {
+    identity_key(&get_process_name(hwnd), &get_window_class(hwnd))
+}
+
+// These keys are saved to disk, so they need a hash that never changes
+fn identity_key(process_name: &str, class: &str) -> String {
+    format!("{:016x}", stable_hash(&format!("{process_name}\0{class}")))
+}
+
+fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_secs() / 86400)
+}
+
+// Remove the overrides that haven't been seen in STALE_AFTER_DAYS, returning how many there were
+fn prune_stale(windows: &mut HashMap<String, WindowOverride>, today: u64) -> usize {
+    let count = windows.len();
+    windows.retain(|_, window_override| {
+        window_override.last_seen > 0 && window_override.last_seen + STALE_AFTER_DAYS >= today
+    });
+    count - windows.len()
+}
+
+/// Returns the saved override for the window, if it has one.
+pub fn get_override(hwnd: HWND) -> Option<WindowOverride> {
+    let key = window_key(hwnd);
+    let mut state = STATE.lock().unwrap();
+    let window_override = state.windows.get_mut(&key)?;
+
+    // Only saved once a day, since this runs for every border that gets created
+    let today = today();
+    if window_override.last_seen == today {
+        return Some(window_override.clone());
+    }
+    window_override.last_seen = today;
+    let window_override = window_override.clone();
+    log_if_err!(state.save());
+
+    Some(window_override)
+}
+
+/// Returns every saved override, keyed by the hash of the window's process name and class.
+pub fn all_overrides() -> HashMap<String, WindowOverride> {
+    STATE.lock().unwrap().windows.clone()
+}
+
+/// Remembers (or with `None`, forgets) a color for the window's border.
+pub fn set_color(hwnd: HWND, color: Option<ColorConfig>) {
+    update(hwnd, |window_override| window_override.color = color);
+}
+
+/// Remembers whether the window's border was turned off.
+pub fn set_disabled(hwnd: HWND, disabled: bool) {
+    update(hwnd, |window_override| window_override.disabled = disabled);
+}
+
+/// Whether the window's border was toggled off.
+pub fn is_toggled_off(hwnd: HWND) -> bool {
+    TOGGLED_OFF.lock().unwrap().contains(&(hwnd.0 as isize))
+}
+
+/// Toggles the window's border off (or back on) until the window is closed.
+pub fn set_toggled_off(hwnd: HWND, toggled_off: bool) {
+    let mut windows = TOGGLED_OFF.lock().unwrap();
+    match toggled_off {
+        true => windows.insert(hwnd.0 as isize),
+        false => windows.remove(&(hwnd.0 as isize)),
+    };
+}
+
+fn update(hwnd: HWND, f: impl FnOnce(&mut WindowOverride)) {
+    let key = window_key(hwnd);
+    let mut state = STATE.lock().unwrap();
+
+    let window_override = state.windows.entry(key.clone()).or_default();
+    f(window_override);
+    window_override.last_seen = today();
+    if window_override.is_empty() {
+        state.windows.remove(&key);
+    }
+
+    log_if_err!(state.save());
+}
+
+/// Returns the current tray choices.
+pub fn tray_choices() -> TrayChoices {
+    if let Some(ref choices) = *UNSAVED_TRAY_CHOICES.lock().unwrap() {
+        return choices.clone();
+    }
+    STATE.lock().unwrap().tray.clone()
+}
+
+/// Replaces the tray choices, saving them to state.yaml if `save` is true.
+pub fn set_tray_choices(choices: TrayChoices, save: bool) {
+    if !save {
+        *UNSAVED_TRAY_CHOICES.lock().unwrap() = Some(choices);
+        return;
+    }
+
+    *UNSAVED_TRAY_CHOICES.lock().unwrap() = None;
+
+    let mut state = STATE.lock().unwrap();
+    state.tray = choices;
+    log_if_err!(state.save());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identity_key_is_stable() {
+        // These are saved to disk, so they can never change
+        assert_eq!(identity_key("", ""), "af63bd4c8601b7df");
+        assert_eq!(
+            identity_key("firefox.exe", "MozillaWindowClass"),
+            identity_key("firefox.exe", "MozillaWindowClass")
+        );
+        assert_ne!(
+            identity_key("firefox.exe", "MozillaWindowClass"),
+            identity_key("firefox.exe", "MozillaDialogClass")
+        );
+    }
+
+    #[test]
+    fn prune_stale_table() {
+        let today = 20_000;
+        // (last_seen, kept)
+        let cases = [
+            (today, true),
+            (today - STALE_AFTER_DAYS, true),
+            (today - STALE_AFTER_DAYS - 1, false),
+            // From before overrides were keyed by class
+            (0, false),
+        ];
+
+        for (last_seen, kept) in cases {
+            let mut windows = HashMap::from([(
+                "window".to_string(),
+                WindowOverride {
+                    disabled: true,
+                    last_seen,
+                    ..Default::default()
+                },
+            )]);
+            let pruned = prune_stale(&mut windows, today);
+            assert_eq!(
+                windows.contains_key("window"),
+                kept,
+                "last_seen {last_seen}"
+            );
+            assert_eq!(pruned, (!kept) as usize);
+        }
+    }
+}
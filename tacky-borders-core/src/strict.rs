@@ -0,0 +1,339 @@
+use anyhow::anyhow;
+use serde_ignored::Path;
+use serde_yaml::Value;
+
+use crate::animations::{AnimationType, Animations};
+use crate::border_config::{Config, WindowRule, DEFAULT_CONFIG};
+
+// Strict mode turns the mistakes that serde would otherwise skip over (like a misspelled option,
+// which just gets its default) into errors, with a guess at what was meant
+pub(crate) fn parse_config(value: Value) -> anyhow::Result<Config> {
+    let mut unknown = Vec::new();
+    let config: Config = serde_ignored::deserialize(value, |path| {
+        let mut segments = Vec::new();
+        path_segments(&path, &mut segments);
+        unknown.push(segments);
+    })
+    .map_err(|e| anyhow!(with_suggestion(e.to_string())))?;
+
+    if unknown.is_empty() {
+        return Ok(config);
+    }
+
+    let schema = schema();
+    let problems: Vec<String> = unknown
+        .iter()
+        .map(|segments| describe_unknown(segments, &schema))
+        .collect();
+
+    Err(anyhow!(
+        "found unknown options (strict mode is on):\n{}",
+        problems.join("\n")
+    ))
+}
+
+// Animation names that aren't built in are taken to be animation_defs, since serde has no way of
+// knowing which defs there are. So a misspelled built-in (like 'Spirl') can only be caught here,
+// once the whole config has been parsed. Returns what's wrong with each unknown one.
+pub(crate) fn unknown_animations(config: &Config) -> Vec<String> {
+    let global = config
+        .global
+        .animations
+        .as_ref()
+        .map(|animations| ("global.animations".to_string(), animations));
+    let rules = config
+        .window_rules
+        .iter()
+        .enumerate()
+        .filter_map(|(index, rule)| {
+            let animations = rule.animations.as_ref()?;
+            Some((format!("window_rules[{index}].animations"), animations))
+        });
+
+    let mut candidates: Vec<String> = AnimationType::ALL
+        .iter()
+        .map(|anim_type| format!("{anim_type:?}"))
+        .chain(config.animation_defs.keys().cloned())
+        .collect();
+    candidates.sort();
+
+    let mut problems = Vec::new();
+    for (path, animations) in global.into_iter().chain(rules) {
+        for (list, anim_types) in [
+            ("active", &animations.active),
+            ("inactive", &animations.inactive),
+        ] {
+            let mut names: Vec<&String> = anim_types
+                .keys()
+                .filter_map(|anim_type| match anim_type {
+                    AnimationType::Custom(name) if !config.animation_defs.contains_key(name) => {
+                        Some(name)
+                    }
+                    _ => None,
+                })
+                .collect();
+            names.sort();
+
+            for name in names {
+                let suggestion = suggest(name, candidates.iter().map(String::as_str));
+                problems.push(format!(
+                    "  '{path}.{list}.{name}' is not a built-in animation or one of the \
+                     animation_defs{}",
+                    did_you_mean(suggestion)
+                ));
+            }
+        }
+    }
+
+    problems
+}
+
+fn path_segments(path: &Path, segments: &mut Vec<String>) {
+    match path {
+        Path::Root => {}
+        Path::Seq { parent, index } => {
+            path_segments(parent, segments);
+            segments.push(index.to_string());
+        }
+        Path::Map { parent, key } => {
+            path_segments(parent, segments);
+            segments.push(key.clone());
+        }
+        Path::Some { parent }
+        | Path::NewtypeStruct { parent }
+        | Path::NewtypeVariant { parent } => path_segments(parent, segments),
+    }
+}
+
+// A config with every option that we know the place of, used to list the valid options next to an
+// unknown one. Unset options serialize as null, so they show up here too.
+fn schema() -> Value {
+    let mut config = Config::default();
+    config.global.animations = Some(Animations::default());
+    config.window_rules.push(WindowRule {
+        animations: Some(Animations::default()),
+        ..Default::default()
+    });
+
+    serde_yaml::to_value(&config).unwrap_or_default()
+}
+
+fn describe_unknown(segments: &[String], schema: &Value) -> String {
+    let Some((unknown, parents)) = segments.split_last() else {
+        return "unknown option".to_string();
+    };
+
+    let mut path = String::new();
+    for segment in segments {
+        match segment.parse::<usize>() {
+            Ok(index) => path += &format!("[{index}]"),
+            Err(_) if path.is_empty() => path += segment,
+            Err(_) => path += &format!(".{segment}"),
+        }
+    }
+
+    // Every window rule has the same options, so the first one stands in for all of them
+    let valid_options = parents
+        .iter()
+        .try_fold(schema, |value, segment| match segment.parse::<usize>() {
+            Ok(_) => value.get(0),
+            Err(_) => value.get(segment.as_str()),
+        })
+        .and_then(Value::as_mapping)
+        .map(|mapping| {
+            mapping
+                .keys()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect::<Vec<String>>()
+        });
+
+    match valid_options {
+        Some(valid_options) => {
+            let suggestion = suggest(unknown, valid_options.iter().map(String::as_str));
+            format!(
+                "  '{path}' is not an option{}, valid options are: {}",
+                did_you_mean(suggestion),
+                valid_options.join(", ")
+            )
+        }
+        // Fall back to every option named in the default config's documentation
+        None => {
+            let suggestion = suggest(unknown, documented_options());
+            format!("  '{path}' is not an option{}", did_you_mean(suggestion))
+        }
+    }
+}
+
+fn documented_options() -> impl Iterator<Item = &'static str> {
+    DEFAULT_CONFIG.lines().filter_map(|line| {
+        let line = line.trim_start().trim_start_matches('#').trim_start();
+        let (key, _) = line.split_once(':')?;
+        (!key.is_empty() && key.chars().all(|c| c.is_ascii_lowercase() || c == '_')).then_some(key)
+    })
+}
+
+// serde's errors for unknown variants (like a misspelled animation name) already list the valid
+// ones, so we just add the closest one
+fn with_suggestion(message: String) -> String {
+    let rest = message
+        .split_once("unknown variant `")
+        .or_else(|| message.split_once("unknown field `"))
+        .map(|(_, rest)| rest);
+    let Some((unknown, expected)) = rest.and_then(|rest| rest.split_once('`')) else {
+        return message;
+    };
+
+    // The valid names are every other chunk between backticks
+    let candidates = expected.split('`').skip(1).step_by(2);
+    match suggest(unknown, candidates).map(str::to_string) {
+        Some(suggestion) => format!("{message} (did you mean `{suggestion}`?)"),
+        None => message,
+    }
+}
+
+fn did_you_mean(suggestion: Option<&str>) -> String {
+    match suggestion {
+        Some(suggestion) => format!(" (did you mean '{suggestion}'?)"),
+        None => String::new(),
+    }
+}
+
+// The closest candidate, as long as it's close enough to plausibly be a typo
+fn suggest<'a>(unknown: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let unknown = unknown.to_ascii_lowercase();
+    let max_distance = (unknown.len() / 3).max(2);
+
+    candidates
+        .map(|candidate| {
+            (
+                edit_distance(&unknown, &candidate.to_ascii_lowercase()),
+                candidate,
+            )
+        })
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+// Levenshtein distance, one row at a time
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a_char != *b_char);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(yaml: &str) -> Config {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn edit_distance_table() {
+        let cases = [
+            ("", "", 0),
+            ("", "fade", 4),
+            ("fade", "", 4),
+            ("fade", "fade", 0),
+            ("spirl", "spiral", 1),
+            ("sprial", "spiral", 2),
+            ("kitten", "sitting", 3),
+            ("flaw", "lawn", 2),
+            ("Fade", "fade", 1),
+        ];
+
+        for (a, b, expected) in cases {
+            assert_eq!(edit_distance(a, b), expected, "'{a}' vs '{b}'");
+        }
+    }
+
+    #[test]
+    fn suggest_table() {
+        let candidates = ["Spiral", "ReverseSpiral", "Fade", "Comet", "border_width"];
+
+        let cases = [
+            ("spirl", Some("Spiral")),
+            ("FADE", Some("Fade")),
+            ("comit", Some("Comet")),
+            ("reversespirl", Some("ReverseSpiral")),
+            ("border_widht", Some("border_width")),
+            // Too far from anything to be a typo
+            ("glow", None),
+            ("rainbow", None),
+            ("", None),
+        ];
+
+        for (unknown, expected) in cases {
+            assert_eq!(
+                suggest(unknown, candidates.into_iter()),
+                expected,
+                "'{unknown}'"
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_animations_suggests_built_ins_and_defs() {
+        let config = parse(
+            "
+            global:
+              border_width: 4
+              border_offset: 0
+              border_radius: 0
+              animations:
+                active:
+                  Spirl: 100
+                  Fade: 100
+                  glow: 100
+            window_rules:
+              - match: Title
+                name: test
+                animations:
+                  inactive:
+                    pulse: 100
+            animation_defs:
+              pulse:
+                steps: [{ animation: Fade }]",
+        );
+
+        let problems = unknown_animations(&config);
+        assert_eq!(problems.len(), 2, "{problems:?}");
+        assert!(problems[0].contains("'global.animations.active.Spirl'"));
+        assert!(problems[0].contains("did you mean 'Spiral'"));
+        assert!(problems[1].contains("'global.animations.active.glow'"));
+    }
+
+    #[test]
+    fn unknown_animations_accepts_defined_ones() {
+        let config = parse(
+            "
+            global:
+              border_width: 4
+              border_offset: 0
+              border_radius: 0
+              animations:
+                active:
+                  pulse: 100
+            window_rules: []
+            animation_defs:
+              pulse:
+                steps: [{ animation: Fade }]",
+        );
+
+        assert!(unknown_animations(&config).is_empty());
+    }
+}
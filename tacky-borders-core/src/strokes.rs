@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+use std::time;
+
+use crate::colors::{Color, ColorConfig};
+
+// An extra stroke drawn outside of the main border, e.g. a 4px gradient with a 2px gap around a
+// 2px white border
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrokeConfig {
+    pub width: f32,
+    // Empty space between this stroke and the one inside of it
+    #[serde(default)]
+    pub gap: f32,
+    // These default to the border's own colors
+    pub active_color: Option<ColorConfig>,
+    pub inactive_color: Option<ColorConfig>,
+    // Whether spiral animations rotate this stroke's gradient too
+    #[serde(default = "default_animate")]
+    pub animate: bool,
+}
+
+fn default_animate() -> bool {
+    true
+}
+
+#[derive(Debug, Clone)]
+pub struct StrokeLayer {
+    pub width: i32,
+    pub gap: i32,
+    pub active_color: Color,
+    pub inactive_color: Color,
+    pub animate: bool,
+    // The stroke's own colors, if it has them. The rest follow the border's colors when they change.
+    own_colors: (Option<ColorConfig>, Option<ColorConfig>),
+}
+
+impl StrokeLayer {
+    pub fn new(
+        config: &StrokeConfig,
+        border_active: &ColorConfig,
+        border_inactive: &ColorConfig,
+        dpi: f32,
+    ) -> Self {
+        let scale = |value: f32| (value.max(0.0) * dpi / 96.0) as i32;
+
+        let mut layer = Self {
+            width: scale(config.width),
+            gap: scale(config.gap),
+            active_color: Color::default(),
+            inactive_color: Color::default(),
+            animate: config.animate,
+            own_colors: (config.active_color.clone(), config.inactive_color.clone()),
+        };
+        layer.set_border_colors(border_active, border_inactive);
+        layer
+    }
+
+    // Called whenever the border's colors change, so the strokes without their own colors keep up
+    pub fn set_border_colors(
+        &mut self,
+        border_active: &ColorConfig,
+        border_inactive: &ColorConfig,
+    ) {
+        let (own_active, own_inactive) = &self.own_colors;
+        self.active_color = own_active
+            .as_ref()
+            .unwrap_or(border_active)
+            .convert_to_color(true);
+        self.inactive_color = own_inactive
+            .as_ref()
+            .unwrap_or(border_inactive)
+            .convert_to_color(false);
+    }
+}
+
+// How much room the extra strokes need outside of the main border
+pub fn total_extent(layers: &[StrokeLayer]) -> i32 {
+    layers.iter().map(|layer| layer.gap + layer.width).sum()
+}
+
+// A halo drawn around everything else, but only for the focused window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActiveExtraConfig {
+    pub width: f32,
+    // Empty space between the halo and the outermost stroke
+    #[serde(default)]
+    pub offset: f32,
+    pub color: ColorConfig,
+    // How long (in milliseconds) the halo takes to fade in on focus and out when focus leaves
+    #[serde(default = "default_active_extra_fade")]
+    pub fade: u64,
+}
+
+fn default_active_extra_fade() -> u64 {
+    150
+}
+
+#[derive(Debug, Clone)]
+pub struct ActiveExtra {
+    pub width: i32,
+    pub offset: i32,
+    pub color: Color,
+    pub fade: time::Duration,
+    // Whether the halo is showing or on its way to showing
+    pub shown: bool,
+    // When the current fade started, and the halo's opacity at the time
+    pub fade_start: Option<(time::Instant, f32)>,
+}
+
+impl ActiveExtra {
+    pub fn new(config: &ActiveExtraConfig, dpi: f32) -> Self {
+        let scale = |value: f32| (value.max(0.0) * dpi / 96.0) as i32;
+
+        Self {
+            width: scale(config.width),
+            offset: scale(config.offset),
+            color: config.color.convert_to_color(true),
+            fade: time::Duration::from_millis(config.fade),
+            shown: false,
+            fade_start: None,
+        }
+    }
+
+    pub fn set_shown(&mut self, shown: bool, now: time::Instant) {
+        if shown != self.shown {
+            self.fade_start = Some((now, self.opacity_at(now)));
+            self.shown = shown;
+        }
+    }
+
+    pub fn opacity_at(&self, now: time::Instant) -> f32 {
+        let target = match self.shown {
+            true => 1.0,
+            false => 0.0,
+        };
+
+        match self.fade_start {
+            Some((start, from)) if !self.fade.is_zero() => {
+                let progress =
+                    now.saturating_duration_since(start).as_secs_f32() / self.fade.as_secs_f32();
+                from + (target - from) * progress.min(1.0)
+            }
+            _ => target,
+        }
+    }
+
+    pub fn is_fading(&self, now: time::Instant) -> bool {
+        self.fade_start
+            .is_some_and(|(start, _)| now.saturating_duration_since(start) < self.fade)
+    }
+
+    // How much room the halo needs outside of the extra strokes
+    pub fn extent(&self) -> i32 {
+        self.offset + self.width
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, Once};
+use std::thread;
+use std::time;
+use windows::Win32::Foundation::HWND;
+use windows::Win32::System::SystemInformation::GetTickCount;
+
+use crate::event_hook;
+use crate::tracking::WindowEvent;
+use crate::utils::*;
+
+// Some apps change their styles after they've been shown, like a game that drops its caption and
+// frame when it goes fullscreen, or a window that turns into a tool window. Nothing tells us when
+// that happens (WM_STYLECHANGED only goes to the window itself), so every window's styles are
+// checked once a second, and borders are added or removed for the ones whose styles changed which
+// border they should have.
+const POLL_INTERVAL: time::Duration = time::Duration::from_secs(1);
+
+static POLLER: Once = Once::new();
+
+// What each top-level window looked like at the last poll
+static FILTER_STATES: Mutex<Option<HashMap<isize, FilterState>>> = Mutex::new(None);
+
+// Which border a window gets from its styles alone, before any rules are checked
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FilterState {
+    Bordered,
+    // Gets its group leader's border, if it follows its owner (see 'follow_owned')
+    ToolWindow,
+    // Would be bordered, but it's borderless fullscreen (see is_frameless_fullscreen())
+    Fullscreen,
+    Filtered,
+}
+
+impl FilterState {
+    fn of(hwnd: HWND) -> Self {
+        match (has_filtered_style(hwnd), is_tool_window(hwnd)) {
+            (false, _) if is_frameless_fullscreen(hwnd) => FilterState::Fullscreen,
+            (false, _) => FilterState::Bordered,
+            (true, true) => FilterState::ToolWindow,
+            (true, false) => FilterState::Filtered,
+        }
+    }
+
+    fn has_border(self) -> bool {
+        matches!(self, FilterState::Bordered | FilterState::ToolWindow)
+    }
+}
+
+// What a poll does about a window whose styles changed
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Action {
+    Nothing,
+    RemoveBorder,
+    AddBorder,
+}
+
+pub(crate) fn start() {
+    POLLER.call_once(|| {
+        let _ = thread::spawn(|| loop {
+            poll();
+            thread::sleep(POLL_INTERVAL);
+        });
+    });
+}
+
+fn poll() {
+    let mut filter_states = FILTER_STATES.lock().unwrap();
+
+    // Every border gets recreated from scratch once borders are resumed anyways
+    if crate::is_suspended() {
+        *filter_states = None;
+        return;
+    }
+
+    // Windows we haven't seen before are only remembered here. Whether they get a border is up to
+    // the usual window events.
+    let last_states = filter_states.take().unwrap_or_default();
+    let states = get_top_level_windows()
+        .into_iter()
+        .map(|hwnd| {
+            let state = FilterState::of(hwnd);
+            let state = match last_states.get(&(hwnd.0 as isize)) {
+                Some(&last) if last != state => apply_filter_state(hwnd, last, state),
+                _ => state,
+            };
+            (hwnd.0 as isize, state)
+        })
+        .collect();
+
+    *filter_states = Some(states);
+}
+
+// Add or remove the window's border for its new styles. Returns the state to remember for it.
+fn apply_filter_state(hwnd: HWND, last: FilterState, state: FilterState) -> FilterState {
+    debug!("styles for {hwnd:?} changed from {last:?} to {state:?}");
+
+    let (action, remembered) = transition(state, get_border_from_window(hwnd).is_some());
+    match action {
+        Action::Nothing => {}
+        Action::RemoveBorder => destroy_border_for_window(hwnd),
+        // The event worker is what creates borders everywhere else, so this can't race it. It also
+        // checks the window rules and everything else, like for any window that was just shown.
+        Action::AddBorder => {
+            event_hook::queue_event(WindowEvent::Shown(hwnd), unsafe { GetTickCount() })
+        }
+    }
+    remembered
+}
+
+// What to do about a window that just changed to 'state', and which state to remember for it
+fn transition(state: FilterState, has_border: bool) -> (Action, FilterState) {
+    match (has_border, state.has_border()) {
+        // A window that goes from one kind of border to another still needs its old border gone
+        // before it can get the new one, so that waits until the next poll
+        (true, _) => (Action::RemoveBorder, FilterState::Filtered),
+        (false, true) => (Action::AddBorder, state),
+        (false, false) => (Action::Nothing, state),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transition_table() {
+        use Action::*;
+        use FilterState::*;
+
+        let cases = [
+            // (new state, has a border) => (action, remembered state)
+            ((Bordered, true), (RemoveBorder, Filtered)),
+            ((Bordered, false), (AddBorder, Bordered)),
+            ((ToolWindow, true), (RemoveBorder, Filtered)),
+            ((ToolWindow, false), (AddBorder, ToolWindow)),
+            ((Fullscreen, true), (RemoveBorder, Filtered)),
+            ((Fullscreen, false), (Nothing, Fullscreen)),
+            ((Filtered, true), (RemoveBorder, Filtered)),
+            ((Filtered, false), (Nothing, Filtered)),
+        ];
+
+        for ((state, has_border), expected) in cases {
+            assert_eq!(
+                transition(state, has_border),
+                expected,
+                "{state:?} with a border: {has_border}"
+            );
+        }
+    }
+
+    #[test]
+    fn border_swaps_take_two_polls() {
+        // A bordered window turns into a tool window: the first poll removes the border and
+        // remembers the window as filtered, so the next one sees a change and adds the new border
+        let (action, remembered) = transition(FilterState::ToolWindow, true);
+        assert_eq!(action, Action::RemoveBorder);
+        assert_ne!(remembered, FilterState::ToolWindow);
+
+        let (action, remembered) = transition(FilterState::ToolWindow, false);
+        assert_eq!(action, Action::AddBorder);
+        assert_eq!(remembered, FilterState::ToolWindow);
+    }
+}
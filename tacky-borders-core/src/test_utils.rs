@@ -0,0 +1,379 @@
+//! Helpers for testing borders against real (but empty) windows.
+//!
+//! [`DummyWindow`] spawns a plain top-level window on its own thread, which can then be moved,
+//! minimized, etc. like any other window. Attach a border to it with
+//! [`Border::attach`](crate::border::Border::attach), drive the window around, and use the
+//! `expect_*` functions to check that the border kept up. They wait a bit for the border to catch
+//! up, since borders react to window events on their own threads.
+//!
+//! [`run_smoke_test`] does all of that for the basics (moving, z-order, minimizing, restoring, and
+//! closing), and is what `tacky-borders --self-test` runs.
+//!
+//! This module is only built with the `test-utils` feature.
+//!
+//! ```no_run
+//! use tacky_borders_core::border::{Border, BorderOptions};
+//! use tacky_borders_core::test_utils::{self, DummyWindow};
+//! use windows::Win32::Foundation::RECT;
+//!
+//! let window = DummyWindow::spawn("test", RECT { left: 100, top: 100, right: 500, bottom: 400 })?;
+//! let border = Border::attach(window.hwnd(), BorderOptions::default())?;
+//!
+//! window.move_to(RECT { left: 200, top: 200, right: 600, bottom: 500 })?;
+//! test_utils::expect_border_around(&window, &border)?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use anyhow::{anyhow, Context};
+use std::ptr;
+use std::sync::{mpsc, Once};
+use std::thread;
+use std::time;
+use windows::core::{HSTRING, PCWSTR};
+use windows::Win32::Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, RECT, WPARAM};
+use windows::Win32::Graphics::Dwm::{DwmGetWindowAttribute, DWMWA_EXTENDED_FRAME_BOUNDS};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, GetWindow, GetWindowRect,
+    IsWindow, LoadCursorW, PostQuitMessage, RegisterClassExW, SetForegroundWindow, SetWindowPos,
+    ShowWindow, TranslateMessage, GW_HWNDPREV, IDC_ARROW, MSG, SWP_NOACTIVATE, SWP_NOZORDER,
+    SW_MINIMIZE, SW_RESTORE, WINDOW_EX_STYLE, WM_CLOSE, WM_DESTROY, WNDCLASSEXW,
+    WS_OVERLAPPEDWINDOW, WS_VISIBLE,
+};
+
+use crate::border::{Border, BorderHandle, BorderOptions};
+use crate::log_if_err;
+use crate::utils::*;
+
+static DUMMY_CLASS: Once = Once::new();
+
+// How long the expect_* functions wait for a border to catch up before giving up
+const EXPECT_TIMEOUT: time::Duration = time::Duration::from_secs(2);
+const EXPECT_POLL_INTERVAL: time::Duration = time::Duration::from_millis(20);
+
+/// An empty top-level window that lives until it's closed or dropped.
+#[derive(Debug)]
+pub struct DummyWindow {
+    // Stored as isize so the window can be sent to other threads
+    hwnd: isize,
+}
+
+impl DummyWindow {
+    /// Creates the window at `rect` (in screen coordinates, including the invisible resize borders)
+    /// and waits until it exists.
+    pub fn spawn(title: &str, rect: RECT) -> anyhow::Result<Self> {
+        register_dummy_class_once();
+
+        let mut title: Vec<u16> = title.encode_utf16().collect();
+        title.push(0);
+
+        let (sender, receiver) = mpsc::channel();
+        let _ = thread::spawn(move || unsafe {
+            let hinstance: HINSTANCE = std::mem::transmute(&crate::__ImageBase);
+
+            let created = CreateWindowExW(
+                WINDOW_EX_STYLE::default(),
+                &HSTRING::from(DUMMY_WINDOW_CLASS),
+                PCWSTR::from_raw(title.as_ptr()),
+                WS_OVERLAPPEDWINDOW | WS_VISIBLE,
+                rect.left,
+                rect.top,
+                rect.right - rect.left,
+                rect.bottom - rect.top,
+                None,
+                None,
+                hinstance,
+                None,
+            );
+
+            let hwnd = created.map(|hwnd| hwnd.0 as isize);
+            let created_ok = hwnd.is_ok();
+            let _ = sender.send(hwnd);
+            if !created_ok {
+                return;
+            }
+
+            let mut message = MSG::default();
+            while GetMessageW(&mut message, HWND::default(), 0, 0).into() {
+                let _ = TranslateMessage(&message);
+                DispatchMessageW(&message);
+            }
+        });
+
+        let hwnd = receiver
+            .recv()
+            .context("dummy window thread exited before creating its window")?
+            .context("could not create dummy window")?;
+
+        Ok(Self { hwnd })
+    }
+
+    pub fn hwnd(&self) -> HWND {
+        HWND(self.hwnd as _)
+    }
+
+    /// Moves and resizes the window without activating it.
+    pub fn move_to(&self, rect: RECT) -> anyhow::Result<()> {
+        unsafe {
+            SetWindowPos(
+                self.hwnd(),
+                None,
+                rect.left,
+                rect.top,
+                rect.right - rect.left,
+                rect.bottom - rect.top,
+                SWP_NOZORDER | SWP_NOACTIVATE,
+            )
+        }
+        .context("could not move dummy window")
+    }
+
+    /// Tries to bring the window to the foreground. Windows only allows this in some situations
+    /// (e.g. when our process is already in the foreground), so this returns false if it didn't
+    /// work.
+    pub fn focus(&self) -> bool {
+        unsafe { SetForegroundWindow(self.hwnd()) }.as_bool()
+    }
+
+    pub fn minimize(&self) {
+        let _ = unsafe { ShowWindow(self.hwnd(), SW_MINIMIZE) };
+    }
+
+    pub fn restore(&self) {
+        let _ = unsafe { ShowWindow(self.hwnd(), SW_RESTORE) };
+    }
+
+    /// Closes the window and waits until it's gone.
+    pub fn close(self) -> anyhow::Result<()> {
+        let hwnd = self.hwnd();
+        // Dropping does the actual closing
+        drop(self);
+
+        match wait_until(|| !unsafe { IsWindow(hwnd) }.as_bool()) {
+            true => Ok(()),
+            false => Err(anyhow!("dummy window {hwnd:?} did not close")),
+        }
+    }
+
+    /// The window's visible frame, which is what borders are drawn around.
+    pub fn frame_rect(&self) -> anyhow::Result<RECT> {
+        get_frame_rect(self.hwnd())
+    }
+}
+
+impl Drop for DummyWindow {
+    fn drop(&mut self) {
+        log_if_err!(post_message_w(self.hwnd(), WM_CLOSE, WPARAM(0), LPARAM(0)));
+    }
+}
+
+fn register_dummy_class_once() {
+    DUMMY_CLASS.call_once(|| unsafe {
+        let hinstance: HINSTANCE = std::mem::transmute(&crate::__ImageBase);
+        let class_name = HSTRING::from(DUMMY_WINDOW_CLASS);
+
+        let window_class = WNDCLASSEXW {
+            cbSize: size_of::<WNDCLASSEXW>() as u32,
+            lpfnWndProc: Some(dummy_wnd_proc),
+            hInstance: hinstance,
+            lpszClassName: PCWSTR(class_name.as_ptr()),
+            hCursor: LoadCursorW(None, IDC_ARROW).unwrap_or_default(),
+            ..Default::default()
+        };
+
+        if RegisterClassExW(&window_class) == 0 {
+            error!("could not register dummy window class");
+        }
+    });
+}
+
+unsafe extern "system" fn dummy_wnd_proc(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    match message {
+        // Each dummy window has its own thread, so end it along with the window
+        WM_DESTROY => {
+            PostQuitMessage(0);
+            LRESULT(0)
+        }
+        _ => DefWindowProcW(window, message, wparam, lparam),
+    }
+}
+
+/// Polls `condition` until it's true, for up to two seconds. Returns whether it ever was.
+pub fn wait_until(mut condition: impl FnMut() -> bool) -> bool {
+    let deadline = time::Instant::now() + EXPECT_TIMEOUT;
+    loop {
+        if condition() {
+            return true;
+        }
+        if time::Instant::now() >= deadline {
+            return false;
+        }
+        thread::sleep(EXPECT_POLL_INTERVAL);
+    }
+}
+
+// Use DWM's bounds so the invisible resize borders don't count, just like borders do
+fn get_frame_rect(hwnd: HWND) -> anyhow::Result<RECT> {
+    let mut rect = RECT::default();
+    unsafe {
+        DwmGetWindowAttribute(
+            hwnd,
+            DWMWA_EXTENDED_FRAME_BOUNDS,
+            ptr::addr_of_mut!(rect) as _,
+            size_of::<RECT>() as u32,
+        )
+    }
+    .context("could not get window frame")?;
+    Ok(rect)
+}
+
+fn get_window_rect(hwnd: HWND) -> anyhow::Result<RECT> {
+    let mut rect = RECT::default();
+    unsafe { GetWindowRect(hwnd, &mut rect) }.context("could not get window rect")?;
+    Ok(rect)
+}
+
+// How far the border window sticks out past the frame on each side: left, top, right, bottom
+fn border_margins(frame: &RECT, border: &RECT) -> [i32; 4] {
+    [
+        frame.left - border.left,
+        frame.top - border.top,
+        border.right - frame.right,
+        border.bottom - frame.bottom,
+    ]
+}
+
+/// Checks that the border window surrounds the tracking window's frame evenly on all sides.
+///
+/// Borders can be wider than `border_width` (extra strokes, ripples, ...), so this doesn't check
+/// for an exact size.
+pub fn expect_border_around(window: &DummyWindow, border: &BorderHandle) -> anyhow::Result<()> {
+    let mut last = None;
+
+    let matched = wait_until(|| {
+        let (Ok(frame), Ok(border_rect)) =
+            (window.frame_rect(), get_window_rect(border.border_window()))
+        else {
+            return false;
+        };
+
+        let margins = border_margins(&frame, &border_rect);
+        last = Some((frame, border_rect));
+
+        // Allow a pixel of rounding error from DPI scaling
+        let (min, max) = (margins.iter().min(), margins.iter().max());
+        matches!((min, max), (Some(&min), Some(&max)) if min >= 0 && max - min <= 1)
+    });
+
+    match (matched, last) {
+        (true, _) => Ok(()),
+        (false, Some((frame, border_rect))) => Err(anyhow!(
+            "border {border_rect:?} does not surround the window frame {frame:?}"
+        )),
+        (false, None) => Err(anyhow!("could not get the window or border rect")),
+    }
+}
+
+/// Checks that the border window sits directly above its tracking window in the z-order.
+pub fn expect_border_above(window: &DummyWindow, border: &BorderHandle) -> anyhow::Result<()> {
+    let above = || unsafe { GetWindow(window.hwnd(), GW_HWNDPREV) };
+
+    match wait_until(|| above() == Ok(border.border_window())) {
+        true => Ok(()),
+        false => Err(anyhow!(
+            "expected border {:?} directly above {:?}, found {:?}",
+            border.border_window(),
+            window.hwnd(),
+            above().ok()
+        )),
+    }
+}
+
+/// Checks that the border window is shown (`true`) or hidden (`false`).
+pub fn expect_border_visible(border: &BorderHandle, visible: bool) -> anyhow::Result<()> {
+    match wait_until(|| is_window_visible(border.border_window()) == visible) {
+        true => Ok(()),
+        false => Err(anyhow!(
+            "expected border {:?} to be {}",
+            border.border_window(),
+            match visible {
+                true => "visible",
+                false => "hidden",
+            }
+        )),
+    }
+}
+
+/// Checks that the window no longer has a border.
+pub fn expect_no_border(tracking_window: HWND) -> anyhow::Result<()> {
+    match wait_until(|| get_border_from_window(tracking_window).is_none()) {
+        true => Ok(()),
+        false => Err(anyhow!("{tracking_window:?} still has a border")),
+    }
+}
+
+/// Runs through the basics of tracking a window, returning the first thing that went wrong.
+pub fn run_smoke_test() -> anyhow::Result<()> {
+    let rect = |left, top, width, height| RECT {
+        left,
+        top,
+        right: left + width,
+        bottom: top + height,
+    };
+
+    let window = DummyWindow::spawn("tacky-borders self-test", rect(200, 200, 640, 480))?;
+    let border = Border::attach(window.hwnd(), BorderOptions::default())?;
+
+    expect_border_visible(&border, true).context("after attaching")?;
+    expect_border_around(&window, &border).context("after attaching")?;
+    expect_border_above(&window, &border).context("after attaching")?;
+
+    window.move_to(rect(300, 250, 800, 500))?;
+    expect_border_around(&window, &border).context("after moving and resizing")?;
+    expect_border_above(&window, &border).context("after moving and resizing")?;
+
+    window.minimize();
+    expect_border_visible(&border, false).context("after minimizing")?;
+
+    window.restore();
+    expect_border_visible(&border, true).context("after restoring")?;
+    expect_border_around(&window, &border).context("after restoring")?;
+
+    let tracking_window = window.hwnd();
+    window.close()?;
+    expect_no_border(tracking_window).context("after closing")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn border_margins_per_side() {
+        let frame = RECT {
+            left: 100,
+            top: 100,
+            right: 500,
+            bottom: 400,
+        };
+        let border = RECT {
+            left: 96,
+            top: 95,
+            right: 504,
+            bottom: 406,
+        };
+        assert_eq!(border_margins(&frame, &border), [4, 5, 4, 6]);
+    }
+
+    #[test]
+    #[ignore = "opens real windows, so it needs a desktop"]
+    fn smoke_test() {
+        run_smoke_test().unwrap();
+    }
+}
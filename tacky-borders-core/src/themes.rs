@@ -0,0 +1,210 @@
+//! Shareable looks for borders, kept apart from personal window rules.
+//!
+//! A theme is a `<name>.theme.yaml` file in the `themes` folder next to the config. It can set the
+//! colors, width, radius, extra strokes, focus halo, and animations, and replaces those options in
+//! the config's `global` section when it's used. Window rules are left alone, so they still win over
+//! the theme.
+//!
+//! The theme comes from the `theme` option, unless another one was picked from the tray menu or
+//! with the `theme` [`ipc`](crate::ipc) command (see [`state::TrayChoices`](crate::state::TrayChoices)).
+
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::animations::Animations;
+use crate::border_config::{Config, Global};
+use crate::colors::{ColorConfig, PalettePreset};
+use crate::strokes::{ActiveExtraConfig, StrokeConfig};
+
+const THEME_EXTENSION: &str = ".theme.yaml";
+
+/// The contents of a theme file. Anything left out keeps the config's value.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Theme {
+    /// Who made the theme, what it's for, etc. This is just for people reading the file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub border_width: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub border_radius: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_color: Option<ColorConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inactive_color: Option<ColorConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub palette_preset: Option<PalettePreset>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strokes: Option<Vec<StrokeConfig>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_extra: Option<ActiveExtraConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub animations: Option<Animations>,
+}
+
+impl Theme {
+    /// Replaces the options in `global` that the theme sets.
+    pub fn apply(&self, global: &mut Global) {
+        if let Some(border_width) = self.border_width {
+            global.border_width = border_width;
+        }
+        if let Some(border_radius) = self.border_radius {
+            global.border_radius = border_radius;
+        }
+
+        // A palette in the config would otherwise win over the theme's own colors
+        if self.active_color.is_some() || self.inactive_color.is_some() {
+            global.palette_preset = None;
+        }
+        if let Some(ref active_color) = self.active_color {
+            global.active_color = active_color.clone();
+        }
+        if let Some(ref inactive_color) = self.inactive_color {
+            global.inactive_color = inactive_color.clone();
+        }
+        if self.palette_preset.is_some() {
+            global.palette_preset = self.palette_preset;
+        }
+
+        if self.strokes.is_some() {
+            global.strokes = self.strokes.clone();
+        }
+        if self.active_extra.is_some() {
+            global.active_extra = self.active_extra.clone();
+        }
+        if self.animations.is_some() {
+            global.animations = self.animations.clone();
+        }
+    }
+}
+
+/// The folder that theme files are read from.
+pub fn get_themes_dir() -> anyhow::Result<PathBuf> {
+    Ok(Config::get_config_dir()?.join("themes"))
+}
+
+/// The names of all the themes in the themes folder, sorted.
+pub fn list_themes() -> Vec<String> {
+    let Some(entries) = get_themes_dir().ok().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+
+    let mut themes: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            file_name.strip_suffix(THEME_EXTENSION).map(str::to_string)
+        })
+        .collect();
+    themes.sort();
+
+    themes
+}
+
+/// Reads the theme with the given name from the themes folder.
+pub fn load_theme(name: &str) -> anyhow::Result<Theme> {
+    let path = theme_path(name)?;
+    let contents = fs::read_to_string(&path)
+        .with_context(|| format!("could not read theme {}", path.display()))?;
+
+    serde_yaml::from_str(&contents).with_context(|| format!("could not parse theme '{name}'"))
+}
+
+/// Writes a theme to the themes folder, returning the path of the file. Fails if the theme already
+/// exists, unless `overwrite` is true.
+pub fn save_theme(name: &str, theme: &Theme, overwrite: bool) -> anyhow::Result<PathBuf> {
+    let path = theme_path(name)?;
+    fs::create_dir_all(get_themes_dir()?).context("could not create the themes folder")?;
+
+    if path.exists() && !overwrite {
+        return Err(anyhow!("{} already exists", path.display()));
+    }
+
+    let contents = serde_yaml::to_string(theme).context("could not serialize theme")?;
+    fs::write(&path, contents).with_context(|| format!("could not write {}", path.display()))?;
+
+    Ok(path)
+}
+
+/// Turns a name into one that's safe to save a theme as (and to type in a terminal): lowercase
+/// letters, digits, `-`, and `_`.
+pub fn sanitize_name(name: &str) -> String {
+    let name: String = name
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(
+            |c| match c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                true => c,
+                false => '-',
+            },
+        )
+        .collect();
+
+    let name = name
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    match name.is_empty() {
+        true => "imported".to_string(),
+        false => name,
+    }
+}
+
+// Names come from the config, the tray menu, and IPC, so anything that would reach outside the
+// themes folder (or isn't a valid file name on Windows) is turned down. Themes that were put there
+// by hand can still have spaces, capitals, etc. in their names.
+fn theme_path(name: &str) -> anyhow::Result<PathBuf> {
+    let is_invalid = |c: char| c.is_control() || r#"<>:"/\|?*"#.contains(c);
+    if name.trim().is_empty() || name.starts_with('.') || name.contains(is_invalid) {
+        return Err(anyhow!("invalid theme name '{name}'"));
+    }
+
+    Ok(get_themes_dir()?.join(format!("{name}{THEME_EXTENSION}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_name_table() {
+        let cases = [
+            ("Dracula", "dracula"),
+            ("  One Dark Pro  ", "one-dark-pro"),
+            ("Solarized (Light)", "solarized-light"),
+            ("snake_case-name", "snake_case-name"),
+            ("../../etc/passwd", "etc-passwd"),
+            ("C:\\Windows\\theme", "c-windows-theme"),
+            ("Café Noir", "caf-noir"),
+            ("---", "imported"),
+            ("", "imported"),
+        ];
+
+        for (name, expected) in cases {
+            assert_eq!(sanitize_name(name), expected, "'{name}'");
+        }
+    }
+
+    #[test]
+    fn theme_path_rejects_names_outside_the_folder() {
+        for name in [
+            "",
+            "  ",
+            "..",
+            "../config",
+            "..\\..\\secrets",
+            "C:\\theme",
+            "themes/dark",
+            ".hidden",
+            "what?",
+            "tab\there",
+        ] {
+            // These fail before the themes folder is even looked up
+            assert!(theme_path(name).is_err(), "{name:?}");
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! A countdown timer (e.g. for pomodoros) that's drawn as a shrinking progress ring on the focused
+//! window's border. The border flashes once time is up.
+//!
+//! Timers can be started with [`start`], the `timer` [`ipc`](crate::ipc) command, the tray menu,
+//! or the `timer` hotkey.
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time;
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+
+use crate::border_config::CONFIG;
+use crate::ipc;
+use crate::progress;
+use crate::utils::{post_message_w, WM_APP_FLASH};
+
+// How often the ring is redrawn. It only moves a fraction of a pixel per tick for most timers.
+const TICK: time::Duration = time::Duration::from_millis(200);
+
+// A day is long enough for any timer, and keeps Duration from overflowing
+const MAX_MINUTES: f32 = 24.0 * 60.0;
+
+// Every timer gets a new id, and only the timer whose id is in RUNNING_TIMER keeps going. 0 means
+// no timer is running.
+static NEXT_TIMER: AtomicU64 = AtomicU64::new(1);
+static RUNNING_TIMER: AtomicU64 = AtomicU64::new(0);
+
+/// The `timer` section of the config.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimerConfig {
+    /// How long timers run for when no duration is given.
+    #[serde(default = "default_minutes")]
+    pub minutes: f32,
+    /// How many times the border flashes when time is up.
+    #[serde(default = "default_flashes")]
+    pub flashes: u32,
+}
+
+impl Default for TimerConfig {
+    fn default() -> Self {
+        Self {
+            minutes: default_minutes(),
+            flashes: default_flashes(),
+        }
+    }
+}
+
+fn default_minutes() -> f32 {
+    25.0
+}
+
+fn default_flashes() -> u32 {
+    5
+}
+
+/// Turns a number of minutes into a timer duration. Fails unless it's more than 0 and at most a
+/// day.
+pub fn duration_from_minutes(minutes: f32) -> anyhow::Result<time::Duration> {
+    if !(minutes > 0.0 && minutes <= MAX_MINUTES) {
+        return Err(anyhow!(
+            "timers have to run for more than 0 and at most {MAX_MINUTES} minutes, not {minutes}"
+        ));
+    }
+
+    time::Duration::try_from_secs_f32(minutes * 60.0).map_err(|e| anyhow!("{e}"))
+}
+
+/// Starts a timer, replacing the current one (if any). Uses the config's duration if `duration`
+/// is `None`.
+pub fn start(duration: Option<time::Duration>) -> time::Duration {
+    let config = CONFIG.lock().unwrap().timer.clone();
+    let duration = duration.unwrap_or_else(|| {
+        duration_from_minutes(config.minutes).unwrap_or_else(|e| {
+            warn!("invalid timer.minutes in config, using the default instead: {e}");
+            time::Duration::from_secs_f32(default_minutes() * 60.0)
+        })
+    });
+
+    let id = NEXT_TIMER.fetch_add(1, Ordering::SeqCst);
+    RUNNING_TIMER.store(id, Ordering::SeqCst);
+
+    let _ = thread::spawn(move || run_timer(id, duration, config.flashes));
+
+    duration
+}
+
+/// Stops the current timer without flashing.
+pub fn stop() {
+    RUNNING_TIMER.store(0, Ordering::SeqCst);
+}
+
+pub fn is_running() -> bool {
+    RUNNING_TIMER.load(Ordering::SeqCst) != 0
+}
+
+/// Starts a timer with the config's duration, or stops the current one.
+pub fn toggle() {
+    match is_running() {
+        true => stop(),
+        false => {
+            start(None);
+        }
+    }
+}
+
+fn run_timer(id: u64, duration: time::Duration, flashes: u32) {
+    let start_time = time::Instant::now();
+    // Stored as isize since HWND isn't Send
+    let mut current_border: Option<isize> = None;
+
+    let clear = |border: Option<isize>| {
+        if let Some(border) = border {
+            let _ = progress::set_border_progress(HWND(border as _), None);
+        }
+    };
+
+    loop {
+        if RUNNING_TIMER.load(Ordering::SeqCst) != id {
+            clear(current_border);
+            return;
+        }
+
+        let elapsed = start_time.elapsed();
+        if elapsed >= duration {
+            break;
+        }
+
+        // The ring follows focus, but stays put if focus moves to a window without a border (like
+        // the taskbar)
+        let focused_border = ipc::find_borders("focused")
+            .ok()
+            .and_then(|borders| borders.first().map(|(_, border)| border.0 as isize));
+        if focused_border.is_some() && focused_border != current_border {
+            clear(current_border);
+            current_border = focused_border;
+        }
+
+        if let Some(border) = current_border {
+            let remaining = 1.0 - elapsed.as_secs_f32() / duration.as_secs_f32();
+            let _ = progress::set_border_progress(HWND(border as _), Some(remaining));
+        }
+
+        thread::sleep(TICK);
+    }
+
+    clear(current_border);
+
+    // Only flash if we weren't cancelled in the meantime
+    if RUNNING_TIMER
+        .compare_exchange(id, 0, Ordering::SeqCst, Ordering::SeqCst)
+        .is_ok()
+    {
+        if let Some(border) = current_border {
+            let _ = post_message_w(
+                HWND(border as _),
+                WM_APP_FLASH,
+                WPARAM(flashes as usize),
+                LPARAM(0),
+            );
+        }
+        info!("timer finished after {duration:?}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duration_from_minutes_accepts_sane_values() {
+        assert_eq!(
+            duration_from_minutes(25.0).unwrap(),
+            time::Duration::from_secs(25 * 60)
+        );
+        assert_eq!(
+            duration_from_minutes(0.5).unwrap(),
+            time::Duration::from_secs(30)
+        );
+        assert!(duration_from_minutes(MAX_MINUTES).is_ok());
+    }
+
+    #[test]
+    fn duration_from_minutes_rejects_everything_else() {
+        for minutes in [
+            0.0,
+            -1.0,
+            MAX_MINUTES + 1.0,
+            1e30,
+            f32::INFINITY,
+            f32::NEG_INFINITY,
+            f32::NAN,
+        ] {
+            assert!(duration_from_minutes(minutes).is_err(), "{minutes}");
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use std::cell::RefCell;
+use windows::Win32::Foundation::HWND;
+
+use crate::border_config::{TrackingBackend, CONFIG};
+use crate::event_hook::WinEventTracker;
+use crate::notifications::{self, NotificationKind};
+use crate::shell_hook::ShellHookTracker;
+use crate::uia_tracker::UiaTracker;
+
+// What trackers tell the event worker, whatever they got it from. Trackers leave out the events
+// that aren't about a window itself (like a scrollbar moving), but anything else that decides
+// whether a window gets a border is up to the event worker.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum WindowEvent {
+    // A new window showed up. Borders treat this like Shown, but not every tracker can tell the two
+    // apart.
+    Created(HWND),
+    Destroyed(HWND),
+    Shown(HWND),
+    Hidden(HWND),
+    Cloaked(HWND),
+    Uncloaked(HWND),
+    // The window moved or resized
+    Moved(HWND),
+    Minimized(HWND),
+    Restored(HWND),
+    // May be a child of the window that has focus now
+    FocusChanged(HWND),
+    TitleChanged(HWND),
+    // The window changed its spot in the z-order
+    Reordered(HWND),
+    // A context menu or a menu bar's menu closed
+    MenuClosed,
+}
+
+// HWNDs are just handles, so it's fine to hand them to the event worker
+unsafe impl Send for WindowEvent {}
+
+impl WindowEvent {
+    // For crash reports, in the same order as index()
+    pub(crate) const NAMES: [&'static str; 13] = [
+        "Created",
+        "Destroyed",
+        "Shown",
+        "Hidden",
+        "Cloaked",
+        "Uncloaked",
+        "Moved",
+        "Minimized",
+        "Restored",
+        "FocusChanged",
+        "TitleChanged",
+        "Reordered",
+        "MenuClosed",
+    ];
+
+    // Which kind of event this is, as an index into NAMES
+    pub(crate) fn index(self) -> usize {
+        match self {
+            WindowEvent::Created(_) => 0,
+            WindowEvent::Destroyed(_) => 1,
+            WindowEvent::Shown(_) => 2,
+            WindowEvent::Hidden(_) => 3,
+            WindowEvent::Cloaked(_) => 4,
+            WindowEvent::Uncloaked(_) => 5,
+            WindowEvent::Moved(_) => 6,
+            WindowEvent::Minimized(_) => 7,
+            WindowEvent::Restored(_) => 8,
+            WindowEvent::FocusChanged(_) => 9,
+            WindowEvent::TitleChanged(_) => 10,
+            WindowEvent::Reordered(_) => 11,
+            WindowEvent::MenuClosed => 12,
+        }
+    }
+
+    // The window the event is about, if it's about one
+    pub(crate) fn hwnd(self) -> Option<HWND> {
+        match self {
+            WindowEvent::Created(hwnd)
+            | WindowEvent::Destroyed(hwnd)
+            | WindowEvent::Shown(hwnd)
+            | WindowEvent::Hidden(hwnd)
+            | WindowEvent::Cloaked(hwnd)
+            | WindowEvent::Uncloaked(hwnd)
+            | WindowEvent::Moved(hwnd)
+            | WindowEvent::Minimized(hwnd)
+            | WindowEvent::Restored(hwnd)
+            | WindowEvent::FocusChanged(hwnd)
+            | WindowEvent::TitleChanged(hwnd)
+            | WindowEvent::Reordered(hwnd) => Some(hwnd),
+            WindowEvent::MenuClosed => None,
+        }
+    }
+}
+
+// Something that finds out about windows opening, moving, getting focus, etc., and hands those
+// events to the event worker (see event_hook::queue_event()). Several can run at once, e.g. UI
+// Automation alongside the WinEvent hook.
+pub(crate) trait WindowTracker {
+    fn name(&self) -> &'static str;
+
+    // Called on start()'s thread, which pumps messages afterwards
+    fn start(&mut self) -> anyhow::Result<()>;
+
+    // Called on the same thread as start(). Returns false if the tracker couldn't be stopped, in
+    // which case it's kept around so the next stop() can try again.
+    fn stop(&mut self) -> bool;
+}
+
+thread_local! {
+    static TRACKERS: RefCell<Vec<Box<dyn WindowTracker>>> = RefCell::new(Vec::new());
+}
+
+// Start the trackers that the config asks for. If WinEvent hooks can't be installed, the shell hook
+// takes over for them.
+pub(crate) fn start() {
+    let (backend, uia_tracking) = {
+        let config = CONFIG.lock().unwrap();
+        (
+            config.global.tracking_backend.unwrap_or_default(),
+            config.global.uia_tracking.unwrap_or(false),
+        )
+    };
+
+    let primary: Box<dyn WindowTracker> = match backend {
+        TrackingBackend::WinEvent => Box::new(WinEventTracker::default()),
+        TrackingBackend::ShellHook => Box::new(ShellHookTracker),
+    };
+    let primary = start_tracker(primary);
+    let primary = match (primary, backend) {
+        (Some(primary), _) => Some(primary),
+        (None, TrackingBackend::WinEvent) => {
+            let fallback = start_tracker(Box::new(ShellHookTracker));
+            if fallback.is_some() {
+                notifications::notify(
+                    NotificationKind::Error,
+                    "Could not install event hook",
+                    "Tracking windows with the shell hook instead, so borders may lag behind them.",
+                );
+            }
+            fallback
+        }
+        (None, TrackingBackend::ShellHook) => None,
+    };
+    if primary.is_none() {
+        notifications::notify(
+            NotificationKind::Error,
+            "Could not install event hook",
+            "Borders won't follow their windows. See the log for details.",
+        );
+    }
+
+    let mut trackers: Vec<Box<dyn WindowTracker>> = primary.into_iter().collect();
+
+    // UI Automation only adds to the other trackers, so it's fine if it doesn't start
+    if uia_tracking {
+        trackers.extend(start_tracker(Box::new(UiaTracker::default())));
+    }
+
+    TRACKERS.replace(trackers);
+}
+
+fn start_tracker(mut tracker: Box<dyn WindowTracker>) -> Option<Box<dyn WindowTracker>> {
+    match tracker.start() {
+        Ok(()) => {
+            info!("tracking windows with the {}", tracker.name());
+            Some(tracker)
+        }
+        Err(e) => {
+            error!("could not start the {}: {e:#}", tracker.name());
+            None
+        }
+    }
+}
+
+// Stop every tracker started by start(). Returns false if any of them couldn't be stopped.
+pub(crate) fn stop() -> bool {
+    let mut trackers = TRACKERS.take();
+    trackers.retain_mut(|tracker| !tracker.stop());
+
+    let stopped = trackers.is_empty();
+    // Keep whatever didn't stop so another stop() can try again
+    TRACKERS.replace(trackers);
+    stopped
+}
@@ -0,0 +1,204 @@
+use anyhow::Context;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use windows::core::implement;
+use windows::Win32::Foundation::HWND;
+use windows::Win32::System::Com::{
+    CoCreateInstance, CoInitializeEx, CoUninitialize, CLSCTX_INPROC_SERVER, COINIT_MULTITHREADED,
+    SAFEARRAY,
+};
+use windows::Win32::System::SystemInformation::GetTickCount;
+use windows::Win32::UI::Accessibility::{
+    CUIAutomation, IUIAutomation, IUIAutomationElement, IUIAutomationFocusChangedEventHandler,
+    IUIAutomationFocusChangedEventHandler_Impl, IUIAutomationStructureChangedEventHandler,
+    IUIAutomationStructureChangedEventHandler_Impl, IUIAutomationTreeWalker, StructureChangeType,
+    StructureChangeType_ChildAdded, StructureChangeType_ChildRemoved,
+    StructureChangeType_ChildrenBulkRemoved, StructureChangeType_ChildrenInvalidated,
+    TreeScope_Children,
+};
+use windows::Win32::UI::WindowsAndMessaging::IsWindow;
+
+use crate::event_hook;
+use crate::registry::BORDERS;
+use crate::tracking::{WindowEvent, WindowTracker};
+
+// Some windows (mostly UWP apps, whose content lives in a CoreWindow inside ApplicationFrameHost)
+// don't send every WinEvent we'd expect, but UI Automation still sees them get focus and come and
+// go. This tracker only adds those events on top of another tracker, since UI Automation doesn't
+// tell us when windows move.
+//
+// Its handlers get called on UI Automation's own threads, so everything runs on a thread of its own
+// in the multithreaded apartment.
+
+// Focus can land on an element deep inside a window that has no HWND itself, so we walk up this
+// many parents at most to find one that does
+const MAX_PARENT_STEPS: usize = 16;
+
+#[derive(Default)]
+pub(crate) struct UiaTracker {
+    // Dropping this (or sending on it) stops the tracker's thread
+    stop: Option<Sender<()>>,
+}
+
+impl WindowTracker for UiaTracker {
+    fn name(&self) -> &'static str {
+        "UI Automation tracker"
+    }
+
+    fn start(&mut self) -> anyhow::Result<()> {
+        let (ready_sender, ready_receiver) = mpsc::channel();
+        let (stop_sender, stop_receiver) = mpsc::channel::<()>();
+
+        let _ = thread::Builder::new()
+            .name("uia tracker".to_string())
+            .spawn(move || unsafe {
+                if let Err(e) = CoInitializeEx(None, COINIT_MULTITHREADED).ok() {
+                    let _ = ready_sender.send(Err(e).context("could not initialize COM"));
+                    return;
+                }
+
+                // The handlers have to be removed before COM is uninitialized
+                match add_event_handlers() {
+                    Ok(automation) => {
+                        let _ = ready_sender.send(Ok(()));
+                        let _ = stop_receiver.recv();
+                        let _ = automation.RemoveAllEventHandlers();
+                    }
+                    Err(e) => {
+                        let _ = ready_sender.send(Err(e));
+                    }
+                }
+
+                CoUninitialize();
+            });
+
+        ready_receiver
+            .recv()
+            .context("uia tracker thread exited early")??;
+        self.stop = Some(stop_sender);
+        Ok(())
+    }
+
+    fn stop(&mut self) -> bool {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        true
+    }
+}
+
+fn add_event_handlers() -> anyhow::Result<IUIAutomation> {
+    unsafe {
+        let automation: IUIAutomation =
+            CoCreateInstance(&CUIAutomation, None, CLSCTX_INPROC_SERVER)
+                .context("could not create UI Automation")?;
+
+        let focus_handler: IUIAutomationFocusChangedEventHandler = FocusChangedHandler {
+            walker: automation.ControlViewWalker()?,
+        }
+        .into();
+        automation
+            .AddFocusChangedEventHandler(None, &focus_handler)
+            .context("could not add focus changed handler")?;
+
+        // Top-level windows are the desktop's children
+        let structure_handler: IUIAutomationStructureChangedEventHandler =
+            StructureChangedHandler.into();
+        automation
+            .AddStructureChangedEventHandler(
+                &automation.GetRootElement()?,
+                TreeScope_Children,
+                None,
+                &structure_handler,
+            )
+            .context("could not add structure changed handler")?;
+
+        Ok(automation)
+    }
+}
+
+#[implement(IUIAutomationFocusChangedEventHandler)]
+struct FocusChangedHandler {
+    walker: IUIAutomationTreeWalker,
+}
+
+impl IUIAutomationFocusChangedEventHandler_Impl for FocusChangedHandler_Impl {
+    fn HandleFocusChangedEvent(
+        &self,
+        sender: Option<&IUIAutomationElement>,
+    ) -> windows::core::Result<()> {
+        let mut element = sender.cloned();
+
+        for _ in 0..MAX_PARENT_STEPS {
+            let Some(current) = element else {
+                break;
+            };
+            if let Some(hwnd) = get_native_window(&current) {
+                // on_focus() finds the top-level window from here
+                queue_event(WindowEvent::FocusChanged(hwnd));
+                break;
+            }
+            element = unsafe { self.walker.GetParentElement(&current) }.ok();
+        }
+
+        Ok(())
+    }
+}
+
+#[implement(IUIAutomationStructureChangedEventHandler)]
+struct StructureChangedHandler;
+
+impl IUIAutomationStructureChangedEventHandler_Impl for StructureChangedHandler_Impl {
+    // The windows crate names these constants like the C enum
+    #[allow(non_upper_case_globals)]
+    fn HandleStructureChangedEvent(
+        &self,
+        sender: Option<&IUIAutomationElement>,
+        change_type: StructureChangeType,
+        _runtime_id: *const SAFEARRAY,
+    ) -> windows::core::Result<()> {
+        match change_type {
+            // The sender is the window that was added
+            StructureChangeType_ChildAdded => {
+                if let Some(hwnd) = sender.and_then(get_native_window) {
+                    queue_event(WindowEvent::Created(hwnd));
+                }
+            }
+            // The sender is the desktop here, and all we get for the window is its runtime ID, so
+            // we look for borders whose windows are gone instead
+            StructureChangeType_ChildRemoved
+            | StructureChangeType_ChildrenBulkRemoved
+            | StructureChangeType_ChildrenInvalidated => {
+                // Monitor frames and the cursor ring don't have windows to lose
+                for hwnd in BORDERS
+                    .entries()
+                    .into_iter()
+                    .filter_map(|(key, _)| key.hwnd())
+                {
+                    if !unsafe { IsWindow(hwnd) }.as_bool() {
+                        queue_event(WindowEvent::Destroyed(hwnd));
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+fn get_native_window(element: &IUIAutomationElement) -> Option<HWND> {
+    let handle = unsafe { element.CurrentNativeWindowHandle() }.ok()?;
+    let hwnd = HWND(handle.0);
+    (!hwnd.is_invalid()).then_some(hwnd)
+}
+
+fn queue_event(event: WindowEvent) {
+    // Paused borders get recreated from scratch once they're resumed
+    if crate::is_suspended() {
+        return;
+    }
+
+    let time = unsafe { GetTickCount() };
+    event_hook::queue_event(event, time);
+}
@@ -0,0 +1,1693 @@
+use windows::core::{w, PCWSTR, PWSTR};
+use windows::Win32::Foundation::{
+    CloseHandle, GetLastError, BOOL, ERROR_ACCESS_DENIED, ERROR_SUCCESS, FALSE, HINSTANCE, HWND,
+    LPARAM, RECT, TRUE, WPARAM,
+};
+use windows::Win32::Graphics::Dwm::{
+    DwmGetWindowAttribute, DWMWA_CLOAKED, DWMWA_EXTENDED_FRAME_BOUNDS,
+    DWMWA_WINDOW_CORNER_PREFERENCE, DWMWCP_DEFAULT, DWMWCP_DONOTROUND, DWMWCP_ROUND,
+    DWMWCP_ROUNDSMALL, DWM_WINDOW_CORNER_PREFERENCE,
+};
+use windows::Win32::Graphics::Gdi::{
+    CombineRgn, CreateRectRgn, CreateRectRgnIndirect, DeleteObject, EnumDisplayMonitors,
+    GetMonitorInfoW, GetRegionData, GetWindowRgn, MonitorFromWindow, COMPLEXREGION, HDC, HMONITOR,
+    MONITORINFO, MONITOR_DEFAULTTONEAREST, MONITOR_DEFAULTTONULL, NULLREGION, RGNDATA, RGN_DIFF,
+    SIMPLEREGION,
+};
+use windows::Win32::System::Registry::{RegGetValueW, HKEY_CURRENT_USER, RRF_RT_REG_BINARY};
+use windows::Win32::System::SystemInformation::GetTickCount;
+use windows::Win32::System::Threading::{
+    GetCurrentProcessId, OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32,
+    PROCESS_QUERY_LIMITED_INFORMATION,
+};
+use windows::Win32::UI::HiDpi::{
+    GetDpiForWindow, SetProcessDpiAwarenessContext, DPI_AWARENESS_CONTEXT,
+};
+use windows::Win32::UI::Input::Ime::ImmDisableIME;
+use windows::Win32::UI::WindowsAndMessaging::{
+    DestroyWindow, EnumWindows, GetAncestor, GetClassNameW, GetForegroundWindow, GetWindow,
+    GetWindowLongW, GetWindowPlacement, GetWindowRect, GetWindowTextW, GetWindowThreadProcessId,
+    IsHungAppWindow, IsIconic, IsWindowVisible, IsZoomed, PostMessageW, RegisterWindowMessageW,
+    SendNotifyMessageW, GA_ROOTOWNER, GWL_EXSTYLE, GWL_STYLE, GW_HWNDPREV, WINDOWPLACEMENT, WM_APP,
+    WM_NCDESTROY, WS_CAPTION, WS_CHILD, WS_EX_LAYERED, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW,
+    WS_EX_TOPMOST, WS_EX_TRANSPARENT, WS_EX_WINDOWEDGE, WS_MAXIMIZE, WS_THICKFRAME,
+};
+
+use anyhow::{anyhow, Context};
+use std::collections::HashMap;
+use std::ptr;
+use std::sync::mpsc::Sender;
+use std::sync::{LazyLock, Mutex};
+use std::thread;
+use std::time;
+
+use crate::accessibility;
+use crate::animations::AnimationType;
+use crate::badges::Badges;
+use crate::border_config::{BorderPlacement, Config, RenderQuality, WindowRule, CONFIG};
+use crate::colors::ColorConfig;
+use crate::do_not_disturb;
+use crate::edges::EdgeColors;
+use crate::effects::{AdaptiveQuality, HoverPeek, Noise, NotResponding, Particles, Ripple};
+use crate::plugins::{self, PluginInstance};
+use crate::registry::{BorderKey, BORDERS};
+use crate::retry;
+use crate::rule_engine::{self, WindowInfo};
+use crate::scripting;
+use crate::state;
+use crate::strokes::{ActiveExtra, StrokeLayer};
+use crate::window_border::WindowBorder;
+use crate::{__ImageBase, SendHWND, INITIAL_WINDOWS};
+
+pub const WM_APP_LOCATIONCHANGE: u32 = WM_APP;
+pub const WM_APP_REORDER: u32 = WM_APP + 1;
+pub const WM_APP_FOCUS: u32 = WM_APP + 2;
+pub const WM_APP_SHOWUNCLOAKED: u32 = WM_APP + 3;
+pub const WM_APP_HIDECLOAKED: u32 = WM_APP + 4;
+pub const WM_APP_MINIMIZESTART: u32 = WM_APP + 5;
+pub const WM_APP_MINIMIZEEND: u32 = WM_APP + 6;
+pub const WM_APP_ANIMATE: u32 = WM_APP + 7;
+pub const WM_APP_TITLECHANGE: u32 = WM_APP + 8;
+pub const WM_APP_SETCOLOR: u32 = WM_APP + 9;
+pub const WM_APP_FLASH: u32 = WM_APP + 10;
+pub const WM_APP_PROGRESS: u32 = WM_APP + 11;
+pub const WM_APP_RELOAD_HOTKEYS: u32 = WM_APP + 12;
+pub const WM_APP_LOCATE: u32 = WM_APP + 13;
+pub const WM_APP_TASKVIEW: u32 = WM_APP + 14;
+pub const WM_APP_OVERLAY_REDRAW: u32 = WM_APP + 15;
+pub const WM_APP_FULLSCREEN: u32 = WM_APP + 16;
+pub const WM_APP_HOVER: u32 = WM_APP + 17;
+
+/// The window class of the empty windows that `tacky-borders preview` and the self-test attach
+/// their own borders to. Running instances leave windows of this class alone.
+pub const DUMMY_WINDOW_CLASS: &str = "tacky-borders-dummy";
+
+// Note: don't use this macro with fatal errors since there's no real logic to handle them
+#[macro_export]
+macro_rules! log_if_err {
+    ($err:expr) => {
+        if let Err(e) = $err {
+            // TODO for some reason if I use {:#} or {:?}, some errors will repeatedly print (like
+            // the one in main.rs for tray_icon_result). It could have something to do with how they
+            // implement .source()
+            error!("{e:#}");
+        }
+    };
+}
+
+pub fn has_filtered_style(hwnd: HWND) -> bool {
+    let style = unsafe { GetWindowLongW(hwnd, GWL_STYLE) as u32 };
+    let ex_style = unsafe { GetWindowLongW(hwnd, GWL_EXSTYLE) as u32 };
+
+    style & WS_CHILD.0 != 0
+        || ex_style & WS_EX_TOOLWINDOW.0 != 0
+        || ex_style & WS_EX_NOACTIVATE.0 != 0
+}
+
+// Tool windows are normally filtered out, but they can still follow their owner's border (see
+// 'follow_owned'). Things like menus and tooltips use WS_EX_NOACTIVATE, so they're left out here.
+pub fn is_tool_window(hwnd: HWND) -> bool {
+    let style = unsafe { GetWindowLongW(hwnd, GWL_STYLE) as u32 };
+    let ex_style = unsafe { GetWindowLongW(hwnd, GWL_EXSTYLE) as u32 };
+
+    style & WS_CHILD.0 == 0
+        && ex_style & WS_EX_TOOLWINDOW.0 != 0
+        && ex_style & WS_EX_NOACTIVATE.0 == 0
+}
+
+// Whether the window covers its whole monitor
+pub fn covers_monitor(hwnd: HWND) -> bool {
+    let mut window_rect = RECT::default();
+    if unsafe { GetWindowRect(hwnd, &mut window_rect) }.is_err() {
+        return false;
+    }
+
+    let mut monitor_info = MONITORINFO {
+        cbSize: size_of::<MONITORINFO>() as u32,
+        ..Default::default()
+    };
+    let monitor = unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST) };
+    if !unsafe { GetMonitorInfoW(monitor, &mut monitor_info) }.as_bool() {
+        return false;
+    }
+
+    window_rect == monitor_info.rcMonitor
+}
+
+// Borderless fullscreen windows (like most games) drop their caption and frame and cover their
+// monitor, so there's nowhere left for a border to go
+pub fn is_frameless_fullscreen(hwnd: HWND) -> bool {
+    let style = unsafe { GetWindowLongW(hwnd, GWL_STYLE) as u32 };
+
+    style & (WS_CAPTION.0 | WS_THICKFRAME.0) == 0 && covers_monitor(hwnd)
+}
+
+pub fn get_window_process_id(hwnd: HWND) -> u32 {
+    let mut process_id = 0;
+    unsafe { GetWindowThreadProcessId(hwnd, Some(&mut process_id)) };
+    process_id
+}
+
+// Find the window that a tool window should take its border from. That's its root owner if it has
+// one, otherwise any other bordered window from the same process.
+pub fn get_group_leader(hwnd: HWND) -> Option<HWND> {
+    let process_id = get_window_process_id(hwnd);
+
+    // Our own border windows are tool windows too, so make sure we never pick those up
+    if process_id == unsafe { GetCurrentProcessId() } {
+        return None;
+    }
+
+    let root_owner = unsafe { GetAncestor(hwnd, GA_ROOTOWNER) };
+    if root_owner != hwnd && !root_owner.is_invalid() {
+        return match has_filtered_style(root_owner) {
+            true => None,
+            false => Some(root_owner),
+        };
+    }
+
+    BORDERS
+        .entries()
+        .into_iter()
+        .filter_map(|(key, _)| key.hwnd())
+        .find(|&leader| {
+            leader != hwnd
+                && !has_filtered_style(leader)
+                && get_window_process_id(leader) == process_id
+        })
+}
+
+pub fn get_window_title(hwnd: HWND) -> String {
+    let mut title_arr: [u16; 256] = [0; 256];
+
+    if unsafe { GetWindowTextW(hwnd, &mut title_arr) } == 0 {
+        let last_error = unsafe { GetLastError() };
+        error!("could not retrieve window title for {hwnd:?}: {last_error:?}");
+    }
+
+    let title_binding = String::from_utf16_lossy(&title_arr);
+    title_binding.split_once("\0").unwrap().0.to_string()
+}
+
+pub fn get_window_class(hwnd: HWND) -> String {
+    let mut class_arr: [u16; 256] = [0; 256];
+
+    if unsafe { GetClassNameW(hwnd, &mut class_arr) } == 0 {
+        let last_error = unsafe { GetLastError() };
+        error!("could not retrieve window class for {hwnd:?}: {last_error:?}");
+    }
+
+    let class_binding = String::from_utf16_lossy(&class_arr);
+    class_binding.split_once("\0").unwrap().0.to_string()
+}
+
+// How long a process name is remembered for. Process IDs get reused once a process exits, so the
+// names can't be kept forever.
+const PROCESS_NAME_TTL: time::Duration = time::Duration::from_secs(30);
+
+static PROCESS_NAMES: LazyLock<Mutex<HashMap<u32, (time::Instant, String)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// Get the name of the executable that owns the window (e.g. "firefox.exe"). This is looked up for
+// every rule match and event, so the names are cached by process ID.
+pub fn get_process_name(hwnd: HWND) -> String {
+    let process_id = get_window_process_id(hwnd);
+
+    let mut process_names = PROCESS_NAMES.lock().unwrap();
+    if let Some((checked_at, name)) = process_names.get(&process_id) {
+        if checked_at.elapsed() < PROCESS_NAME_TTL {
+            return name.clone();
+        }
+    }
+
+    // Elevated and protected processes can't be opened without being elevated ourselves, which
+    // happens all the time and isn't worth an error. Failures are cached too, so we don't keep
+    // trying to open them.
+    let name = match query_process_name(process_id) {
+        Ok(name) => name,
+        Err(e) if e.code() == ERROR_ACCESS_DENIED.to_hresult() => {
+            debug!("could not open process for {hwnd:?}: {e}");
+            String::new()
+        }
+        Err(e) => {
+            error!("could not retrieve process name for {hwnd:?}: {e}");
+            String::new()
+        }
+    };
+
+    process_names.retain(|_, (checked_at, _)| checked_at.elapsed() < PROCESS_NAME_TTL);
+    process_names.insert(process_id, (time::Instant::now(), name.clone()));
+
+    name
+}
+
+fn query_process_name(process_id: u32) -> windows::core::Result<String> {
+    let handle = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, FALSE, process_id) }?;
+
+    let mut path_arr: [u16; 260] = [0; 260];
+    let mut path_len = path_arr.len() as u32;
+    let result = unsafe {
+        QueryFullProcessImageNameW(
+            handle,
+            PROCESS_NAME_WIN32,
+            PWSTR(path_arr.as_mut_ptr()),
+            &mut path_len,
+        )
+    };
+    let _ = unsafe { CloseHandle(handle) };
+    result?;
+
+    let path = String::from_utf16_lossy(&path_arr[..path_len as usize]);
+    Ok(path.rsplit('\\').next().unwrap_or_default().to_string())
+}
+
+pub fn get_window_info(hwnd: HWND) -> WindowInfo {
+    WindowInfo {
+        title: get_window_title(hwnd),
+        class: get_window_class(hwnd),
+        process: get_process_name(hwnd),
+        monitor: get_monitor_index(hwnd),
+        maximized: unsafe { IsZoomed(hwnd).as_bool() },
+    }
+}
+
+// Get all the display monitors in the order that Windows enumerates them
+pub fn get_monitors() -> Vec<HMONITOR> {
+    let mut monitors: Vec<HMONITOR> = Vec::new();
+
+    if !unsafe {
+        EnumDisplayMonitors(
+            HDC::default(),
+            None,
+            Some(enum_monitors_callback),
+            LPARAM(ptr::addr_of_mut!(monitors) as isize),
+        )
+    }
+    .as_bool()
+    {
+        error!("could not enumerate display monitors");
+    }
+
+    monitors
+}
+
+unsafe extern "system" fn enum_monitors_callback(
+    hmonitor: HMONITOR,
+    _hdc: HDC,
+    _rect: *mut RECT,
+    lparam: LPARAM,
+) -> BOOL {
+    let monitors = &mut *(lparam.0 as *mut Vec<HMONITOR>);
+    monitors.push(hmonitor);
+    TRUE
+}
+
+// Get the 1-based index of the monitor the window is on
+pub fn get_monitor_index(hwnd: HWND) -> Option<u32> {
+    let monitor = unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTONULL) };
+    if monitor.is_invalid() {
+        return None;
+    }
+
+    get_monitors()
+        .iter()
+        .position(|&hmonitor| hmonitor == monitor)
+        .map(|index| index as u32 + 1)
+}
+
+// Get the work area (the monitor minus the taskbar and other app bars) of the window's monitor
+pub fn get_work_area(hwnd: HWND) -> Option<RECT> {
+    let monitor = unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST) };
+    get_monitor_work_area(monitor)
+}
+
+pub fn get_monitor_work_area(monitor: HMONITOR) -> Option<RECT> {
+    let mut monitor_info = MONITORINFO {
+        cbSize: size_of::<MONITORINFO>() as u32,
+        ..Default::default()
+    };
+    match unsafe { GetMonitorInfoW(monitor, &mut monitor_info) }.as_bool() {
+        true => Some(monitor_info.rcWork),
+        false => None,
+    }
+}
+
+pub fn get_monitor_rect(monitor: HMONITOR) -> Option<RECT> {
+    let mut monitor_info = MONITORINFO {
+        cbSize: size_of::<MONITORINFO>() as u32,
+        ..Default::default()
+    };
+    match unsafe { GetMonitorInfoW(monitor, &mut monitor_info) }.as_bool() {
+        true => Some(monitor_info.rcMonitor),
+        false => None,
+    }
+}
+
+// Get the window rule from 'window_rules' in the config
+pub fn get_window_rule(hwnd: HWND) -> WindowRule {
+    let window = get_window_info(hwnd);
+
+    rule_engine::get_rule(&CONFIG.lock().unwrap().window_rules, &window)
+}
+
+pub fn is_window_visible(hwnd: HWND) -> bool {
+    unsafe { IsWindowVisible(hwnd).as_bool() }
+}
+
+pub fn is_rect_visible(rect: &RECT) -> bool {
+    rect.top >= 0 || rect.left >= 0 || rect.bottom >= 0 || rect.right >= 0
+}
+
+pub fn do_rects_overlap(rect1: &RECT, rect2: &RECT) -> bool {
+    rect1.left < rect2.right
+        && rect1.right > rect2.left
+        && rect1.top < rect2.bottom
+        && rect1.bottom > rect2.top
+}
+
+pub fn are_rects_same_size(rect1: &RECT, rect2: &RECT) -> bool {
+    rect1.right - rect1.left == rect2.right - rect2.left
+        && rect1.bottom - rect1.top == rect2.bottom - rect2.top
+}
+
+pub fn is_cloaked(hwnd: HWND) -> bool {
+    let mut is_cloaked = FALSE;
+    if let Err(e) = unsafe {
+        DwmGetWindowAttribute(
+            hwnd,
+            DWMWA_CLOAKED,
+            ptr::addr_of_mut!(is_cloaked) as _,
+            size_of::<BOOL>() as u32,
+        )
+    } {
+        error!("could not check if window is cloaked: {e}");
+        return true;
+    }
+    is_cloaked.as_bool()
+}
+
+pub fn is_active_window(hwnd: HWND) -> bool {
+    unsafe { GetForegroundWindow() == hwnd }
+}
+
+// Whether the foreground window is a member of the given window group
+pub fn is_group_active(group: &str) -> bool {
+    let foreground = unsafe { GetForegroundWindow() };
+    BORDERS
+        .group(BorderKey::window(foreground))
+        .is_some_and(|foreground_group| foreground_group == group)
+}
+
+// WinEvent timestamps come from GetTickCount, so this converts them into an Instant that can be
+// shared between border threads
+pub fn instant_from_tick_count(tick_count: u32) -> time::Instant {
+    let now = time::Instant::now();
+    let age = unsafe { GetTickCount() }.wrapping_sub(tick_count);
+
+    // Anything older than a second is probably a bogus timestamp, so just use the current time
+    match age <= 1000 {
+        true => now
+            .checked_sub(time::Duration::from_millis(age as u64))
+            .unwrap_or(now),
+        false => now,
+    }
+}
+
+// Windows doesn't have an API for Night Light, but its state is stored as an undocumented blob in
+// the registry. The byte at index 18 is 0x15 when it's on and 0x13 when it's off.
+const NIGHT_LIGHT_STATE_KEY: PCWSTR = w!("Software\\Microsoft\\Windows\\CurrentVersion\\CloudStore\\Store\\DefaultAccount\\Current\\default$windows.data.bluelightreduction.bluelightreductionstate\\windows.data.bluelightreduction.bluelightreductionstate");
+
+// Borders check this on every render, so we only re-read the registry every few seconds
+static NIGHT_LIGHT_CACHE: Mutex<Option<(time::Instant, bool)>> = Mutex::new(None);
+
+pub fn is_night_light_enabled() -> bool {
+    let mut cache = NIGHT_LIGHT_CACHE.lock().unwrap();
+    if let Some((checked_at, enabled)) = *cache {
+        if checked_at.elapsed() < time::Duration::from_secs(5) {
+            return enabled;
+        }
+    }
+
+    let mut data = [0u8; 64];
+    let mut data_len = data.len() as u32;
+    let result = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            NIGHT_LIGHT_STATE_KEY,
+            w!("Data"),
+            RRF_RT_REG_BINARY,
+            None,
+            Some(data.as_mut_ptr() as _),
+            Some(&mut data_len),
+        )
+    };
+
+    // If the key doesn't exist (e.g. Night Light has never been used), it's definitely off
+    let enabled = result == ERROR_SUCCESS && data_len > 18 && data[18] == 0x15;
+    *cache = Some((time::Instant::now(), enabled));
+
+    enabled
+}
+
+// Expand '~' at the start of a path and any %VAR% or ${VAR} environment variables in it. Variables
+// that aren't set are left as they are, so the error about the path makes more sense.
+pub fn expand_path(path: &str) -> String {
+    let expanded = match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => match dirs::home_dir() {
+            Some(home_dir) => format!("{}{rest}", home_dir.display()),
+            None => path.to_string(),
+        },
+        _ => path.to_string(),
+    };
+
+    // One left-to-right pass over both syntaxes, so the value of one variable never gets expanded
+    // again by the other
+    let mut result = String::new();
+    let mut rest = expanded.as_str();
+
+    while let Some(start) = rest.find(['$', '%']) {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        // The variable's name and how long the whole '${NAME}' or '%NAME%' is
+        let variable = match rest.strip_prefix("${") {
+            Some(after) => after.find('}').map(|end| (&after[..end], end + 3)),
+            None => rest
+                .strip_prefix('%')
+                .and_then(|after| after.find('%').map(|end| (&after[..end], end + 2))),
+        };
+
+        // A '$' that doesn't start '${', or a '%' or '${' that's never closed, is just a character
+        let Some((name, len)) = variable else {
+            result.push_str(&rest[..1]);
+            rest = &rest[1..];
+            continue;
+        };
+
+        match std::env::var(name) {
+            Ok(value) if !name.is_empty() => result.push_str(&value),
+            _ => result.push_str(&rest[..len]),
+        }
+        rest = &rest[len..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+// FNV-1a, for hashes that are saved to disk or shared between builds. Unlike std's hasher, it's
+// guaranteed to give the same results across Rust versions.
+pub fn stable_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+// Explorer broadcasts this to every top-level window whenever it creates the taskbar, which also
+// happens when explorer.exe restarts. It's 0 if the message couldn't be registered.
+pub fn taskbar_created_message() -> u32 {
+    static MESSAGE: LazyLock<u32> =
+        LazyLock::new(|| unsafe { RegisterWindowMessageW(w!("TaskbarCreated")) });
+    *MESSAGE
+}
+
+pub fn post_message_w(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> windows::core::Result<()> {
+    unsafe { PostMessageW(hwnd, msg, wparam, lparam) }
+}
+
+pub fn send_notify_message_w(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> windows::core::Result<()> {
+    unsafe { SendNotifyMessageW(hwnd, msg, wparam, lparam) }
+}
+
+pub fn imm_disable_ime(param0: u32) -> BOOL {
+    unsafe { ImmDisableIME(param0) }
+}
+
+pub fn set_process_dpi_awareness_context(
+    value: DPI_AWARENESS_CONTEXT,
+) -> windows::core::Result<()> {
+    unsafe { SetProcessDpiAwarenessContext(value) }
+}
+
+pub fn has_native_border(hwnd: HWND) -> bool {
+    unsafe {
+        let style = GetWindowLongW(hwnd, GWL_STYLE) as u32;
+        let ex_style = GetWindowLongW(hwnd, GWL_EXSTYLE) as u32;
+
+        ex_style & WS_EX_WINDOWEDGE.0 != 0 && style & WS_MAXIMIZE.0 == 0
+    }
+}
+
+// Whether Windows considers the window "Not Responding", i.e. it hasn't picked up messages for a few
+// seconds. This only looks at when the window last did, so unlike sending it a message, it never
+// blocks (which would freeze whichever border is asking).
+pub fn is_window_hung(hwnd: HWND) -> bool {
+    unsafe { IsHungAppWindow(hwnd) }.as_bool()
+}
+
+// Check whether 'rect' is completely covered by the windows above 'hwnd' in the z-order. Layered
+// windows (like other borders) might be see-through, so they don't count as covering anything.
+pub fn is_window_occluded(hwnd: HWND, rect: &RECT) -> bool {
+    unsafe {
+        let visible_region = CreateRectRgnIndirect(rect);
+        let mut is_occluded = false;
+
+        let mut above = GetWindow(hwnd, GW_HWNDPREV);
+        while let Ok(window) = above {
+            above = GetWindow(window, GW_HWNDPREV);
+
+            let Some(window_rect) = get_covering_rect(window) else {
+                continue;
+            };
+
+            let window_region = CreateRectRgnIndirect(&window_rect);
+            let result = CombineRgn(visible_region, visible_region, window_region, RGN_DIFF);
+            let _ = DeleteObject(window_region);
+
+            if result == NULLREGION {
+                is_occluded = true;
+                break;
+            }
+        }
+
+        let _ = DeleteObject(visible_region);
+        is_occluded
+    }
+}
+
+// The windows stacked between 'lower' and 'upper' that overlap 'rect', as screen rects. Returns None
+// if 'upper' isn't above 'lower' at all.
+pub fn get_windows_between(lower: HWND, upper: HWND, rect: &RECT) -> Option<Vec<RECT>> {
+    let mut rects = Vec::new();
+
+    let mut above = unsafe { GetWindow(lower, GW_HWNDPREV) };
+    while let Ok(window) = above {
+        if window == upper {
+            return Some(rects);
+        }
+        above = unsafe { GetWindow(window, GW_HWNDPREV) };
+
+        if let Some(window_rect) = get_covering_rect(window).filter(|r| do_rects_overlap(r, rect)) {
+            rects.push(window_rect);
+        }
+    }
+
+    None
+}
+
+// The parts of 'rect' covered by the windows above 'hwnd' in the z-order, as screen rects. Windows
+// with a shape of their own (from SetWindowRgn) only cover their shape.
+pub fn get_occluding_rects(hwnd: HWND, rect: &RECT) -> Vec<RECT> {
+    let mut rects = Vec::new();
+
+    let mut above = unsafe { GetWindow(hwnd, GW_HWNDPREV) };
+    while let Ok(window) = above {
+        above = unsafe { GetWindow(window, GW_HWNDPREV) };
+
+        let Some(window_rect) = get_covering_rect(window).filter(|r| do_rects_overlap(r, rect))
+        else {
+            continue;
+        };
+        match get_window_region_rects(window) {
+            Some(region_rects) => rects.extend(
+                region_rects
+                    .into_iter()
+                    .filter(|region_rect| do_rects_overlap(region_rect, rect)),
+            ),
+            None => rects.push(window_rect),
+        }
+    }
+
+    rects
+}
+
+// Shapes with more rects than this (like big rounded ones) just cover their bounds, since every
+// rect makes the border's mask more expensive to build
+const MAX_REGION_RECTS: usize = 64;
+
+// The screen rects making up a window's shape, or None if it doesn't have one
+fn get_window_region_rects(hwnd: HWND) -> Option<Vec<RECT>> {
+    unsafe {
+        let region = CreateRectRgn(0, 0, 0, 0);
+
+        let rects = (|| {
+            if !matches!(GetWindowRgn(hwnd, region), SIMPLEREGION | COMPLEXREGION) {
+                return None;
+            }
+
+            // Window regions are relative to the top left of the window
+            let mut window_rect = RECT::default();
+            GetWindowRect(hwnd, &mut window_rect).ok()?;
+            let to_screen = |rect: RECT| RECT {
+                left: rect.left + window_rect.left,
+                top: rect.top + window_rect.top,
+                right: rect.right + window_rect.left,
+                bottom: rect.bottom + window_rect.top,
+            };
+
+            let size = GetRegionData(region, 0, None);
+            if size == 0 {
+                return None;
+            }
+            // A u32 buffer keeps the RGNDATA aligned
+            let mut buffer = vec![0u32; (size as usize).div_ceil(4)];
+            let data = buffer.as_mut_ptr() as *mut RGNDATA;
+            if GetRegionData(region, size, Some(data)) == 0 {
+                return None;
+            }
+
+            let header = (*data).rdh;
+            if header.nCount as usize > MAX_REGION_RECTS {
+                return Some(vec![to_screen(header.rcBound)]);
+            }
+
+            let first = ptr::addr_of!((*data).Buffer) as *const RECT;
+            Some(
+                (0..header.nCount as usize)
+                    .map(|i| to_screen(first.add(i).read_unaligned()))
+                    .collect(),
+            )
+        })();
+
+        let _ = DeleteObject(region);
+        rects
+    }
+}
+
+// The part of the screen a window covers, or None if it can't cover anything. Layered windows
+// (like other borders) might be see-through, so they don't count.
+fn get_covering_rect(hwnd: HWND) -> Option<RECT> {
+    unsafe {
+        let ex_style = GetWindowLongW(hwnd, GWL_EXSTYLE) as u32;
+        if !is_window_visible(hwnd)
+            || IsIconic(hwnd).as_bool()
+            || ex_style & (WS_EX_LAYERED.0 | WS_EX_TRANSPARENT.0) != 0
+            || is_cloaked(hwnd)
+        {
+            return None;
+        }
+
+        // Use DWM's bounds so the invisible resize borders don't count
+        let mut rect = RECT::default();
+        DwmGetWindowAttribute(
+            hwnd,
+            DWMWA_EXTENDED_FRAME_BOUNDS,
+            ptr::addr_of_mut!(rect) as _,
+            size_of::<RECT>() as u32,
+        )
+        .ok()
+        .map(|_| rect)
+    }
+}
+
+pub fn is_topmost(hwnd: HWND) -> bool {
+    let ex_style = unsafe { GetWindowLongW(hwnd, GWL_EXSTYLE) as u32 };
+    ex_style & WS_EX_TOPMOST.0 != 0
+}
+
+pub fn get_show_cmd(hwnd: HWND) -> u32 {
+    let mut wp: WINDOWPLACEMENT = WINDOWPLACEMENT::default();
+    if let Err(e) = unsafe { GetWindowPlacement(hwnd, &mut wp) } {
+        error!("could not retrieve window placement: {e}");
+        return 0;
+    }
+    wp.showCmd
+}
+
+pub fn create_border_for_window(tracking_window: HWND) {
+    debug!("creating border for: {:?}", tracking_window);
+    let window = SendHWND(tracking_window);
+
+    let _ = thread::spawn(move || {
+        let window_sent = window;
+
+        // Dummy windows (from `tacky-borders preview` and the self-test) attach their own borders,
+        // and another instance's border under those would only get in the way
+        if get_window_class(window_sent.0) == DUMMY_WINDOW_CLASS {
+            return;
+        }
+
+        let mut window_rule = get_window_rule(window_sent.0);
+        if window_rule.enabled == Some(false) {
+            info!("border is disabled for {:?}!", window_sent.0);
+            return;
+        }
+
+        // Restore anything that was changed about this window's border before a restart or reload
+        if let Some(window_override) = state::get_override(window_sent.0) {
+            if window_override.disabled {
+                info!("border was turned off for {:?}!", window_sent.0);
+                return;
+            }
+            if let Some(color) = window_override.color {
+                window_rule.active_color = Some(color.clone());
+                window_rule.inactive_color = Some(color);
+            }
+        }
+        if state::is_toggled_off(window_sent.0) {
+            info!("border was toggled off for {:?}!", window_sent.0);
+            return;
+        }
+
+        let script = CONFIG.lock().unwrap().global.script.clone();
+        if let Some(ref script) = script {
+            let script_window = get_window_info(window_sent.0);
+            if scripting::eval_enabled(script, &script_window) == Some(false) {
+                info!("border is disabled by script for {:?}!", window_sent.0);
+                return;
+            }
+        }
+
+        run_border_thread(window_sent.0, window_rule, None);
+    });
+}
+
+// Tool windows that follow their group leader use the leader's rule, so they get the same options
+// (and optionally the group color) no matter what the tool window itself is called
+pub fn create_group_border_for_window(tracking_window: HWND, leader: HWND) {
+    debug!("creating group border for {tracking_window:?} (leader {leader:?})");
+    let window = SendHWND(tracking_window);
+    let leader = SendHWND(leader);
+
+    let _ = thread::spawn(move || {
+        let window_sent = window;
+        let leader_sent = leader;
+
+        let mut window_rule = get_window_rule(leader_sent.0);
+
+        let config = CONFIG.lock().unwrap();
+        let follow_owned = window_rule
+            .follow_owned
+            .or(config.global.follow_owned)
+            .unwrap_or(false);
+        let group_color = window_rule
+            .group_color
+            .clone()
+            .or(config.global.group_color.clone());
+        drop(config);
+
+        if !follow_owned || window_rule.enabled == Some(false) {
+            return;
+        }
+
+        if let Some(group_color) = group_color {
+            window_rule.active_color = Some(group_color.clone());
+            window_rule.inactive_color = Some(group_color);
+        }
+
+        run_border_thread(window_sent.0, window_rule, None);
+    });
+}
+
+// The active and inactive colors for a border made from 'window_rule'
+pub(crate) fn get_border_colors(
+    tracking_window: HWND,
+    window_rule: &WindowRule,
+    config: &Config,
+) -> (ColorConfig, ColorConfig) {
+    let (mut active, mut inactive) = get_rule_colors(window_rule, config);
+
+    // Colors returned by script hooks take priority over everything else
+    if let Some(ref script) = config.global.script {
+        let scripted_colors = scripting::eval_colors(script, &get_window_info(tracking_window));
+        if let Some(scripted_active) = scripted_colors.active {
+            active = scripted_active;
+        }
+        if let Some(scripted_inactive) = scripted_colors.inactive {
+            inactive = scripted_inactive;
+        }
+    }
+
+    // Accessibility mode wins over even the scripts, since the whole point is that every border
+    // looks the same
+    if accessibility::is_enabled() {
+        (_, active, inactive) = accessibility::style(&config.accessibility);
+    }
+
+    (active, inactive)
+}
+
+// The colors from the config alone, without the ones scripts or accessibility mode pick
+pub(crate) fn get_rule_colors(
+    window_rule: &WindowRule,
+    config: &Config,
+) -> (ColorConfig, ColorConfig) {
+    let (mut active, mut inactive) = config.global.base_colors(window_rule);
+
+    // Groups share their colors between all of their windows
+    let group = window_rule.group.as_ref();
+    if let Some(window_group) = group.and_then(|name| config.groups.get(name)) {
+        if let Some(ref group_active) = window_group.active_color {
+            active = group_active.clone();
+        }
+        if let Some(ref group_inactive) = window_group.inactive_color {
+            inactive = group_inactive.clone();
+        }
+    }
+
+    (active, inactive)
+}
+
+// Creates the border window and runs its message loop on the current thread, so this only returns
+// once the border is destroyed. If 'created' is given, the new border window (or the reason it
+// could not be created) is sent through it before entering the loop.
+pub(crate) fn run_border_thread(
+    tracking_window: HWND,
+    window_rule: WindowRule,
+    created: Option<Sender<anyhow::Result<isize>>>,
+) {
+    let border = match create_border_struct(tracking_window, &window_rule) {
+        Ok(val) => val,
+        Err(e) => {
+            error!("{e}");
+            if let Some(ref created) = created {
+                let _ = created.send(Err(e));
+            }
+            return;
+        }
+    };
+    drop(window_rule);
+
+    run_border(border, created);
+}
+
+// Registers an already configured border, creates its window, and runs its message loop on the
+// current thread
+pub(crate) fn run_border(mut border: WindowBorder, created: Option<Sender<anyhow::Result<isize>>>) {
+    let tracking_window = border.tracking_window;
+    let key = border.registry_key();
+
+    // Attached borders report their failures to whoever attached them, and monitor frames and the
+    // cursor ring aren't tied to a window that could be retried
+    let retry = created.is_none() && border.monitor.is_none() && border.cursor_ring_size.is_none();
+    let report = |result: anyhow::Result<isize>| {
+        if let Some(ref created) = created {
+            let _ = created.send(result);
+        }
+    };
+
+    // Only create the border window if the tracking window doesn't already have a border
+    let hinstance: HINSTANCE = unsafe { std::mem::transmute(&__ImageBase) };
+    let group = border.group.clone();
+    let registered = BORDERS.register(
+        key,
+        group,
+        || {
+            if let Err(e) = border.create_border_window(hinstance) {
+                error!("could not create border window: {e}");
+                let e = anyhow!("could not create border window: {e}");
+                if retry {
+                    retry::schedule(tracking_window, &e);
+                }
+                return Err(e);
+            }
+            Ok(border.border_window)
+        },
+        // Whatever took our reservation away (like a reload) doesn't want this border anymore
+        |border_window| unsafe {
+            let _ = DestroyWindow(border_window);
+        },
+    );
+    if let Err(e) = registered {
+        report(Err(e));
+        return;
+    }
+
+    report(Ok(border.border_window.0 as isize));
+
+    // Drop these values (to save some RAM?) before calling init and entering a message loop
+    drop(created);
+    let _ = hinstance;
+
+    // Note: init() contains a loop, so this should never return unless it's an Error
+    if let Err(e) = border.init() {
+        error!("{e}");
+
+        // init() only fails if the border couldn't get a renderer at all
+        if retry && border.renderer.is_none() {
+            retry::schedule(tracking_window, &e);
+        }
+    }
+}
+
+fn create_border_struct(
+    tracking_window: HWND,
+    window_rule: &WindowRule,
+) -> anyhow::Result<WindowBorder> {
+    let config = CONFIG.lock().unwrap();
+
+    let dpi = unsafe { GetDpiForWindow(tracking_window) } as f32;
+    if dpi == 0.0 {
+        return Err(anyhow!("received invalid dpi of 0.0 from GetDpiForWindow"));
+    }
+
+    let config_radius = window_rule
+        .border_radius
+        .unwrap_or(config.global.border_radius);
+    let inputs = BorderInputs {
+        colors: get_border_colors(tracking_window, window_rule, &config),
+        dpi,
+        window_radius: match config_radius == -1.0 {
+            true => get_window_radius(tracking_window, dpi),
+            false => 0.0,
+        },
+        // If the tracking window is part of the initial windows list (meaning it was already open
+        // when tacky-borders was launched), then there should be no initialize delay.
+        initial: INITIAL_WINDOWS
+            .lock()
+            .unwrap()
+            .contains(&(tracking_window.0 as isize)),
+        minimal: do_not_disturb::is_minimal(),
+        accessibility: accessibility::is_enabled(),
+        disabled_animations: state::tray_choices().disabled_animations,
+    };
+
+    // Relative plugin paths are relative to the config directory
+    let plugin_path = window_rule
+        .plugin
+        .as_ref()
+        .or(config.global.plugin.as_ref())
+        .filter(|_| !inputs.minimal);
+    let plugin = match plugin_path {
+        Some(path) => {
+            match Config::get_config_dir().and_then(|dir| plugins::load_plugin(&dir.join(path))) {
+                Ok(plugin) => Some(PluginInstance::new(plugin)),
+                Err(e) => {
+                    error!("could not load plugin for {tracking_window:?}: {e:#}");
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+
+    let mut border = build_border(&config, window_rule, inputs);
+    border.tracking_window = tracking_window;
+    border.plugin = plugin;
+
+    Ok(border)
+}
+
+// What build_border() needs to know about the window itself, which simulated windows make up
+pub(crate) struct BorderInputs {
+    pub(crate) colors: (ColorConfig, ColorConfig),
+    pub(crate) dpi: f32,
+    // The window's own corner radius, for border_radius: -1
+    pub(crate) window_radius: f32,
+    // Whether the window was already open when tacky-borders started
+    pub(crate) initial: bool,
+    // Do not disturb's minimal style
+    pub(crate) minimal: bool,
+    pub(crate) accessibility: bool,
+    // Animations turned off for every border from the tray menu
+    pub(crate) disabled_animations: Vec<AnimationType>,
+}
+
+// Everything about a border that comes from the config. Real borders and simulated ones are both
+// made here, so they can't drift apart.
+pub(crate) fn build_border(
+    config: &Config,
+    window_rule: &WindowRule,
+    inputs: BorderInputs,
+) -> WindowBorder {
+    // TODO holy this is ugly
+    let mut config_width = window_rule
+        .border_width
+        .unwrap_or(config.global.border_width);
+    let config_offset = window_rule
+        .border_offset
+        .unwrap_or(config.global.border_offset);
+    let config_radius = window_rule
+        .border_radius
+        .unwrap_or(config.global.border_radius);
+    let (config_active, config_inactive) = inputs.colors;
+    let group = window_rule.group.clone();
+
+    // Do not disturb's minimal style keeps the colors, but drops everything else that draws
+    // attention (the animations and effects are filtered out further down)
+    let minimal = inputs.minimal;
+    if minimal {
+        config_width = config.do_not_disturb.border_width;
+    }
+
+    let accessibility = inputs.accessibility.then_some(&config.accessibility);
+    if let Some(accessibility) = accessibility {
+        (config_width, _, _) = accessibility::style(accessibility);
+    }
+
+    // The progress ring uses the active color unless it has its own
+    let own_progress_color = window_rule
+        .progress_color
+        .clone()
+        .or(config.global.progress_color.clone());
+    let mut progress_color = own_progress_color
+        .as_ref()
+        .unwrap_or(&config_active)
+        .convert_to_color(true);
+    progress_color.set_fade_opacity(1.0);
+
+    // Convert ColorConfig structs to Color
+    let active_color = config_active.convert_to_color(true);
+    let inactive_color = config_inactive.convert_to_color(false);
+
+    // Adjust the border width and radius based on the monitor/window dpi
+    let dpi = inputs.dpi;
+
+    let clip_to_work_area = window_rule
+        .clip_to_work_area
+        .or(config.global.clip_to_work_area)
+        .unwrap_or(true);
+
+    let protect_shell = window_rule
+        .protect_taskbar
+        .or(config.global.protect_taskbar)
+        .unwrap_or(true);
+
+    let pause_when_occluded = window_rule
+        .pause_when_occluded
+        .or(config.global.pause_when_occluded)
+        .unwrap_or(true);
+
+    let zorder_policy = window_rule
+        .zorder_policy
+        .or(config.global.zorder_policy)
+        .unwrap_or_default();
+
+    let occlusion_clipping = window_rule
+        .occlusion_clipping
+        .or(config.global.occlusion_clipping)
+        .unwrap_or(false);
+
+    let topmost = window_rule.topmost.unwrap_or(false);
+    let high_frequency_tracking = window_rule.high_frequency_tracking.unwrap_or(false);
+
+    let renderer_backend = window_rule
+        .renderer
+        .or(config.global.renderer)
+        .unwrap_or_default();
+
+    let placement = window_rule
+        .placement
+        .or(config.global.placement)
+        .unwrap_or_default();
+
+    let border_width = (config_width * dpi / 96.0) as i32;
+    let border_radius = convert_config_radius(
+        border_width,
+        config_radius,
+        placement,
+        inputs.window_radius,
+        dpi,
+    );
+
+    let mut animations = window_rule
+        .animations
+        .clone()
+        .unwrap_or(config.global.animations.clone().unwrap_or_default());
+    animations.handoff.shrink = animations.handoff.shrink.min(config_width).max(0.0) * dpi / 96.0;
+    if let Some(accessibility) = accessibility {
+        animations.handoff.flash |= accessibility.blink;
+    }
+    animations.resolve_custom(&config.animation_defs);
+
+    // Animations can be turned off for every border from the tray menu
+    for anim_type in inputs.disabled_animations {
+        animations.active.remove(&anim_type);
+        animations.inactive.remove(&anim_type);
+    }
+    if minimal {
+        animations.active.clear();
+        animations.inactive.clear();
+        animations.handoff = Default::default();
+    }
+
+    // Windows that were already open when tacky-borders was launched don't get an initialize delay
+    let initialize_delay = match inputs.initial {
+        true => 0,
+        false => window_rule
+            .initialize_delay
+            .unwrap_or(config.global.initialize_delay.unwrap_or(250)),
+    };
+    let unminimize_delay = window_rule
+        .unminimize_delay
+        .unwrap_or(config.global.unminimize_delay.unwrap_or(200));
+
+    let render_quality = window_rule
+        .render_quality
+        .or(config.global.render_quality)
+        .unwrap_or(RenderQuality::Medium);
+    let antialias = window_rule
+        .antialiasing
+        .or(config.global.antialiasing)
+        .unwrap_or(render_quality.antialiasing());
+    // Anything above 4x doesn't look any better with linear downscaling and just eats up VRAM
+    let supersampling = window_rule
+        .supersampling
+        .or(config.global.supersampling)
+        .unwrap_or(render_quality.supersampling())
+        .clamp(1, 4);
+    let blend_mode = window_rule
+        .blend_mode
+        .or(config.global.blend_mode)
+        .unwrap_or_default();
+    let noise = window_rule
+        .noise
+        .as_ref()
+        .or(config.global.noise.as_ref())
+        .filter(|_| !minimal)
+        .map(Noise::new);
+
+    let night_light_temperature = match window_rule
+        .night_light_compensation
+        .or(config.global.night_light_compensation)
+        .unwrap_or(false)
+    {
+        true => Some(config.global.night_light_temperature.unwrap_or(4500.0)),
+        false => None,
+    };
+
+    let contrast_guard = window_rule
+        .contrast_guard
+        .as_ref()
+        .or(config.global.contrast_guard.as_ref())
+        .cloned();
+
+    let not_responding = window_rule
+        .not_responding
+        .as_ref()
+        .or(config.global.not_responding.as_ref())
+        .map(NotResponding::new);
+
+    let badges = window_rule
+        .badges
+        .as_ref()
+        .or(config.global.badges.as_ref())
+        .map(|badges| Badges::new(badges, dpi));
+
+    let mut locate_color = window_rule
+        .locate_color
+        .as_ref()
+        .or(config.global.locate_color.as_ref())
+        .cloned()
+        .unwrap_or(ColorConfig::SolidConfig("#ffffff".to_string()))
+        .convert_to_color(true);
+    locate_color.set_fade_opacity(1.0);
+
+    let ripple = window_rule
+        .ripple
+        .as_ref()
+        .or(config.global.ripple.as_ref())
+        .filter(|_| !minimal)
+        .map(|ripple| Ripple::new(ripple, dpi));
+
+    let particles = window_rule
+        .particles
+        .as_ref()
+        .or(config.global.particles.as_ref())
+        .filter(|_| !minimal)
+        .map(|particles| Particles::new(particles, dpi));
+
+    let hover_peek = window_rule
+        .hover_peek
+        .as_ref()
+        .or(config.global.hover_peek.as_ref())
+        .map(HoverPeek::new);
+
+    let adaptive_quality = config
+        .global
+        .adaptive_quality
+        .as_ref()
+        .map(AdaptiveQuality::new);
+
+    let dash_color = window_rule
+        .dash_color
+        .as_ref()
+        .or(config.global.dash_color.as_ref())
+        .map(|dash_color| {
+            let mut dash_color = dash_color.convert_to_color(true);
+            dash_color.set_fade_opacity(1.0);
+            dash_color
+        });
+
+    let edge_colors = EdgeColors::new(
+        window_rule
+            .edge_colors
+            .as_ref()
+            .or(config.global.edge_colors.as_ref()),
+        window_rule
+            .style
+            .or(config.global.style)
+            .unwrap_or_default(),
+    );
+
+    // Extra strokes default to the border's own colors
+    let stroke_layers = window_rule
+        .strokes
+        .as_ref()
+        .or(config.global.strokes.as_ref())
+        .filter(|_| !minimal)
+        .map(|strokes| {
+            strokes
+                .iter()
+                .map(|stroke| StrokeLayer::new(stroke, &config_active, &config_inactive, dpi))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let active_extra = window_rule
+        .active_extra
+        .as_ref()
+        .or(config.global.active_extra.as_ref())
+        .filter(|_| !minimal)
+        .map(|active_extra| ActiveExtra::new(active_extra, dpi));
+
+    let rect_adjust = window_rule
+        .rect_adjust
+        .or(config.global.rect_adjust)
+        .unwrap_or_default()
+        .scale(dpi);
+
+    WindowBorder {
+        border_width,
+        border_offset: config_offset,
+        border_radius,
+        placement,
+        clip_to_work_area,
+        protect_shell,
+        pause_when_occluded,
+        zorder_policy,
+        occlusion_clipping,
+        topmost,
+        high_frequency_tracking,
+        renderer_backend,
+        rect_adjust,
+        group,
+        night_light_temperature,
+        contrast_guard,
+        badges,
+        not_responding,
+        progress_color,
+        own_progress_color,
+        dash_color,
+        edge_colors,
+        stroke_layers,
+        active_extra,
+        ripple,
+        particles,
+        hover_peek,
+        adaptive_quality,
+        locate_color,
+        comet_color: animations.comet.color(),
+        active_color,
+        inactive_color,
+        animations,
+        initialize_delay,
+        unminimize_delay,
+        antialias,
+        supersampling,
+        blend_mode,
+        noise,
+        ..Default::default()
+    }
+}
+
+fn convert_config_radius(
+    border_width: i32,
+    config_radius: f32,
+    placement: BorderPlacement,
+    window_radius: f32,
+    dpi: f32,
+) -> f32 {
+    // TODO use an enum for config_radius instead (-1.0 means we should automatically get radius,
+    // so maybe use "Auto" for the enum)
+    match config_radius {
+        -1.0 => {
+            // The radius is for the middle of the stroke, which is half the border width
+            // outside or inside of the window's corner
+            match (window_radius, placement) {
+                (0.0, _) => 0.0,
+                (_, BorderPlacement::Outer) => window_radius + border_width as f32 / 2.0,
+                (_, BorderPlacement::Inner) => (window_radius - border_width as f32 / 2.0).max(0.0),
+            }
+        }
+        _ => config_radius * dpi / 96.0,
+    }
+}
+
+fn get_window_radius(tracking_window: HWND, dpi: f32) -> f32 {
+    let mut corner_preference = DWM_WINDOW_CORNER_PREFERENCE::default();
+
+    if let Err(e) = unsafe {
+        DwmGetWindowAttribute(
+            tracking_window,
+            DWMWA_WINDOW_CORNER_PREFERENCE,
+            ptr::addr_of_mut!(corner_preference) as _,
+            size_of::<DWM_WINDOW_CORNER_PREFERENCE>() as u32,
+        )
+    } {
+        error!("could not retrieve window corner preference: {e}");
+    }
+
+    match corner_preference {
+        DWMWCP_DEFAULT => 8.0 * dpi / 96.0,
+        DWMWCP_DONOTROUND => 0.0,
+        DWMWCP_ROUND => 8.0 * dpi / 96.0,
+        DWMWCP_ROUNDSMALL => 4.0 * dpi / 96.0,
+        _ => 8.0 * dpi / 96.0,
+    }
+}
+
+// Replace both the active and inactive color of a border
+pub fn set_border_color(border_window: HWND, color: ColorConfig) -> windows::core::Result<()> {
+    set_border_colors(border_window, color.clone(), color)
+}
+
+pub fn set_border_colors(
+    border_window: HWND,
+    active: ColorConfig,
+    inactive: ColorConfig,
+) -> windows::core::Result<()> {
+    let colors_ptr = Box::into_raw(Box::new((active, inactive)));
+
+    let result = post_message_w(
+        border_window,
+        WM_APP_SETCOLOR,
+        WPARAM(0),
+        LPARAM(colors_ptr as isize),
+    );
+
+    // If the message wasn't posted, the border thread will never free the colors, so we do it
+    if result.is_err() {
+        drop(unsafe { Box::from_raw(colors_ptr) });
+    }
+
+    result
+}
+
+// Get all the top-level windows, in z-order
+pub fn get_top_level_windows() -> Vec<HWND> {
+    let mut windows: Vec<HWND> = Vec::new();
+
+    if let Err(e) = unsafe {
+        EnumWindows(
+            Some(enum_top_level_callback),
+            LPARAM(ptr::addr_of_mut!(windows) as isize),
+        )
+    } {
+        error!("could not enumerate windows: {e}");
+    }
+
+    windows
+}
+
+unsafe extern "system" fn enum_top_level_callback(hwnd: HWND, lparam: LPARAM) -> BOOL {
+    let windows = &mut *(lparam.0 as *mut Vec<HWND>);
+    windows.push(hwnd);
+    TRUE
+}
+
+pub fn destroy_border_for_window(tracking_window: HWND) {
+    let Some(border_window) = BORDERS.get(BorderKey::window(tracking_window)) else {
+        return;
+    };
+
+    log_if_err!(
+        post_message_w(border_window, WM_NCDESTROY, WPARAM(0), LPARAM(0))
+            .context("destroy_border_for_window")
+    );
+}
+
+pub fn get_border_from_window(hwnd: HWND) -> Option<HWND> {
+    BORDERS.get(BorderKey::window(hwnd))
+}
+
+pub fn show_border_for_window(hwnd: HWND) {
+    // If the border already exists, simply post a 'SHOW' message to its message queue. Otherwise,
+    // create a new border.
+    if let Some(border) = get_border_from_window(hwnd) {
+        log_if_err!(
+            post_message_w(border, WM_APP_SHOWUNCLOAKED, WPARAM(0), LPARAM(0))
+                .context("show_border_for_window")
+        );
+    } else if is_window_visible(hwnd) && !is_cloaked(hwnd) {
+        if !has_filtered_style(hwnd) {
+            // style_watch takes the border away once the window goes fullscreen, so it shouldn't
+            // come right back the next time the window is shown
+            if !is_frameless_fullscreen(hwnd) {
+                create_border_for_window(hwnd);
+            }
+        } else if is_tool_window(hwnd) {
+            if let Some(leader) = get_group_leader(hwnd) {
+                create_group_border_for_window(hwnd, leader);
+            }
+        }
+    }
+}
+
+pub fn hide_border_for_window(hwnd: HWND) -> bool {
+    if let Some(border) = get_border_from_window(hwnd) {
+        log_if_err!(
+            post_message_w(border, WM_APP_HIDECLOAKED, WPARAM(0), LPARAM(0))
+                .context("hide_border_for_window")
+        );
+    }
+    true
+}
+
+// Bezier curve algorithm together with @0xJWLabs
+const SUBDIVISION_PRECISION: f32 = 0.0001; // Precision for binary subdivision
+const SUBDIVISION_MAX_ITERATIONS: u32 = 10; // Maximum number of iterations for binary subdivision
+
+pub enum BezierError {
+    InvalidControlPoint,
+}
+
+impl std::fmt::Display for BezierError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BezierError::InvalidControlPoint => {
+                write!(f, "cubic-bezier control points must be in the range [0, 1]")
+            }
+        }
+    }
+}
+
+struct Point {
+    x: f32,
+    y: f32,
+}
+
+fn lerp(t: f32, p1: f32, p2: f32) -> f32 {
+    p1 + (p2 - p1) * t
+}
+
+// Compute the cubic Bézier curve using De Casteljau's algorithm.
+fn de_casteljau(t: f32, p_i: f32, p1: f32, p2: f32, p_f: f32) -> f32 {
+    // First level
+    let q1 = lerp(t, p_i, p1);
+    let q2 = lerp(t, p1, p2);
+    let q3 = lerp(t, p2, p_f);
+
+    // Second level
+    let r1 = lerp(t, q1, q2);
+    let r2 = lerp(t, q2, q3);
+
+    // Final level
+    lerp(t, r1, r2)
+}
+
+// Generates a cubic Bézier curve function from control points.
+pub fn cubic_bezier(
+    x1: f32,
+    y1: f32,
+    x2: f32,
+    y2: f32,
+) -> Result<impl Fn(f32) -> f32, BezierError> {
+    // Ensure control points are within bounds.
+    //
+    // I think any y-value for the control points should be fine. But, we can't have negative
+    // x-values for the control points, otherwise the cubic bezier function could have multiple
+    // outputs for any given input 'x' (different from the control points' x-values), meaning we
+    // would have a mathematical non-function.
+    if !(0.0..=1.0).contains(&x1) || !(0.0..=1.0).contains(&x2) {
+        return Err(BezierError::InvalidControlPoint);
+    }
+
+    Ok(move |x: f32| {
+        // If the curve is linear, shortcut.
+        if x1 == y1 && x2 == y2 {
+            return x;
+        }
+
+        // Boundary cases
+        if x == 0.0 || x == 1.0 {
+            return x;
+        }
+
+        let mut t0 = 0.0;
+        let mut t1 = 1.0;
+        let mut t = x;
+
+        let p_i = Point { x: 0.0, y: 0.0 }; // Start point
+        let p1 = Point { x: x1, y: y1 }; // First control point
+        let p2 = Point { x: x2, y: y2 }; // Second control point
+        let p_f = Point { x: 1.0, y: 1.0 }; // End point
+
+        // Search for `t` from the 'x' given as an argument to this function.
+        //
+        // Note: 'x' and 't' are not the same. 'x' refers to the position along the x-axis, whereas
+        // 't' refers to the position along the control point lines, hence why we need to search.
+        for _ in 0..SUBDIVISION_MAX_ITERATIONS {
+            // Evaluate the x-component of the Bézier curve at `t`
+            let x_val = de_casteljau(t, p_i.x, p1.x, p2.x, p_f.x);
+            let error = x - x_val;
+
+            // Adjust the range based on the error.
+            if error.abs() < SUBDIVISION_PRECISION {
+                break;
+            }
+            if error > 0.0 {
+                t0 = t;
+            } else {
+                t1 = t;
+            }
+            t = (t0 + t1) / 2.0;
+        }
+
+        // After finding 't', evalaute the y-component of the Bezier curve
+        de_casteljau(t, p_i.y, p1.y, p2.y, p_f.y)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_path_table() {
+        std::env::set_var("TACKY_EXPAND_DIR", "C:\\Tacky");
+        std::env::set_var("TACKY_EXPAND_PERCENT", "%TACKY_EXPAND_DIR%");
+        std::env::remove_var("TACKY_EXPAND_UNSET");
+        let home = dirs::home_dir().unwrap().display().to_string();
+
+        let cases = [
+            ("~", home.clone()),
+            ("~/plugin.dll", format!("{home}/plugin.dll")),
+            ("~\\plugin.dll", format!("{home}\\plugin.dll")),
+            // Only the current user's home is supported
+            ("~user/plugin.dll", "~user/plugin.dll".to_string()),
+            ("a/~/b", "a/~/b".to_string()),
+            ("%TACKY_EXPAND_DIR%\\a.dll", "C:\\Tacky\\a.dll".to_string()),
+            ("${TACKY_EXPAND_DIR}/a.dll", "C:\\Tacky/a.dll".to_string()),
+            (
+                "${TACKY_EXPAND_DIR}%TACKY_EXPAND_DIR%",
+                "C:\\TackyC:\\Tacky".to_string(),
+            ),
+            // Values aren't expanded again
+            ("${TACKY_EXPAND_PERCENT}", "%TACKY_EXPAND_DIR%".to_string()),
+            (
+                "%TACKY_EXPAND_UNSET%/a",
+                "%TACKY_EXPAND_UNSET%/a".to_string(),
+            ),
+            (
+                "${TACKY_EXPAND_UNSET}/a",
+                "${TACKY_EXPAND_UNSET}/a".to_string(),
+            ),
+            ("%%", "%%".to_string()),
+            ("${}", "${}".to_string()),
+            // A lone '%' or an unclosed '${' doesn't stop the rest from being expanded
+            ("50%/${TACKY_EXPAND_DIR}", "50%/C:\\Tacky".to_string()),
+            ("${TACKY_EXPAND_DIR", "${TACKY_EXPAND_DIR".to_string()),
+            (
+                "$TACKY_EXPAND_DIR/%TACKY_EXPAND_DIR%",
+                "$TACKY_EXPAND_DIR/C:\\Tacky".to_string(),
+            ),
+        ];
+
+        for (path, expected) in cases {
+            assert_eq!(expand_path(path), expected, "{path}");
+        }
+    }
+
+    fn config(yaml: &str) -> Config {
+        Config::parse(yaml).unwrap()
+    }
+
+    fn inputs(config: &Config, dpi: f32) -> BorderInputs {
+        BorderInputs {
+            colors: get_rule_colors(&WindowRule::default(), config),
+            dpi,
+            window_radius: 8.0,
+            initial: false,
+            minimal: false,
+            accessibility: false,
+            disabled_animations: Vec::new(),
+        }
+    }
+
+    const CONFIG: &str = "
+version: 2
+global:
+  border_width: 4
+  border_offset: 0
+  border_radius: -1
+  active_color: '#ffffff'
+  inactive_color: '#000000'
+  initialize_delay: 100
+  ripple: { distance: 10 }
+window_rules: []
+";
+
+    #[test]
+    fn build_border_scales_with_dpi() {
+        let config = config(CONFIG);
+
+        let border = build_border(&config, &WindowRule::default(), inputs(&config, 96.0));
+        assert_eq!(border.border_width, 4);
+        // Half of the stroke is outside of the window's own corner
+        assert_eq!(border.border_radius, 10.0);
+        assert_eq!(border.initialize_delay, 100);
+
+        let border = build_border(&config, &WindowRule::default(), inputs(&config, 192.0));
+        assert_eq!(border.border_width, 8);
+    }
+
+    #[test]
+    fn stable_hash_is_fnv1a() {
+        // The FNV-1a offset basis, and the published test vectors for "a" and "foobar"
+        assert_eq!(stable_hash(""), 0xcbf29ce484222325);
+        assert_eq!(stable_hash("a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(stable_hash("foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn build_border_skips_the_delay_and_effects_when_asked() {
+        let config = config(CONFIG);
+
+        let border = build_border(
+            &config,
+            &WindowRule::default(),
+            BorderInputs {
+                initial: true,
+                minimal: true,
+                ..inputs(&config, 96.0)
+            },
+        );
+        assert_eq!(border.initialize_delay, 0);
+        assert!(border.ripple.is_none());
+        assert_eq!(
+            border.border_width,
+            config.do_not_disturb.border_width as i32
+        );
+    }
+}
@@ -0,0 +1,2814 @@
+use crate::accessibility;
+use crate::anim_timer::AnimationTimer;
+use crate::animations::{self, *};
+use crate::badges::Badges;
+use crate::border_config::{BlendMode, BorderPlacement, RectAdjust, ZOrderPolicy, CONFIG};
+use crate::colors::*;
+use crate::d2d_renderer::RENDER_FACTORY;
+use crate::edges::{EdgeCache, EdgeColors};
+use crate::effects::{
+    self, AdaptiveQuality, ContrastGuardConfig, HoverPeek, Noise, NotResponding, Particles, Ripple,
+};
+use crate::event_hook;
+use crate::lifecycle::{self, LifecycleState, RunningTransition, TransitionAnimation};
+use crate::log_if_err;
+use crate::monitor_frames::{self, ActiveMonitor};
+use crate::plugins::{PluginBorderState, PluginBrushParams, PluginInstance};
+use crate::progress;
+use crate::registry::{BorderKey, BORDERS};
+use crate::renderer::{
+    self, BorderPainter, BorderRenderer, DrawnArea, FrameState, RendererBackend,
+};
+use crate::retry;
+use crate::scripting;
+use crate::shell_exclusion;
+use crate::strokes::{self, ActiveExtra, StrokeLayer};
+use crate::utils::*;
+use anyhow::{anyhow, Context};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use std::mem::ManuallyDrop;
+use std::ptr;
+use std::thread;
+use std::time;
+use windows::core::{w, Interface, PCWSTR};
+use windows::Foundation::Numerics::Matrix3x2;
+use windows::Win32::Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, POINT, RECT, WPARAM};
+use windows::Win32::Graphics::Direct2D::Common::D2D_RECT_F;
+use windows::Win32::Graphics::Direct2D::{
+    ID2D1Bitmap, ID2D1Brush, ID2D1DeviceContext, ID2D1Geometry, ID2D1Layer, ID2D1PathGeometry,
+    ID2D1RenderTarget, ID2D1StrokeStyle, D2D1_ANTIALIAS_MODE, D2D1_ANTIALIAS_MODE_ALIASED,
+    D2D1_ANTIALIAS_MODE_PER_PRIMITIVE, D2D1_BRUSH_PROPERTIES, D2D1_COMBINE_MODE_EXCLUDE,
+    D2D1_DASH_STYLE_DASH, D2D1_DEFAULT_FLATTENING_TOLERANCE, D2D1_LAYER_OPTIONS_NONE,
+    D2D1_LAYER_PARAMETERS, D2D1_PRIMITIVE_BLEND, D2D1_PRIMITIVE_BLEND_ADD,
+    D2D1_PRIMITIVE_BLEND_MAX, D2D1_PRIMITIVE_BLEND_SOURCE_OVER, D2D1_ROUNDED_RECT,
+    D2D1_STROKE_STYLE_PROPERTIES,
+};
+use windows::Win32::Graphics::Dwm::{DwmGetWindowAttribute, DWMWA_EXTENDED_FRAME_BOUNDS};
+use windows::Win32::Graphics::Gdi::{ValidateRect, HMONITOR};
+use windows::Win32::System::SystemInformation::GetTickCount;
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DispatchMessageW, GetCursorPos, GetMessageW, GetWindow,
+    GetWindowLongPtrW, KillTimer, PostQuitMessage, SetTimer, SetWindowLongPtrW, SetWindowPos,
+    ShowWindow, TranslateMessage, CREATESTRUCTW, GWLP_USERDATA, GWL_EXSTYLE, GW_HWNDPREV,
+    HWND_NOTOPMOST, HWND_TOP, HWND_TOPMOST, MSG, SET_WINDOW_POS_FLAGS, SWP_HIDEWINDOW,
+    SWP_NOACTIVATE, SWP_NOREDRAW, SWP_NOSENDCHANGING, SWP_NOZORDER, SWP_SHOWWINDOW, SW_SHOWNA,
+    WM_CREATE, WM_DISPLAYCHANGE, WM_NCDESTROY, WM_PAINT, WM_SETTINGCHANGE, WM_TIMER,
+    WM_WINDOWPOSCHANGED, WM_WINDOWPOSCHANGING, WS_DISABLED, WS_EX_LAYERED, WS_EX_TOOLWINDOW,
+    WS_EX_TOPMOST, WS_EX_TRANSPARENT, WS_POPUP,
+};
+
+#[derive(Debug, Default)]
+pub struct WindowBorder {
+    pub border_window: HWND,
+    pub tracking_window: HWND,
+    pub window_rect: RECT,
+    pub border_width: i32,
+    pub border_offset: i32,
+    pub placement: BorderPlacement,
+    // Set for monitor frames, which follow this monitor's work area instead of a window
+    pub monitor: Option<HMONITOR>,
+    // Set for monitor frames that light up while their monitor is the active one
+    pub monitor_highlight: Option<ActiveMonitor>,
+    // Set for the cursor ring (to its diameter), which follows the mouse cursor instead of a window
+    pub cursor_ring_size: Option<i32>,
+    pub clip_to_work_area: bool,
+    // Keep the border from drawing over the taskbar, Start menu, and notification flyouts
+    pub protect_shell: bool,
+    pub pause_when_occluded: bool,
+    pub zorder_policy: ZOrderPolicy,
+    // With ZOrderPolicy::Clip, the screen rects of the windows between the border and the tracking
+    // window, which the border doesn't draw over
+    pub covering_rects: Vec<RECT>,
+    // Keep occluding_rects up to date, so the border is never drawn over the windows stacked above
+    // the tracking window
+    pub occlusion_clipping: bool,
+    pub occluding_rects: Vec<RECT>,
+    // Set if the border can join an always-on-top tracking window in the topmost band
+    pub topmost: bool,
+    pub high_frequency_tracking: bool,
+    // When the tracking window last moved, while we're polling it with high_frequency_tracking
+    pub last_location_change: Option<time::Instant>,
+    // Set while other windows completely cover the border, so we can skip animation frames
+    pub is_occluded: bool,
+    // The part of the border window inside the monitor's work area, if the border sticks out of it
+    pub work_area_clip: Option<D2D_RECT_F>,
+    pub border_radius: f32,
+    // Applied to the window rect from DWM before we make space for the border
+    pub rect_adjust: RectAdjust,
+    pub group: Option<String>,
+    // Set if the colors should be compensated for Night Light while it's on
+    pub night_light_temperature: Option<f32>,
+    pub contrast_guard: Option<ContrastGuardConfig>,
+    // The average luminance of what's behind the border, from the last contrast guard sample
+    pub background_luminance: Option<f32>,
+    pub badges: Option<Badges>,
+    pub not_responding: Option<NotResponding>,
+    // Set while the tracking window isn't responding to messages (only checked with not_responding)
+    pub is_hung: bool,
+    // Used for dash_color, and for not_responding while the window is hung
+    pub dashed_stroke_style: Option<ID2D1StrokeStyle>,
+    // Dashes drawn over the border's colors
+    pub dash_color: Option<Color>,
+    // Set with the set-progress command, from 0.0 to 1.0
+    pub progress: Option<f32>,
+    pub progress_color: Color,
+    // The progress ring's own color. Without one, it follows the active color when that changes.
+    pub own_progress_color: Option<ColorConfig>,
+    // Set if the active color is drawn with a different color on each edge
+    pub edge_colors: Option<EdgeColors>,
+    // What draw_edges() drew with last time. It's only a cache, so it's filled in while drawing.
+    pub edge_cache: RefCell<EdgeCache>,
+    // Extra strokes drawn outside of the main one, from the inside out
+    pub stroke_layers: Vec<StrokeLayer>,
+    // A halo around everything else that only shows while the window is focused
+    pub active_extra: Option<ActiveExtra>,
+    pub ripple: Option<Ripple>,
+    // Particles that spawn along the border while the window is focused
+    pub particles: Option<Particles>,
+    pub hover_peek: Option<HoverPeek>,
+    pub adaptive_quality: Option<AdaptiveQuality>,
+    // When the locate effect (from the 'locate' command) started, if it's running
+    pub locate_start: Option<time::Instant>,
+    pub locate_color: Color,
+    // The color of the Comet animation's comets
+    pub comet_color: Color,
+    // The comet pieces drawn so far. It's only a cache, so it's filled in while drawing.
+    pub comet_geometries: RefCell<CometGeometries>,
+    pub brush_properties: D2D1_BRUSH_PROPERTIES,
+    pub renderer_backend: RendererBackend,
+    pub renderer: Option<Box<dyn BorderRenderer>>,
+    // The pixel size the renderer was last resized to
+    pub render_size: Option<(u32, u32)>,
+    pub rounded_rect: D2D1_ROUNDED_RECT,
+    pub active_color: Color,
+    pub inactive_color: Color,
+    pub animations: Animations,
+    pub lifecycle: LifecycleState,
+    // The animation of the last lifecycle transition, while it's still running
+    pub transition: Option<RunningTransition>,
+    pub last_render_time: Option<time::Instant>,
+    pub anim_timer: Option<AnimationTimer>,
+    pub antialias: bool,
+    // How many times larger to render the border before scaling it down (1 means disabled)
+    pub supersampling: u32,
+    pub blend_mode: BlendMode,
+    pub noise: Option<Noise>,
+    pub plugin: Option<PluginInstance>,
+    // The brush parameters returned by the plugin on the last animation frame
+    pub plugin_params: PluginBrushParams,
+    // Delay border visbility when tracking window is in animation
+    pub initialize_delay: u64,
+    pub unminimize_delay: u64,
+    // This is to pause the border from doing anything when it doesn't need to
+    pub pause: bool,
+    // Set while the border is hidden for Task View, so it knows to come back once Task View closes
+    pub hidden_for_task_view: bool,
+    // Set while game mode has the border hidden for a fullscreen app on its monitor
+    pub hidden_for_fullscreen: bool,
+    pub is_active_window: bool,
+    // How many more times the border should blink on/off for flash()
+    pub flash_remaining: u32,
+    // Set while flash() has the border blinked off
+    pub flash_hidden: bool,
+    // How many times in a row the renderer has failed
+    pub render_failures: u32,
+    // Set once Direct2D has failed too many times and we've fallen back to GDI
+    pub safe_mode: bool,
+}
+
+const FLASH_TIMER_ID: usize = 1;
+const FLASH_INTERVAL_MS: u32 = 150;
+const CONTRAST_TIMER_ID: usize = 2;
+const BADGE_TIMER_ID: usize = 3;
+const BADGE_POLL_INTERVAL_MS: u32 = 1000;
+const NOT_RESPONDING_TIMER_ID: usize = 4;
+const OCCLUSION_TIMER_ID: usize = 5;
+const OCCLUSION_POLL_INTERVAL_MS: u32 = 500;
+const ACTIVE_MONITOR_TIMER_ID: usize = 6;
+const ACTIVE_MONITOR_POLL_INTERVAL_MS: u32 = 100;
+const CURSOR_RING_TIMER_ID: usize = 7;
+// USER_TIMER_MINIMUM, so the ring keeps up with the cursor as well as it can
+const CURSOR_RING_INTERVAL_MS: u32 = 10;
+const RIPPLE_TIMER_ID: usize = 8;
+const RIPPLE_INTERVAL_MS: u32 = 16;
+const LOCATE_TIMER_ID: usize = 9;
+const LOCATE_INTERVAL_MS: u32 = 16;
+const HOVER_PEEK_TIMER_ID: usize = 10;
+const ACTIVE_EXTRA_TIMER_ID: usize = 11;
+const ACTIVE_EXTRA_INTERVAL_MS: u32 = 16;
+const HIGH_FREQUENCY_TIMER_ID: usize = 12;
+const PARTICLE_TIMER_ID: usize = 13;
+const PARTICLE_INTERVAL_MS: u32 = 16;
+// USER_TIMER_MINIMUM, like the cursor ring. We stop polling once the window has been still this long.
+const HIGH_FREQUENCY_INTERVAL_MS: u32 = 10;
+const HIGH_FREQUENCY_LINGER: time::Duration = time::Duration::from_millis(300);
+const HOVER_FADE_INTERVAL_MS: u32 = 16;
+// The locate effect pulses the border this many times, up to LOCATE_GROWTH times thicker than usual
+const LOCATE_DURATION: time::Duration = time::Duration::from_millis(1200);
+const LOCATE_PULSES: f32 = 3.0;
+const LOCATE_GROWTH: f32 = 3.0;
+// How many pieces each comet is drawn in, to fade out its tail
+const COMET_PIECES: u32 = 12;
+// Comet pieces start about this many pixels apart, but there's never more than MAX_COMET_STEPS spots
+// for them to start on, so the cache stays small on huge windows
+const COMET_STEP_PX: f32 = 2.0;
+const MAX_COMET_STEPS: u32 = 2048;
+
+// The pieces that draw_comets() has built so far, since building count * COMET_PIECES geometries
+// every frame adds up. Pieces only start on one of 'steps' evenly spaced spots around the border,
+// so the same few get used over and over. They're thrown out whenever the border's shape or the
+// comets' length changes.
+#[derive(Debug, Default)]
+pub struct CometGeometries {
+    rounded_rect: D2D1_ROUNDED_RECT,
+    piece_length: f32,
+    steps: u32,
+    pieces: HashMap<u32, ID2D1PathGeometry>,
+}
+
+impl CometGeometries {
+    // The piece that starts closest to 'start', as a fraction of the way around the border
+    fn piece(
+        &mut self,
+        rounded_rect: &D2D1_ROUNDED_RECT,
+        piece_length: f32,
+        start: f32,
+    ) -> windows::core::Result<ID2D1PathGeometry> {
+        if self.steps == 0
+            || self.rounded_rect != *rounded_rect
+            || self.piece_length != piece_length
+        {
+            let perimeter = progress::perimeter_length(rounded_rect);
+            *self = CometGeometries {
+                rounded_rect: *rounded_rect,
+                piece_length,
+                steps: ((perimeter / COMET_STEP_PX).ceil() as u32).clamp(1, MAX_COMET_STEPS),
+                pieces: HashMap::new(),
+            };
+        }
+
+        let step = (start.rem_euclid(1.0) * self.steps as f32).round() as u32 % self.steps;
+        if let Some(piece) = self.pieces.get(&step) {
+            return Ok(piece.clone());
+        }
+
+        let start = step as f32 / self.steps as f32;
+        let piece = progress::create_perimeter_geometry(
+            &RENDER_FACTORY,
+            rounded_rect,
+            start,
+            start + piece_length,
+        )?;
+        self.pieces.insert(step, piece.clone());
+
+        Ok(piece)
+    }
+}
+// Give up on Direct2D and fall back to GDI after this many failed renders in a row
+const MAX_RENDER_FAILURES: u32 = 3;
+
+impl WindowBorder {
+    pub fn create_border_window(&mut self, hinstance: HINSTANCE) -> windows::core::Result<()> {
+        let self_title = match (self.monitor, self.cursor_ring_size) {
+            (Some(monitor), _) => format!("{} | monitor frame | {:?}", "tacky-border", monitor),
+            (None, Some(_)) => format!("{} | cursor ring", "tacky-border"),
+            (None, None) => format!(
+                "{} | {} | {:?}",
+                "tacky-border",
+                get_window_title(self.tracking_window),
+                self.tracking_window
+            ),
+        };
+        let mut string: Vec<u16> = self_title.encode_utf16().collect();
+        string.push(0);
+
+        unsafe {
+            self.border_window = CreateWindowExW(
+                WS_EX_LAYERED | WS_EX_TOPMOST | WS_EX_TOOLWINDOW | WS_EX_TRANSPARENT,
+                w!("border"),
+                PCWSTR::from_raw(string.as_ptr()),
+                WS_POPUP | WS_DISABLED,
+                0,
+                0,
+                0,
+                0,
+                None,
+                None,
+                hinstance,
+                Some(ptr::addr_of!(*self) as _),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn init(&mut self) -> anyhow::Result<()> {
+        // Delay the border while the tracking window is in its creation animation
+        thread::sleep(time::Duration::from_millis(self.initialize_delay));
+
+        self.brush_properties = D2D1_BRUSH_PROPERTIES {
+            opacity: 1.0,
+            transform: Matrix3x2::identity(),
+        };
+
+        self.rounded_rect = D2D1_ROUNDED_RECT {
+            rect: Default::default(),
+            radiusX: self.border_radius,
+            radiusY: self.border_radius,
+        };
+
+        unsafe {
+            // If Direct2D doesn't work at all, a plain border is better than none
+            match renderer::create_renderer(self.renderer_backend, self) {
+                Ok(renderer) => self.renderer = Some(renderer),
+                Err(e) => {
+                    error!("could not create renderer in init(): {e:#}");
+                    self.enter_safe_mode();
+                }
+            }
+
+            // Not even GDI works (yet), so give up on this border window. run_border() retries it.
+            if self.renderer.is_none() {
+                SetWindowLongPtrW(self.border_window, GWLP_USERDATA, 0);
+                self.exit_border_thread();
+                return Err(anyhow!(
+                    "could not create any renderer for {:?}",
+                    self.tracking_window
+                ));
+            }
+            retry::succeeded(self.tracking_window);
+
+            self.is_active_window = self.is_focused();
+
+            // The halo starts out already shown on the focused window instead of fading in
+            if let Some(active_extra) = self.active_extra.as_mut() {
+                active_extra.shown = self.is_active_window;
+            }
+
+            self.animations.current = match self.is_active_window {
+                true => self.animations.active.clone(),
+                false => self.animations.inactive.clone(),
+            };
+
+            self.transition_to(LifecycleState::Visible, time::Instant::now());
+
+            log_if_err!(self.update_window_rect());
+
+            if let Some(badges) = self.badges.as_mut() {
+                badges.update(self.tracking_window);
+            }
+
+            if has_native_border(self.tracking_window) {
+                log_if_err!(self.update_position(Some(SWP_SHOWWINDOW)));
+                log_if_err!(self.render());
+
+                // Sometimes, it doesn't show the window at first, so we wait 5ms and update it.
+                // This is very hacky and needs to be looked into. It may be related to the issue
+                // detailed in the wnd_proc. TODO
+                thread::sleep(time::Duration::from_millis(5));
+                log_if_err!(self.update_position(Some(SWP_SHOWWINDOW)));
+                log_if_err!(self.render());
+            }
+
+            self.set_anim_timer();
+
+            if let Some(ref contrast_guard) = self.contrast_guard {
+                SetTimer(
+                    self.border_window,
+                    CONTRAST_TIMER_ID,
+                    contrast_guard.interval.max(100),
+                    None,
+                );
+            }
+
+            let hung_dashes = self
+                .not_responding
+                .as_ref()
+                .is_some_and(|not_responding| not_responding.dashed);
+            if hung_dashes || self.dash_color.is_some() {
+                let stroke_properties = D2D1_STROKE_STYLE_PROPERTIES {
+                    dashStyle: D2D1_DASH_STYLE_DASH,
+                    miterLimit: 10.0,
+                    ..Default::default()
+                };
+                self.dashed_stroke_style = RENDER_FACTORY
+                    .CreateStrokeStyle(&stroke_properties, None)
+                    .inspect_err(|e| error!("could not create dashed stroke style: {e}"))
+                    .ok();
+            }
+
+            if let Some(ref not_responding) = self.not_responding {
+                SetTimer(
+                    self.border_window,
+                    NOT_RESPONDING_TIMER_ID,
+                    not_responding.interval,
+                    None,
+                );
+            }
+
+            // Focus events don't fire when the cursor moves or a window is dragged to another
+            // monitor, so highlighted frames have to check for themselves
+            if self.monitor_highlight.is_some() {
+                SetTimer(
+                    self.border_window,
+                    ACTIVE_MONITOR_TIMER_ID,
+                    ACTIVE_MONITOR_POLL_INTERVAL_MS,
+                    None,
+                );
+            }
+
+            if self.cursor_ring_size.is_some() {
+                SetTimer(
+                    self.border_window,
+                    CURSOR_RING_TIMER_ID,
+                    CURSOR_RING_INTERVAL_MS,
+                    None,
+                );
+            }
+
+            if self.pause_when_occluded || self.occlusion_clipping {
+                SetTimer(
+                    self.border_window,
+                    OCCLUSION_TIMER_ID,
+                    OCCLUSION_POLL_INTERVAL_MS,
+                    None,
+                );
+            }
+
+            if let Some(hover_peek) = self.hover_peek.as_mut() {
+                // The poll only says when the cursor moves between windows, so a border that shows
+                // up under the cursor has to check for itself
+                effects::start_hover_poll(self.border_window);
+                hover_peek.set_hovered(
+                    effects::is_cursor_over(self.tracking_window),
+                    time::Instant::now(),
+                );
+                self.start_hover_timer();
+            }
+
+            if self.badges.is_some() {
+                SetTimer(
+                    self.border_window,
+                    BADGE_TIMER_ID,
+                    BADGE_POLL_INTERVAL_MS,
+                    None,
+                );
+            }
+
+            let mut message = MSG::default();
+            while GetMessageW(&mut message, HWND::default(), 0, 0).into() {
+                let _ = TranslateMessage(&message);
+                DispatchMessageW(&message);
+            }
+            debug!("exiting border thread for {:?}!", self.tracking_window);
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn antialias_mode(&self) -> D2D1_ANTIALIAS_MODE {
+        match self.antialias {
+            true => D2D1_ANTIALIAS_MODE_PER_PRIMITIVE,
+            false => D2D1_ANTIALIAS_MODE_ALIASED,
+        }
+    }
+
+    fn update_window_rect(&mut self) -> anyhow::Result<()> {
+        if let Some(monitor) = self.monitor {
+            return self.update_frame_rect(monitor);
+        }
+        if let Some(size) = self.cursor_ring_size {
+            return self.update_cursor_ring_rect(size);
+        }
+
+        if let Err(e) = unsafe {
+            DwmGetWindowAttribute(
+                self.tracking_window,
+                DWMWA_EXTENDED_FRAME_BOUNDS,
+                ptr::addr_of_mut!(self.window_rect) as _,
+                size_of::<RECT>() as u32,
+            )
+            .context(format!(
+                "Could not get window rect for {:?}",
+                self.tracking_window
+            ))
+        } {
+            self.destroy_anim_timer();
+            self.exit_border_thread();
+
+            return Err(e);
+        }
+
+        let tracking_rect = self.set_tracking_rect(self.window_rect);
+
+        self.work_area_clip = match self.clip_to_work_area {
+            true => self.get_work_area_clip(&tracking_rect),
+            false => None,
+        };
+
+        Ok(())
+    }
+
+    // Sets window_rect from the tracking window's frame, making space for the border. Returns the
+    // frame with the rule's adjustments applied.
+    pub(crate) fn set_tracking_rect(&mut self, frame_rect: RECT) -> RECT {
+        // Some apps (Electron apps, games with custom title bars, etc.) draw their frame somewhere
+        // other than what DWM reports, so we apply the rule's adjustments on top of DWM's rect
+        let tracking_rect = RECT {
+            left: frame_rect.left - self.rect_adjust.left,
+            top: frame_rect.top - self.rect_adjust.top,
+            right: frame_rect.right + self.rect_adjust.right,
+            bottom: frame_rect.bottom + self.rect_adjust.bottom,
+        };
+
+        // Increase the size of the window rect to make space for the border. Inner borders are
+        // drawn over the window itself, so they only need room for the extra strokes.
+        let outer_width = match self.placement {
+            BorderPlacement::Outer => self.outer_width(),
+            BorderPlacement::Inner => self.outer_margin(),
+        };
+        self.window_rect = RECT {
+            left: tracking_rect.left - outer_width,
+            top: tracking_rect.top - outer_width,
+            right: tracking_rect.right + outer_width,
+            bottom: tracking_rect.bottom + outer_width,
+        };
+
+        tracking_rect
+    }
+
+    fn update_frame_rect(&mut self, monitor: HMONITOR) -> anyhow::Result<()> {
+        let Some(work_area) = get_monitor_work_area(monitor) else {
+            // The monitor was probably disconnected
+            self.destroy_anim_timer();
+            self.exit_border_thread();
+
+            return Err(anyhow!("could not get work area for monitor {monitor:?}"));
+        };
+
+        self.window_rect = work_area;
+
+        Ok(())
+    }
+
+    fn update_cursor_ring_rect(&mut self, size: i32) -> anyhow::Result<()> {
+        let mut point = POINT::default();
+        unsafe { GetCursorPos(&mut point) }.context("could not get cursor position")?;
+
+        let left = point.x - size / 2;
+        let top = point.y - size / 2;
+        self.window_rect = RECT {
+            left,
+            top,
+            right: left + size,
+            bottom: top + size,
+        };
+
+        Ok(())
+    }
+
+    // If the window itself fits in its monitor's work area but the border doesn't (e.g. the window
+    // is snapped against the taskbar or the edge of the screen), get the part of the border window
+    // that's inside the work area. Windows that already span monitors are left alone, since
+    // clipping to just one of them would cut off the rest of the border.
+    fn get_work_area_clip(&self, tracking_rect: &RECT) -> Option<D2D_RECT_F> {
+        let work_area = get_work_area(self.tracking_window)?;
+        let contains = |outer: &RECT, inner: &RECT| {
+            inner.left >= outer.left
+                && inner.top >= outer.top
+                && inner.right <= outer.right
+                && inner.bottom <= outer.bottom
+        };
+
+        if !contains(&work_area, tracking_rect) || contains(&work_area, &self.window_rect) {
+            return None;
+        }
+
+        // Convert to the border window's coordinates
+        Some(D2D_RECT_F {
+            left: (work_area.left - self.window_rect.left) as f32,
+            top: (work_area.top - self.window_rect.top) as f32,
+            right: (work_area.right - self.window_rect.left) as f32,
+            bottom: (work_area.bottom - self.window_rect.top) as f32,
+        })
+    }
+
+    // Whether the border is already directly above the tracking window in the z-order
+    fn is_zorder_correct(&self) -> bool {
+        // Monitor frames and the cursor ring just stay in the topmost band they were created in
+        if self.monitor.is_some() || self.cursor_ring_size.is_some() {
+            return true;
+        }
+
+        // Without 'topmost', the border of a topmost window only has to stay out of the topmost band
+        if !self.topmost && is_topmost(self.tracking_window) {
+            return !is_topmost(self.border_window);
+        }
+
+        unsafe { GetWindow(self.tracking_window, GW_HWNDPREV) == Ok(self.border_window) }
+    }
+
+    // Whether the border has to move back to right above the tracking window. With zorder_policy,
+    // it can stay above windows that ended up in between, as long as it's still above the tracking
+    // window. This also keeps covering_rects up to date.
+    fn should_restack(&mut self) -> bool {
+        if self.is_zorder_correct() {
+            self.covering_rects.clear();
+            return false;
+        }
+        if self.zorder_policy == ZOrderPolicy::Lower {
+            return true;
+        }
+
+        match get_windows_between(self.tracking_window, self.border_window, &self.window_rect) {
+            Some(rects) => {
+                self.covering_rects = match self.zorder_policy {
+                    ZOrderPolicy::Clip => rects,
+                    _ => Vec::new(),
+                };
+                false
+            }
+            None => {
+                self.covering_rects.clear();
+                true
+            }
+        }
+    }
+
+    // Find the hwndInsertAfter for SetWindowPos that places the border directly above the tracking
+    // window. SetWindowPos puts the border right below hwndInsertAfter, so that's usually whatever
+    // window is currently above the tracking window.
+    fn get_insert_after(&self) -> HWND {
+        // Putting the border right above a topmost window would put it over every topmost window
+        // below that one too (e.g. a picture-in-picture border over the taskbar), so this has to be
+        // asked for. Otherwise, the border goes to the top of the normal band.
+        let tracking_topmost = is_topmost(self.tracking_window);
+        if tracking_topmost && !self.topmost {
+            return HWND_NOTOPMOST;
+        }
+
+        let Ok(hwnd_above_tracking) = (unsafe { GetWindow(self.tracking_window, GW_HWNDPREV) })
+        else {
+            // If there's no window above, tracking_window is already the highest in z-order. The
+            // border might have been moved out of the topmost band before, so ask for it explicitly.
+            return match tracking_topmost {
+                true => HWND_TOPMOST,
+                false => HWND_TOP,
+            };
+        };
+
+        // If a topmost window (like a context menu or tooltip) is right above a normal tracking
+        // window, inserting after it would put the border at the bottom of the topmost band, in
+        // front of every normal window. HWND_NOTOPMOST puts it at the top of the normal band
+        // instead, which is right above the tracking window.
+        if is_topmost(hwnd_above_tracking) && !is_topmost(self.tracking_window) {
+            return HWND_NOTOPMOST;
+        }
+
+        hwnd_above_tracking
+    }
+
+    fn update_position(&mut self, c_flags: Option<SET_WINDOW_POS_FLAGS>) -> anyhow::Result<()> {
+        unsafe {
+            let mut u_flags =
+                SWP_NOSENDCHANGING | SWP_NOACTIVATE | SWP_NOREDRAW | c_flags.unwrap_or_default();
+
+            // If the border is already right above the tracking window (or is staying where it is
+            // with zorder_policy), there's no need to change the z-order (plus it results in an
+            // error if we try it).
+            if !self.should_restack() {
+                u_flags |= SWP_NOZORDER;
+            }
+
+            // Place the window border above the tracking window
+            if let Err(e) = SetWindowPos(
+                self.border_window,
+                self.get_insert_after(),
+                self.window_rect.left,
+                self.window_rect.top,
+                self.window_rect.right - self.window_rect.left,
+                self.window_rect.bottom - self.window_rect.top,
+                u_flags,
+            )
+            .context(format!(
+                "could not set window position for {:?}",
+                self.tracking_window
+            )) {
+                self.destroy_anim_timer();
+                self.exit_border_thread();
+
+                return Err(e);
+            }
+        }
+
+        if let Some(ref mut renderer) = self.renderer {
+            renderer.window_changed(self.window_rect);
+        }
+
+        // The windows above it cover a different part of the border after it moves
+        if self.update_occluding_rects() {
+            log_if_err!(self.render());
+        }
+        Ok(())
+    }
+
+    // Find the parts of the border that the windows above the tracking window cover, with
+    // occlusion_clipping. Returns true if they changed.
+    fn update_occluding_rects(&mut self) -> bool {
+        if !self.occlusion_clipping {
+            return false;
+        }
+
+        let occluding_rects = get_occluding_rects(self.tracking_window, &self.window_rect);
+        if occluding_rects == self.occluding_rects {
+            return false;
+        }
+        self.occluding_rects = occluding_rects;
+        true
+    }
+
+    // Moves the border to another state of its lifecycle, timing the transition's animation (if
+    // any) from the given time
+    pub(crate) fn transition_to(&mut self, to: LifecycleState, time: time::Instant) {
+        let from = self.lifecycle;
+        if from == to {
+            return;
+        }
+        if !from.can_transition_to(to) {
+            debug!(
+                "ignoring lifecycle transition from {from:?} to {to:?} for {:?}",
+                self.tracking_window
+            );
+            return;
+        }
+        self.lifecycle = to;
+
+        match to {
+            LifecycleState::Hiding => {
+                // This makes the next fade start from nothing once the border is shown again
+                self.transition = None;
+                self.animations.fade_shown = false;
+                self.active_color.set_fade_opacity(0.0);
+                self.inactive_color.set_fade_opacity(0.0);
+            }
+            LifecycleState::Destroyed => self.transition = None,
+            _ => self.start_transition(from, to, time),
+        }
+    }
+
+    fn start_transition(&mut self, from: LifecycleState, to: LifecycleState, time: time::Instant) {
+        self.transition = self
+            .transition_for(from, to)
+            .filter(|transition| transition.animation != TransitionAnimation::None);
+
+        match self.transition {
+            // Restart the fade timeline from the current progress
+            Some(_) => self.animations.fade_start = Some((time, self.animations.fade_progress)),
+            None => {
+                self.animations.fade_progress = match self.is_active_window {
+                    true => 1.0,
+                    false => 0.0,
+                };
+                let (top_color, bottom_color) = match self.is_active_window {
+                    true => (&mut self.active_color, &mut self.inactive_color),
+                    false => (&mut self.inactive_color, &mut self.active_color),
+                };
+                top_color.set_fade_opacity(1.0);
+                bottom_color.set_fade_opacity(0.0);
+                self.animations.fade_shown = true;
+
+                // There's nothing to wait for before settling in
+                if to == LifecycleState::Visible {
+                    self.settle(time);
+                }
+            }
+        }
+    }
+
+    // The transition's hook from the config if there is one. Otherwise, borders with a Fade
+    // animation fade, except when appearing without a delay to fade during (or settling in after).
+    fn transition_for(
+        &self,
+        from: LifecycleState,
+        to: LifecycleState,
+    ) -> Option<RunningTransition> {
+        let fade_speed = self.animations.current.get(&AnimationType::Fade).copied();
+
+        if let Some(hook) = lifecycle::find_hook(&self.animations.transitions, from, to) {
+            return Some(RunningTransition {
+                animation: hook.animation,
+                speed: hook.speed.or(fade_speed).unwrap_or(200.0),
+            });
+        }
+
+        let fades = match (from, to) {
+            (LifecycleState::Creating, LifecycleState::Visible) => self.initialize_delay != 0,
+            (_, LifecycleState::Visible) => self.unminimize_delay != 0,
+            (LifecycleState::Visible, _) => false,
+            _ => true,
+        };
+        fade_speed.filter(|_| fades).map(|speed| RunningTransition {
+            animation: TransitionAnimation::Fade,
+            speed,
+        })
+    }
+
+    // Goes from Visible to either Focused or Unfocused
+    fn settle(&mut self, time: time::Instant) {
+        let to = match self.is_active_window {
+            true => LifecycleState::Focused,
+            false => LifecycleState::Unfocused,
+        };
+        self.transition_to(to, time);
+    }
+
+    // Switches the border between the active and inactive colors/animations, with the fade (if
+    // any) timed from focus_time
+    pub(crate) fn set_active(&mut self, is_active: bool, focus_time: time::Instant) {
+        self.is_active_window = is_active;
+
+        // Update the current animations list
+        self.animations.current = match self.is_active_window {
+            true => self.animations.active.clone(),
+            false => self.animations.inactive.clone(),
+        };
+        self.animations.custom_starts.clear();
+
+        // Focusing a window usually brings it to the front, so don't wait for the next occlusion
+        // check to start animating again
+        if self.is_active_window {
+            self.is_occluded = false;
+            if let Some(ref anim_timer) = self.anim_timer {
+                anim_timer.set_paused(false);
+            }
+        }
+
+        if let Some(active_extra) = self.active_extra.as_mut() {
+            active_extra.set_shown(is_active, focus_time);
+        }
+
+        // Fade the border out after losing focus, unless the cursor is still resting on the window
+        if let Some(hover_peek) = self.hover_peek.as_mut() {
+            hover_peek.reset_shown(focus_time);
+            self.start_hover_timer();
+        }
+
+        match self.lifecycle {
+            LifecycleState::Focused | LifecycleState::Unfocused => self.settle(focus_time),
+            // Keep appearing, just towards the new colors
+            LifecycleState::Visible if self.transition.is_some() => {
+                self.animations.fade_start = Some((focus_time, self.animations.fade_progress));
+            }
+            // The border picks the right colors once it's shown
+            _ => {}
+        }
+    }
+
+    // Advance the spiral and fade animations to the given time. Returns true if anything moved.
+    pub(crate) fn step_animations(&mut self, now: time::Instant) -> bool {
+        let mut update = false;
+        let mut delta = AnimationDelta::default();
+        self.animations.custom_comet = None;
+
+        for (anim_type, anim_speed) in self.animations.current.clone().iter() {
+            let anim_delta = match anim_type {
+                AnimationType::Spiral => animations::animate_spiral(
+                    self,
+                    anim_type,
+                    now,
+                    *anim_speed * SPIRAL_SPEED_FACTOR,
+                ),
+                AnimationType::ReverseSpiral => animations::animate_spiral(
+                    self,
+                    anim_type,
+                    now,
+                    *anim_speed * -SPIRAL_SPEED_FACTOR,
+                ),
+                // The fade crossfades the colors themselves, so it has nothing to merge
+                AnimationType::Fade => continue,
+                // Comets are drawn over the border, so neither do they
+                AnimationType::Comet => {
+                    animations::animate_comet(self, now, *anim_speed / COMET_SPEED_DIVISOR);
+                    update = true;
+                    continue;
+                }
+                AnimationType::Custom(name) => {
+                    match animations::animate_custom(self, name, now, *anim_speed) {
+                        Some(anim_delta) => anim_delta,
+                        None => continue,
+                    }
+                }
+            };
+            delta = delta.merge(anim_delta);
+            update = true;
+        }
+
+        // Spirals that stopped (e.g. after losing focus) stay at the angle they stopped at
+        for (anim_type, spiral) in self.animations.spirals.iter() {
+            if !self.animations.current.contains_key(anim_type) {
+                delta.rotation = (delta.rotation + spiral.angle) % 360.0;
+            }
+        }
+        self.animations.delta = delta;
+
+        if let Some(transition) = self.transition {
+            let fade_speed = transition.speed / FADE_SPEED_DIVISOR;
+            if animations::animate_fade(self, now, fade_speed) {
+                self.transition = None;
+                if self.lifecycle == LifecycleState::Visible {
+                    self.settle(now);
+                }
+            }
+            update = true;
+        }
+
+        update
+    }
+
+    fn render(&mut self) -> anyhow::Result<()> {
+        self.last_render_time = Some(time::Instant::now());
+
+        self.update_rounded_rect();
+
+        // Anchor the spiral rotation using the rect we are about to draw with, then apply the merged
+        // transform (e.g. from a plugin) on top of that
+        let delta = self.animation_delta();
+        self.brush_properties.transform =
+            animations::spiral_transform(self, delta.rotation) * delta.transform;
+
+        // The renderer can't be borrowed mutably while the frame state borrows the rest of the
+        // border, so we take it out while it draws (this can result in an error at the start
+        // because render() can be called before self.renderer is set... for now we just ignore it)
+        let Some(mut renderer) = self.renderer.take() else {
+            return Err(anyhow!("renderer has not been set yet"));
+        };
+        let result = self.draw_frame(renderer.as_mut());
+        let backend = renderer.backend();
+        self.renderer = Some(renderer);
+
+        match result {
+            Ok(_) => {
+                self.render_failures = 0;
+                Ok(())
+            }
+            Err(e) => {
+                self.render_failures += 1;
+
+                // GDI is as simple as it gets, so there's nothing left to fall back to
+                if backend == RendererBackend::Gdi || self.render_failures < MAX_RENDER_FAILURES {
+                    return Err(e);
+                }
+
+                error!(
+                    "could not render border for {:?} {} times in a row: {e:#}",
+                    self.tracking_window, self.render_failures
+                );
+                self.enter_safe_mode();
+                self.render()
+            }
+        }
+    }
+
+    fn draw_frame(&mut self, renderer: &mut dyn BorderRenderer) -> anyhow::Result<()> {
+        let size = (
+            (self.window_rect.right - self.window_rect.left) as u32,
+            (self.window_rect.bottom - self.window_rect.top) as u32,
+        );
+        if self.render_size != Some(size) {
+            renderer.resize(size.0, size.1)?;
+            self.render_size = Some(size);
+        }
+
+        renderer.draw(&self.frame_state())
+    }
+
+    fn frame_state(&self) -> FrameState<'_> {
+        let color = match self.is_active_window {
+            true => &self.active_color,
+            false => &self.inactive_color,
+        };
+
+        FrameState {
+            window_rect: self.window_rect,
+            rounded_rect: self.rounded_rect,
+            stroke_width: self.stroke_width(),
+            color: self.adjust_color(Cow::Borrowed(color)).into_owned(),
+            visible: !self.flash_hidden && self.peek_opacity() > 0.0,
+            opacity: self.peek_opacity(),
+            drawn_area: self.drawn_area(),
+            supersampling: self.render_supersampling(),
+            noise: self.noise.as_ref(),
+            brush_transform: self.brush_properties.transform,
+            occluding_rects: &self.occluding_rects,
+            painter: self,
+        }
+    }
+
+    // Everything we draw follows the rounded rect, and the extra strokes, halo, ripple, and locate
+    // effect are all outside of the main stroke. So, nothing gets drawn any further in from the
+    // edges than the inside of the main stroke (or the badges along the top), give or take the
+    // particles.
+    fn drawn_area(&self) -> DrawnArea {
+        let rect = self.rounded_rect.rect;
+
+        // Plus a pixel for antialiasing
+        let mut edge = rect.left.max(rect.top) + self.stroke_width() / 2.0 + 1.0;
+        if let Some(ref badges) = self.badges {
+            edge = edge.max(badges.bottom(&rect) + 1.0);
+        }
+        // Particles can drift inwards though, like snow falling off the top edge
+        if let Some(particles) = self
+            .particles
+            .as_ref()
+            .filter(|particles| !particles.is_empty())
+        {
+            edge += particles.extent() as f32;
+        }
+
+        DrawnArea {
+            edge,
+            corner: edge + self.border_radius,
+        }
+    }
+
+    // Tick the hover_peek fade (or its delay) until it's done, if it has anything to do
+    fn start_hover_timer(&self) {
+        let is_changing = self
+            .hover_peek
+            .as_ref()
+            .is_some_and(|hover_peek| hover_peek.is_changing(time::Instant::now()));
+        if is_changing && !self.is_active_window {
+            unsafe {
+                SetTimer(
+                    self.border_window,
+                    HOVER_PEEK_TIMER_ID,
+                    HOVER_FADE_INTERVAL_MS,
+                    None,
+                )
+            };
+        }
+    }
+
+    // How visible the border is with hover_peek, which only ever hides inactive borders
+    fn peek_opacity(&self) -> f32 {
+        match (&self.hover_peek, self.is_active_window) {
+            (Some(hover_peek), false) => hover_peek.opacity,
+            _ => 1.0,
+        }
+    }
+
+    fn enter_safe_mode(&mut self) {
+        warn!(
+            "falling back to a plain GDI border for {:?}",
+            self.tracking_window
+        );
+
+        match renderer::create_renderer(RendererBackend::Gdi, self) {
+            Ok(gdi_renderer) => {
+                if let Some(mut old_renderer) = self.renderer.replace(gdi_renderer) {
+                    old_renderer.release();
+                }
+                self.render_size = None;
+                self.render_failures = 0;
+                self.safe_mode = true;
+                renderer::set_safe_mode(true);
+            }
+            Err(e) => error!("could not create GDI renderer: {e:#}"),
+        }
+    }
+
+    // Puts back the extended styles the border window was created with, in case something took them
+    // away. Losing WS_EX_LAYERED also throws out whatever the renderer set up on the window, so the
+    // renderer gets recreated in that case.
+    fn restore_window_styles(&mut self) {
+        let wanted = (WS_EX_LAYERED | WS_EX_TOOLWINDOW | WS_EX_TRANSPARENT).0 as isize;
+        let ex_style = unsafe { GetWindowLongPtrW(self.border_window, GWL_EXSTYLE) };
+        if ex_style & wanted == wanted {
+            return;
+        }
+
+        warn!(
+            "border window for {:?} lost its styles; restoring them",
+            self.tracking_window
+        );
+        unsafe { SetWindowLongPtrW(self.border_window, GWL_EXSTYLE, ex_style | wanted) };
+
+        let Some(mut old_renderer) = self.renderer.take() else {
+            return;
+        };
+        let backend = old_renderer.backend();
+        old_renderer.release();
+        self.render_size = None;
+
+        match renderer::create_renderer(backend, self) {
+            Ok(renderer) => self.renderer = Some(renderer),
+            Err(e) => {
+                error!("could not recreate renderer: {e:#}");
+                self.enter_safe_mode();
+            }
+        }
+    }
+
+    fn update_rounded_rect(&mut self) {
+        // Convert and store the border's width (including any extra strokes) and offset as f32
+        let width = self.outer_width() as f32;
+        let offset = self.border_offset as f32;
+
+        // Keep the inner edge of the stroke in place so a thinner stroke shrinks away from the
+        // window instead of into it
+        let inset = width - self.stroke_width() / 2.0 - offset;
+
+        self.rounded_rect.rect = D2D_RECT_F {
+            left: inset,
+            top: inset,
+            right: (self.window_rect.right - self.window_rect.left) as f32 - inset,
+            bottom: (self.window_rect.bottom - self.window_rect.top) as f32 - inset,
+        };
+    }
+
+    // Draw the border onto the given render target, clipped to the work area and around the
+    // taskbar and Start menu if needed
+    pub(crate) fn draw_border(
+        &self,
+        render_target: &ID2D1RenderTarget,
+        noise_bitmap: Option<&ID2D1Bitmap>,
+    ) -> anyhow::Result<()> {
+        let exclusion_mask = self
+            .create_exclusion_mask()
+            .context("could not create exclusion mask")?;
+
+        let peek_opacity = self.peek_opacity();
+
+        unsafe {
+            if let Some(ref clip) = self.work_area_clip {
+                render_target.PushAxisAlignedClip(clip, D2D1_ANTIALIAS_MODE_ALIASED);
+            }
+            if peek_opacity < 1.0 {
+                let layer_parameters = D2D1_LAYER_PARAMETERS {
+                    contentBounds: D2D_RECT_F {
+                        left: f32::MIN,
+                        top: f32::MIN,
+                        right: f32::MAX,
+                        bottom: f32::MAX,
+                    },
+                    geometricMask: ManuallyDrop::new(None),
+                    maskAntialiasMode: D2D1_ANTIALIAS_MODE_ALIASED,
+                    maskTransform: Matrix3x2::identity(),
+                    opacity: peek_opacity,
+                    opacityBrush: ManuallyDrop::new(None),
+                    layerOptions: D2D1_LAYER_OPTIONS_NONE,
+                };
+                render_target.PushLayer(&layer_parameters, None::<&ID2D1Layer>);
+            }
+            if let Some(ref mask) = exclusion_mask {
+                let mut layer_parameters = D2D1_LAYER_PARAMETERS {
+                    contentBounds: D2D_RECT_F {
+                        left: f32::MIN,
+                        top: f32::MIN,
+                        right: f32::MAX,
+                        bottom: f32::MAX,
+                    },
+                    geometricMask: ManuallyDrop::new(Some(mask.clone())),
+                    maskAntialiasMode: D2D1_ANTIALIAS_MODE_ALIASED,
+                    maskTransform: Matrix3x2::identity(),
+                    opacity: 1.0,
+                    opacityBrush: ManuallyDrop::new(None),
+                    layerOptions: D2D1_LAYER_OPTIONS_NONE,
+                };
+                render_target.PushLayer(&layer_parameters, None::<&ID2D1Layer>);
+                // PushLayer holds its own reference to the mask
+                ManuallyDrop::drop(&mut layer_parameters.geometricMask);
+            }
+        }
+
+        let result = self.draw_layers(render_target, noise_bitmap);
+
+        unsafe {
+            if exclusion_mask.is_some() {
+                render_target.PopLayer();
+            }
+            if peek_opacity < 1.0 {
+                render_target.PopLayer();
+            }
+            if self.work_area_clip.is_some() {
+                render_target.PopAxisAlignedClip();
+            }
+        }
+
+        result
+    }
+
+    // Build a mask covering the border window minus any taskbars, Start menu, or notification
+    // flyouts (with protect_shell), and the windows in covering_rects and occluding_rects, that
+    // overlap it. Returns None if nothing overlaps.
+    fn create_exclusion_mask(&self) -> windows::core::Result<Option<ID2D1Geometry>> {
+        let mut excluded = match self.protect_shell {
+            true => shell_exclusion::get_excluded_rects(),
+            false => Vec::new(),
+        };
+        excluded.extend(self.covering_rects.iter().copied());
+        excluded.extend(self.occluding_rects.iter().copied());
+
+        create_exclusion_geometry(&self.window_rect, &excluded)
+    }
+
+    // Draw the active and inactive colors onto the given render target
+    fn draw_layers(
+        &self,
+        render_target: &ID2D1RenderTarget,
+        noise_bitmap: Option<&ID2D1Bitmap>,
+    ) -> anyhow::Result<()> {
+        // TODO wtf is this mess..
+        let active_opacity = self.active_color.fade_opacity();
+        let inactive_opacity = self.inactive_color.fade_opacity();
+
+        let (bottom_opacity, top_opacity) = match self.is_active_window {
+            true => (inactive_opacity, active_opacity),
+            false => (active_opacity, inactive_opacity),
+        };
+
+        let (bottom_color, top_color) = match self.is_active_window {
+            true => (&self.inactive_color, &self.active_color),
+            false => (&self.active_color, &self.inactive_color),
+        };
+        let (bottom_color, top_color) = (
+            self.adjust_color(Cow::Borrowed(bottom_color)),
+            self.adjust_color(Cow::Borrowed(top_color)),
+        );
+
+        // The locate effect flashes the border with its own color at the peak of every pulse
+        let locate_flash = matches!(self.locate_pulse(), Some(pulse) if pulse > 0.5);
+        let top_color = match locate_flash {
+            true => Cow::Borrowed(&self.locate_color),
+            false => top_color,
+        };
+        let top_opacity = match self.locate_pulse() {
+            Some(_) => top_opacity.max(self.locate_color.fade_opacity()),
+            None => top_opacity,
+        };
+
+        self.draw_active_extra(render_target)?;
+        self.draw_stroke_layers(render_target)?;
+
+        // Mid-fade, solid colors are mixed into one instead of drawn over each other, which would
+        // make translucent ones dip in alpha (see Color::mix). Blend modes need a bottom layer to
+        // blend the top one with, so they keep both, and so do edge colors.
+        let mixed = match bottom_opacity > 0.0
+            && top_opacity > 0.0
+            && self.blend_mode == BlendMode::Normal
+            && self.edge_colors.is_none()
+        {
+            true => Color::mix(&bottom_color, bottom_opacity, &top_color, top_opacity),
+            false => None,
+        };
+
+        // Edge colors stand in for the active color, whichever layer it's on
+        let (bottom_edges, top_edges) = match self.is_active_window {
+            true => (None, self.edge_colors.as_ref().filter(|_| !locate_flash)),
+            false => (self.edge_colors.as_ref(), None),
+        };
+
+        if let Some(mixed) = mixed {
+            let mixed_brush = mixed
+                .create_brush(render_target, &self.window_rect, &self.brush_properties)
+                .context("could not create ID2D1Brush")?;
+            self.apply_animation_opacity(&mixed_brush);
+
+            self.draw_rectangle(render_target, &mixed_brush);
+        } else {
+            if bottom_opacity > 0.0 {
+                match bottom_edges {
+                    Some(edge_colors) => self.draw_edges(render_target, edge_colors)?,
+                    None => {
+                        let bottom_brush = bottom_color
+                            .create_brush(render_target, &self.window_rect, &self.brush_properties)
+                            .context("could not create ID2D1Brush")?;
+                        self.apply_animation_opacity(&bottom_brush);
+
+                        self.draw_rectangle(render_target, &bottom_brush);
+                    }
+                }
+            }
+            if top_opacity > 0.0 {
+                // Only the top layer uses the blend mode since the bottom layer has nothing beneath
+                // it
+                self.set_primitive_blend(render_target, self.primitive_blend());
+                match top_edges {
+                    Some(edge_colors) => self.draw_edges(render_target, edge_colors)?,
+                    None => {
+                        let top_brush = top_color
+                            .create_brush(render_target, &self.window_rect, &self.brush_properties)
+                            .context("could not create ID2D1Brush")?;
+                        self.apply_animation_opacity(&top_brush);
+
+                        self.draw_rectangle(render_target, &top_brush);
+                    }
+                }
+                self.set_primitive_blend(render_target, D2D1_PRIMITIVE_BLEND_SOURCE_OVER);
+            }
+        }
+
+        self.draw_dashes(render_target, bottom_opacity.max(top_opacity))?;
+        self.draw_comets(render_target, bottom_opacity.max(top_opacity))?;
+        self.draw_particles(render_target, bottom_opacity.max(top_opacity))?;
+        self.draw_ripple(render_target, &top_color)?;
+
+        // Draw the film grain on top, fading it along with the border
+        if let (Some(noise), Some(noise_bitmap)) = (self.visible_noise(), noise_bitmap) {
+            let noise_opacity = bottom_opacity.max(top_opacity);
+            let noise_brush = noise
+                .create_brush(render_target, noise_bitmap, noise_opacity)
+                .context("could not create noise brush")?;
+            self.draw_rectangle(render_target, &noise_brush);
+        }
+
+        if let Some(progress) = self.progress {
+            let geometry =
+                progress::create_progress_geometry(&RENDER_FACTORY, &self.rounded_rect, progress)
+                    .context("could not create progress geometry")?;
+            let progress_brush = self
+                .progress_color
+                .create_brush(render_target, &self.window_rect, &self.brush_properties)
+                .context("could not create progress brush")?;
+
+            unsafe {
+                progress_brush.SetOpacity(bottom_opacity.max(top_opacity));
+                render_target.DrawGeometry(&geometry, &progress_brush, self.stroke_width(), None);
+            }
+        }
+
+        if let Some(ref badges) = self.badges {
+            badges
+                .draw(
+                    render_target,
+                    &self.rounded_rect.rect,
+                    self.border_radius,
+                    bottom_opacity.max(top_opacity),
+                )
+                .context("could not draw badges")?;
+        }
+
+        Ok(())
+    }
+
+    // Apply the not responding style, Night Light compensation, and contrast guard to a color
+    pub(crate) fn adjust_color<'a>(&self, color: Cow<'a, Color>) -> Cow<'a, Color> {
+        let color = match self.not_responding {
+            Some(ref not_responding) if self.is_hung => {
+                Cow::Owned(not_responding.color_with_opacity(color.fade_opacity()))
+            }
+            _ => color,
+        };
+
+        let color = match self.night_light_temperature {
+            Some(kelvin) if is_night_light_enabled() => {
+                Cow::Owned(color.compensate_night_light(kelvin))
+            }
+            _ => color,
+        };
+
+        match (&self.contrast_guard, self.background_luminance) {
+            (Some(contrast_guard), Some(luminance)) => {
+                Cow::Owned(color.with_min_contrast(luminance, contrast_guard.min_contrast))
+            }
+            _ => color,
+        }
+    }
+
+    // The main stroke plus everything we draw outside of it
+    fn outer_width(&self) -> i32 {
+        self.border_width + self.outer_margin()
+    }
+
+    // Room for the extra strokes (and the gaps between them), the focus halo, the focus ripple, the
+    // particles, and the locate effect while it's running
+    fn outer_margin(&self) -> i32 {
+        let ripple_distance = self.ripple.as_ref().map_or(0, |ripple| ripple.distance);
+        let particles = self.particles.as_ref().map_or(0, Particles::extent);
+        let locate_growth = match self.locate_start {
+            Some(_) => (self.border_width as f32 * LOCATE_GROWTH).ceil() as i32,
+            None => 0,
+        };
+        let active_extra = self.active_extra.as_ref().map_or(0, ActiveExtra::extent);
+        strokes::total_extent(&self.stroke_layers)
+            + active_extra
+            + ripple_distance.max(particles)
+            + locate_growth
+    }
+
+    // How far into the current locate pulse we are (0.0 to 1.0 and back), or None if the locate
+    // effect isn't running
+    fn locate_pulse(&self) -> Option<f32> {
+        let elapsed = self.locate_start?.elapsed().as_secs_f32() / LOCATE_DURATION.as_secs_f32();
+        match elapsed < 1.0 {
+            true => Some((elapsed * LOCATE_PULSES * PI).sin().abs()),
+            false => None,
+        }
+    }
+
+    fn start_locate(&mut self) {
+        let was_locating = self.locate_start.is_some();
+        self.locate_start = Some(time::Instant::now());
+
+        // Make room for the pulse, which grows outwards from the window
+        if !was_locating {
+            log_if_err!(self.update_window_rect());
+            log_if_err!(self.update_position(None));
+        }
+
+        unsafe {
+            SetTimer(
+                self.border_window,
+                LOCATE_TIMER_ID,
+                LOCATE_INTERVAL_MS,
+                None,
+            )
+        };
+    }
+
+    // Draw the extra strokes around the main one, from the outside in. They fade along with the
+    // main border, but each one has its own colors.
+    fn draw_stroke_layers(&self, render_target: &ID2D1RenderTarget) -> anyhow::Result<()> {
+        if self.stroke_layers.is_empty() {
+            return Ok(());
+        }
+
+        // Distance from the edge of the border window to the outer edge of the main stroke. We use
+        // the full border width here so the layers don't move during a handoff shrink.
+        let mut edge = (self.outer_width() - self.border_offset - self.border_width) as f32;
+        let main_inset = self.rounded_rect.rect.left;
+
+        let mut layer_rects = Vec::with_capacity(self.stroke_layers.len());
+        for layer in self.stroke_layers.iter() {
+            let center = edge - layer.gap as f32 - layer.width as f32 / 2.0;
+            edge -= (layer.gap + layer.width) as f32;
+
+            // How much bigger this layer's rect is than the main stroke's
+            let expand = main_inset - center;
+            let rect = self.rounded_rect.rect;
+            let radius = match self.border_radius {
+                0.0 => 0.0,
+                radius => radius + expand,
+            };
+            let rounded_rect = D2D1_ROUNDED_RECT {
+                rect: D2D_RECT_F {
+                    left: rect.left - expand,
+                    top: rect.top - expand,
+                    right: rect.right + expand,
+                    bottom: rect.bottom + expand,
+                },
+                radiusX: radius,
+                radiusY: radius,
+            };
+
+            layer_rects.push((layer, rounded_rect));
+        }
+
+        let static_brush_properties = D2D1_BRUSH_PROPERTIES {
+            opacity: 1.0,
+            transform: Matrix3x2::identity(),
+        };
+
+        for (layer, rounded_rect) in layer_rects.iter().rev() {
+            let brush_properties = match layer.animate {
+                true => &self.brush_properties,
+                false => &static_brush_properties,
+            };
+
+            // Same layering as the main stroke: the color we're fading to goes on top
+            let mut active_color = layer.active_color.clone();
+            active_color.set_fade_opacity(self.active_color.fade_opacity());
+            let mut inactive_color = layer.inactive_color.clone();
+            inactive_color.set_fade_opacity(self.inactive_color.fade_opacity());
+
+            let [bottom, top] = match self.is_active_window {
+                true => [inactive_color, active_color],
+                false => [active_color, inactive_color],
+            };
+
+            // Mixed mid-fade, same as the main stroke
+            let (bottom_opacity, top_opacity) = (bottom.fade_opacity(), top.fade_opacity());
+            let mixed = match bottom_opacity > 0.0 && top_opacity > 0.0 {
+                true => Color::mix(&bottom, bottom_opacity, &top, top_opacity),
+                false => None,
+            };
+            let colors = match mixed {
+                Some(mixed) => vec![mixed],
+                None => vec![bottom, top],
+            };
+
+            for color in colors {
+                if color.drawn_alpha() <= 0.0 {
+                    continue;
+                }
+
+                let color = self.adjust_color(Cow::Owned(color));
+                let brush = color
+                    .create_brush(render_target, &self.window_rect, brush_properties)
+                    .context("could not create brush for stroke layer")?;
+                self.apply_animation_opacity(&brush);
+
+                self.draw_stroke(render_target, &brush, rounded_rect, layer.width as f32);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Draw the focus halo just outside of the outermost extra stroke
+    fn draw_active_extra(&self, render_target: &ID2D1RenderTarget) -> anyhow::Result<()> {
+        let Some(ref active_extra) = self.active_extra else {
+            return Ok(());
+        };
+        let opacity = active_extra.opacity_at(time::Instant::now());
+        if opacity <= 0.0 || active_extra.width == 0 {
+            return Ok(());
+        }
+
+        // Same math as draw_stroke_layers, picking up where the last layer left off
+        let edge = (self.outer_width() - self.border_offset - self.border_width) as f32
+            - strokes::total_extent(&self.stroke_layers) as f32;
+        let center = edge - active_extra.offset as f32 - active_extra.width as f32 / 2.0;
+
+        let expand = self.rounded_rect.rect.left - center;
+        let rect = self.rounded_rect.rect;
+        let radius = match self.border_radius {
+            0.0 => 0.0,
+            radius => radius + expand,
+        };
+        let rounded_rect = D2D1_ROUNDED_RECT {
+            rect: D2D_RECT_F {
+                left: rect.left - expand,
+                top: rect.top - expand,
+                right: rect.right + expand,
+                bottom: rect.bottom + expand,
+            },
+            radiusX: radius,
+            radiusY: radius,
+        };
+
+        let color = self.adjust_color(Cow::Borrowed(&active_extra.color));
+        let brush = color
+            .create_brush(render_target, &self.window_rect, &self.brush_properties)
+            .context("could not create brush for active_extra")?;
+        unsafe { brush.SetOpacity(brush.GetOpacity() * opacity) };
+        self.apply_animation_opacity(&brush);
+
+        self.draw_stroke(
+            render_target,
+            &brush,
+            &rounded_rect,
+            active_extra.width as f32,
+        );
+
+        Ok(())
+    }
+
+    // Draw the focus ripple as a copy of the stroke that grows outwards as it fades away
+    fn draw_ripple(&self, render_target: &ID2D1RenderTarget, color: &Color) -> anyhow::Result<()> {
+        let Some(ripple) = self.ripple.as_ref() else {
+            return Ok(());
+        };
+        let Some(progress) = ripple.progress() else {
+            return Ok(());
+        };
+
+        let expand = ripple.distance as f32 * progress;
+        let rect = self.rounded_rect.rect;
+        let radius = match self.border_radius {
+            0.0 => 0.0,
+            radius => radius + expand,
+        };
+        let rounded_rect = D2D1_ROUNDED_RECT {
+            rect: D2D_RECT_F {
+                left: rect.left - expand,
+                top: rect.top - expand,
+                right: rect.right + expand,
+                bottom: rect.bottom + expand,
+            },
+            radiusX: radius,
+            radiusY: radius,
+        };
+
+        let brush = color
+            .create_brush(render_target, &self.window_rect, &self.brush_properties)
+            .context("could not create ripple brush")?;
+        unsafe { brush.SetOpacity(1.0 - progress) };
+
+        self.draw_stroke(render_target, &brush, &rounded_rect, self.stroke_width());
+
+        Ok(())
+    }
+
+    fn apply_animation_opacity(&self, brush: &ID2D1Brush) {
+        let opacity = self.animation_delta().opacity.max(0.0);
+        if opacity != 1.0 {
+            unsafe { brush.SetOpacity(brush.GetOpacity() * opacity) };
+        }
+    }
+
+    // The built-in animations' delta from the last frame, with the plugin's (if any) on top
+    fn animation_delta(&self) -> AnimationDelta {
+        match self.plugin {
+            Some(_) => self.animations.delta.merge(self.plugin_params.delta()),
+            None => self.animations.delta,
+        }
+    }
+
+    fn plugin_state(&self) -> PluginBorderState {
+        PluginBorderState {
+            width: (self.window_rect.right - self.window_rect.left) as f32,
+            height: (self.window_rect.bottom - self.window_rect.top) as f32,
+            border_width: self.border_width as f32,
+            border_radius: self.border_radius,
+            is_active: self.is_active_window as u32,
+            ..Default::default()
+        }
+    }
+
+    fn primitive_blend(&self) -> D2D1_PRIMITIVE_BLEND {
+        match self.blend_mode {
+            BlendMode::Normal => D2D1_PRIMITIVE_BLEND_SOURCE_OVER,
+            BlendMode::Add => D2D1_PRIMITIVE_BLEND_ADD,
+            BlendMode::Lighten => D2D1_PRIMITIVE_BLEND_MAX,
+        }
+    }
+
+    fn set_primitive_blend(&self, render_target: &ID2D1RenderTarget, blend: D2D1_PRIMITIVE_BLEND) {
+        if self.blend_mode == BlendMode::Normal {
+            return;
+        }
+
+        // Primitive blending requires ID2D1DeviceContext, which is available on Windows 8+
+        match render_target.cast::<ID2D1DeviceContext>() {
+            Ok(device_context) => unsafe { device_context.SetPrimitiveBlend(blend) },
+            Err(e) => error!(
+                "could not set blend mode for {:?}: {e}",
+                self.tracking_window
+            ),
+        }
+    }
+
+    // With a handoff shrink, inactive borders are drawn thinner and grow back as they fade in. The
+    // locate effect makes the border pulse thicker on top of that.
+    pub(crate) fn stroke_width(&self) -> f32 {
+        let locate_scale = 1.0 + LOCATE_GROWTH * self.locate_pulse().unwrap_or(0.0);
+        let width = self.border_width as f32 + self.animation_delta().width;
+
+        let shrink = self.animations.handoff.shrink;
+        if shrink == 0.0 {
+            return width.max(0.0) * locate_scale;
+        }
+
+        let progress = self
+            .animations
+            .fade_easing
+            .ease(self.animations.fade_progress);
+        (width - shrink * (1.0 - progress)).max(0.0) * locate_scale
+    }
+
+    fn draw_rectangle(&self, render_target: &ID2D1RenderTarget, brush: &ID2D1Brush) {
+        self.draw_stroke(
+            render_target,
+            brush,
+            &self.rounded_rect,
+            self.stroke_width(),
+        );
+    }
+
+    // Draw dash_color's dashes over the colors
+    fn draw_dashes(&self, render_target: &ID2D1RenderTarget, opacity: f32) -> anyhow::Result<()> {
+        let (Some(dash_color), Some(stroke_style)) = (&self.dash_color, &self.dashed_stroke_style)
+        else {
+            return Ok(());
+        };
+
+        let brush = self
+            .adjust_color(Cow::Borrowed(dash_color))
+            .create_brush(render_target, &self.window_rect, &self.brush_properties)
+            .context("could not create dash brush")?;
+        self.apply_animation_opacity(&brush);
+        unsafe { brush.SetOpacity(brush.GetOpacity() * opacity) };
+
+        self.draw_stroke_with_style(
+            render_target,
+            &brush,
+            &self.rounded_rect,
+            self.stroke_width(),
+            Some(stroke_style),
+        );
+
+        Ok(())
+    }
+
+    // Draw the Comet animation's comets over the border. A gradient can't bend around the corners
+    // with the path, so each comet is split into short pieces that fade out towards its tail.
+    fn draw_comets(&self, render_target: &ID2D1RenderTarget, opacity: f32) -> anyhow::Result<()> {
+        // The Comet animation's own comets win over a custom animation's
+        let position = match self.animations.current.contains_key(&AnimationType::Comet) {
+            true => Some(self.animations.comet_timeline.position),
+            false => self.animations.custom_comet,
+        };
+        let Some(position) = position.filter(|_| opacity > 0.0) else {
+            return Ok(());
+        };
+
+        let comet = &self.animations.comet;
+        let count = comet.count();
+        let length = comet.length.clamp(0.0, 1.0);
+        let stroke_width = self.stroke_width();
+
+        let brush = self
+            .comet_color
+            .create_brush(render_target, &self.window_rect, &self.brush_properties)
+            .context("could not create comet brush")?;
+        self.apply_animation_opacity(&brush);
+        let comet_opacity = unsafe { brush.GetOpacity() } * opacity;
+
+        let mut geometries = self.comet_geometries.borrow_mut();
+        for i in 0..count {
+            let head = position + i as f32 / count as f32;
+
+            // The last piece is the comet's head, the only one at full opacity
+            for piece in 0..COMET_PIECES {
+                let start = head - length * (COMET_PIECES - piece) as f32 / COMET_PIECES as f32;
+                let geometry = geometries
+                    .piece(&self.rounded_rect, length / COMET_PIECES as f32, start)
+                    .context("could not create comet geometry")?;
+
+                unsafe {
+                    brush.SetOpacity(comet_opacity * (piece + 1) as f32 / COMET_PIECES as f32);
+                    render_target.DrawGeometry(&geometry, &brush, stroke_width, None);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn draw_particles(
+        &self,
+        render_target: &ID2D1RenderTarget,
+        opacity: f32,
+    ) -> anyhow::Result<()> {
+        let Some(particles) = self.particles.as_ref().filter(|p| !p.is_empty()) else {
+            return Ok(());
+        };
+
+        // Particles come off the focused border, so they take its color unless they have their own
+        let color = match particles.color {
+            Some(ref color) => Cow::Borrowed(color),
+            None => {
+                let mut color = self.active_color.clone();
+                color.set_fade_opacity(1.0);
+                self.adjust_color(Cow::Owned(color))
+            }
+        };
+
+        let brush = color
+            .create_brush(render_target, &self.window_rect, &self.brush_properties)
+            .context("could not create particle brush")?;
+        self.apply_animation_opacity(&brush);
+        unsafe { brush.SetOpacity(brush.GetOpacity() * opacity) };
+
+        particles.draw(render_target, &brush);
+
+        Ok(())
+    }
+
+    // Draw the active color one edge at a time, blending from one edge's color into the next around
+    // the corners
+    fn draw_edges(
+        &self,
+        render_target: &ID2D1RenderTarget,
+        edge_colors: &EdgeColors,
+    ) -> anyhow::Result<()> {
+        let fade_opacity = self.active_color.fade_opacity();
+        let colors = edge_colors.resolve(&self.active_color).map(|color| {
+            let mut color = self.adjust_color(Cow::Owned(color)).into_owned();
+            color.set_fade_opacity(1.0);
+            color
+        });
+        let stroke_width = self.stroke_width();
+
+        let mut edge_cache = self.edge_cache.borrow_mut();
+        edge_cache
+            .update(
+                render_target,
+                &self.window_rect,
+                &self.rounded_rect,
+                stroke_width,
+                colors,
+                &self.brush_properties,
+            )
+            .context("could not create edge brushes")?;
+
+        for (segment, brush) in edge_cache.iter() {
+            unsafe {
+                brush.SetTransform(&self.brush_properties.transform);
+                brush.SetOpacity(fade_opacity);
+            }
+            self.apply_animation_opacity(brush);
+
+            unsafe {
+                render_target.PushAxisAlignedClip(&segment.clip, D2D1_ANTIALIAS_MODE_ALIASED)
+            };
+            self.draw_stroke(render_target, brush, &self.rounded_rect, stroke_width);
+            unsafe { render_target.PopAxisAlignedClip() };
+        }
+
+        Ok(())
+    }
+
+    fn draw_stroke(
+        &self,
+        render_target: &ID2D1RenderTarget,
+        brush: &ID2D1Brush,
+        rounded_rect: &D2D1_ROUNDED_RECT,
+        stroke_width: f32,
+    ) {
+        let stroke_style = self.hung_stroke_style();
+        self.draw_stroke_with_style(
+            render_target,
+            brush,
+            rounded_rect,
+            stroke_width,
+            stroke_style,
+        );
+    }
+
+    // The dashes that not_responding draws the border with while the window is hung
+    fn hung_stroke_style(&self) -> Option<&ID2D1StrokeStyle> {
+        let dashed = self
+            .not_responding
+            .as_ref()
+            .is_some_and(|not_responding| not_responding.dashed);
+        self.dashed_stroke_style
+            .as_ref()
+            .filter(|_| self.is_hung && dashed)
+    }
+
+    fn draw_stroke_with_style(
+        &self,
+        render_target: &ID2D1RenderTarget,
+        brush: &ID2D1Brush,
+        rounded_rect: &D2D1_ROUNDED_RECT,
+        stroke_width: f32,
+        stroke_style: Option<&ID2D1StrokeStyle>,
+    ) {
+        unsafe {
+            match rounded_rect.radiusX {
+                0.0 => render_target.DrawRectangle(
+                    &rounded_rect.rect,
+                    brush,
+                    stroke_width,
+                    stroke_style,
+                ),
+                _ => render_target.DrawRoundedRectangle(
+                    rounded_rect,
+                    brush,
+                    stroke_width,
+                    stroke_style,
+                ),
+            }
+        }
+    }
+
+    fn start_flash(&mut self, count: u32) {
+        // Each flash hides and then shows the border once
+        self.flash_remaining = count * 2;
+        unsafe { SetTimer(self.border_window, FLASH_TIMER_ID, FLASH_INTERVAL_MS, None) };
+    }
+
+    fn set_anim_timer(&mut self) {
+        let has_animated_noise = self.noise.as_ref().is_some_and(|noise| noise.is_animated());
+
+        if (!self.animations.active.is_empty()
+            || !self.animations.inactive.is_empty()
+            || has_animated_noise
+            || self.plugin.is_some())
+            && self.anim_timer.is_none()
+        {
+            let timer_duration = (1000.0 / self.animation_fps() as f32) as u64;
+            self.anim_timer = Some(AnimationTimer::start(self.border_window, timer_duration));
+        }
+    }
+
+    fn destroy_anim_timer(&mut self) {
+        if let Some(anim_timer) = self.anim_timer.as_mut() {
+            anim_timer.stop();
+            self.anim_timer = None;
+        }
+    }
+
+    // The fps animations run at, which adaptive quality can lower while frames are slow
+    fn animation_fps(&self) -> i32 {
+        match self.adaptive_quality {
+            Some(ref adaptive_quality) => adaptive_quality.fps(self.animations.fps),
+            None => self.animations.fps,
+        }
+    }
+
+    pub(crate) fn render_supersampling(&self) -> u32 {
+        match self.adaptive_quality {
+            Some(ref adaptive_quality) if !adaptive_quality.allows_supersampling() => 1,
+            _ => self.supersampling,
+        }
+    }
+
+    fn visible_noise(&self) -> Option<&Noise> {
+        match self.adaptive_quality {
+            Some(ref adaptive_quality) if !adaptive_quality.allows_noise() => None,
+            _ => self.noise.as_ref(),
+        }
+    }
+
+    // Measure how long the animation frame took, since 'now' was taken right before it was drawn
+    fn record_frame_time(&mut self, now: time::Instant) {
+        let Some(adaptive_quality) = self.adaptive_quality.as_mut() else {
+            return;
+        };
+        if !adaptive_quality.record_frame(now.elapsed(), time::Instant::now()) {
+            return;
+        }
+
+        let level = adaptive_quality.level;
+        let frame_time = adaptive_quality.frame_time;
+        debug!(
+            "animation quality for {:?} is now level {level} ({frame_time:.1}ms frames)",
+            self.tracking_window
+        );
+
+        // Restart the animation timer at the new fps
+        if self.anim_timer.is_some() {
+            self.destroy_anim_timer();
+            self.set_anim_timer();
+        }
+        log_if_err!(self.render());
+    }
+
+    // A border counts as focused if its own window is, or if any window in its group is
+    fn is_focused(&self) -> bool {
+        if let Some(monitor) = self.monitor {
+            return self
+                .monitor_highlight
+                .is_some_and(|highlight| highlight.current() == monitor);
+        }
+
+        is_active_window(self.tracking_window) || self.group.as_deref().is_some_and(is_group_active)
+    }
+
+    // What the border is registered under. Monitor frames and the cursor ring only have a stand-in
+    // tracking window, so they have keys of their own.
+    pub(crate) fn registry_key(&self) -> BorderKey {
+        match (self.monitor, self.cursor_ring_size) {
+            (Some(monitor), _) => BorderKey::monitor(monitor),
+            (None, Some(_)) => BorderKey::CursorRing,
+            (None, None) => BorderKey::window(self.tracking_window),
+        }
+    }
+
+    fn exit_border_thread(&mut self) {
+        BORDERS.unregister(self.registry_key(), self.border_window);
+        unsafe { PostQuitMessage(0) };
+    }
+
+    pub(crate) unsafe extern "system" fn s_wnd_proc(
+        window: HWND,
+        message: u32,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> LRESULT {
+        // Retrieve the pointer to this WindowBorder struct using GWLP_USERDATA
+        let mut border_pointer: *mut WindowBorder = GetWindowLongPtrW(window, GWLP_USERDATA) as _;
+
+        // If a pointer has not yet been assigned to GWLP_USERDATA, assign it here using the LPARAM
+        // from CreateWindowExW
+        if border_pointer.is_null() && message == WM_CREATE {
+            let create_struct: *mut CREATESTRUCTW = lparam.0 as *mut _;
+            border_pointer = (*create_struct).lpCreateParams as *mut _;
+            SetWindowLongPtrW(window, GWLP_USERDATA, border_pointer as _);
+        }
+
+        match !border_pointer.is_null() {
+            true => (*border_pointer).wnd_proc(window, message, wparam, lparam),
+            false => DefWindowProcW(window, message, wparam, lparam),
+        }
+    }
+
+    unsafe fn wnd_proc(
+        &mut self,
+        window: HWND,
+        message: u32,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> LRESULT {
+        match message {
+            // EVENT_OBJECT_LOCATIONCHANGE
+            WM_APP_LOCATIONCHANGE => {
+                if self.pause {
+                    return LRESULT(0);
+                }
+
+                // TODO I could probably move some of this message's code into a new message for
+                // EVENT_SYSTEM_MOVESIZESTART and MOVESIZEEND but the relevant code doesn't seem to
+                // eat up much CPU anyways
+                if !has_native_border(self.tracking_window) {
+                    log_if_err!(self.update_position(Some(SWP_HIDEWINDOW)));
+                    return LRESULT(0);
+                }
+
+                if !is_window_visible(self.border_window) {
+                    let _ = ShowWindow(self.border_window, SW_SHOWNA);
+                }
+
+                let old_rect = self.window_rect;
+                log_if_err!(self.update_window_rect());
+                log_if_err!(self.update_position(None));
+
+                // TODO When a window is minimized, all four points of the rect go way below 0. For
+                // some reason, after unminimizing/restoring, render() will sometimes render at
+                // this minimized size. For now, I just do self.window_rect = old_rect to fix that.
+                if !is_rect_visible(&self.window_rect) {
+                    self.window_rect = old_rect;
+                } else if !are_rects_same_size(&self.window_rect, &old_rect) {
+                    // Only re-render the border when its size changes
+                    log_if_err!(self.render());
+                }
+
+                // Events only come in so fast, so poll for the frames in between them too
+                if self.high_frequency_tracking
+                    && self
+                        .last_location_change
+                        .replace(time::Instant::now())
+                        .is_none()
+                {
+                    SetTimer(
+                        window,
+                        HIGH_FREQUENCY_TIMER_ID,
+                        HIGH_FREQUENCY_INTERVAL_MS,
+                        None,
+                    );
+                }
+            }
+            // EVENT_OBJECT_REORDER
+            WM_APP_REORDER => {
+                // For apps like firefox, when you hover over a tab, a popup window spawns that
+                // changes the z-order and causes the border to sit under the tracking window. To
+                // remedy that, we just re-update the position/z-order when windows are reordered.
+                // Every border gets this message, so skip the ones that are already in place.
+                if self.pause {
+                    return LRESULT(0);
+                }
+
+                let covering_rects = self.covering_rects.clone();
+                if self.should_restack() {
+                    log_if_err!(self.update_position(None));
+                }
+                if self.update_occluding_rects() || self.covering_rects != covering_rects {
+                    log_if_err!(self.render());
+                }
+            }
+            // EVENT_OBJECT_FOCUS
+            WM_APP_FOCUS => {
+                let was_active_window = self.is_active_window;
+
+                // Every border gets the same event time in WPARAM, so timing the fade from it keeps
+                // the old and new active borders in sync even if one thread gets here later
+                let focus_time = instant_from_tick_count(wparam.0 as u32);
+                self.set_active(self.is_focused(), focus_time);
+
+                if self.animations.handoff.flash && self.is_active_window && !was_active_window {
+                    self.start_flash(1);
+                }
+
+                if self.is_active_window != was_active_window && self.active_extra.is_some() {
+                    SetTimer(
+                        window,
+                        ACTIVE_EXTRA_TIMER_ID,
+                        ACTIVE_EXTRA_INTERVAL_MS,
+                        None,
+                    );
+                }
+
+                if self.is_active_window && !was_active_window {
+                    if let Some(ripple) = self.ripple.as_mut() {
+                        ripple.start = Some(time::Instant::now());
+                        SetTimer(window, RIPPLE_TIMER_ID, RIPPLE_INTERVAL_MS, None);
+                    }
+                }
+
+                if self.is_active_window && self.particles.is_some() {
+                    SetTimer(window, PARTICLE_TIMER_ID, PARTICLE_INTERVAL_MS, None);
+                }
+
+                log_if_err!(self.update_position(None));
+                log_if_err!(self.render());
+            }
+            // EVENT_OBJECT_SHOW / EVENT_OBJECT_UNCLOAKED
+            WM_APP_SHOWUNCLOAKED => {
+                // Game mode shows the border again once the fullscreen app is gone
+                if self.hidden_for_fullscreen {
+                    return LRESULT(0);
+                }
+
+                // Wait until Task View is closed (e.g. for windows on the desktop it switched to)
+                if event_hook::is_task_view_open() {
+                    self.hidden_for_task_view = true;
+                    return LRESULT(0);
+                }
+
+                // With GlazeWM, if I switch to another workspace while a window is minimized and
+                // switch back, then we will receive this message even though the window is not yet
+                // visible. And, the window rect will be all weird. So, we apply the following fix.
+                let old_rect = self.window_rect;
+                log_if_err!(self.update_window_rect());
+                if !is_rect_visible(&self.window_rect) {
+                    self.window_rect = old_rect;
+                    return LRESULT(0);
+                }
+
+                if has_native_border(self.tracking_window) {
+                    self.transition_to(LifecycleState::Visible, time::Instant::now());
+                    log_if_err!(self.update_position(Some(SWP_SHOWWINDOW)));
+                    log_if_err!(self.render());
+                }
+
+                self.set_anim_timer();
+
+                self.pause = false;
+            }
+            // EVENT_OBJECT_HIDE / EVENT_OBJECT_CLOAKED
+            WM_APP_HIDECLOAKED => {
+                self.hidden_for_task_view = false;
+                self.transition_to(LifecycleState::Hiding, time::Instant::now());
+                log_if_err!(self.update_position(Some(SWP_HIDEWINDOW)));
+
+                self.destroy_anim_timer();
+
+                self.pause = true;
+            }
+            // EVENT_OBJECT_MINIMIZESTART
+            WM_APP_MINIMIZESTART => {
+                self.hidden_for_task_view = false;
+                self.transition_to(LifecycleState::Hiding, time::Instant::now());
+                log_if_err!(self.update_position(Some(SWP_HIDEWINDOW)));
+
+                self.destroy_anim_timer();
+
+                self.pause = true;
+            }
+            // EVENT_SYSTEM_MINIMIZEEND
+            // When a window is about to be unminimized, hide the border and let the thread sleep
+            // to wait for the window animation to finish, then show the border.
+            WM_APP_MINIMIZEEND => {
+                if self.hidden_for_fullscreen {
+                    return LRESULT(0);
+                }
+
+                thread::sleep(time::Duration::from_millis(self.unminimize_delay));
+
+                if has_native_border(self.tracking_window) {
+                    self.transition_to(LifecycleState::Visible, time::Instant::now());
+                    log_if_err!(self.update_window_rect());
+                    log_if_err!(self.update_position(Some(SWP_SHOWWINDOW)));
+                    log_if_err!(self.render());
+                }
+
+                self.set_anim_timer();
+
+                self.pause = false;
+            }
+            WM_APP_ANIMATE => {
+                // Nobody can see the animation while the border is covered up
+                if self.pause || self.is_occluded {
+                    return LRESULT(0);
+                }
+
+                // Use the same timestamp for every animation in this frame
+                let now = time::Instant::now();
+                let render_elapsed = self.last_render_time.unwrap_or(now).elapsed();
+
+                let mut update = self.step_animations(now);
+
+                if self
+                    .visible_noise()
+                    .is_some_and(|noise| noise.is_animated())
+                {
+                    update = true;
+                }
+
+                let plugin_state = self.plugin_state();
+                if let Some(plugin) = self.plugin.as_mut() {
+                    self.plugin_params = plugin.on_frame(plugin_state, now);
+                    update = true;
+                }
+
+                let interval = 1.0 / self.animation_fps() as f32;
+                let diff = render_elapsed.as_secs_f32() - interval;
+                if update && (diff.abs() <= 0.001 || diff >= 0.0) {
+                    log_if_err!(self.render());
+                    self.record_frame_time(now);
+                }
+            }
+            // EVENT_OBJECT_NAMECHANGE
+            WM_APP_TITLECHANGE => {
+                let script = CONFIG.lock().unwrap().global.script.clone();
+                let Some(script) = script else {
+                    return LRESULT(0);
+                };
+
+                let script_window = get_window_info(self.tracking_window);
+                if scripting::eval_enabled(&script, &script_window) == Some(false) {
+                    info!(
+                        "border is disabled by script for {:?}!",
+                        self.tracking_window
+                    );
+                    destroy_border_for_window(self.tracking_window);
+                    return LRESULT(0);
+                }
+
+                // Swap in the new colors while keeping the current opacities so fades aren't reset
+                let scripted_colors = scripting::eval_colors(&script, &script_window);
+                if let Some(active) = scripted_colors.active {
+                    let mut active_color = active.convert_to_color(true);
+                    active_color.set_fade_opacity(self.active_color.fade_opacity());
+                    self.active_color = active_color;
+                }
+                if let Some(inactive) = scripted_colors.inactive {
+                    let mut inactive_color = inactive.convert_to_color(false);
+                    inactive_color.set_fade_opacity(self.inactive_color.fade_opacity());
+                    self.inactive_color = inactive_color;
+                }
+
+                log_if_err!(self.render());
+            }
+            // Border::set_color()
+            WM_APP_SETCOLOR => {
+                // The ColorConfigs were boxed by the sender, so we take ownership of them here
+                let (active_config, inactive_config) =
+                    *Box::from_raw(lparam.0 as *mut (ColorConfig, ColorConfig));
+
+                let mut active_color = active_config.convert_to_color(true);
+                active_color.set_fade_opacity(self.active_color.fade_opacity());
+                self.active_color = active_color;
+
+                let mut inactive_color = inactive_config.convert_to_color(false);
+                inactive_color.set_fade_opacity(self.inactive_color.fade_opacity());
+                self.inactive_color = inactive_color;
+
+                // Same as in build_border(), for everything that uses the border's colors
+                let mut progress_color = self
+                    .own_progress_color
+                    .as_ref()
+                    .unwrap_or(&active_config)
+                    .convert_to_color(true);
+                progress_color.set_fade_opacity(1.0);
+                self.progress_color = progress_color;
+
+                for layer in self.stroke_layers.iter_mut() {
+                    layer.set_border_colors(&active_config, &inactive_config);
+                }
+
+                log_if_err!(self.render());
+            }
+            // The set-progress IPC command
+            WM_APP_PROGRESS => {
+                self.progress = progress::progress_from_wparam(wparam);
+                log_if_err!(self.render());
+            }
+            // Border::flash()
+            WM_APP_FLASH => {
+                self.start_flash(wparam.0 as u32);
+            }
+            WM_APP_LOCATE => {
+                self.start_locate();
+            }
+            // Task View shows a preview of every window, and borders would float over the previews
+            WM_APP_TASKVIEW if wparam.0 != 0 => {
+                if is_window_visible(window) {
+                    self.transition_to(LifecycleState::Hiding, time::Instant::now());
+                    log_if_err!(self.update_position(Some(SWP_HIDEWINDOW)));
+                    self.destroy_anim_timer();
+                    self.hidden_for_task_view = true;
+                    self.pause = true;
+                }
+            }
+            WM_APP_TASKVIEW => {
+                if !self.hidden_for_task_view {
+                    return LRESULT(0);
+                }
+                self.hidden_for_task_view = false;
+
+                // The window may have been moved or switched to from Task View
+                log_if_err!(self.update_window_rect());
+                if is_rect_visible(&self.window_rect) {
+                    self.transition_to(LifecycleState::Visible, time::Instant::now());
+                    log_if_err!(self.update_position(Some(SWP_SHOWWINDOW)));
+                    log_if_err!(self.render());
+                    self.set_anim_timer();
+                    self.pause = false;
+                }
+            }
+            // Game mode hides the borders on a monitor while a fullscreen app is on it
+            WM_APP_FULLSCREEN if wparam.0 != 0 => {
+                self.hidden_for_fullscreen = true;
+                if is_window_visible(window) {
+                    self.transition_to(LifecycleState::Hiding, time::Instant::now());
+                    log_if_err!(self.update_position(Some(SWP_HIDEWINDOW)));
+                    self.destroy_anim_timer();
+                    self.pause = true;
+                }
+            }
+            WM_APP_FULLSCREEN => {
+                if !self.hidden_for_fullscreen {
+                    return LRESULT(0);
+                }
+                self.hidden_for_fullscreen = false;
+
+                // The window may have been hidden or minimized in the meantime (monitor frames
+                // don't have a window of their own)
+                if self.monitor.is_none()
+                    && (!is_window_visible(self.tracking_window)
+                        || is_cloaked(self.tracking_window))
+                {
+                    return LRESULT(0);
+                }
+
+                log_if_err!(self.update_window_rect());
+                if is_rect_visible(&self.window_rect) {
+                    self.transition_to(LifecycleState::Visible, time::Instant::now());
+                    log_if_err!(self.update_position(Some(SWP_SHOWWINDOW)));
+                    log_if_err!(self.render());
+                    self.set_anim_timer();
+                    self.pause = false;
+                }
+            }
+            WM_TIMER if wparam.0 == LOCATE_TIMER_ID => {
+                if self.locate_pulse().is_none() {
+                    // Shrink the border window back down now that the pulse is over
+                    let _ = KillTimer(window, LOCATE_TIMER_ID);
+                    self.locate_start = None;
+                    log_if_err!(self.update_window_rect());
+                    log_if_err!(self.update_position(None));
+                }
+
+                log_if_err!(self.render());
+            }
+            WM_TIMER if wparam.0 == FLASH_TIMER_ID => {
+                self.flash_remaining = self.flash_remaining.saturating_sub(1);
+
+                // Odd counts hide the border and even counts show it again
+                self.flash_hidden = self.flash_remaining % 2 == 1;
+                log_if_err!(self.render());
+
+                if self.flash_remaining == 0 {
+                    let _ = KillTimer(window, FLASH_TIMER_ID);
+                }
+            }
+            WM_TIMER if wparam.0 == CONTRAST_TIMER_ID => {
+                if self.pause {
+                    return LRESULT(0);
+                }
+
+                let luminance = effects::sample_background_luminance(&self.window_rect);
+
+                // Only re-render if the background changed enough to matter
+                let changed = match (luminance, self.background_luminance) {
+                    (Some(new), Some(old)) => (new - old).abs() > 0.02,
+                    (new, old) => new.is_some() != old.is_some(),
+                };
+                if changed {
+                    self.background_luminance = luminance;
+                    log_if_err!(self.render());
+                }
+            }
+            WM_TIMER if wparam.0 == NOT_RESPONDING_TIMER_ID => {
+                if self.pause {
+                    return LRESULT(0);
+                }
+
+                let is_hung = is_window_hung(self.tracking_window);
+                if is_hung != self.is_hung {
+                    debug!(
+                        "{:?} is {}",
+                        self.tracking_window,
+                        match is_hung {
+                            true => "not responding",
+                            false => "responding again",
+                        }
+                    );
+                    self.is_hung = is_hung;
+                    log_if_err!(self.render());
+                }
+            }
+            WM_TIMER if wparam.0 == ACTIVE_MONITOR_TIMER_ID => {
+                if self.pause || self.is_focused() == self.is_active_window {
+                    return LRESULT(0);
+                }
+
+                // Handle it like a focus change so the frame fades over in the same way
+                log_if_err!(post_message_w(
+                    window,
+                    WM_APP_FOCUS,
+                    WPARAM(GetTickCount() as usize),
+                    LPARAM(0)
+                ));
+            }
+            WM_TIMER if wparam.0 == RIPPLE_TIMER_ID => {
+                // Draw one last frame without the ripple once it's done
+                if self
+                    .ripple
+                    .as_ref()
+                    .is_some_and(|ripple| ripple.progress().is_none())
+                {
+                    let _ = KillTimer(window, RIPPLE_TIMER_ID);
+                    if let Some(ripple) = self.ripple.as_mut() {
+                        ripple.start = None;
+                    }
+                }
+
+                if !self.pause {
+                    log_if_err!(self.render());
+                }
+            }
+            WM_TIMER if wparam.0 == ACTIVE_EXTRA_TIMER_ID => {
+                // Draw one last frame at the final opacity once the fade is done
+                let is_fading = self
+                    .active_extra
+                    .as_ref()
+                    .is_some_and(|active_extra| active_extra.is_fading(time::Instant::now()));
+                if !is_fading {
+                    let _ = KillTimer(window, ACTIVE_EXTRA_TIMER_ID);
+                }
+
+                if !self.pause {
+                    log_if_err!(self.render());
+                }
+            }
+            WM_TIMER if wparam.0 == PARTICLE_TIMER_ID => {
+                // New particles only come off the border while it's focused and showing, and the
+                // timer stops once the last of them has faded away
+                let spawning = self.is_active_window && !self.pause && !self.is_occluded;
+                let rounded_rect = self.rounded_rect;
+                let Some(particles) = self.particles.as_mut() else {
+                    let _ = KillTimer(window, PARTICLE_TIMER_ID);
+                    return LRESULT(0);
+                };
+
+                if !particles.step(&rounded_rect, spawning, time::Instant::now()) {
+                    let _ = KillTimer(window, PARTICLE_TIMER_ID);
+                    particles.last_update = None;
+                }
+
+                if !self.pause {
+                    log_if_err!(self.render());
+                }
+            }
+            WM_APP_HOVER => {
+                let Some(hover_peek) = self.hover_peek.as_mut() else {
+                    return LRESULT(0);
+                };
+                hover_peek.set_hovered(wparam.0 != 0, time::Instant::now());
+                self.start_hover_timer();
+            }
+            WM_TIMER if wparam.0 == HOVER_PEEK_TIMER_ID => {
+                // Active borders are always shown, and set_active() starts this again once the
+                // window loses focus
+                let Some(hover_peek) = self.hover_peek.as_mut().filter(|_| !self.is_active_window)
+                else {
+                    let _ = KillTimer(window, HOVER_PEEK_TIMER_ID);
+                    return LRESULT(0);
+                };
+
+                // Paused borders still finish the fade, so the timer doesn't run for as long as
+                // they're paused
+                let old_opacity = hover_peek.opacity;
+                if !hover_peek.update(time::Instant::now()) {
+                    let _ = KillTimer(window, HOVER_PEEK_TIMER_ID);
+                }
+
+                if hover_peek.opacity != old_opacity && !self.pause {
+                    log_if_err!(self.render());
+                }
+            }
+            WM_TIMER if wparam.0 == CURSOR_RING_TIMER_ID => {
+                if self.pause {
+                    return LRESULT(0);
+                }
+
+                // The ring never changes size, so it only needs to be moved, not redrawn
+                let old_rect = self.window_rect;
+                log_if_err!(self.update_window_rect());
+                if self.window_rect != old_rect {
+                    log_if_err!(self.update_position(None));
+                }
+            }
+            WM_TIMER if wparam.0 == HIGH_FREQUENCY_TIMER_ID => {
+                let is_still = self
+                    .last_location_change
+                    .is_none_or(|last| last.elapsed() >= HIGH_FREQUENCY_LINGER);
+                if self.pause || is_still {
+                    let _ = KillTimer(window, HIGH_FREQUENCY_TIMER_ID);
+                    self.last_location_change = None;
+                    return LRESULT(0);
+                }
+
+                let old_rect = self.window_rect;
+                log_if_err!(self.update_window_rect());
+                if !is_rect_visible(&self.window_rect) {
+                    self.window_rect = old_rect;
+                } else if self.window_rect != old_rect {
+                    // Keep polling for as long as the window keeps moving, with or without events
+                    self.last_location_change = Some(time::Instant::now());
+                    log_if_err!(self.update_position(None));
+                    if !are_rects_same_size(&self.window_rect, &old_rect) {
+                        log_if_err!(self.render());
+                    }
+                }
+            }
+            WM_TIMER if wparam.0 == OCCLUSION_TIMER_ID => {
+                if self.pause {
+                    return LRESULT(0);
+                }
+
+                // Windows above this one can move without telling us
+                if self.update_occluding_rects() {
+                    log_if_err!(self.render());
+                }
+                if !self.pause_when_occluded {
+                    return LRESULT(0);
+                }
+
+                let is_occluded = is_window_occluded(self.border_window, &self.window_rect);
+                if is_occluded != self.is_occluded {
+                    self.is_occluded = is_occluded;
+
+                    // No need to wake up for frames nobody can see
+                    if let Some(ref anim_timer) = self.anim_timer {
+                        anim_timer.set_paused(is_occluded);
+                    }
+
+                    // Catch up on whatever changed while we were skipping frames
+                    if !is_occluded {
+                        log_if_err!(self.render());
+                    }
+                }
+            }
+            WM_TIMER if wparam.0 == BADGE_TIMER_ID => {
+                if self.pause {
+                    return LRESULT(0);
+                }
+
+                let changed = self
+                    .badges
+                    .as_mut()
+                    .is_some_and(|badges| badges.update(self.tracking_window));
+                if changed {
+                    log_if_err!(self.render());
+                }
+            }
+            WM_SETTINGCHANGE if accessibility::is_system_setting(wparam.0 as u32) => {
+                accessibility::system_settings_changed();
+            }
+            // Monitor frames follow the work area, which changes when monitors are rearranged or
+            // the taskbar moves
+            WM_DISPLAYCHANGE | WM_SETTINGCHANGE if self.monitor.is_some() => {
+                if message == WM_DISPLAYCHANGE {
+                    // Give any newly connected monitors a frame too
+                    monitor_frames::create_frames();
+                }
+
+                let old_rect = self.window_rect;
+                log_if_err!(self.update_window_rect());
+                if self.window_rect != old_rect {
+                    log_if_err!(self.update_position(None));
+                    log_if_err!(self.render());
+                }
+            }
+            WM_PAINT => {
+                let _ = ValidateRect(window, None);
+            }
+            WM_NCDESTROY => {
+                self.transition_to(LifecycleState::Destroyed, time::Instant::now());
+                self.destroy_anim_timer();
+                if let Some(mut renderer) = self.renderer.take() {
+                    renderer.release();
+                }
+                if self.safe_mode {
+                    renderer::set_safe_mode(false);
+                }
+                if self.hover_peek.is_some() {
+                    effects::stop_hover_poll(self.border_window);
+                }
+                SetWindowLongPtrW(window, GWLP_USERDATA, 0);
+                self.exit_border_thread();
+            }
+            // Ignore these window position messages
+            WM_WINDOWPOSCHANGING | WM_WINDOWPOSCHANGED => {}
+            // Explorer restarting tends to leave layered and topmost windows out of order, so we put
+            // the border back above its tracking window. For regular borders, we then update it like
+            // the tracking window just moved, which also shows or hides it as needed.
+            message if message != 0 && message == taskbar_created_message() => {
+                info!(
+                    "taskbar was recreated; restoring border for {:?}",
+                    self.tracking_window
+                );
+                self.restore_window_styles();
+                log_if_err!(self.update_position(None));
+                log_if_err!(self.render());
+
+                if self.lifecycle.is_shown()
+                    && self.monitor.is_none()
+                    && self.cursor_ring_size.is_none()
+                {
+                    log_if_err!(post_message_w(
+                        window,
+                        WM_APP_LOCATIONCHANGE,
+                        WPARAM(0),
+                        LPARAM(0)
+                    ));
+                }
+            }
+            _ => {
+                return DefWindowProcW(window, message, wparam, lparam);
+            }
+        }
+        LRESULT(0)
+    }
+}
+
+impl BorderPainter for WindowBorder {
+    fn paint(
+        &self,
+        render_target: &ID2D1RenderTarget,
+        noise_bitmap: Option<&ID2D1Bitmap>,
+    ) -> anyhow::Result<()> {
+        self.draw_border(render_target, noise_bitmap)
+    }
+}
+
+// A mask covering 'window_rect' minus the given screen rects, in the window's coordinates. Returns
+// None if none of them overlap it.
+pub(crate) fn create_exclusion_geometry(
+    window_rect: &RECT,
+    excluded: &[RECT],
+) -> windows::core::Result<Option<ID2D1Geometry>> {
+    let overlapping: Vec<&RECT> = excluded
+        .iter()
+        .filter(|rect| do_rects_overlap(rect, window_rect))
+        .collect();
+
+    if overlapping.is_empty() {
+        return Ok(None);
+    }
+
+    let to_local = |rect: &RECT| D2D_RECT_F {
+        left: (rect.left - window_rect.left) as f32,
+        top: (rect.top - window_rect.top) as f32,
+        right: (rect.right - window_rect.left) as f32,
+        bottom: (rect.bottom - window_rect.top) as f32,
+    };
+
+    unsafe {
+        let mut mask: ID2D1Geometry = RENDER_FACTORY
+            .CreateRectangleGeometry(&to_local(window_rect))?
+            .cast()?;
+
+        for rect in overlapping.into_iter() {
+            let excluded = RENDER_FACTORY.CreateRectangleGeometry(&to_local(rect))?;
+            let combined = RENDER_FACTORY.CreatePathGeometry()?;
+            let sink = combined.Open()?;
+            mask.CombineWithGeometry(
+                &excluded,
+                D2D1_COMBINE_MODE_EXCLUDE,
+                None,
+                D2D1_DEFAULT_FLATTENING_TOLERANCE,
+                &sink,
+            )?;
+            sink.Close()?;
+
+            mask = combined.cast()?;
+        }
+
+        Ok(Some(mask))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::mock::{RecordingRenderer, RenderCall};
+    use windows::Win32::Graphics::Direct2D::Common::D2D1_COLOR_F;
+
+    fn color(hex: &str) -> Color {
+        let mut color = ColorConfig::SolidConfig(hex.to_string()).convert_to_color(true);
+        color.set_fade_opacity(1.0);
+        color
+    }
+
+    fn border(width: i32, height: i32) -> WindowBorder {
+        WindowBorder {
+            window_rect: RECT {
+                left: 0,
+                top: 0,
+                right: width,
+                bottom: height,
+            },
+            border_width: 4,
+            supersampling: 1,
+            active_color: color("#ff0000"),
+            inactive_color: color("#0000ff"),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn renderer_is_resized_only_when_the_window_is() {
+        let mut border = border(100, 50);
+        let mut renderer = RecordingRenderer::default();
+
+        border.draw_frame(&mut renderer).unwrap();
+        border.draw_frame(&mut renderer).unwrap();
+        border.window_rect.right = 200;
+        border.draw_frame(&mut renderer).unwrap();
+
+        let resizes: Vec<&RenderCall> = renderer
+            .calls
+            .iter()
+            .filter(|call| matches!(call, RenderCall::Resize(..)))
+            .collect();
+        assert_eq!(
+            resizes,
+            [&RenderCall::Resize(100, 50), &RenderCall::Resize(200, 50)]
+        );
+        assert_eq!(renderer.frames().len(), 3);
+        assert!(matches!(renderer.calls[0], RenderCall::Resize(100, 50)));
+    }
+
+    #[test]
+    fn frames_use_the_color_for_the_focus_state() {
+        let mut border = border(100, 50);
+        let mut renderer = RecordingRenderer::default();
+
+        border.is_active_window = true;
+        border.draw_frame(&mut renderer).unwrap();
+        border.is_active_window = false;
+        border.draw_frame(&mut renderer).unwrap();
+
+        let colors: Vec<D2D1_COLOR_F> = renderer.frames().iter().map(|frame| frame.color).collect();
+        assert_eq!(
+            colors,
+            [
+                D2D1_COLOR_F {
+                    r: 1.0,
+                    g: 0.0,
+                    b: 0.0,
+                    a: 1.0
+                },
+                D2D1_COLOR_F {
+                    r: 0.0,
+                    g: 0.0,
+                    b: 1.0,
+                    a: 1.0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn flashes_blink_the_frame_off() {
+        let mut border = border(100, 50);
+        let mut renderer = RecordingRenderer::default();
+
+        border.flash_hidden = true;
+        border.draw_frame(&mut renderer).unwrap();
+        border.flash_hidden = false;
+        border.draw_frame(&mut renderer).unwrap();
+
+        let visible: Vec<bool> = renderer
+            .frames()
+            .iter()
+            .map(|frame| frame.visible)
+            .collect();
+        assert_eq!(visible, [false, true]);
+    }
+
+    #[test]
+    fn frames_carry_the_border_state() {
+        let mut border = border(100, 50);
+        border.supersampling = 2;
+        let mut renderer = RecordingRenderer::default();
+
+        border.draw_frame(&mut renderer).unwrap();
+
+        let frame = renderer.frames()[0].clone();
+        assert_eq!(frame.window_rect, border.window_rect);
+        assert_eq!(frame.stroke_width, 4.0);
+        assert_eq!(frame.supersampling, 2);
+        assert_eq!(frame.drawn_area, border.drawn_area());
+    }
+}